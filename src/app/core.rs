@@ -1,21 +1,29 @@
 //! Core SlircApp struct definition and initialization
 
+use chrono::{DateTime, Utc};
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use eframe::egui;
 use std::collections::HashSet;
 use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::backend::run_backend;
+use crate::buffer::HistoryState;
 use crate::config::{
-    load_nickserv_password, load_settings, save_settings, ConnectionConfig, Settings,
+    load_nickserv_password, load_settings, parse_server_address, save_settings, ConnectionConfig,
+    Network, Settings, TimestampFormat, DEFAULT_FLOOD_BURST, DEFAULT_FLOOD_REFILL_MS,
+    DEFAULT_PING_TIMEOUT_SECS,
 };
 use crate::dialog_manager::DialogManager;
 use crate::input_state::InputState;
-use crate::protocol::{BackendAction, GuiEvent};
+use crate::protocol::{BackendAction, HistoryAnchor, NetworkAction, NetworkEvent, NetworkId};
 use crate::state::ClientState;
 use crate::ui;
 use crate::ui::shortcuts::ShortcutRegistry;
 
+/// Least time between two MARKREADs for the buffer being viewed
+const READ_MARKER_INTERVAL: Duration = Duration::from_secs(5);
+
 pub struct SlircApp {
     // Core state (buffers, networks, connection status, etc.)
     pub state: ClientState,
@@ -23,21 +31,38 @@ pub struct SlircApp {
     // Connection settings (form inputs)
     pub connection: ConnectionConfig,
 
-    // Channels for backend communication
-    pub action_tx: Sender<BackendAction>,
-    pub event_rx: Receiver<GuiEvent>,
+    // Channels for backend communication (tagged with the target network)
+    pub action_tx: Sender<NetworkAction>,
+    pub event_rx: Receiver<NetworkEvent>,
 
     // Input state (message composition, history, tab completion)
     pub input: InputState,
 
     // Context menu state
     pub context_menu_visible: bool,
-    pub context_menu_target: Option<String>,
-    pub open_windows: HashSet<String>,
+    pub context_menu_target: Option<(NetworkId, String)>,
+    pub open_windows: HashSet<(NetworkId, String)>,
+
+    // User whose WHOIS card is shown
+    pub whois_card: Option<(NetworkId, String)>,
 
     // Theme
     pub theme: String,
 
+    // How message timestamps are shown
+    pub timestamp_format: TimestampFormat,
+
+    // Message or unread divider to bring into view on the next frame
+    pub scroll_target: Option<ui::messages::ScrollTarget>,
+
+    // Buffer shown on the previous frame, to notice when another is opened
+    pub viewed_buffer: Option<(NetworkId, String)>,
+
+    // Read marker for the viewed buffer not yet passed to the server, and
+    // when the last MARKREAD for it went out
+    pub unsent_read: Option<DateTime<Utc>>,
+    pub read_sent_at: Option<Instant>,
+
     // UI visibility toggles
     pub show_channel_list: bool,
     pub show_user_list: bool,
@@ -64,12 +89,13 @@ impl SlircApp {
 
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // Create channels for UI <-> Backend
-        let (action_tx, action_rx) = unbounded::<BackendAction>();
-        let (event_tx, event_rx) = unbounded::<GuiEvent>();
+        let (action_tx, action_rx) = unbounded::<NetworkAction>();
+        let (event_tx, event_rx) = unbounded::<NetworkEvent>();
 
-        // Spawn the backend thread
+        // Spawn the backend thread; it wakes the UI whenever it sends an event
+        let repaint = cc.egui_ctx.clone();
         thread::spawn(move || {
-            run_backend(action_rx, event_tx);
+            run_backend(action_rx, event_tx, repaint);
         });
         // Try to load persisted settings and apply theme in creation context
         let settings = load_settings();
@@ -101,8 +127,14 @@ impl SlircApp {
             context_menu_visible: false,
             context_menu_target: None,
             open_windows: HashSet::new(),
+            whois_card: None,
 
             theme: "dark".to_string(),
+            timestamp_format: TimestampFormat::default(),
+            scroll_target: None,
+            viewed_buffer: None,
+            unsent_read: None,
+            read_sent_at: None,
 
             show_channel_list: true,
             show_user_list: true,
@@ -133,45 +165,137 @@ impl SlircApp {
             if !s.theme.is_empty() {
                 app.theme = s.theme;
             }
+            app.timestamp_format = s.timestamp_format;
             app.state.networks = s.networks.clone();
 
-            // Auto-connect to networks with auto_connect flag
-            for network in &s.networks {
-                if network.auto_connect {
-                    if let Some(server_addr) = network.servers.first() {
-                        let parts: Vec<&str> = server_addr.split(':').collect();
-                        let server = parts[0].to_string();
-                        let port: u16 = parts.get(1).and_then(|p| p.parse().ok()).unwrap_or(6667);
-
-                        // Set state fields for event processing
-                        app.state.server_name = server_addr.clone();
-                        app.state.our_nick = network.nick.clone();
-
-                        let _ = app.action_tx.send(BackendAction::Connect {
-                            server,
-                            port,
-                            nickname: network.nick.clone(),
-                            username: network.nick.clone(),
-                            realname: format!("SLIRC User ({})", network.nick),
-                            use_tls: network.use_tls,
-                            auto_reconnect: network.auto_reconnect,
-                            sasl_password: load_nickserv_password(&network.name),
-                        });
-
-                        // Auto-join favorite channels
-                        for channel in &network.favorite_channels {
-                            let _ = app.action_tx.send(BackendAction::Join(channel.clone()));
-                        }
-
-                        // Only auto-connect to the first network with the flag
-                        break;
-                    }
-                }
+            // Auto-connect to every network with the auto_connect flag
+            for network in s.networks.iter().filter(|n| n.auto_connect) {
+                app.connect_network(network);
             }
         }
         app
     }
 
+    /// Our nick on the active network, or the connect form's before we have one
+    pub(super) fn active_nick(&self) -> &str {
+        match self.state.our_nick() {
+            "" => &self.connection.nickname,
+            nick => nick,
+        }
+    }
+
+    /// Send an action to the backend for the given network.
+    pub(super) fn send_action(&self, network: &str, action: BackendAction) {
+        let _ = self.action_tx.send(NetworkAction::new(network, action));
+    }
+
+    /// Mark everything in the active buffer as read and tell the server,
+    /// which passes it on to our other clients (`draft/read-marker`).
+    /// Nothing is read while the window is in the background; coming back
+    /// counts as opening the buffer again, so the divider shows what arrived.
+    ///
+    /// While lines keep arriving the server hears at most one MARKREAD per
+    /// `READ_MARKER_INTERVAL`; whatever is held back goes out when the
+    /// buffer is left or the window loses focus.
+    pub fn mark_active_buffer_read(&mut self, focused: bool, now: Instant) {
+        let viewing = (
+            self.state.active_network.clone(),
+            self.state.active_buffer.clone(),
+        );
+        let opened = self.viewed_buffer.as_ref() != Some(&viewing);
+        if opened || !focused {
+            self.flush_read_marker(now);
+            self.read_sent_at = None;
+        }
+        if !focused {
+            self.viewed_buffer = None;
+            return;
+        }
+        self.viewed_buffer = Some(viewing);
+        let marker = self
+            .state
+            .active_channel_buffer_mut()
+            .and_then(|buffer| buffer.mark_read(opened));
+        if marker.is_some() {
+            self.unsent_read = marker;
+        }
+        let due = self.read_sent_at.map_or(true, |sent| {
+            now.duration_since(sent) >= READ_MARKER_INTERVAL
+        });
+        if due {
+            self.flush_read_marker(now);
+        }
+    }
+
+    /// Whether a read marker is being held back, so the UI wakes up to send it
+    pub fn has_unsent_read(&self) -> bool {
+        self.unsent_read.is_some()
+    }
+
+    /// Send the held-back read marker for the buffer last viewed
+    fn flush_read_marker(&mut self, now: Instant) {
+        let Some(time) = self.unsent_read.take() else {
+            return;
+        };
+        if let Some((network, target)) = self.viewed_buffer.clone() {
+            self.send_action(&network, BackendAction::MarkRead { target, time });
+            self.read_sent_at = Some(now);
+        }
+    }
+
+    /// Ask the server for scrollback in `target`; the buffer is marked as
+    /// loading until the `GuiEvent::History` reply arrives.
+    pub(super) fn fetch_history(&mut self, network: &str, target: &str, anchor: HistoryAnchor) {
+        if let Some(buffer) = self.state.session_mut(network).buffer_mut(target) {
+            buffer.history = HistoryState::Loading;
+        }
+        self.send_action(
+            network,
+            BackendAction::FetchHistory {
+                target: target.to_string(),
+                anchor,
+            },
+        );
+    }
+
+    /// Connect to a saved network using its first server address; the other
+    /// servers are tried in turn when reconnecting.
+    /// The network's name is used as its id for the session.
+    pub(super) fn connect_network(&mut self, network: &Network) {
+        if let Some(server_addr) = network.servers.first() {
+            let (server, port) = parse_server_address(server_addr, 6667);
+
+            // Set session fields for event processing
+            let session = self.state.session_mut(&network.name);
+            session.server_name = server_addr.clone();
+            session.our_nick = network.nick.clone();
+
+            self.send_action(
+                &network.name,
+                BackendAction::Connect {
+                    server,
+                    port,
+                    fallback_servers: network.servers[1..].to_vec(),
+                    nickname: network.nick.clone(),
+                    alt_nicks: network.alt_nicks.clone(),
+                    username: network.nick.clone(),
+                    realname: format!("SLIRC User ({})", network.nick),
+                    use_tls: network.use_tls,
+                    auto_reconnect: network.auto_reconnect,
+                    ping_timeout_secs: network.ping_timeout_secs,
+                    flood_burst: network.flood_burst,
+                    flood_refill_ms: network.flood_refill_ms,
+                    sasl_password: load_nickserv_password(&network.name),
+                },
+            );
+
+            // Auto-join favorite channels
+            for channel in &network.favorite_channels {
+                self.send_action(&network.name, BackendAction::Join(channel.clone()));
+            }
+        }
+    }
+
     pub(super) fn save_networks(&self) {
         let settings = Settings {
             server: self.connection.server.clone(),
@@ -180,6 +304,7 @@ impl SlircApp {
             history: self.input.history.clone(),
             theme: self.theme.clone(),
             networks: self.state.networks.clone(),
+            timestamp_format: self.timestamp_format,
         };
         if let Err(e) = save_settings(&settings) {
             eprintln!("Failed to save networks: {}", e);
@@ -187,25 +312,37 @@ impl SlircApp {
     }
 
     /// Initiate a connection to the server using current UI inputs.
-    /// Sets state.server_name and state.our_nick before sending connect action.
+    /// The server host is used as the network id, and becomes the active network.
+    /// Sets the session's server_name and our_nick before sending connect action.
     pub(super) fn do_connect(&mut self) {
         // Parse server:port from connection config
         let (server, port) = self.connection.parse_server();
-
-        // Set state fields for event processing (like Halloy's configured_nick pattern)
-        self.state.server_name = self.connection.server.clone();
-        self.state.our_nick = self.connection.nickname.clone();
-
-        let _ = self.action_tx.send(BackendAction::Connect {
-            server,
-            port,
-            nickname: self.connection.nickname.clone(),
-            username: self.connection.nickname.clone(),
-            realname: format!("SLIRC User ({})", self.connection.nickname),
-            use_tls: self.connection.use_tls,
-            auto_reconnect: true,
-            sasl_password: None,
-        });
+        let network = server.clone();
+
+        // Set session fields for event processing (like Halloy's configured_nick pattern)
+        let session = self.state.session_mut(&network);
+        session.server_name = self.connection.server.clone();
+        session.our_nick = self.connection.nickname.clone();
+        self.state.active_network = network.clone();
+
+        self.send_action(
+            &network,
+            BackendAction::Connect {
+                server,
+                port,
+                fallback_servers: Vec::new(),
+                nickname: self.connection.nickname.clone(),
+                alt_nicks: Vec::new(),
+                username: self.connection.nickname.clone(),
+                realname: format!("SLIRC User ({})", self.connection.nickname),
+                use_tls: self.connection.use_tls,
+                auto_reconnect: true,
+                ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+                flood_burst: DEFAULT_FLOOD_BURST,
+                flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+                sasl_password: None,
+            },
+        );
     }
 }
 
@@ -219,6 +356,7 @@ impl Drop for SlircApp {
             history: self.input.history.clone(),
             theme: self.theme.clone(),
             networks: self.state.networks.clone(),
+            timestamp_format: self.timestamp_format,
         };
         if let Err(e) = save_settings(&settings) {
             eprintln!("Failed to save settings: {}", e);
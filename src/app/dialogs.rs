@@ -3,7 +3,6 @@
 use eframe::egui;
 
 use super::SlircApp;
-use crate::config::load_nickserv_password;
 use crate::protocol::BackendAction;
 use crate::ui;
 use crate::ui::dialogs::DialogAction;
@@ -13,6 +12,9 @@ impl SlircApp {
     pub(super) fn render_dialogs(&mut self, ctx: &egui::Context) {
         // Floating status toasts (top-right corner)
         ui::dialogs::render_status_toasts(ctx, &self.state.status_messages);
+        if let Some(action) = ui::dialogs::render_invite_toasts(ctx, &self.state.pending_invites) {
+            self.handle_dialog_action(action);
+        }
 
         // Delegate to DialogManager for all dialog rendering
         let (actions, networks_to_save) = self.dialogs.render(ctx);
@@ -29,47 +31,73 @@ impl SlircApp {
         }
     }
 
-    /// Handle dialog actions by sending appropriate backend commands
+    /// Handle dialog actions by sending appropriate backend commands.
+    /// Actions apply to the active network unless they name their own.
     fn handle_dialog_action(&mut self, action: DialogAction) {
+        let active = self.state.active_network.clone();
         match action {
             DialogAction::ChangeNick(new_nick) => {
-                let _ = self.action_tx.send(BackendAction::Nick(new_nick));
+                self.send_action(&active, BackendAction::Nick(new_nick));
             }
             DialogAction::SetTopic { channel, topic } => {
-                let _ = self
-                    .action_tx
-                    .send(BackendAction::SetTopic { channel, topic });
+                self.send_action(&active, BackendAction::SetTopic { channel, topic });
             }
             DialogAction::JoinChannel(channel) => {
-                let _ = self.action_tx.send(BackendAction::Join(channel));
+                self.send_action(&active, BackendAction::Join(channel));
             }
-            DialogAction::NetworkConnect(network) => {
-                if let Some(server_addr) = network.servers.first() {
-                    let parts: Vec<&str> = server_addr.split(':').collect();
-                    let server = parts[0].to_string();
-                    let port: u16 = parts.get(1).and_then(|p| p.parse().ok()).unwrap_or(6667);
-
-                    // Set state fields for event processing
-                    self.state.server_name = server_addr.clone();
-                    self.state.our_nick = network.nick.clone();
-
-                    let _ = self.action_tx.send(BackendAction::Connect {
-                        server,
-                        port,
-                        nickname: network.nick.clone(),
-                        username: network.nick.clone(),
-                        realname: format!("SLIRC User ({})", network.nick),
-                        use_tls: network.use_tls,
-                        auto_reconnect: network.auto_reconnect,
-                        sasl_password: load_nickserv_password(&network.name),
-                    });
-
-                    // Auto-join favorite channels
-                    for channel in &network.favorite_channels {
-                        let _ = self.action_tx.send(BackendAction::Join(channel.clone()));
-                    }
+            DialogAction::AnswerInvite {
+                network,
+                channel,
+                accept,
+            } => {
+                self.state
+                    .pending_invites
+                    .retain(|i| !(i.network == network && i.channel == channel));
+                if accept {
+                    self.send_action(&network, BackendAction::Join(channel));
                 }
             }
+            DialogAction::SendMultiline {
+                network,
+                target,
+                text,
+                lines,
+            } => {
+                // The pasted text was kept in the input until confirmed; leave
+                // it alone if the user has since moved on or edited it
+                let unchanged = self.input.message_input == text
+                    && self.state.active_network == network
+                    && self.state.active_buffer == target;
+                if unchanged {
+                    self.input.history_pos = None;
+                    self.input.history_saved_input = None;
+                    self.input.message_input.clear();
+                }
+                self.input.history.push(text);
+                let client_tags = self.input.take_reply_tags(&network, &target);
+                self.send_action(
+                    &network,
+                    BackendAction::SendMultiline {
+                        target,
+                        lines,
+                        client_tags,
+                    },
+                );
+            }
+            DialogAction::SetChannelModes {
+                network,
+                channel,
+                changes,
+            } => {
+                self.send_action(
+                    &network,
+                    BackendAction::SetChannelModes { channel, changes },
+                );
+            }
+            DialogAction::NetworkConnect(network) => {
+                self.connect_network(&network);
+                self.state.active_network = network.name.clone();
+            }
             DialogAction::NetworkSave {
                 index: _,
                 network: _,
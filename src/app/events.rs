@@ -2,7 +2,7 @@
 
 use super::SlircApp;
 use crate::events;
-use crate::protocol::GuiEvent;
+use crate::protocol::{BackendAction, GuiEvent, HistoryAnchor, NetworkEvent};
 use crate::ui::dialogs::ChannelListItem;
 
 impl SlircApp {
@@ -10,7 +10,7 @@ impl SlircApp {
         // Collect channel list events separately
         let mut regular_events = Vec::new();
 
-        while let Ok(event) = self.event_rx.try_recv() {
+        while let Ok(NetworkEvent { network, event }) = self.event_rx.try_recv() {
             match event {
                 GuiEvent::ChannelListItem {
                     channel,
@@ -29,22 +29,75 @@ impl SlircApp {
                     self.dialogs.channel_browser_complete();
                 }
                 other => {
-                    regular_events.push(other);
+                    regular_events.push((network, other));
                 }
             }
         }
 
         // Process regular events
-        for event in regular_events {
-            self.process_single_event(event);
+        for (network, event) in regular_events {
+            self.process_single_event(&network, event);
         }
     }
 
-    fn process_single_event(&mut self, event: GuiEvent) {
+    fn process_single_event(&mut self, network: &str, event: GuiEvent) {
+        // A rejected nick change on the active network: ask for another nick
+        if let GuiEvent::NickError { nick, reason } = &event {
+            if self.state.active_network == network {
+                let current = self
+                    .state
+                    .sessions
+                    .get(network)
+                    .map(|s| s.our_nick.clone())
+                    .unwrap_or_default();
+                self.dialogs.open_nick_rejected(&current, nick, reason);
+            }
+        }
+
+        let joined = match &event {
+            GuiEvent::JoinedChannel(channel) => Some(channel.clone()),
+            _ => None,
+        };
+
+        let modes_changed = match &event {
+            GuiEvent::ChannelMode { channel, .. }
+            | GuiEvent::ChannelModeIs { channel, .. }
+            | GuiEvent::ModeList { channel, .. } => Some(channel.clone()),
+            _ => None,
+        };
+
         // Process event and check if nick changed
-        if let Some(new_nick) = events::process_single_event(&mut self.state, event) {
-            // Update UI nickname field when server confirms nick change
-            self.connection.nickname = new_nick;
+        if let Some(new_nick) = events::process_single_event(&mut self.state, network, event) {
+            // Update UI nickname field when server confirms a nick change on the active network
+            if self.state.active_network == network {
+                self.connection.nickname = new_nick;
+            }
+        }
+
+        // Keep an open channel settings dialog on the server's modes
+        if let Some(channel) = modes_changed {
+            if let Some(session) = self.state.sessions.get(network) {
+                if let Some(buffer) = session.buffer(&channel) {
+                    self.dialogs.update_channel_settings(
+                        network,
+                        &channel,
+                        &session.support,
+                        &buffer.channel_modes,
+                    );
+                }
+            }
+        }
+
+        // Fill a freshly joined channel with its modes and recent scrollback
+        if let Some(channel) = joined {
+            self.fetch_history(network, &channel, HistoryAnchor::Latest);
+            self.send_action(
+                network,
+                BackendAction::QueryModes {
+                    channel,
+                    lists: Vec::new(),
+                },
+            );
         }
     }
 }
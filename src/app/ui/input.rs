@@ -1,11 +1,15 @@
 //! Message input panel with history and tab completion
 
+use std::time::{Duration, Instant};
+
 use chrono::Local;
 use eframe::egui;
 
 use crate::app::SlircApp;
 use crate::commands;
 use crate::protocol::BackendAction;
+use crate::state::{default_support, no_buffers, SYSTEM_BUFFER};
+use crate::ui;
 
 impl SlircApp {
     /// Render the input panel at the bottom of the window
@@ -25,6 +29,55 @@ impl SlircApp {
                     .stroke(egui::Stroke::new(1.0, theme.border_medium)),
             )
             .show(ctx, |ui| {
+                // Who else is typing here; repaint so the line expires
+                let typing = self.state.active_channel_buffer().and_then(|buffer| {
+                    ui::messages::typing_line(&buffer.typing_nicks(Instant::now()))
+                });
+                if let Some(typing) = typing {
+                    ui.label(
+                        egui::RichText::new(typing)
+                            .size(12.0)
+                            .italics()
+                            .color(theme.text_muted),
+                    );
+                    ui.add_space(2.0);
+                    ctx.request_repaint_after(Duration::from_secs(1));
+                }
+
+                // The message being replied to, with a button to cancel
+                let reply = self
+                    .input
+                    .reply_in(&self.state.active_network, &self.state.active_buffer)
+                    .cloned();
+                if let Some(reply) = reply {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            egui::RichText::new("Replying to")
+                                .size(12.0)
+                                .color(theme.text_muted),
+                        );
+                        ui.label(
+                            egui::RichText::new(&reply.sender)
+                                .size(12.0)
+                                .strong()
+                                .color(ui::theme::nick_color(&reply.sender)),
+                        );
+                        ui.label(
+                            egui::RichText::new(ui::messages::reply_snippet(&reply.text))
+                                .size(12.0)
+                                .color(theme.text_secondary),
+                        );
+                        if ui
+                            .small_button("✕")
+                            .on_hover_text("Cancel reply (Esc)")
+                            .clicked()
+                        {
+                            self.input.replying_to = None;
+                        }
+                    });
+                    ui.add_space(4.0);
+                }
+
                 ui.horizontal(|ui| {
                     // Styled input frame with rounding and focus indication
                     let input_frame = egui::Frame::new()
@@ -105,15 +158,20 @@ impl SlircApp {
                             let prefix = self.input.message_input[start..end].trim();
                             if self.input.completions.is_empty() {
                                 // first time: gather completions
+                                let (buffers_order, buffers) = match self.state.active_session() {
+                                    Some(session) => (&session.buffers_order[..], &session.buffers),
+                                    None => (&[][..], no_buffers()),
+                                };
                                 self.input.completions = self.input.collect_completions(
                                     prefix,
-                                    &self.state.buffers_order,
+                                    buffers_order,
                                     &self.state.active_buffer,
-                                    &self.state.buffers,
+                                    buffers,
+                                    self.state.active_support(),
                                 );
                                 self.input.completion_prefix = Some(prefix.to_string());
                                 self.input.completion_target_channel =
-                                    prefix.starts_with('#') || prefix.starts_with('&');
+                                    self.state.active_support().is_channel(prefix);
                             }
                             if !self.input.completions.is_empty() {
                                 if shift {
@@ -140,29 +198,79 @@ impl SlircApp {
                             self.input.completions.clear();
                             self.input.completion_index = None;
                             self.input.completion_prefix = None;
+                            self.input.replying_to = None;
                         }
 
                         if enter_detected && !self.input.message_input.is_empty() {
+                            // Pasted text: confirm before sending one message per line,
+                            // even if it starts with a slash. The input is kept (minus
+                            // Enter's newline) until the user confirms.
+                            let can_send = self.state.is_connected()
+                                && self.state.active_buffer != SYSTEM_BUFFER;
+                            let pasted = self.input.multiline_lines();
+                            if let Some(lines) = pasted.clone().filter(|_| can_send) {
+                                let kept = self
+                                    .input
+                                    .message_input
+                                    .trim_end_matches(['\r', '\n'])
+                                    .len();
+                                self.input.message_input.truncate(kept);
+                                self.dialogs.open_paste_confirm(
+                                    &self.state.active_network,
+                                    &self.state.active_buffer,
+                                    &self.input.message_input,
+                                    lines,
+                                );
+                                response.surrender_focus();
+                                return;
+                            }
                             // If it begins with a slash, treat as a command
                             if self.input.message_input.starts_with('/') {
-                                if commands::handle_user_command(
+                                // Borrow the session directly so system_log stays free
+                                let session = self.state.sessions.get(&self.state.active_network);
+                                let buffers =
+                                    session.map(|s| &s.buffers).unwrap_or_else(no_buffers);
+                                let support =
+                                    session.map(|s| &s.support).unwrap_or_else(default_support);
+                                if pasted.is_some() {
+                                    // Never run a command with the rest of a paste attached
+                                    let ts = Local::now().format("%H:%M:%S").to_string();
+                                    self.state.system_log.push(format!(
+                                        "[{}] ⚠ A command must fit on one line: not run",
+                                        ts
+                                    ));
+                                } else if commands::handle_user_command(
                                     &self.input.message_input,
+                                    &self.state.active_network,
                                     &self.state.active_buffer,
-                                    &self.state.buffers,
+                                    buffers,
+                                    support,
                                     &self.action_tx,
                                     &mut self.state.system_log,
-                                    &mut self.connection.nickname,
                                 ) {
                                     self.input.history.push(self.input.message_input.clone());
                                 }
                             } else {
                                 // Normal message
-                                if self.state.is_connected {
-                                    if self.state.active_buffer != "System" {
-                                        let _ = self.action_tx.send(BackendAction::SendMessage {
-                                            target: self.state.active_buffer.clone(),
-                                            text: self.input.message_input.clone(),
-                                        });
+                                if self.state.is_connected() {
+                                    if self.state.active_buffer != SYSTEM_BUFFER {
+                                        let client_tags = self.input.take_reply_tags(
+                                            &self.state.active_network,
+                                            &self.state.active_buffer,
+                                        );
+                                        self.send_action(
+                                            &self.state.active_network,
+                                            BackendAction::SendMessage {
+                                                target: self.state.active_buffer.clone(),
+                                                text: self
+                                                    .input
+                                                    .message_input
+                                                    .trim_end_matches(['\r', '\n'])
+                                                    .to_string(),
+                                                client_tags,
+                                            },
+                                        );
+                                        self.input.typing.message_sent();
                                         self.input.history.push(self.input.message_input.clone());
                                     }
                                 } else {
@@ -185,10 +293,38 @@ impl SlircApp {
                 });
             });
 
+        self.notify_typing(ctx);
+
         if enter_pressed {
             Some(true)
         } else {
             None
         }
     }
+
+    /// Send our own typing notifications as the input changes, unless the
+    /// active network has them turned off
+    fn notify_typing(&mut self, ctx: &egui::Context) {
+        let network = &self.state.active_network;
+        let enabled = self.state.is_connected()
+            && self.state.active_buffer != SYSTEM_BUFFER
+            && self
+                .state
+                .networks
+                .iter()
+                .find(|n| &n.name == network)
+                .map_or(true, |n| n.send_typing);
+        let target = enabled.then_some(self.state.active_buffer.as_str());
+        let updates =
+            self.input
+                .typing
+                .update(network, target, &self.input.message_input, Instant::now());
+        for (network, target, state) in updates {
+            self.send_action(&network, BackendAction::Typing { target, state });
+        }
+        // Wake up later to send `paused` if the user stops typing
+        if self.input.typing.is_active() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+    }
 }
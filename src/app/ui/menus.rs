@@ -1,18 +1,19 @@
 //! Context menus and floating windows
 
+use chrono::Utc;
 use eframe::egui;
 use slirc_proto::ctcp::Ctcp;
 
 use crate::app::SlircApp;
-use crate::buffer::ChannelBuffer;
 use crate::protocol::BackendAction;
+use crate::state::SYSTEM_BUFFER;
 use crate::ui;
 
 impl SlircApp {
     /// Render context menu popup (as a floating window)
     pub(in crate::app) fn render_context_menu(&mut self, ctx: &egui::Context) {
         if self.context_menu_visible {
-            if let Some(target) = self.context_menu_target.clone() {
+            if let Some((network, target)) = self.context_menu_target.clone() {
                 // If the target starts with "user:", this is a user context menu
                 if let Some(user) = target.strip_prefix("user:") {
                     egui::Window::new(format!("User: {}", user))
@@ -21,34 +22,34 @@ impl SlircApp {
                         .show(ctx, |ui| {
                             if ui.button("Query (PM)").clicked() {
                                 // Create or switch to private message buffer
-                                if !self.state.buffers.contains_key(user) {
-                                    self.state
-                                        .buffers
-                                        .insert(user.to_string(), ChannelBuffer::new());
-                                    self.state.buffers_order.push(user.to_string());
-                                }
-                                self.state.active_buffer = user.to_string();
+                                self.state.ensure_buffer(&network, user);
+                                self.state.switch_to_buffer(&network, user);
                                 self.context_menu_visible = false;
                             }
                             if ui.button("Whois").clicked() {
-                                let _ = self.action_tx.send(BackendAction::Whois(user.to_string()));
+                                self.send_action(&network, BackendAction::Whois(user.to_string()));
+                                // Drop the old reply so the card waits for the new one
+                                let session = self.state.session_mut(&network);
+                                let key = session.irc_name(user);
+                                session.whois.remove(&key);
+                                self.whois_card = Some((network.clone(), user.to_string()));
                                 self.context_menu_visible = false;
                             }
                             if ui.button("Cancel").clicked() {
                                 self.context_menu_visible = false;
                             }
                             // Show op actions if we're an op in this channel
-                            if self.state.active_buffer.starts_with('#')
-                                || self.state.active_buffer.starts_with('&')
-                            {
+                            let support = self.state.active_support();
+                            if support.is_channel(&self.state.active_buffer) {
                                 let is_op = self
                                     .state
-                                    .buffers
-                                    .get(&self.state.active_buffer)
+                                    .active_channel_buffer()
                                     .map(|b| {
                                         b.users.iter().any(|u| {
-                                            u.nick == self.connection.nickname
-                                                && ui::theme::prefix_rank(u.prefix) >= 3
+                                            support
+                                                .case_mapping()
+                                                .names_equal(&u.nick, self.state.our_nick())
+                                                && support.is_at_least(u.prefix, 'o')
                                         })
                                     })
                                     .unwrap_or(false);
@@ -56,43 +57,58 @@ impl SlircApp {
                                     ui.separator();
                                     ui.label("Op Actions:");
                                     if ui.button("Op (+o)").clicked() {
-                                        let _ = self.action_tx.send(BackendAction::SetUserMode {
-                                            channel: self.state.active_buffer.clone(),
-                                            nick: user.to_string(),
-                                            mode: "+o".to_string(),
-                                        });
+                                        self.send_action(
+                                            &network,
+                                            BackendAction::SetUserMode {
+                                                channel: self.state.active_buffer.clone(),
+                                                nick: user.to_string(),
+                                                mode: "+o".to_string(),
+                                            },
+                                        );
                                         self.context_menu_visible = false;
                                     }
                                     if ui.button("Deop (-o)").clicked() {
-                                        let _ = self.action_tx.send(BackendAction::SetUserMode {
-                                            channel: self.state.active_buffer.clone(),
-                                            nick: user.to_string(),
-                                            mode: "-o".to_string(),
-                                        });
+                                        self.send_action(
+                                            &network,
+                                            BackendAction::SetUserMode {
+                                                channel: self.state.active_buffer.clone(),
+                                                nick: user.to_string(),
+                                                mode: "-o".to_string(),
+                                            },
+                                        );
                                         self.context_menu_visible = false;
                                     }
                                     if ui.button("Voice (+v)").clicked() {
-                                        let _ = self.action_tx.send(BackendAction::SetUserMode {
-                                            channel: self.state.active_buffer.clone(),
-                                            nick: user.to_string(),
-                                            mode: "+v".to_string(),
-                                        });
+                                        self.send_action(
+                                            &network,
+                                            BackendAction::SetUserMode {
+                                                channel: self.state.active_buffer.clone(),
+                                                nick: user.to_string(),
+                                                mode: "+v".to_string(),
+                                            },
+                                        );
                                         self.context_menu_visible = false;
                                     }
                                     if ui.button("Devoice (-v)").clicked() {
-                                        let _ = self.action_tx.send(BackendAction::SetUserMode {
-                                            channel: self.state.active_buffer.clone(),
-                                            nick: user.to_string(),
-                                            mode: "-v".to_string(),
-                                        });
+                                        self.send_action(
+                                            &network,
+                                            BackendAction::SetUserMode {
+                                                channel: self.state.active_buffer.clone(),
+                                                nick: user.to_string(),
+                                                mode: "-v".to_string(),
+                                            },
+                                        );
                                         self.context_menu_visible = false;
                                     }
                                     if ui.button("Kick").clicked() {
-                                        let _ = self.action_tx.send(BackendAction::Kick {
-                                            channel: self.state.active_buffer.clone(),
-                                            nick: user.to_string(),
-                                            reason: None,
-                                        });
+                                        self.send_action(
+                                            &network,
+                                            BackendAction::Kick {
+                                                channel: self.state.active_buffer.clone(),
+                                                nick: user.to_string(),
+                                                reason: None,
+                                            },
+                                        );
                                         self.context_menu_visible = false;
                                     }
                                 }
@@ -104,22 +120,25 @@ impl SlircApp {
                         .collapsible(false)
                         .show(ctx, |ui| {
                             if ui.button("Part").clicked() {
-                                let _ = self.action_tx.send(BackendAction::Part {
-                                    channel: target.clone(),
-                                    message: None,
-                                });
+                                self.send_action(
+                                    &network,
+                                    BackendAction::Part {
+                                        channel: target.clone(),
+                                        message: None,
+                                    },
+                                );
                                 self.context_menu_visible = false;
                             }
                             if ui.button("Close").clicked() {
-                                self.state.buffers.remove(&target);
-                                self.state.buffers_order.retain(|b| b != &target);
-                                if self.state.active_buffer == target {
-                                    self.state.active_buffer = "System".into();
+                                let was_active = self.state.is_active(&network, &target);
+                                self.state.session_mut(&network).remove_buffer(&target);
+                                if was_active {
+                                    self.state.active_buffer = SYSTEM_BUFFER.into();
                                 }
                                 self.context_menu_visible = false;
                             }
                             if ui.button("Open in new window").clicked() {
-                                self.open_windows.insert(target.clone());
+                                self.open_windows.insert((network.clone(), target.clone()));
                                 self.context_menu_visible = false;
                             }
                             if ui.button("Cancel").clicked() {
@@ -131,11 +150,30 @@ impl SlircApp {
         }
     }
 
+    /// Render the WHOIS card for the user picked from the context menu
+    pub(in crate::app) fn render_whois_card(&mut self, ctx: &egui::Context) {
+        let Some((network, nick)) = &self.whois_card else {
+            return;
+        };
+        let info = self
+            .state
+            .session(network)
+            .and_then(|s| s.whois.get(&s.irc_name(nick)));
+        let mut open = true;
+        ui::whois_card::render_whois_card(ctx, nick, info, &mut open, &self.get_theme());
+        if !open {
+            self.whois_card = None;
+        }
+    }
+
     /// Render floating buffer windows
     pub(in crate::app) fn render_floating_windows(&mut self, ctx: &egui::Context) {
-        for open_name in self.open_windows.clone() {
+        let timestamp_format = self.timestamp_format;
+        let now = Utc::now();
+        for (network, open_name) in self.open_windows.clone() {
             let mut open = true;
             egui::Window::new(format!("Window: {}", open_name))
+                .id(egui::Id::new(("floating_window", &network, &open_name)))
                 .open(&mut open)
                 .resizable(true)
                 .show(ctx, |ui| {
@@ -143,7 +181,11 @@ impl SlircApp {
                         ui.heading(&open_name);
                     });
                     ui.separator();
-                    if let Some(buffer) = self.state.buffers.get(&open_name) {
+                    if let Some(buffer) = self
+                        .state
+                        .session(&network)
+                        .and_then(|s| s.buffer(&open_name))
+                    {
                         egui::ScrollArea::vertical().show(ui, |ui| {
                             for msg in &buffer.messages {
                                 // Check if this is a CTCP ACTION message using slirc_proto
@@ -151,8 +193,11 @@ impl SlircApp {
                                     if let Some(action) = ctcp.params {
                                         ui.horizontal(|ui| {
                                             ui.label(
-                                                egui::RichText::new(format!("[{}]", msg.timestamp))
-                                                    .color(egui::Color32::LIGHT_GRAY),
+                                                egui::RichText::new(format!(
+                                                    "[{}]",
+                                                    timestamp_format.format(&msg.timestamp, now)
+                                                ))
+                                                .color(egui::Color32::LIGHT_GRAY),
                                             );
                                             ui.label(
                                                 egui::RichText::new("*")
@@ -173,8 +218,11 @@ impl SlircApp {
                                 }
                                 ui.horizontal(|ui| {
                                     ui.label(
-                                        egui::RichText::new(format!("[{}]", msg.timestamp))
-                                            .color(egui::Color32::LIGHT_GRAY),
+                                        egui::RichText::new(format!(
+                                            "[{}]",
+                                            timestamp_format.format(&msg.timestamp, now)
+                                        ))
+                                        .color(egui::Color32::LIGHT_GRAY),
                                     );
                                     ui.label(
                                         egui::RichText::new(format!("<{}>", msg.sender))
@@ -188,7 +236,7 @@ impl SlircApp {
                     }
                 });
             if !open {
-                self.open_windows.remove(&open_name);
+                self.open_windows.remove(&(network, open_name));
             }
         }
     }
@@ -3,6 +3,9 @@
 use eframe::egui;
 
 use crate::app::SlircApp;
+use crate::input_state::ReplyTarget;
+use crate::protocol::BackendAction;
+use crate::state::{default_support, no_buffers};
 use crate::ui;
 
 impl SlircApp {
@@ -22,10 +25,13 @@ impl SlircApp {
                 if let Some(menu_action) = ui::menu::render_menu_bar(
                     ctx,
                     ui,
-                    self.state.is_connected,
+                    self.state.is_connected(),
+                    &self.state.active_network,
                     &self.state.active_buffer,
+                    self.state.active_support(),
                     &mut self.show_channel_list,
                     &mut self.show_user_list,
+                    &mut self.timestamp_format,
                     &mut self.quick_switcher,
                     &self.action_tx,
                 ) {
@@ -65,8 +71,15 @@ impl SlircApp {
                     &mut self.connection.server,
                     &mut self.connection.nickname,
                     &mut self.input.channel_input,
-                    self.state.is_connected,
+                    self.state.is_connected(),
+                    self.state.active_session().and_then(|s| s.reconnect_at),
+                    self.state.active_session().and_then(|s| s.lag),
+                    self.state
+                        .active_session()
+                        .map_or(0, |s| s.send_queue_depth),
                     &mut self.connection.use_tls,
+                    &self.state.active_network,
+                    self.state.active_support(),
                     &self.action_tx,
                 ) {
                     match toolbar_action {
@@ -74,7 +87,8 @@ impl SlircApp {
                             self.do_connect();
                         }
                         ui::toolbar::ToolbarAction::OpenNickChangeDialog => {
-                            self.dialogs.open_nick_change(&self.connection.nickname);
+                            let nick = self.active_nick().to_string();
+                            self.dialogs.open_nick_change(&nick);
                         }
                     }
                 }
@@ -88,19 +102,19 @@ impl SlircApp {
         egui::CentralPanel::default()
             .frame(egui::Frame::new().fill(chat_bg).inner_margin(0.0))
             .show(ctx, |ui| {
-                // Use state.our_nick if connected, otherwise fall back to UI input
-                let current_nick = if self.state.our_nick.is_empty() {
-                    &self.connection.nickname
-                } else {
-                    &self.state.our_nick
-                };
+                let current_nick = self.active_nick().to_string();
 
                 // Render topic bar for channels (above messages)
+                // (the session is borrowed directly so system_log stays free)
+                let session = self.state.sessions.get(&self.state.active_network);
+                let buffers = session.map(|s| &s.buffers).unwrap_or_else(no_buffers);
+                let support = session.map(|s| &s.support).unwrap_or_else(default_support);
                 if let Some(topic_action) = ui::topic_bar::render_topic_bar(
                     ui,
                     &self.state.active_buffer,
-                    &self.state.buffers,
-                    current_nick,
+                    buffers,
+                    support,
+                    &current_nick,
                     &theme,
                     &mut self.state.system_log,
                 ) {
@@ -108,16 +122,14 @@ impl SlircApp {
                         ui::topic_bar::TopicBarAction::EditTopic(channel) => {
                             let current_topic = self
                                 .state
-                                .buffers
-                                .get(&channel)
+                                .active_session()
+                                .and_then(|s| s.buffer(&channel))
                                 .map(|b| b.topic.clone())
                                 .unwrap_or_default();
                             self.dialogs.open_topic_editor(&channel, &current_topic);
                         }
                         ui::topic_bar::TopicBarAction::ToggleMute => {
-                            if let Some(buffer) =
-                                self.state.buffers.get_mut(&self.state.active_buffer)
-                            {
+                            if let Some(buffer) = self.state.active_channel_buffer_mut() {
                                 buffer.notifications_muted = !buffer.notifications_muted;
                             }
                         }
@@ -127,6 +139,40 @@ impl SlircApp {
                                 .system_log
                                 .push("Search not implemented yet".to_string());
                         }
+                        ui::topic_bar::TopicBarAction::Rejoin(channel) => {
+                            let network = self.state.active_network.clone();
+                            self.send_action(&network, BackendAction::Join(channel));
+                        }
+                        ui::topic_bar::TopicBarAction::ChannelSettings(channel) => {
+                            let network = self.state.active_network.clone();
+                            if let Some(session) = self.state.active_session() {
+                                let modes = session
+                                    .buffer(&channel)
+                                    .map(|b| b.channel_modes.clone())
+                                    .unwrap_or_default();
+                                let can_edit = ui::topic_bar::check_mode_permission(
+                                    &channel,
+                                    &session.buffers,
+                                    &session.support,
+                                    &current_nick,
+                                );
+                                let lists = self
+                                    .dialogs
+                                    .open_channel_settings(
+                                        &network,
+                                        &channel,
+                                        &modes,
+                                        &session.support,
+                                        can_edit,
+                                    )
+                                    .list_modes();
+                                // Fresh modes and mask lists for the dialog
+                                self.send_action(
+                                    &network,
+                                    BackendAction::QueryModes { channel, lists },
+                                );
+                            }
+                        }
                         ui::topic_bar::TopicBarAction::ShowPinned => {
                             // TODO: Implement pinned messages view
                             self.state
@@ -137,7 +183,8 @@ impl SlircApp {
                 }
 
                 // Messages panel with inner margin
-                egui::Frame::new()
+                let scroll_to = self.scroll_target.take();
+                let message_action = egui::Frame::new()
                     .fill(chat_bg)
                     .inner_margin(12.0)
                     .show(ui, |ui| {
@@ -145,11 +192,65 @@ impl SlircApp {
                             ctx,
                             ui,
                             &self.state.active_buffer,
-                            &self.state.buffers,
+                            self.state.active_channel_buffer(),
                             &self.state.system_log,
-                            current_nick,
+                            &current_nick,
+                            self.state.active_support().case_mapping(),
+                            self.timestamp_format,
+                            scroll_to.as_ref(),
+                        )
+                    })
+                    .inner;
+                match message_action {
+                    Some(ui::messages::MessageAction::LoadOlderHistory) => {
+                        if let Some(anchor) = self
+                            .state
+                            .active_channel_buffer()
+                            .and_then(|b| b.older_history_anchor())
+                        {
+                            let network = self.state.active_network.clone();
+                            let buffer_name = self.state.active_buffer.clone();
+                            self.fetch_history(&network, &buffer_name, anchor);
+                        }
+                    }
+                    Some(ui::messages::MessageAction::Reply {
+                        msgid,
+                        sender,
+                        text,
+                    }) => {
+                        self.input.replying_to = Some(ReplyTarget {
+                            network: self.state.active_network.clone(),
+                            buffer: self.state.active_buffer.clone(),
+                            msgid,
+                            sender,
+                            text,
+                        });
+                    }
+                    Some(ui::messages::MessageAction::React {
+                        msgid,
+                        reaction,
+                        add,
+                    }) => {
+                        self.send_action(
+                            &self.state.active_network,
+                            BackendAction::React {
+                                target: self.state.active_buffer.clone(),
+                                msgid,
+                                reaction,
+                                add,
+                            },
                         );
-                    });
+                    }
+                    Some(ui::messages::MessageAction::JumpToMessage(msgid)) => {
+                        self.scroll_target = Some(ui::messages::ScrollTarget::Message(msgid));
+                        ctx.request_repaint();
+                    }
+                    Some(ui::messages::MessageAction::JumpToFirstUnread) => {
+                        self.scroll_target = Some(ui::messages::ScrollTarget::FirstUnread);
+                        ctx.request_repaint();
+                    }
+                    None => {}
+                }
             });
     }
 }
@@ -1,7 +1,7 @@
 //! Main update loop and global shortcuts
 
 use eframe::egui;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use super::SlircApp;
 
@@ -41,16 +41,22 @@ impl eframe::App for SlircApp {
                 let current_fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
                 ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!current_fullscreen));
             }
+            // Ctrl+Shift+U: Jump to the first unread message
+            if i.modifiers.ctrl && i.modifiers.shift && i.key_pressed(egui::Key::U) {
+                self.scroll_target = Some(crate::ui::messages::ScrollTarget::FirstUnread);
+            }
             // Ctrl+B: Toggle channel list
             if i.modifiers.ctrl && i.key_pressed(egui::Key::B) {
                 self.show_channel_list = !self.show_channel_list;
             }
         });
 
-        // Request repaint to keep checking for events
-        ctx.request_repaint_after(Duration::from_millis(100));
-        // Purge old status messages (toasts) older than 4 seconds
+        // Purge old status messages (toasts) older than 4 seconds.
+        // Network events wake the UI on their own; only toasts need a timer.
         self.state.purge_old_status_messages(4);
+        if !self.state.status_messages.is_empty() {
+            ctx.request_repaint_after(Duration::from_millis(500));
+        }
 
         // Render UI sections
         self.render_menu_bar(ctx);
@@ -61,33 +67,35 @@ impl eframe::App for SlircApp {
             use crate::ui;
             ui::panels::render_channel_list(
                 ctx,
-                &self.state.buffers,
-                &self.state.buffers_order,
-                &mut self.state.active_buffer,
+                &mut self.state,
                 &mut self.context_menu_visible,
                 &mut self.context_menu_target,
-                &mut self.state.collapsed_sections,
-                &mut self.state.channel_filter,
             );
-            // Clear unread after switching buffer
-            if let Some(buf) = self.state.buffers.get_mut(&self.state.active_buffer) {
-                buf.clear_unread();
-            }
+        }
+        // Whatever is shown now has been read, including after a switch
+        let focused = ctx.input(|i| i.focused);
+        self.mark_active_buffer_read(focused, Instant::now());
+        if self.has_unsent_read() {
+            ctx.request_repaint_after(Duration::from_secs(1));
         }
         // (Removed top horizontal buffer tabs — left navigation is the single source of truth.)
 
         // Right panel: User list (for channels)
         if self.show_user_list
-            && (self.state.active_buffer.starts_with('#')
-                || self.state.active_buffer.starts_with('&'))
+            && self
+                .state
+                .active_support()
+                .is_channel(&self.state.active_buffer)
         {
-            if let Some(buffer) = self.state.buffers.get(&self.state.active_buffer) {
+            if let Some(buffer) = self.state.active_channel_buffer() {
                 use crate::ui;
                 ui::panels::render_user_list(
                     ctx,
                     buffer,
+                    self.state.active_support(),
+                    &self.state.active_network,
                     &self.state.active_buffer,
-                    &self.connection.nickname,
+                    self.state.our_nick(),
                     &mut self.context_menu_visible,
                     &mut self.context_menu_target,
                 );
@@ -102,6 +110,7 @@ impl eframe::App for SlircApp {
 
         // Context menu popup (as a floating window)
         self.render_context_menu(ctx);
+        self.render_whois_card(ctx);
 
         // Floating buffer windows
         self.render_floating_windows(ctx);
@@ -110,12 +119,8 @@ impl eframe::App for SlircApp {
         self.render_dialogs(ctx);
 
         // Quick switcher overlay (Ctrl+K)
-        if let Some(selected_buffer) = self.quick_switcher.render(ctx, &self.state.buffers) {
-            self.state.active_buffer = selected_buffer.clone();
-            if let Some(buffer) = self.state.buffers.get_mut(&selected_buffer) {
-                buffer.clear_unread();
-                buffer.has_highlight = false;
-            }
+        if let Some((network, buffer)) = self.quick_switcher.render(ctx, &self.state.sessions) {
+            self.state.switch_to_buffer(&network, &buffer);
         }
 
         // Shortcuts help overlay (Ctrl+/ or F1)
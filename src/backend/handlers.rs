@@ -3,11 +3,16 @@
 //! This module handles the conversion of incoming IRC messages to GUI events.
 //! It's extracted from the main backend loop to improve maintainability.
 
-use crossbeam_channel::Sender;
+use chrono::{DateTime, Utc};
 use slirc_proto::mode::{ChannelMode, Mode};
 use slirc_proto::{Command, Message, Prefix};
 
-use crate::protocol::{GuiEvent, UserInfo};
+use super::sender::EventSender;
+use crate::channel_modes::ModeChange;
+use crate::protocol::{
+    GuiEvent, MessageTags, TypingState, UserInfo, REACT_TAG, REPLY_TAG, TYPING_TAG, UNREACT_TAG,
+};
+use crate::server_support::ServerSupport;
 
 /// Route an IRC message to appropriate GUI event handlers
 ///
@@ -18,33 +23,42 @@ use crate::protocol::{GuiEvent, UserInfo};
 /// # Arguments
 /// * `msg` - The IRC message to process
 /// * `current_nick` - The client's current nickname (for self-detection)
-/// * `event_tx` - Channel sender for dispatching GUI events
+/// * `support` - The connection's ISUPPORT model, updated by 005 replies
+/// * `event_tx` - Network-tagged sender for dispatching GUI events
 ///
 /// # Returns
 /// `Some(new_nick)` if the message was a NICK change affecting us, otherwise `None`
 pub fn route_message(
     msg: &Message,
     current_nick: &str,
-    event_tx: &Sender<GuiEvent>,
+    support: &mut ServerSupport,
+    event_tx: &EventSender,
 ) -> Option<String> {
+    // Servers may echo our nick in another case
+    let is_us = {
+        let mapping = support.case_mapping();
+        move |nick: &str| mapping.names_equal(nick, current_nick)
+    };
     match &msg.command {
-        // RPL_ISUPPORT (005) - Server capabilities
+        // RPL_ISUPPORT (005): <me> <token>... :are supported by this server
         Command::Response(code, args) if code.code() == 5 => {
-            // Parse ISUPPORT tokens using slirc-proto
-            let params: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
-            let isupport = slirc_proto::Isupport::from_response_args(&params);
-
-            // Extract useful info and send to UI
-            let _ = event_tx.send(GuiEvent::ServerInfo {
-                network: isupport
-                    .as_ref()
-                    .and_then(|i| i.network())
-                    .map(|s| s.to_string()),
-                casemapping: isupport
-                    .as_ref()
-                    .and_then(|i| i.casemapping())
-                    .map(|s| s.to_string()),
-            });
+            if args.len() > 2 {
+                let tokens: Vec<&str> =
+                    args[1..args.len() - 1].iter().map(|s| s.as_str()).collect();
+                support.apply(&tokens);
+                let _ = event_tx.send(GuiEvent::ServerInfo(support.clone()));
+            }
+            None
+        }
+
+        // RPL_CHANNELMODEIS (324): <me> <channel> <modes> [<args>...]
+        Command::Response(code, args) if code.code() == 324 => {
+            if args.len() >= 3 {
+                let _ = event_tx.send(GuiEvent::ChannelModeIs {
+                    channel: args[1].clone(),
+                    changes: ModeChange::parse(&args[2], &args[3..], support),
+                });
+            }
             None
         }
 
@@ -59,22 +73,36 @@ pub fn route_message(
             None
         }
 
+        // RPL_TOPICWHOTIME (333): <me> <channel> <setter> <unix time>
+        Command::Response(code, args) if code.code() == 333 => {
+            let set_at = args
+                .get(3)
+                .and_then(|secs| secs.parse::<i64>().ok())
+                .and_then(|secs| DateTime::from_timestamp(secs, 0));
+            if let (Some(channel), Some(setter), Some(set_at)) = (args.get(1), args.get(2), set_at)
+            {
+                // Some servers give the setter's full mask
+                let set_by = setter.split('!').next().unwrap_or_default();
+                let _ = event_tx.send(GuiEvent::TopicWhoTime {
+                    channel: channel.clone(),
+                    set_by: set_by.to_string(),
+                    set_at,
+                });
+            }
+            None
+        }
+
         // RPL_NAMREPLY (353)
         Command::Response(code, args) if code.code() == 353 => {
             if args.len() >= 4 {
                 let channel = args[2].clone();
                 let mut names: Vec<UserInfo> = Vec::new();
-                for s in args[3].split_whitespace() {
-                    let mut chars = s.chars();
-                    let prefix = chars
-                        .next()
-                        .filter(|c| matches!(c, '@' | '+' | '%' | '&' | '~'));
-                    let nick = if prefix.is_some() {
-                        chars.as_str().to_string()
-                    } else {
-                        s.to_string()
-                    };
-                    names.push(UserInfo { nick, prefix });
+                for entry in args[3].split_whitespace() {
+                    let (prefix, nick) = support.split_prefix(entry);
+                    names.push(UserInfo {
+                        nick: nick.to_string(),
+                        prefix,
+                    });
                 }
                 let _ = event_tx.send(GuiEvent::Names { channel, names });
             }
@@ -117,6 +145,8 @@ pub fn route_message(
                 target: target.clone(),
                 sender,
                 text: text.clone(),
+                time: message_time(msg),
+                tags: message_tags(msg),
             });
             None
         }
@@ -128,34 +158,101 @@ pub fn route_message(
                 target: target.clone(),
                 sender: format!("-{}-", sender),
                 text: text.clone(),
+                time: message_time(msg),
+                tags: message_tags(msg),
             });
             None
         }
 
+        // TAGMSG carries no text; only reactions and typing are shown
+        Command::Raw(cmd, args) if cmd.eq_ignore_ascii_case("TAGMSG") => {
+            for event in [reaction_event(msg, args), typing_event(msg, args)]
+                .into_iter()
+                .flatten()
+            {
+                let _ = event_tx.send(event);
+            }
+            None
+        }
+
+        // MARKREAD <target> timestamp=<time>: read marker from the server
+        Command::Raw(cmd, args) if cmd.eq_ignore_ascii_case("MARKREAD") => {
+            if let Some(event) = read_marker_event(args) {
+                let _ = event_tx.send(event);
+            }
+            None
+        }
+
         // JOIN
         Command::JOIN(channel, _, _) => {
             let nick = msg.source_nickname().unwrap_or("").to_string();
-            if nick == current_nick {
+            if is_us(&nick) {
                 let _ = event_tx.send(GuiEvent::JoinedChannel(channel.clone()));
             } else {
                 let _ = event_tx.send(GuiEvent::UserJoined {
                     channel: channel.clone(),
                     nick,
+                    time: message_time(msg),
                 });
             }
             None
         }
 
+        // KICK
+        Command::KICK(channel, nick, reason) => {
+            let by = msg.source_nickname().unwrap_or("").to_string();
+            let reason = reason.clone().filter(|r| !r.is_empty());
+            if is_us(nick) {
+                let _ = event_tx.send(GuiEvent::Kicked {
+                    channel: channel.clone(),
+                    by,
+                    reason,
+                });
+            } else {
+                let _ = event_tx.send(GuiEvent::UserKicked {
+                    channel: channel.clone(),
+                    nick: nick.clone(),
+                    by,
+                    reason,
+                    time: message_time(msg),
+                });
+            }
+            None
+        }
+
+        // INVITE (only invites for us; invite-notify copies are ignored)
+        Command::INVITE(nick, channel) => {
+            if is_us(nick) {
+                let _ = event_tx.send(GuiEvent::Invited {
+                    channel: channel.clone(),
+                    by: msg.source_nickname().unwrap_or("").to_string(),
+                });
+            }
+            None
+        }
+
+        // TOPIC changed live
+        Command::TOPIC(channel, Some(topic)) => {
+            let _ = event_tx.send(GuiEvent::TopicChanged {
+                channel: channel.clone(),
+                topic: topic.clone(),
+                set_by: msg.source_nickname().unwrap_or("").to_string(),
+                time: message_time(msg),
+            });
+            None
+        }
+
         // PART
         Command::PART(channel, message) => {
             let nick = msg.source_nickname().unwrap_or("").to_string();
-            if nick == current_nick {
+            if is_us(&nick) {
                 let _ = event_tx.send(GuiEvent::PartedChannel(channel.clone()));
             } else {
                 let _ = event_tx.send(GuiEvent::UserParted {
                     channel: channel.clone(),
                     nick,
                     message: message.clone(),
+                    time: message_time(msg),
                 });
             }
             None
@@ -170,7 +267,7 @@ pub fn route_message(
             });
 
             // Return new nick if it affects us (caller will update current_nick)
-            if oldnick == current_nick {
+            if is_us(&oldnick) {
                 Some(newnick.clone())
             } else {
                 None
@@ -183,6 +280,7 @@ pub fn route_message(
                 let _ = event_tx.send(GuiEvent::UserQuit {
                     nick: nick.to_string(),
                     message: message.clone(),
+                    time: message_time(msg),
                 });
             }
             None
@@ -197,113 +295,36 @@ pub fn route_message(
         // Channel mode changes (e.g. +o/-o): update UI user prefixes and channel modes
         Command::ChannelMODE(channel, modes) => {
             let set_by = msg.source_nickname().unwrap_or("server").to_string();
-            let mut channel_mode_changes = String::new();
-
-            for m in modes {
-                match m {
-                    Mode::Plus(ChannelMode::Oper, Some(nick)) => {
-                        let _ = event_tx.send(GuiEvent::UserMode {
-                            channel: channel.clone(),
-                            nick: nick.clone(),
-                            prefix: Some('@'),
-                            added: true,
-                        });
-                    }
-                    Mode::Minus(ChannelMode::Oper, Some(nick)) => {
-                        let _ = event_tx.send(GuiEvent::UserMode {
-                            channel: channel.clone(),
-                            nick: nick.clone(),
-                            prefix: Some('@'),
-                            added: false,
-                        });
-                    }
-                    Mode::Plus(ChannelMode::Voice, Some(nick)) => {
-                        let _ = event_tx.send(GuiEvent::UserMode {
-                            channel: channel.clone(),
-                            nick: nick.clone(),
-                            prefix: Some('+'),
-                            added: true,
-                        });
-                    }
-                    Mode::Minus(ChannelMode::Voice, Some(nick)) => {
-                        let _ = event_tx.send(GuiEvent::UserMode {
-                            channel: channel.clone(),
-                            nick: nick.clone(),
-                            prefix: Some('+'),
-                            added: false,
-                        });
-                    }
-                    Mode::Plus(ChannelMode::Halfop, Some(nick)) => {
-                        let _ = event_tx.send(GuiEvent::UserMode {
-                            channel: channel.clone(),
-                            nick: nick.clone(),
-                            prefix: Some('%'),
-                            added: true,
-                        });
-                    }
-                    Mode::Minus(ChannelMode::Halfop, Some(nick)) => {
-                        let _ = event_tx.send(GuiEvent::UserMode {
-                            channel: channel.clone(),
-                            nick: nick.clone(),
-                            prefix: Some('%'),
-                            added: false,
-                        });
-                    }
-                    Mode::Plus(ChannelMode::Admin, Some(nick)) => {
-                        let _ = event_tx.send(GuiEvent::UserMode {
-                            channel: channel.clone(),
-                            nick: nick.clone(),
-                            prefix: Some('&'),
-                            added: true,
-                        });
-                    }
-                    Mode::Minus(ChannelMode::Admin, Some(nick)) => {
-                        let _ = event_tx.send(GuiEvent::UserMode {
-                            channel: channel.clone(),
-                            nick: nick.clone(),
-                            prefix: Some('&'),
-                            added: false,
-                        });
-                    }
-                    Mode::Plus(ChannelMode::Founder, Some(nick)) => {
-                        let _ = event_tx.send(GuiEvent::UserMode {
-                            channel: channel.clone(),
-                            nick: nick.clone(),
-                            prefix: Some('~'),
-                            added: true,
-                        });
-                    }
-                    Mode::Minus(ChannelMode::Founder, Some(nick)) => {
-                        let _ = event_tx.send(GuiEvent::UserMode {
-                            channel: channel.clone(),
-                            nick: nick.clone(),
-                            prefix: Some('~'),
-                            added: false,
-                        });
-                    }
-                    // Channel-level modes (no nick arg) - track for topic bar
-                    Mode::Plus(mode, None) => {
-                        if let Some(c) = channel_mode_char(mode) {
-                            channel_mode_changes.push('+');
-                            channel_mode_changes.push(c);
-                        }
-                    }
-                    Mode::Minus(mode, None) => {
-                        if let Some(c) = channel_mode_char(mode) {
-                            channel_mode_changes.push('-');
-                            channel_mode_changes.push(c);
+            let mut changes = Vec::new();
+
+            // slirc_proto pairs modes with arguments by its own table; pair
+            // them again by the server's ISUPPORT
+            let (mode_string, args) = raw_mode_line(modes);
+            for change in ModeChange::parse(&mode_string, &args, support) {
+                match support.prefix_for_mode(change.mode) {
+                    // Membership modes (+o nick, +v nick, ...) change a user's prefix
+                    Some(prefix) => {
+                        if let Some(nick) = change.arg {
+                            let _ = event_tx.send(GuiEvent::UserMode {
+                                channel: channel.clone(),
+                                nick,
+                                prefix: Some(prefix),
+                                added: change.added,
+                            });
                         }
                     }
-                    _ => {}
+                    // Channel-level modes: flags, key, limit and list masks
+                    None => changes.push(change),
                 }
             }
 
             // Emit channel mode event if any channel-level modes changed
-            if !channel_mode_changes.is_empty() {
+            if !changes.is_empty() {
                 let _ = event_tx.send(GuiEvent::ChannelMode {
                     channel: channel.clone(),
-                    modes: channel_mode_changes,
+                    changes,
                     set_by,
+                    time: message_time(msg),
                 });
             }
 
@@ -315,9 +336,111 @@ pub fn route_message(
     }
 }
 
-/// Convert a ChannelMode to its single-character representation for display
+/// When a message was sent: the IRCv3 `server-time` tag if present and
+/// valid, otherwise now (the moment it reached us)
+pub fn message_time(msg: &Message) -> DateTime<Utc> {
+    msg.tags
+        .iter()
+        .flatten()
+        .find(|tag| tag.0 == "time")
+        .and_then(|tag| tag.1.as_deref())
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now)
+}
+
+/// The tags of `msg` that the UI keeps with a message
+pub fn message_tags(msg: &Message) -> MessageTags {
+    let mut tags = MessageTags::default();
+    for tag in msg.tags.iter().flatten() {
+        let value = tag.1.clone();
+        match tag.0.as_str() {
+            "msgid" => tags.msgid = value,
+            "account" => tags.account = value,
+            "label" => tags.label = value,
+            name if name.starts_with('+') => {
+                tags.client
+                    .push((name.to_string(), value.unwrap_or_default()));
+            }
+            _ => {}
+        }
+    }
+    tags
+}
+
+/// A `+draft/react` or `+draft/unreact` TAGMSG as a reaction event
+fn reaction_event(msg: &Message, args: &[String]) -> Option<GuiEvent> {
+    let tags = message_tags(msg);
+    let msgid = tags.client_tag(REPLY_TAG).filter(|id| !id.is_empty())?;
+    let (reaction, added) = match tags.client_tag(REACT_TAG) {
+        Some(reaction) => (reaction, true),
+        None => (tags.client_tag(UNREACT_TAG)?, false),
+    };
+    if reaction.is_empty() {
+        return None;
+    }
+    Some(GuiEvent::Reaction {
+        target: args.first()?.clone(),
+        sender: msg.source_nickname()?.to_string(),
+        msgid: msgid.to_string(),
+        reaction: reaction.to_string(),
+        added,
+    })
+}
+
+/// A `+typing` TAGMSG as a typing event
+fn typing_event(msg: &Message, args: &[String]) -> Option<GuiEvent> {
+    let tags = message_tags(msg);
+    let state = TypingState::parse(tags.client_tag(TYPING_TAG)?)?;
+    Some(GuiEvent::Typing {
+        target: args.first()?.clone(),
+        sender: msg.source_nickname()?.to_string(),
+        state,
+    })
+}
+
+/// A `MARKREAD` reply as a read marker event; `timestamp=*` (never read)
+/// has nothing to apply
+fn read_marker_event(args: &[String]) -> Option<GuiEvent> {
+    let time = args.get(1)?.strip_prefix("timestamp=")?;
+    let time = DateTime::parse_from_rfc3339(time).ok()?;
+    Some(GuiEvent::ReadMarker {
+        target: args.first()?.clone(),
+        time: time.with_timezone(&Utc),
+    })
+}
+
+/// The mode letter of a ChannelMode. Whether it is a membership, list or
+/// plain mode is up to the server's ISUPPORT, not the variant.
+/// The mode string and arguments of a parsed MODE line, in their original order
+fn raw_mode_line(modes: &[Mode<ChannelMode>]) -> (String, Vec<String>) {
+    let mut mode_string = String::new();
+    let mut args = Vec::new();
+    for m in modes {
+        let (sign, mode, arg) = match m {
+            Mode::Plus(mode, arg) => ('+', mode, arg),
+            Mode::Minus(mode, arg) => ('-', mode, arg),
+            _ => continue,
+        };
+        if let Some(letter) = channel_mode_char(mode) {
+            mode_string.push(sign);
+            mode_string.push(letter);
+        }
+        args.extend(arg.clone());
+    }
+    (mode_string, args)
+}
+
 fn channel_mode_char(mode: &ChannelMode) -> Option<char> {
     match mode {
+        ChannelMode::Founder | ChannelMode::Quiet => Some('q'),
+        ChannelMode::Admin => Some('a'),
+        ChannelMode::Oper => Some('o'),
+        ChannelMode::Halfop => Some('h'),
+        ChannelMode::Voice => Some('v'),
+        ChannelMode::Ban => Some('b'),
+        ChannelMode::Exception => Some('e'),
+        ChannelMode::InviteException => Some('I'),
         ChannelMode::Moderated => Some('m'),
         ChannelMode::ProtectedTopic => Some('t'),
         ChannelMode::NoExternalMessages => Some('n'),
@@ -326,19 +449,211 @@ fn channel_mode_char(mode: &ChannelMode) -> Option<char> {
         ChannelMode::RegisteredOnly => Some('r'),
         ChannelMode::Key => Some('k'),
         ChannelMode::Limit => Some('l'),
-        // User prefix modes and list modes are handled separately
-        ChannelMode::Oper
-        | ChannelMode::Voice
-        | ChannelMode::Halfop
-        | ChannelMode::Admin
-        | ChannelMode::Founder
-        | ChannelMode::Ban
-        | ChannelMode::Exception
-        | ChannelMode::InviteException
-        | ChannelMode::Quiet => None,
-        // Unknown modes - try to emit them anyway if they have a char
         ChannelMode::Unknown(c) => Some(*c),
-        // Catch-all for any new modes added to the non-exhaustive enum
-        _ => None,
+        // Any new modes added to the non-exhaustive enum
+        other => other.to_string().chars().next(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_util::{drain_events, event_sender, parse};
+    use crate::channel_modes::format_mode_changes;
+    use chrono::TimeZone;
+
+    /// Route `line` as if we were `tester`, returning the events it produced
+    fn route(line: &str) -> Vec<GuiEvent> {
+        let (sender, rx) = event_sender();
+        let mut support = ServerSupport::default();
+        support.apply(&["PREFIX=(qaohv)~&@%+", "CHANTYPES=#&!"]);
+        route_message(&parse(line), "tester", &mut support, &sender);
+        drain_events(&rx)
+    }
+
+    #[test]
+    fn test_route_kick_invite_topic() {
+        assert!(matches!(
+            route(":op!u@h KICK #chan tester :spamming").as_slice(),
+            [GuiEvent::Kicked { by, reason: Some(reason), .. }] if by == "op" && reason == "spamming"
+        ));
+        assert!(matches!(
+            route(":op!u@h KICK #chan alice").as_slice(),
+            [GuiEvent::UserKicked { nick, reason: None, .. }] if nick == "alice"
+        ));
+        assert!(matches!(
+            route(":bob!u@h INVITE tester #secret").as_slice(),
+            [GuiEvent::Invited { channel, by }] if channel == "#secret" && by == "bob"
+        ));
+        assert!(route(":bob!u@h INVITE alice #secret").is_empty());
+        assert!(matches!(
+            route(":bob!u@h TOPIC #chan :New topic").as_slice(),
+            [GuiEvent::TopicChanged { topic, set_by, .. }] if topic == "New topic" && set_by == "bob"
+        ));
+        match route(":server 333 tester #chan bob!u@h 1709294400").as_slice() {
+            [GuiEvent::TopicWhoTime {
+                channel,
+                set_by,
+                set_at,
+            }] => {
+                assert_eq!(channel, "#chan");
+                assert_eq!(set_by, "bob");
+                assert_eq!(*set_at, Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap());
+            }
+            other => panic!("expected topic setter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_route_uses_server_support() {
+        match route(":server 353 tester = #chan :~+owner %half carol").as_slice() {
+            [GuiEvent::Names { names, .. }] => {
+                let names: Vec<_> = names.iter().map(|u| (u.prefix, u.nick.as_str())).collect();
+                assert_eq!(
+                    names,
+                    [(Some('~'), "owner"), (Some('%'), "half"), (None, "carol")]
+                );
+            }
+            other => panic!("expected names, got {:?}", other),
+        }
+        assert!(matches!(
+            route(":op!u@h MODE #chan +h alice").as_slice(),
+            [GuiEvent::UserMode {
+                prefix: Some('%'),
+                added: true,
+                ..
+            }]
+        ));
+        assert!(matches!(
+            route(":op!u@h MODE #chan +mb-t *!*@spam").as_slice(),
+            [GuiEvent::ChannelMode { changes, .. }]
+                if format_mode_changes(changes) == "+mb-t *!*@spam"
+        ));
+        // -l takes no argument (CHANMODES group C), so alice belongs to +o
+        match route(":op!u@h MODE #chan -l+o alice").as_slice() {
+            [GuiEvent::UserMode {
+                nick,
+                prefix: Some('@'),
+                added: true,
+                ..
+            }, GuiEvent::ChannelMode { changes, .. }] => {
+                assert_eq!(nick, "alice");
+                assert_eq!(format_mode_changes(changes), "-l");
+            }
+            other => panic!("expected a user mode and a channel mode, got {:?}", other),
+        }
+        match route(":server 324 tester #chan +ntkl secret 50").as_slice() {
+            [GuiEvent::ChannelModeIs { channel, changes }] => {
+                assert_eq!(channel, "#chan");
+                assert_eq!(format_mode_changes(changes), "+ntkl secret 50");
+            }
+            other => panic!("expected channel modes, got {:?}", other),
+        }
+        match route(":server 005 tester PREFIX=(ov)@+ CHANTYPES=+ :are supported").as_slice() {
+            [GuiEvent::ServerInfo(support)] => {
+                assert!(support.is_channel("+chan"));
+                assert!(!support.is_prefix('%'));
+            }
+            other => panic!("expected server info, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_message_time_from_tag() {
+        let msg = parse("@time=2024-03-01T12:34:56.789Z :alice!u@h PRIVMSG #chan :hi");
+        let expected = Utc.with_ymd_and_hms(2024, 3, 1, 12, 34, 56).unwrap()
+            + chrono::Duration::milliseconds(789);
+        assert_eq!(message_time(&msg), expected);
+    }
+
+    #[test]
+    fn test_message_time_falls_back_to_now() {
+        let before = Utc::now();
+        let untagged = parse(":alice!u@h PRIVMSG #chan :hi");
+        assert!(message_time(&untagged) >= before);
+        let invalid = parse("@time=yesterday :alice!u@h PRIVMSG #chan :hi");
+        assert!(message_time(&invalid) >= before);
+    }
+
+    #[test]
+    fn test_reaction_event() {
+        let react = parse("@+draft/reply=m1;+draft/react=👍 :alice!u@h TAGMSG #chan");
+        let Command::Raw(_, args) = &react.command else {
+            panic!("TAGMSG should be a raw command");
+        };
+        match reaction_event(&react, args) {
+            Some(GuiEvent::Reaction {
+                target,
+                sender,
+                msgid,
+                reaction,
+                added,
+            }) => {
+                assert_eq!(target, "#chan");
+                assert_eq!(sender, "alice");
+                assert_eq!(msgid, "m1");
+                assert_eq!(reaction, "👍");
+                assert!(added);
+            }
+            other => panic!("expected a reaction, got {:?}", other),
+        }
+
+        let unreact = parse("@+draft/reply=m1;+draft/unreact=👍 :alice!u@h TAGMSG #chan");
+        assert!(matches!(
+            reaction_event(&unreact, &["#chan".to_string()]),
+            Some(GuiEvent::Reaction { added: false, .. })
+        ));
+
+        // Typing notifications and reactions without a parent are ignored
+        let typing = parse("@+typing=active :alice!u@h TAGMSG #chan");
+        assert!(reaction_event(&typing, &["#chan".to_string()]).is_none());
+        assert!(matches!(
+            typing_event(&typing, &["#chan".to_string()]),
+            Some(GuiEvent::Typing {
+                state: TypingState::Active,
+                ..
+            })
+        ));
+        let unknown = parse("@+typing=thinking :alice!u@h TAGMSG #chan");
+        assert!(typing_event(&unknown, &["#chan".to_string()]).is_none());
+        let orphan = parse("@+draft/react=👍 :alice!u@h TAGMSG #chan");
+        assert!(reaction_event(&orphan, &["#chan".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_read_marker_event() {
+        let args = |line: &str| match parse(line).command {
+            Command::Raw(_, args) => args,
+            other => panic!("MARKREAD should be a raw command, got {:?}", other),
+        };
+        match read_marker_event(&args(
+            ":server MARKREAD #chan timestamp=2024-03-01T12:00:00.000Z",
+        )) {
+            Some(GuiEvent::ReadMarker { target, time }) => {
+                assert_eq!(target, "#chan");
+                assert_eq!(time, Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap());
+            }
+            other => panic!("expected a read marker, got {:?}", other),
+        }
+        assert!(read_marker_event(&args(":server MARKREAD #chan timestamp=*")).is_none());
+        assert!(read_marker_event(&args(":server MARKREAD #chan")).is_none());
+    }
+
+    #[test]
+    fn test_message_tags() {
+        let msg = parse(
+            "@msgid=abc;account=alice;label=l1;+draft/reply=xyz;+draft/react=👍;+typing;time=x \
+             :alice!u@h PRIVMSG #chan :hi",
+        );
+        let tags = message_tags(&msg);
+        assert_eq!(tags.msgid.as_deref(), Some("abc"));
+        assert_eq!(tags.account.as_deref(), Some("alice"));
+        assert_eq!(tags.label.as_deref(), Some("l1"));
+        assert_eq!(tags.client_tag("+draft/reply"), Some("xyz"));
+        assert_eq!(tags.client_tag("+draft/react"), Some("👍"));
+        assert_eq!(tags.client_tag("+typing"), Some(""));
+        assert_eq!(tags.client.len(), 3);
+        let untagged = parse(":alice!u@h PRIVMSG #chan :hi");
+        assert_eq!(message_tags(&untagged), MessageTags::default());
     }
 }
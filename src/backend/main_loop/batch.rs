@@ -0,0 +1,179 @@
+//! IRCv3 `batch`: grouping server lines that belong together.
+//!
+//! `BATCH +ref <type> [params]` opens a batch and `BATCH -ref` closes it; every
+//! line in between tagged `batch=ref` belongs to it. Lines are held until the
+//! outermost batch closes so the whole group can be handled at once. A batch
+//! opened inside another one is folded into its parent when it closes.
+
+use std::collections::HashMap;
+
+use slirc_proto::{Command, Message};
+
+/// Capability name
+pub const BATCH_CAP: &str = "batch";
+
+/// A batch that has been opened but not closed yet
+#[derive(Debug)]
+struct OpenBatch {
+    kind: String,
+    params: Vec<String>,
+    /// Reference of the enclosing batch, if nested
+    parent: Option<String>,
+    messages: Vec<Message>,
+}
+
+/// A complete top-level batch
+#[derive(Debug)]
+pub struct FinishedBatch {
+    /// Batch type, e.g. `chathistory` or `netsplit`
+    pub kind: String,
+    /// Parameters after the type (the target, for `chathistory`)
+    pub params: Vec<String>,
+    /// Lines of the batch (and of any nested batches), in arrival order
+    pub messages: Vec<Message>,
+}
+
+/// What to do with a line after batch tracking has seen it
+#[derive(Debug)]
+pub enum Observed {
+    /// Not part of any batch: handle it as usual
+    Unbatched(Message),
+    /// Held until its batch closes
+    Held,
+    /// A top-level batch just closed
+    Finished(FinishedBatch),
+}
+
+/// Batches currently open on the connection
+#[derive(Debug, Default)]
+pub struct Batches {
+    open: HashMap<String, OpenBatch>,
+}
+
+impl Batches {
+    /// Track `BATCH` lines and hold lines that belong to an open batch
+    pub fn observe(&mut self, message: Message) -> Observed {
+        let parent = batch_tag(&message)
+            .filter(|reference| self.open.contains_key(*reference))
+            .map(str::to_string);
+
+        if let Command::Raw(cmd, args) = &message.command {
+            if cmd.eq_ignore_ascii_case("BATCH") {
+                if let Some(reference) = args.first().and_then(|r| r.strip_prefix('+')) {
+                    self.open.insert(
+                        reference.to_string(),
+                        OpenBatch {
+                            kind: args.get(1).cloned().unwrap_or_default(),
+                            params: args.iter().skip(2).cloned().collect(),
+                            parent,
+                            messages: Vec::new(),
+                        },
+                    );
+                    return Observed::Held;
+                }
+                if let Some(reference) = args.first().and_then(|r| r.strip_prefix('-')) {
+                    return self.close(reference);
+                }
+            }
+        }
+
+        match parent.and_then(|reference| self.open.get_mut(&reference)) {
+            Some(batch) => {
+                batch.messages.push(message);
+                Observed::Held
+            }
+            None => Observed::Unbatched(message),
+        }
+    }
+
+    /// Forget every open batch (new connection)
+    pub fn clear(&mut self) {
+        self.open.clear();
+    }
+
+    fn close(&mut self, reference: &str) -> Observed {
+        let Some(batch) = self.open.remove(reference) else {
+            return Observed::Held;
+        };
+        if let Some(parent) = batch.parent.as_ref().and_then(|p| self.open.get_mut(p)) {
+            parent.messages.extend(batch.messages);
+            return Observed::Held;
+        }
+        Observed::Finished(FinishedBatch {
+            kind: batch.kind,
+            params: batch.params,
+            messages: batch.messages,
+        })
+    }
+}
+
+/// Value of the `batch` tag, if any
+fn batch_tag(message: &Message) -> Option<&str> {
+    message
+        .tags
+        .iter()
+        .flatten()
+        .find(|tag| tag.0 == "batch")
+        .and_then(|tag| tag.1.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_util::parse;
+
+    #[test]
+    fn test_lines_held_until_batch_closes() {
+        let mut batches = Batches::default();
+        assert!(matches!(
+            batches.observe(parse(":server BATCH +abc chathistory #chan")),
+            Observed::Held
+        ));
+        assert!(matches!(
+            batches.observe(parse("@batch=abc :alice!u@h PRIVMSG #chan :one")),
+            Observed::Held
+        ));
+        assert!(matches!(
+            batches.observe(parse(":bob!u@h PRIVMSG #chan :live")),
+            Observed::Unbatched(_)
+        ));
+        assert!(matches!(
+            batches.observe(parse("@batch=abc :alice!u@h PRIVMSG #chan :two")),
+            Observed::Held
+        ));
+        match batches.observe(parse(":server BATCH -abc")) {
+            Observed::Finished(batch) => {
+                assert_eq!(batch.kind, "chathistory");
+                assert_eq!(batch.params, vec!["#chan".to_string()]);
+                assert_eq!(batch.messages.len(), 2);
+            }
+            other => panic!("expected a finished batch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_nested_batch_folds_into_parent() {
+        let mut batches = Batches::default();
+        batches.observe(parse(":server BATCH +outer chathistory #chan"));
+        batches.observe(parse(
+            "@batch=outer :server BATCH +inner draft/multiline #chan",
+        ));
+        batches.observe(parse("@batch=inner :alice!u@h PRIVMSG #chan :a"));
+        batches.observe(parse("@batch=inner :alice!u@h PRIVMSG #chan :b"));
+        assert!(matches!(
+            batches.observe(parse("@batch=outer :server BATCH -inner")),
+            Observed::Held
+        ));
+        match batches.observe(parse(":server BATCH -outer")) {
+            Observed::Finished(batch) => assert_eq!(batch.messages.len(), 2),
+            other => panic!("expected a finished batch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unknown_batch_reference_passes_through() {
+        let mut batches = Batches::default();
+        let message = parse("@batch=nope :alice!u@h PRIVMSG #chan :hi");
+        assert!(matches!(batches.observe(message), Observed::Unbatched(_)));
+    }
+}
@@ -0,0 +1,310 @@
+//! IRCv3 `echo-message`: our own lines are shown once the server relays them.
+//!
+//! Every PRIVMSG/NOTICE we send is tracked as pending until the server echoes
+//! it back to us. Echoes arrive in the order the lines were sent, so the oldest
+//! pending line for the echo's target is the one confirmed. Lines that the
+//! server refuses (401/403/404) or never echoes fail instead.
+//!
+//! A 401 or 403 may just as well answer a WHOIS or another command, so an
+//! error only fails a line when `labeled-response` ties it to that line.
+//! Without labels only 404, which nothing but a message to a channel gets,
+//! fails the oldest line for its channel; other refused lines time out.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use slirc_proto::{Command, Message};
+
+use crate::backend::handlers::message_tags;
+use crate::irc_name::CaseMapping;
+use crate::protocol::GuiEvent;
+
+/// Capability name
+pub const ECHO_MESSAGE_CAP: &str = "echo-message";
+
+/// Capability that tags the server's replies with the label of our command
+pub const LABELED_RESPONSE_CAP: &str = "labeled-response";
+
+/// How long to wait for an echo before giving up on a line
+const ECHO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A line we sent and haven't seen echoed yet
+#[derive(Debug)]
+struct PendingEcho {
+    id: u64,
+    target: String,
+    /// `labeled-response` label the line was sent with
+    label: Option<String>,
+    sent_at: Instant,
+}
+
+/// Our own lines waiting for their echo
+#[derive(Debug, Default)]
+pub struct PendingEchoes {
+    pending: VecDeque<PendingEcho>,
+    /// Counter used to make ids unique
+    next_id: u64,
+}
+
+impl PendingEchoes {
+    /// Track a line just queued for `target`, returning its id
+    pub fn push(&mut self, target: &str) -> u64 {
+        self.next_id += 1;
+        self.pending.push_back(PendingEcho {
+            id: self.next_id,
+            target: target.to_string(),
+            label: None,
+            sent_at: Instant::now(),
+        });
+        self.next_id
+    }
+
+    /// Label to send line `id` with, so replies to it can be told apart
+    pub fn label(&mut self, id: u64) -> Option<String> {
+        let echo = self.pending.iter_mut().find(|echo| echo.id == id)?;
+        let label = format!("echo{}", id);
+        echo.label = Some(label.clone());
+        Some(label)
+    }
+
+    /// Settle the oldest pending line if `message` is its echo. Nicks and
+    /// targets compare under the server's `mapping`.
+    pub fn observe_echo(
+        &mut self,
+        message: &Message,
+        current_nick: &str,
+        mapping: CaseMapping,
+    ) -> Option<GuiEvent> {
+        let (target, text) = match &message.command {
+            Command::PRIVMSG(target, text) | Command::NOTICE(target, text) => (target, text),
+            _ => return None,
+        };
+        if !message
+            .source_nickname()
+            .is_some_and(|nick| mapping.names_equal(nick, current_nick))
+        {
+            return None;
+        }
+        let tags = message_tags(message);
+        let echo = match &tags.label {
+            Some(label) => self.take_labeled(label)?,
+            None => self.take_oldest(target, mapping)?,
+        };
+        Some(GuiEvent::OwnMessageEchoed {
+            id: echo.id,
+            target: echo.target,
+            text: text.clone(),
+            tags,
+        })
+    }
+
+    /// Fail the line a "can't send" error answers
+    pub fn observe_error(&mut self, message: &Message, mapping: CaseMapping) -> Option<GuiEvent> {
+        // 401 ERR_NOSUCHNICK, 403 ERR_NOSUCHCHANNEL, 404 ERR_CANNOTSENDTOCHAN:
+        // <client> <target> :<reason>
+        let Command::Response(code, args) = &message.command else {
+            return None;
+        };
+        if !matches!(code.code(), 401 | 403 | 404) || args.len() < 3 {
+            return None;
+        }
+        let echo = match message_tags(message).label {
+            Some(label) => self.take_labeled(&label)?,
+            // Unlabeled, only a 404 surely answers a message, and only an
+            // unlabeled line can be waiting for an unlabeled reply
+            None if code.code() == 404 => {
+                let oldest = self
+                    .pending
+                    .iter()
+                    .find(|echo| mapping.names_equal(&echo.target, &args[1]))?;
+                if oldest.label.is_some() {
+                    return None;
+                }
+                self.take_oldest(&args[1], mapping)?
+            }
+            None => return None,
+        };
+        Some(GuiEvent::OwnMessageFailed {
+            id: echo.id,
+            target: echo.target,
+            reason: args[args.len() - 1].clone(),
+        })
+    }
+
+    /// Fail every line that has waited longer than the timeout
+    pub fn expire(&mut self) -> Vec<GuiEvent> {
+        let now = Instant::now();
+        let mut failed = Vec::new();
+        while let Some(echo) = self.pending.front() {
+            if now.duration_since(echo.sent_at) < ECHO_TIMEOUT {
+                break;
+            }
+            if let Some(echo) = self.pending.pop_front() {
+                failed.push(GuiEvent::OwnMessageFailed {
+                    id: echo.id,
+                    target: echo.target,
+                    reason: "No echo from server".to_string(),
+                });
+            }
+        }
+        failed
+    }
+
+    /// Fail every pending line (the connection is gone)
+    pub fn fail_all(&mut self, reason: &str) -> Vec<GuiEvent> {
+        self.pending
+            .drain(..)
+            .map(|echo| GuiEvent::OwnMessageFailed {
+                id: echo.id,
+                target: echo.target,
+                reason: reason.to_string(),
+            })
+            .collect()
+    }
+
+    /// When the oldest pending line times out
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.front().map(|echo| echo.sent_at + ECHO_TIMEOUT)
+    }
+
+    /// Remove and return the oldest pending line for `target`
+    fn take_oldest(&mut self, target: &str, mapping: CaseMapping) -> Option<PendingEcho> {
+        let index = self
+            .pending
+            .iter()
+            .position(|echo| mapping.names_equal(&echo.target, target))?;
+        self.pending.remove(index)
+    }
+
+    /// Remove and return the pending line sent with `label`
+    fn take_labeled(&mut self, label: &str) -> Option<PendingEcho> {
+        let index = self
+            .pending
+            .iter()
+            .position(|echo| echo.label.as_deref() == Some(label))?;
+        self.pending.remove(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_util::parse;
+
+    #[test]
+    fn test_echo_confirms_oldest_for_target() {
+        let mut echoes = PendingEchoes::default();
+        let first = echoes.push("#chan");
+        let other = echoes.push("bob");
+        let second = echoes.push("#chan");
+
+        let event = echoes.observe_echo(
+            &parse(":me!u@h PRIVMSG #chan :hello"),
+            "me",
+            CaseMapping::Rfc1459,
+        );
+        assert!(matches!(event, Some(GuiEvent::OwnMessageEchoed { id, .. }) if id == first));
+        let event = echoes.observe_echo(
+            &parse(":me!u@h PRIVMSG #CHAN :again"),
+            "me",
+            CaseMapping::Rfc1459,
+        );
+        assert!(matches!(event, Some(GuiEvent::OwnMessageEchoed { id, .. }) if id == second));
+        let event = echoes.observe_echo(
+            &parse(":me!u@h PRIVMSG bob :hi"),
+            "me",
+            CaseMapping::Rfc1459,
+        );
+        assert!(matches!(event, Some(GuiEvent::OwnMessageEchoed { id, .. }) if id == other));
+        assert!(echoes.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_echo_matches_under_casemapping() {
+        let mut echoes = PendingEchoes::default();
+        let id = echoes.push("#chan[1]");
+        let message = parse(":Me[m]!u@h PRIVMSG #CHAN{1} :hello");
+        assert!(matches!(
+            echoes.observe_echo(&message, "me{m}", CaseMapping::Rfc1459),
+            Some(GuiEvent::OwnMessageEchoed { id: echoed, .. }) if echoed == id
+        ));
+    }
+
+    #[test]
+    fn test_other_senders_are_not_echoes() {
+        let mut echoes = PendingEchoes::default();
+        echoes.push("#chan");
+        let message = parse(":alice!u@h PRIVMSG #chan :hello");
+        assert!(echoes
+            .observe_echo(&message, "me", CaseMapping::Rfc1459)
+            .is_none());
+        assert!(echoes.next_deadline().is_some());
+    }
+
+    #[test]
+    fn test_cannot_send_fails_pending_line() {
+        let mut echoes = PendingEchoes::default();
+        let id = echoes.push("#quiet");
+        let message = parse(":server 404 me #quiet :Cannot send to channel (+m)");
+        match echoes.observe_error(&message, CaseMapping::Rfc1459) {
+            Some(GuiEvent::OwnMessageFailed {
+                id: failed, reason, ..
+            }) => {
+                assert_eq!(failed, id);
+                assert_eq!(reason, "Cannot send to channel (+m)");
+            }
+            other => panic!("expected a failure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unlabeled_no_such_nick_ignored() {
+        let mut echoes = PendingEchoes::default();
+        echoes.push("bob");
+        // Could answer a WHOIS just as well as our message
+        let message = parse(":server 401 me bob :No such nick/channel");
+        assert!(echoes
+            .observe_error(&message, CaseMapping::Rfc1459)
+            .is_none());
+        assert!(echoes.next_deadline().is_some());
+    }
+
+    #[test]
+    fn test_labels_tie_errors_to_lines() {
+        let mut echoes = PendingEchoes::default();
+        let first = echoes.push("bob");
+        let second = echoes.push("bob");
+        assert_eq!(echoes.label(second).as_deref(), Some("echo2"));
+        echoes.label(first);
+
+        // An unrelated error, unlabeled or with another label, fails nothing
+        let message = parse(":server 401 me bob :No such nick/channel");
+        assert!(echoes
+            .observe_error(&message, CaseMapping::Rfc1459)
+            .is_none());
+        let message = parse("@label=whois7 :server 401 me bob :No such nick/channel");
+        assert!(echoes
+            .observe_error(&message, CaseMapping::Rfc1459)
+            .is_none());
+
+        let message = parse("@label=echo2 :server 401 me bob :No such nick/channel");
+        assert!(matches!(
+            echoes.observe_error(&message, CaseMapping::Rfc1459),
+            Some(GuiEvent::OwnMessageFailed { id, .. }) if id == second
+        ));
+        let message = parse("@label=echo1 :me!u@h PRIVMSG bob :hi");
+        assert!(matches!(
+            echoes.observe_echo(&message, "me", CaseMapping::Rfc1459),
+            Some(GuiEvent::OwnMessageEchoed { id, .. }) if id == first
+        ));
+    }
+
+    #[test]
+    fn test_fail_all_drains() {
+        let mut echoes = PendingEchoes::default();
+        echoes.push("#a");
+        echoes.push("#b");
+        assert_eq!(echoes.fail_all("Connection lost").len(), 2);
+        assert!(echoes.expire().is_empty());
+    }
+}
@@ -1,11 +1,35 @@
 //! Event handlers for backend actions and server messages.
 
+use super::batch::{Batches, FinishedBatch, Observed, BATCH_CAP};
 use super::connection;
-use super::state::{PendingRegistration, RegistrationState, SaslSubState, ServerCaps};
-use crate::protocol::{BackendAction, GuiEvent};
-use crossbeam_channel::Sender;
+use super::echo::{PendingEchoes, ECHO_MESSAGE_CAP, LABELED_RESPONSE_CAP};
+use super::history::{ChatHistory, CHATHISTORY_CAP};
+use super::keepalive::Keepalive;
+use super::mode_lists::ModeLists;
+use super::multiline::{build_batches, MultilineLimits, MULTILINE_CAP};
+use super::reconnect::{ConnectionParams, ReconnectState};
+use super::send_queue::SendQueue;
+use super::split::split_message;
+use super::state::{
+    ConnectionScoped, PendingRegistration, RegistrationState, SaslSubState, ServerCaps,
+};
+use super::whois::WhoisReplies;
+use crate::backend::sender::EventSender;
+use crate::channel_modes::{batch_mode_changes, mode_line};
+use crate::config::parse_server_address;
+use crate::protocol::{
+    BackendAction, GuiEvent, MessageTags, REACT_TAG, REPLY_TAG, TYPING_TAG, UNREACT_TAG,
+};
+use chrono::{SecondsFormat, Utc};
 use slirc_proto::sasl::{encode_plain, SaslMechanism};
-use slirc_proto::{CapSubCommand, Command, Message, Transport};
+use slirc_proto::{CapSubCommand, Command, Message, Tag, Transport};
+use std::time::{Duration, Instant};
+
+/// Capability for tags on messages, including client-only ones like `+draft/reply`
+const MESSAGE_TAGS_CAP: &str = "message-tags";
+
+/// Capability for read markers shared between our clients (`MARKREAD`)
+const READ_MARKER_CAP: &str = "draft/read-marker";
 
 /// Handle a backend action from the GUI
 #[allow(clippy::too_many_arguments)]
@@ -13,154 +37,154 @@ pub async fn handle_backend_action(
     action: BackendAction,
     transport: &mut Option<Transport>,
     current_nick: &mut String,
-    last_connection_params: &mut Option<(String, u16, String, String, String, bool, bool)>,
+    last_connection_params: &mut Option<ConnectionParams>,
+    reconnect: &mut ReconnectState,
+    keepalive: &mut Keepalive,
+    send_queue: &mut SendQueue,
+    conn: &mut ConnectionScoped,
     reg_state: &mut RegistrationState,
     server_caps: &mut ServerCaps,
     pending_reg: &mut Option<PendingRegistration>,
-    event_tx: &Sender<GuiEvent>,
+    event_tx: &EventSender,
 ) {
     match action {
         BackendAction::Connect {
             server,
             port,
+            fallback_servers,
             nickname,
+            alt_nicks,
             username,
             realname,
             use_tls,
             auto_reconnect,
+            ping_timeout_secs,
+            flood_burst,
+            flood_refill_ms,
             sasl_password,
         } => {
-            *current_nick = nickname.clone();
+            // Requested server first, then the rest of the network's servers
+            let default_port = if use_tls { 6697 } else { 6667 };
+            let mut servers = vec![(server, port)];
+            for addr in &fallback_servers {
+                let entry = parse_server_address(addr, default_port);
+                if !servers.contains(&entry) {
+                    servers.push(entry);
+                }
+            }
 
             // Save connection parameters for potential reconnect
-            *last_connection_params = Some((
-                server.clone(),
-                port,
-                nickname.clone(),
-                username.clone(),
-                realname.clone(),
+            let params = ConnectionParams {
+                servers,
+                nickname,
+                alt_nicks,
+                username,
+                realname,
                 use_tls,
                 auto_reconnect,
-            ));
-
-            // Reset CAP negotiation state
-            *reg_state = RegistrationState::Initial;
-            *server_caps = ServerCaps::default();
-            *pending_reg = Some(PendingRegistration {
-                nickname: nickname.clone(),
-                username: username.clone(),
-                realname: realname.clone(),
+                ping_timeout: Duration::from_secs(ping_timeout_secs),
+                flood_burst,
+                flood_refill: Duration::from_millis(flood_refill_ms),
                 sasl_password,
-            });
-
-            // Try to connect
-            let addr = format!("{}:{}", server, port);
-            let protocol = if use_tls { "TLS" } else { "TCP" };
-            let _ = event_tx.send(GuiEvent::RawMessage(format!(
-                "Connecting to {} via {}...",
-                addr, protocol
-            )));
-
-            match connection::establish_connection(&server, port, use_tls).await {
-                Ok(mut transport_inst) => {
-                    // Start IRCv3 CAP negotiation
-                    let _ = event_tx.send(GuiEvent::RawMessage(
-                        "Starting CAP negotiation...".to_string(),
-                    ));
+            };
 
-                    // Send CAP LS 302 (version 302 for modern features)
-                    let cap_ls = Message::from(Command::CAP(
-                        None,
-                        CapSubCommand::LS,
-                        Some("302".to_string()),
-                        None,
-                    ));
-                    if let Err(e) = transport_inst.write_message(&cap_ls).await {
-                        let _ =
-                            event_tx.send(GuiEvent::Error(format!("Failed to send CAP LS: {}", e)));
-                        return;
-                    }
+            // A fresh connection starts with a fresh backoff and no channels to restore
+            reconnect.cancel();
+            reconnect.channels.clear();
+            conn.reset("Disconnected", event_tx);
+            // The UI starts over from the configured nick
+            current_nick.clear();
+            *pending_reg = None;
 
-                    *reg_state = RegistrationState::CapLsSent;
-                    *transport = Some(transport_inst);
-                }
-                Err(e) => {
-                    let _ = event_tx.send(GuiEvent::Error(e));
-                }
+            let connected = connect(
+                &params,
+                0,
+                transport,
+                keepalive,
+                send_queue,
+                current_nick,
+                reg_state,
+                server_caps,
+                pending_reg,
+                event_tx,
+            )
+            .await;
+            if !connected && params.auto_reconnect {
+                schedule_reconnect(reconnect, event_tx);
             }
+            *last_connection_params = Some(params);
         }
 
         BackendAction::Disconnect => {
             if let Some(ref mut t) = transport {
-                let quit_msg = Message::quit_with_message("Leaving");
-                let _ = t.write_message(&quit_msg).await;
+                // QUIT jumps the queue; anything still waiting is dropped
+                send_queue.clear();
+                send_queue.push_priority(Message::quit_with_message("Leaving"));
+                send_queue.flush(t, event_tx).await;
             }
             *transport = None;
             *last_connection_params = None; // Clear on manual disconnect
+            conn.reset("Disconnected", event_tx);
+            reconnect.cancel();
             let _ = event_tx.send(GuiEvent::Disconnected("User disconnected".into()));
         }
 
+        BackendAction::ReconnectNow => {
+            if transport.is_none() && last_connection_params.is_some() {
+                reconnect.next_attempt = Some(Instant::now());
+            }
+        }
+
+        BackendAction::CancelReconnect => {
+            if reconnect.next_attempt.is_some() {
+                reconnect.cancel();
+                let _ = event_tx.send(GuiEvent::Disconnected("Reconnect cancelled".into()));
+            }
+        }
+
         BackendAction::Join(channel) => {
-            if let Some(ref mut t) = transport {
-                let join_msg = Message::join(&channel);
-                if let Err(e) = t.write_message(&join_msg).await {
-                    let _ = event_tx.send(GuiEvent::Error(format!("Failed to join: {}", e)));
-                }
+            if transport.is_some() {
+                send_queue.push(Message::join(&channel));
             }
         }
 
         BackendAction::Part { channel, message } => {
-            if let Some(ref mut t) = transport {
+            if transport.is_some() {
                 let part_msg = if let Some(msg) = message {
                     Message::part_with_message(&channel, &msg)
                 } else {
                     Message::part(&channel)
                 };
-                if let Err(e) = t.write_message(&part_msg).await {
-                    let _ = event_tx.send(GuiEvent::Error(format!("Failed to part: {}", e)));
-                }
+                send_queue.push(part_msg);
             }
         }
 
         BackendAction::Nick(newnick) => {
-            if let Some(ref mut t) = transport {
-                let nick_msg = Message::nick(&newnick);
-                if let Err(e) = t.write_message(&nick_msg).await {
-                    let _ = event_tx.send(GuiEvent::Error(format!("Failed to send NICK: {}", e)));
-                } else {
-                    let old_nick = current_nick.clone();
-                    *current_nick = newnick.clone();
-                    let _ = event_tx.send(GuiEvent::NickChanged {
-                        old: old_nick,
-                        new: newnick.clone(),
-                    });
-                }
+            if transport.is_some() {
+                // current_nick changes when the server echoes the NICK back
+                send_queue.push(Message::nick(&newnick));
             } else {
                 let _ = event_tx.send(GuiEvent::Error("Not connected".into()));
             }
         }
 
         BackendAction::Whois(target) => {
-            if let Some(ref mut t) = transport {
-                let whois =
-                    Message::from(slirc_proto::command::Command::WHOIS(None, target.clone()));
-                if let Err(e) = t.write_message(&whois).await {
-                    let _ = event_tx.send(GuiEvent::Error(format!("Failed to send WHOIS: {}", e)));
-                }
+            if transport.is_some() {
+                send_queue.push(Message::from(slirc_proto::command::Command::WHOIS(
+                    None,
+                    target.clone(),
+                )));
             } else {
                 let _ = event_tx.send(GuiEvent::Error("Not connected".into()));
             }
         }
 
         BackendAction::SetTopic { channel, topic } => {
-            if let Some(ref mut t) = transport {
-                let topic_cmd = Message::from(slirc_proto::command::Command::TOPIC(
+            if transport.is_some() {
+                send_queue.push(Message::from(slirc_proto::command::Command::TOPIC(
                     channel.clone(),
                     Some(topic.clone()),
-                ));
-                if let Err(e) = t.write_message(&topic_cmd).await {
-                    let _ = event_tx.send(GuiEvent::Error(format!("Failed to set topic: {}", e)));
-                }
+                )));
             }
         }
 
@@ -169,15 +193,13 @@ pub async fn handle_backend_action(
             nick,
             reason,
         } => {
-            if let Some(ref mut t) = transport {
+            if transport.is_some() {
                 let kick_msg = if let Some(r) = reason {
                     Message::kick_with_reason(&channel, &nick, &r)
                 } else {
                     Message::kick(&channel, &nick)
                 };
-                if let Err(e) = t.write_message(&kick_msg).await {
-                    let _ = event_tx.send(GuiEvent::Error(format!("Failed to kick: {}", e)));
-                }
+                send_queue.push(kick_msg);
             }
         }
 
@@ -186,13 +208,10 @@ pub async fn handle_backend_action(
             nick,
             mode,
         } => {
-            if let Some(ref mut t) = transport {
+            if transport.is_some() {
                 // Create a raw MODE command with args: MODE <channel> <mode> <nick>
                 if let Ok(mode_msg) = Message::new(None, "MODE", vec![&channel, &mode, &nick]) {
-                    if let Err(e) = t.write_message(&mode_msg).await {
-                        let _ = event_tx
-                            .send(GuiEvent::Error(format!("Failed to set user mode: {}", e)));
-                    }
+                    send_queue.push(mode_msg);
                 }
             }
         }
@@ -204,62 +223,482 @@ pub async fn handle_backend_action(
                 } else {
                     Message::quit()
                 };
-                let _ = t.write_message(&quit_msg).await;
+                // QUIT jumps the queue; anything still waiting is dropped
+                send_queue.clear();
+                send_queue.push_priority(quit_msg);
+                send_queue.flush(t, event_tx).await;
             }
             *transport = None;
             *last_connection_params = None; // Clear on manual quit
+            conn.reset("Disconnected", event_tx);
+            reconnect.cancel();
             let _ = event_tx.send(GuiEvent::Disconnected("User quit".into()));
         }
 
+        BackendAction::QueryModes { channel, lists } => {
+            if transport.is_some() {
+                if let Ok(mode_msg) = Message::new(None, "MODE", vec![&channel]) {
+                    send_queue.push(mode_msg);
+                }
+                // One list per request; not every server takes several
+                for mode in lists {
+                    let list = format!("+{}", mode);
+                    if let Ok(mode_msg) = Message::new(None, "MODE", vec![&channel, &list]) {
+                        send_queue.push(mode_msg);
+                    }
+                }
+            }
+        }
+
+        BackendAction::SetChannelModes { channel, changes } => {
+            if transport.is_some() {
+                for batch in batch_mode_changes(&changes, server_caps.support.modes) {
+                    let (modes, args) = mode_line(batch);
+                    let params: Vec<&str> = [channel.as_str(), modes.as_str()]
+                        .into_iter()
+                        .chain(args)
+                        .collect();
+                    if let Ok(mode_msg) = Message::new(None, "MODE", params) {
+                        send_queue.push(mode_msg);
+                    }
+                }
+            } else {
+                let _ = event_tx.send(GuiEvent::Error("Not connected".into()));
+            }
+        }
+
         BackendAction::List => {
-            if let Some(ref mut t) = transport {
+            if transport.is_some() {
                 if let Ok(list_msg) = Message::new(None, "LIST", vec![]) {
-                    if let Err(e) = t.write_message(&list_msg).await {
-                        let _ = event_tx.send(GuiEvent::Error(format!(
-                            "Failed to request channel list: {}",
-                            e
-                        )));
-                    }
+                    send_queue.push(list_msg);
                 }
             } else {
                 let _ = event_tx.send(GuiEvent::Error("Not connected".into()));
             }
         }
 
-        BackendAction::SendMessage { target, text } => {
-            if let Some(ref mut t) = transport {
-                let privmsg = Message::privmsg(&target, &text);
-                if let Err(e) = t.write_message(&privmsg).await {
-                    let _ = event_tx.send(GuiEvent::Error(format!("Failed to send: {}", e)));
+        BackendAction::SendMessage {
+            target,
+            text,
+            client_tags,
+        } => {
+            if transport.is_some() {
+                // Client tags are dropped by servers without message-tags
+                let client_tags = if server_caps.enabled.contains(MESSAGE_TAGS_CAP) {
+                    client_tags
                 } else {
-                    // Echo our own message to the UI
-                    let _ = event_tx.send(GuiEvent::MessageReceived {
-                        target: target.clone(),
-                        sender: current_nick.clone(),
-                        text,
+                    Vec::new()
+                };
+                let own_tags = MessageTags {
+                    client: client_tags.clone(),
+                    ..Default::default()
+                };
+                // Split so that every line fits in 512 bytes once relayed
+                let budget = conn
+                    .self_prefix
+                    .text_budget(current_nick, "PRIVMSG", &target);
+                for piece in split_message(&text, budget) {
+                    let mut privmsg = Message::privmsg(&target, &piece);
+                    if !client_tags.is_empty() {
+                        privmsg.tags = Some(
+                            client_tags
+                                .iter()
+                                .map(|(name, value)| Tag(name.clone(), Some(value.clone())))
+                                .collect(),
+                        );
+                    }
+                    let (sender, tags) = (current_nick.clone(), own_tags.clone());
+                    show_own_line(
+                        &target,
+                        sender,
+                        piece,
+                        tags,
+                        Some(&mut privmsg),
+                        server_caps,
+                        &mut conn.echoes,
+                        event_tx,
+                    );
+                    send_queue.push(privmsg);
+                }
+            }
+        }
+
+        BackendAction::SendNotice { target, text } => {
+            if transport.is_some() {
+                let budget = conn
+                    .self_prefix
+                    .text_budget(current_nick, "NOTICE", &target);
+                for piece in split_message(&text, budget) {
+                    let Ok(mut notice) = Message::new(None, "NOTICE", vec![&target, &piece]) else {
+                        continue;
+                    };
+                    let (sender, tags) = (format!("-{}-", current_nick), MessageTags::default());
+                    show_own_line(
+                        &target,
+                        sender,
+                        piece,
+                        tags,
+                        Some(&mut notice),
+                        server_caps,
+                        &mut conn.echoes,
+                        event_tx,
+                    );
+                    send_queue.push(notice);
+                }
+            }
+        }
+
+        BackendAction::SendMultiline {
+            target,
+            lines,
+            client_tags,
+        } => {
+            if transport.is_some() {
+                // Client tags are dropped by servers without message-tags
+                let client_tags = if server_caps.enabled.contains(MESSAGE_TAGS_CAP) {
+                    client_tags
+                } else {
+                    Vec::new()
+                };
+                // They are sent and shown once, with the first line
+                let mut line_tags: Vec<Tag> = client_tags
+                    .iter()
+                    .map(|(name, value)| Tag(name.clone(), Some(value.clone())))
+                    .collect();
+                let mut own_tags = Some(MessageTags {
+                    client: client_tags,
+                    ..Default::default()
+                });
+                let budget = conn
+                    .self_prefix
+                    .text_budget(current_nick, "PRIVMSG", &target);
+                let multiline = server_caps.multiline.filter(|_| {
+                    server_caps.enabled.contains(BATCH_CAP)
+                        && server_caps.enabled.contains(MULTILINE_CAP)
+                });
+                if let Some(limits) = multiline {
+                    // One logical message; the server keeps the lines together
+                    for mut batch in build_batches(&target, &lines, budget, limits) {
+                        // Client tags go on the opening BATCH, not its lines
+                        if let Some(start) = batch.first_mut().filter(|_| !line_tags.is_empty()) {
+                            start.tags = Some(std::mem::take(&mut line_tags));
+                        }
+                        for message in &batch {
+                            if let Command::PRIVMSG(_, text) = &message.command {
+                                let (sender, text) = (current_nick.clone(), text.clone());
+                                let tags = own_tags.take().unwrap_or_default();
+                                // The batch as a whole would carry the label
+                                show_own_line(
+                                    &target,
+                                    sender,
+                                    text,
+                                    tags,
+                                    None,
+                                    server_caps,
+                                    &mut conn.echoes,
+                                    event_tx,
+                                );
+                            }
+                        }
+                        send_queue.push_batch(batch);
+                    }
+                } else {
+                    // One PRIVMSG per line, paced by the send queue; blank
+                    // lines can't be sent on their own
+                    for line in lines.into_iter().filter(|line| !line.is_empty()) {
+                        for piece in split_message(&line, budget) {
+                            let mut privmsg = Message::privmsg(&target, &piece);
+                            if !line_tags.is_empty() {
+                                privmsg.tags = Some(std::mem::take(&mut line_tags));
+                            }
+                            let sender = current_nick.clone();
+                            let tags = own_tags.take().unwrap_or_default();
+                            show_own_line(
+                                &target,
+                                sender,
+                                piece,
+                                tags,
+                                Some(&mut privmsg),
+                                server_caps,
+                                &mut conn.echoes,
+                                event_tx,
+                            );
+                            send_queue.push(privmsg);
+                        }
+                    }
+                }
+            }
+        }
+
+        BackendAction::React {
+            target,
+            msgid,
+            reaction,
+            add,
+        } => {
+            // Reactions are client tags; without message-tags they can't be sent
+            if transport.is_some() && server_caps.enabled.contains(MESSAGE_TAGS_CAP) {
+                if let Ok(mut tagmsg) = Message::new(None, "TAGMSG", vec![&target]) {
+                    let tag = if add { REACT_TAG } else { UNREACT_TAG };
+                    tagmsg.tags = Some(vec![
+                        Tag(REPLY_TAG.to_string(), Some(msgid.clone())),
+                        Tag(tag.to_string(), Some(reaction.clone())),
+                    ]);
+                    send_queue.push(tagmsg);
+                    // With echo-message our reaction comes back like anyone else's
+                    if !server_caps.enabled.contains(ECHO_MESSAGE_CAP) {
+                        let _ = event_tx.send(GuiEvent::Reaction {
+                            target,
+                            sender: current_nick.clone(),
+                            msgid,
+                            reaction,
+                            added: add,
+                        });
+                    }
+                }
+            }
+        }
+
+        BackendAction::Typing { target, state } => {
+            if transport.is_some() && server_caps.enabled.contains(MESSAGE_TAGS_CAP) {
+                if let Ok(mut tagmsg) = Message::new(None, "TAGMSG", vec![&target]) {
+                    tagmsg.tags = Some(vec![Tag(
+                        TYPING_TAG.to_string(),
+                        Some(state.as_str().to_string()),
+                    )]);
+                    send_queue.push(tagmsg);
+                }
+            }
+        }
+
+        BackendAction::MarkRead { target, time } => {
+            if transport.is_some() && server_caps.enabled.contains(READ_MARKER_CAP) {
+                let timestamp = format!(
+                    "timestamp={}",
+                    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+                );
+                if let Ok(markread) = Message::new(None, "MARKREAD", vec![&target, &timestamp]) {
+                    send_queue.push(markread);
+                }
+            }
+        }
+
+        BackendAction::FetchHistory { target, anchor } => {
+            let supported = transport.is_some()
+                && server_caps.enabled.contains(BATCH_CAP)
+                && server_caps.enabled.contains(CHATHISTORY_CAP);
+            let request = if supported {
+                let max = server_caps.support.chathistory;
+                conn.history.request(&target, &anchor, max)
+            } else {
+                None
+            };
+            match request {
+                Some(message) => send_queue.push(message),
+                // Nothing to load: an empty reply stops the buffer asking
+                None => {
+                    let _ = event_tx.send(GuiEvent::History {
+                        target,
+                        messages: Vec::new(),
                     });
                 }
             }
         }
     }
+
+    // Send whatever the flood limit allows right away; the rest is sent by
+    // the connection loop as the bucket refills
+    if let Some(ref mut t) = transport {
+        send_queue.flush(t, event_tx).await;
+    }
+}
+
+/// Show one of our own lines in the UI. With `echo-message` it stays pending
+/// until the server relays it back; otherwise it is shown as sent right away.
+/// With `labeled-response`, `line` (the message about to be sent) is labeled
+/// so that errors can be matched to it.
+#[allow(clippy::too_many_arguments)]
+fn show_own_line(
+    target: &str,
+    sender: String,
+    text: String,
+    tags: MessageTags,
+    line: Option<&mut Message>,
+    server_caps: &ServerCaps,
+    echoes: &mut PendingEchoes,
+    event_tx: &EventSender,
+) {
+    if server_caps.enabled.contains(ECHO_MESSAGE_CAP) {
+        let id = echoes.push(target);
+        if let Some(line) = line.filter(|_| server_caps.enabled.contains(LABELED_RESPONSE_CAP)) {
+            if let Some(label) = echoes.label(id) {
+                line.tags
+                    .get_or_insert_with(Vec::new)
+                    .push(Tag("label".to_string(), Some(label)));
+            }
+        }
+        let _ = event_tx.send(GuiEvent::OwnMessagePending {
+            id,
+            target: target.to_string(),
+            sender,
+            text,
+            tags,
+        });
+    } else {
+        let _ = event_tx.send(GuiEvent::MessageReceived {
+            target: target.to_string(),
+            sender,
+            text,
+            time: Utc::now(),
+            tags,
+        });
+    }
 }
 
-/// Handle a message received from the IRC server
+/// Open a connection to the server for `attempt` (rotating through
+/// `params.servers`) and start CAP negotiation.
+/// Returns false if the connection could not be established.
+#[allow(clippy::too_many_arguments)]
+pub async fn connect(
+    params: &ConnectionParams,
+    attempt: u32,
+    transport: &mut Option<Transport>,
+    keepalive: &mut Keepalive,
+    send_queue: &mut SendQueue,
+    current_nick: &mut String,
+    reg_state: &mut RegistrationState,
+    server_caps: &mut ServerCaps,
+    pending_reg: &mut Option<PendingRegistration>,
+    event_tx: &EventSender,
+) -> bool {
+    let (server, port) = params.server_for_attempt(attempt);
+    // A reconnect may register with a different nick than the last one the
+    // server confirmed, which is the one the UI still shows
+    let known_nick = match pending_reg.take() {
+        Some(pr) => pr.known_nick,
+        None if !current_nick.is_empty() => current_nick.clone(),
+        None => params.nickname.clone(),
+    };
+    *current_nick = params.nickname.clone();
+
+    // Reset CAP negotiation state
+    *reg_state = RegistrationState::Initial;
+    *server_caps = ServerCaps::default();
+    *pending_reg = Some(PendingRegistration {
+        nickname: params.nickname.clone(),
+        username: params.username.clone(),
+        realname: params.realname.clone(),
+        sasl_password: params.sasl_password.clone(),
+        nick_fallback: params.nick_fallback(),
+        known_nick,
+    });
+
+    // Try to connect
+    let addr = format!("{}:{}", server, port);
+    let protocol = if params.use_tls { "TLS" } else { "TCP" };
+    let _ = event_tx.send(GuiEvent::RawMessage(format!(
+        "Connecting to {} via {}...",
+        addr, protocol
+    )));
+
+    match connection::establish_connection(server, *port, params.use_tls).await {
+        Ok(mut transport_inst) => {
+            // Start IRCv3 CAP negotiation
+            let _ = event_tx.send(GuiEvent::RawMessage(
+                "Starting CAP negotiation...".to_string(),
+            ));
+
+            // Send CAP LS 302 (version 302 for modern features)
+            let cap_ls = Message::from(Command::CAP(
+                None,
+                CapSubCommand::LS,
+                Some("302".to_string()),
+                None,
+            ));
+            if let Err(e) = transport_inst.write_message(&cap_ls).await {
+                let _ = event_tx.send(GuiEvent::Error(format!("Failed to send CAP LS: {}", e)));
+                return false;
+            }
+
+            *reg_state = RegistrationState::CapLsSent;
+            *transport = Some(transport_inst);
+            keepalive.reset(params.ping_timeout, Instant::now());
+            send_queue.reset(params.flood_burst, params.flood_refill);
+            true
+        }
+        Err(e) => {
+            let _ = event_tx.send(GuiEvent::Error(e));
+            false
+        }
+    }
+}
+
+/// Schedule the next automatic reconnect and tell the UI when it will happen
+pub fn schedule_reconnect(reconnect: &mut ReconnectState, event_tx: &EventSender) {
+    let (attempt, delay) = reconnect.schedule();
+    let _ = event_tx.send(GuiEvent::Reconnecting { attempt, delay });
+}
+
+/// Handle a message received from the IRC server.
+/// Replies go through `send_queue`; only CAP/SASL/registration traffic, which
+/// happens once per connection, is written to `transport` directly.
 #[allow(clippy::too_many_arguments)]
 pub async fn handle_server_message(
     message: Message,
     transport: &mut Transport,
+    send_queue: &mut SendQueue,
+    echoes: &mut PendingEchoes,
+    batches: &mut Batches,
+    history: &mut ChatHistory,
+    whois: &mut WhoisReplies,
+    mode_lists: &mut ModeLists,
     current_nick: &mut String,
     reg_state: &mut RegistrationState,
     server_caps: &mut ServerCaps,
     pending_reg: &mut Option<PendingRegistration>,
-    event_tx: &Sender<GuiEvent>,
+    event_tx: &EventSender,
 ) {
+    // Lines inside a batch are held until it closes, then handled together
+    let message = match batches.observe(message) {
+        Observed::Unbatched(message) => message,
+        Observed::Held => return,
+        Observed::Finished(batch) => {
+            if let Some(event) = history.observe_batch(&batch, server_caps.support.case_mapping()) {
+                let _ = event_tx.send(event);
+            } else {
+                replay_batch(batch, echoes, current_nick, server_caps, event_tx);
+            }
+            return;
+        }
+    };
+
+    let mapping = server_caps.support.case_mapping();
+    if let Some(event) = history.observe_fail(&message, mapping) {
+        let _ = event_tx.send(event);
+        return;
+    }
+    if let Some(event) = whois.observe(&message, mapping) {
+        let _ = event_tx.send(event);
+    }
+    if let Some(event) = mode_lists.observe(&message, mapping) {
+        let _ = event_tx.send(event);
+    }
+
+    // With echo-message our own lines come back from the server: settle them
+    if server_caps.enabled.contains(ECHO_MESSAGE_CAP) {
+        if let Some(event) = echoes.observe_echo(&message, current_nick, mapping) {
+            let _ = event_tx.send(event);
+            return;
+        }
+        if let Some(event) = echoes.observe_error(&message, mapping) {
+            let _ = event_tx.send(event);
+        }
+    }
+
     match &message.command {
         // PING -> PONG
         Command::PING(server, _) => {
-            let pong = Message::pong(server);
-            let _ = transport.write_message(&pong).await;
+            send_queue.push_priority(Message::pong(server));
         }
 
         // CAP LS/ACK/NAK responses during negotiation
@@ -336,8 +775,70 @@ pub async fn handle_server_message(
             let _ = event_tx.send(GuiEvent::RawMessage(format!("Logged in as {}", account)));
         }
 
+        // ERR_UNAVAILRESOURCE (437) for a channel: it is juped or held after a split
+        Command::Response(code, args)
+            if code.code() == 437
+                && args
+                    .get(1)
+                    .is_some_and(|target| server_caps.support.is_channel(target)) =>
+        {
+            let reason = args
+                .last()
+                .filter(|_| args.len() > 2)
+                .map_or("Channel temporarily unavailable", |s| s.as_str());
+            let _ = event_tx.send(GuiEvent::Error(format!(
+                "Cannot join {}: {}",
+                args[1], reason
+            )));
+        }
+
+        // ERR_ERRONEUSNICKNAME (432), ERR_NICKNAMEINUSE (433), ERR_NICKCOLLISION (436),
+        // ERR_UNAVAILRESOURCE (437) for a nick
+        Command::Response(code, args) if matches!(code.code(), 432 | 433 | 436 | 437) => {
+            let rejected = args.get(1).cloned().unwrap_or_default();
+            let reason = args
+                .last()
+                .cloned()
+                .unwrap_or_else(|| "Nickname unavailable".to_string());
+
+            if *reg_state == RegistrationState::Registered {
+                let _ = event_tx.send(GuiEvent::NickError {
+                    nick: rejected,
+                    reason,
+                });
+            } else if let Some(pr) = pending_reg.as_mut() {
+                // Registration can't complete without a nick: try the next one
+                if let Some(nick) = pr.nick_fallback.next_nick() {
+                    let _ = event_tx.send(GuiEvent::RawMessage(format!(
+                        "Nickname {} unavailable ({}), trying {}",
+                        rejected, reason, nick
+                    )));
+                    let _ = transport.write_message(&Message::nick(&nick)).await;
+                    pr.nickname = nick.clone();
+                    *current_nick = nick;
+                } else {
+                    let _ = event_tx.send(GuiEvent::Error(format!(
+                        "No usable nickname: {} ({})",
+                        rejected, reason
+                    )));
+                    let quit = Message::quit_with_message("No usable nickname");
+                    let _ = transport.write_message(&quit).await;
+                }
+            }
+        }
+
         // RPL_WELCOME (001) - Registration complete
-        Command::Response(code, _) if code.code() == 1 => {
+        Command::Response(code, args) if code.code() == 1 => {
+            // The nick we registered with may be a fallback, or truncated by the server
+            if let (Some(pr), Some(nick)) = (pending_reg.as_ref(), args.first()) {
+                if &pr.known_nick != nick {
+                    let _ = event_tx.send(GuiEvent::NickChanged {
+                        old: pr.known_nick.clone(),
+                        new: nick.clone(),
+                    });
+                }
+                *current_nick = nick.clone();
+            }
             *reg_state = RegistrationState::Registered;
             *pending_reg = None;
             let _ = event_tx.send(GuiEvent::Connected);
@@ -346,15 +847,46 @@ pub async fn handle_server_message(
         // All other messages: route through handler module
         _ => {
             // Route message and potentially update current_nick
-            if let Some(new_nick) =
-                super::super::handlers::route_message(&message, current_nick, event_tx)
-            {
+            if let Some(new_nick) = super::super::handlers::route_message(
+                &message,
+                current_nick,
+                &mut server_caps.support,
+                event_tx,
+            ) {
                 *current_nick = new_nick;
             }
         }
     }
 }
 
+/// Handle the lines of a finished batch that isn't scrollback (netsplit,
+/// multiline, ...) as if they had arrived one by one
+fn replay_batch(
+    batch: FinishedBatch,
+    echoes: &mut PendingEchoes,
+    current_nick: &mut String,
+    server_caps: &mut ServerCaps,
+    event_tx: &EventSender,
+) {
+    for message in batch.messages {
+        if server_caps.enabled.contains(ECHO_MESSAGE_CAP) {
+            let mapping = server_caps.support.case_mapping();
+            if let Some(event) = echoes.observe_echo(&message, current_nick, mapping) {
+                let _ = event_tx.send(event);
+                continue;
+            }
+        }
+        if let Some(new_nick) = super::super::handlers::route_message(
+            &message,
+            current_nick,
+            &mut server_caps.support,
+            event_tx,
+        ) {
+            *current_nick = new_nick;
+        }
+    }
+}
+
 /// Handle CAP negotiation messages
 #[allow(clippy::too_many_arguments)]
 async fn handle_cap_message(
@@ -365,7 +897,7 @@ async fn handle_cap_message(
     reg_state: &mut RegistrationState,
     server_caps: &mut ServerCaps,
     pending_reg: &Option<PendingRegistration>,
-    event_tx: &Sender<GuiEvent>,
+    event_tx: &EventSender,
 ) {
     match subcommand {
         CapSubCommand::LS => {
@@ -397,6 +929,10 @@ async fn handle_cap_message(
                         server_caps.sasl_mechanisms = vec![SaslMechanism::Plain];
                     }
                 }
+
+                if cap_name == MULTILINE_CAP {
+                    server_caps.multiline = cap_value.and_then(MultilineLimits::parse);
+                }
             }
 
             server_caps.cap_ls_more = is_multiline;
@@ -416,7 +952,14 @@ async fn handle_cap_message(
                     "multi-prefix",
                     "server-time",
                     "account-notify",
+                    "account-tag",
                     "away-notify",
+                    MESSAGE_TAGS_CAP,
+                    BATCH_CAP,
+                    CHATHISTORY_CAP,
+                    READ_MARKER_CAP,
+                    ECHO_MESSAGE_CAP,
+                    LABELED_RESPONSE_CAP,
                 ];
                 for cap in &desired_caps {
                     if server_caps.available.contains(*cap) {
@@ -424,6 +967,14 @@ async fn handle_cap_message(
                     }
                 }
 
+                // Multiline batches need batch too, and limits we understand
+                if server_caps.multiline.is_some()
+                    && server_caps.available.contains(BATCH_CAP)
+                    && server_caps.available.contains(MULTILINE_CAP)
+                {
+                    requested.push(MULTILINE_CAP);
+                }
+
                 // Request SASL only if we have a password
                 let want_sasl = pending_reg
                     .as_ref()
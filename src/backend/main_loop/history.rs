@@ -0,0 +1,256 @@
+//! IRCv3 `draft/chathistory`: fetching scrollback from the server.
+//!
+//! Requests are answered with a `chathistory` batch holding the messages, or
+//! with `FAIL CHATHISTORY` when the server refuses. Either way the UI gets a
+//! `GuiEvent::History` for the target so it knows the request is done; an
+//! empty list means there is nothing older to load.
+
+use std::collections::VecDeque;
+
+use chrono::SecondsFormat;
+use slirc_proto::{Command, Message};
+
+use super::batch::FinishedBatch;
+use crate::backend::handlers::{message_tags, message_time};
+use crate::irc_name::CaseMapping;
+use crate::protocol::{GuiEvent, HistoryAnchor, HistoryMessage};
+
+/// Capability name
+pub const CHATHISTORY_CAP: &str = "draft/chathistory";
+
+/// Batch type the server wraps replies in
+const CHATHISTORY_BATCH: &str = "chathistory";
+
+/// Messages asked for per request, unless the server allows fewer
+const DEFAULT_LIMIT: usize = 50;
+
+/// Outstanding CHATHISTORY requests
+#[derive(Debug, Default)]
+pub struct ChatHistory {
+    /// Targets we asked about, oldest request first
+    requests: VecDeque<String>,
+}
+
+impl ChatHistory {
+    /// Build the request for `target` and remember that it is outstanding.
+    /// `max` is the server's `CHATHISTORY` ISUPPORT limit, if any.
+    pub fn request(
+        &mut self,
+        target: &str,
+        anchor: &HistoryAnchor,
+        max: Option<usize>,
+    ) -> Option<Message> {
+        let limit = max.map_or(DEFAULT_LIMIT, |max| DEFAULT_LIMIT.min(max));
+        let limit = limit.to_string();
+        let message = match anchor {
+            HistoryAnchor::Latest => {
+                Message::new(None, "CHATHISTORY", vec!["LATEST", target, "*", &limit])
+            }
+            HistoryAnchor::Before { msgid, time } => {
+                let reference = match msgid {
+                    Some(id) => format!("msgid={}", id),
+                    None => format!(
+                        "timestamp={}",
+                        time.to_rfc3339_opts(SecondsFormat::Millis, true)
+                    ),
+                };
+                Message::new(
+                    None,
+                    "CHATHISTORY",
+                    vec!["BEFORE", target, &reference, &limit],
+                )
+            }
+        }
+        .ok()?;
+        self.requests.push_back(target.to_string());
+        Some(message)
+    }
+
+    /// Turn a finished `chathistory` batch into the event for its target
+    pub fn observe_batch(
+        &mut self,
+        batch: &FinishedBatch,
+        mapping: CaseMapping,
+    ) -> Option<GuiEvent> {
+        if batch.kind != CHATHISTORY_BATCH {
+            return None;
+        }
+        let target = batch.params.first()?.clone();
+        if let Some(index) = self
+            .requests
+            .iter()
+            .position(|t| mapping.names_equal(t, &target))
+        {
+            self.requests.remove(index);
+        }
+        let messages = batch.messages.iter().filter_map(history_message).collect();
+        Some(GuiEvent::History { target, messages })
+    }
+
+    /// `FAIL CHATHISTORY <code> [<context>...] :<description>` ends the
+    /// request for the target named in its context with nothing, or the
+    /// oldest request if it names none of ours
+    pub fn observe_fail(&mut self, message: &Message, mapping: CaseMapping) -> Option<GuiEvent> {
+        let Command::Raw(cmd, args) = &message.command else {
+            return None;
+        };
+        if !cmd.eq_ignore_ascii_case("FAIL")
+            || args.first().map(String::as_str) != Some("CHATHISTORY")
+        {
+            return None;
+        }
+        let context = args
+            .get(2..args.len().saturating_sub(1))
+            .unwrap_or_default();
+        let index = self
+            .requests
+            .iter()
+            .position(|t| context.iter().any(|param| mapping.names_equal(t, param)))
+            .unwrap_or(0);
+        let target = self.requests.remove(index)?;
+        Some(GuiEvent::History {
+            target,
+            messages: Vec::new(),
+        })
+    }
+
+    /// Forget outstanding requests (new connection)
+    pub fn clear(&mut self) {
+        self.requests.clear();
+    }
+}
+
+/// A PRIVMSG or NOTICE from history, in the shape the UI shows it
+fn history_message(message: &Message) -> Option<HistoryMessage> {
+    let (sender, text) = match &message.command {
+        Command::PRIVMSG(_, text) => {
+            let sender = message.source_nickname().unwrap_or("unknown").to_string();
+            (sender, text)
+        }
+        Command::NOTICE(_, text) => {
+            let sender = message.source_nickname().unwrap_or("server");
+            (format!("-{}-", sender), text)
+        }
+        _ => return None,
+    };
+    Some(HistoryMessage {
+        sender,
+        text: text.clone(),
+        time: message_time(message),
+        tags: message_tags(message),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::main_loop::batch::{Batches, Observed};
+    use crate::backend::test_util::parse;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_request_lines() {
+        let mut history = ChatHistory::default();
+        let latest = history
+            .request("#chan", &HistoryAnchor::Latest, None)
+            .unwrap();
+        assert_eq!(
+            latest.to_string().trim_end(),
+            "CHATHISTORY LATEST #chan * 50"
+        );
+
+        let by_id = HistoryAnchor::Before {
+            msgid: Some("abc".into()),
+            time: Utc::now(),
+        };
+        let before = history.request("#chan", &by_id, None).unwrap();
+        assert_eq!(
+            before.to_string().trim_end(),
+            "CHATHISTORY BEFORE #chan msgid=abc 50"
+        );
+
+        let by_time = HistoryAnchor::Before {
+            msgid: None,
+            time: Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap(),
+        };
+        let before = history.request("#chan", &by_time, None).unwrap();
+        assert_eq!(
+            before.to_string().trim_end(),
+            "CHATHISTORY BEFORE #chan timestamp=2024-03-01T12:00:00.000Z 50"
+        );
+    }
+
+    #[test]
+    fn test_isupport_lowers_limit() {
+        let mut history = ChatHistory::default();
+        let latest = history
+            .request("#chan", &HistoryAnchor::Latest, Some(20))
+            .unwrap();
+        assert!(latest.to_string().trim_end().ends_with(" 20"));
+        let latest = history
+            .request("#chan", &HistoryAnchor::Latest, Some(500))
+            .unwrap();
+        assert!(latest.to_string().trim_end().ends_with(" 50"));
+    }
+
+    #[test]
+    fn test_batch_becomes_history_event() {
+        let mut history = ChatHistory::default();
+        history.request("#chan", &HistoryAnchor::Latest, None);
+        let mut batches = Batches::default();
+        batches.observe(parse(":server BATCH +h1 chathistory #chan"));
+        batches.observe(parse(
+            "@batch=h1;msgid=m1;time=2024-03-01T12:00:00.000Z :alice!u@h PRIVMSG #chan :hi",
+        ));
+        batches.observe(parse("@batch=h1;msgid=m2 :bob!u@h NOTICE #chan :note"));
+        let Observed::Finished(batch) = batches.observe(parse(":server BATCH -h1")) else {
+            panic!("batch should be finished");
+        };
+        match history.observe_batch(&batch, CaseMapping::Rfc1459) {
+            Some(GuiEvent::History { target, messages }) => {
+                assert_eq!(target, "#chan");
+                assert_eq!(messages.len(), 2);
+                assert_eq!(messages[0].tags.msgid.as_deref(), Some("m1"));
+                assert_eq!(messages[0].sender, "alice");
+                assert_eq!(messages[1].sender, "-bob-");
+            }
+            other => panic!("expected history, got {:?}", other),
+        }
+        assert!(history.requests.is_empty());
+    }
+
+    #[test]
+    fn test_fail_ends_request_it_names() {
+        let mut history = ChatHistory::default();
+        history.request("#first", &HistoryAnchor::Latest, None);
+        history.request("#second", &HistoryAnchor::Latest, None);
+        let fail = parse(":server FAIL CHATHISTORY INVALID_TARGET LATEST #first :No such channel");
+        match history.observe_fail(&fail, CaseMapping::Rfc1459) {
+            Some(GuiEvent::History { target, messages }) => {
+                assert_eq!(target, "#first");
+                assert!(messages.is_empty());
+            }
+            other => panic!("expected empty history, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_fail_ends_request_for_its_target() {
+        let mut history = ChatHistory::default();
+        history.request("#first", &HistoryAnchor::Latest, None);
+        history.request("#second", &HistoryAnchor::Latest, None);
+        let fail = parse(":server FAIL CHATHISTORY MESSAGE_ERROR LATEST #Second :Try later");
+        assert!(matches!(
+            history.observe_fail(&fail, CaseMapping::Rfc1459),
+            Some(GuiEvent::History { target, .. }) if target == "#second"
+        ));
+
+        // Without a target the oldest request is the best guess
+        let fail = parse(":server FAIL CHATHISTORY NEED_MORE_PARAMS :Missing parameters");
+        assert!(matches!(
+            history.observe_fail(&fail, CaseMapping::Rfc1459),
+            Some(GuiEvent::History { target, .. }) if target == "#first"
+        ));
+        assert!(history.observe_fail(&fail, CaseMapping::Rfc1459).is_none());
+    }
+}
@@ -0,0 +1,183 @@
+//! Client-initiated PING keepalive, lag measurement and dead-link detection.
+
+use std::time::{Duration, Instant};
+
+use slirc_proto::{Command, Message};
+
+use crate::config::DEFAULT_PING_TIMEOUT_SECS;
+
+/// How often we PING the server while registered
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default silence after which the link is considered dead
+pub const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(DEFAULT_PING_TIMEOUT_SECS);
+
+/// What the connection task should do after polling the keepalive
+#[derive(Debug, PartialEq)]
+pub enum KeepaliveCheck {
+    /// Nothing to do
+    Idle,
+    /// Send `PING <token>`
+    SendPing(String),
+    /// Nothing has been received for longer than the timeout
+    TimedOut(Duration),
+}
+
+/// Keepalive bookkeeping for a single connection
+#[derive(Debug)]
+pub struct Keepalive {
+    /// Silence after which the link is declared dead
+    timeout: Duration,
+    /// When we last received anything from the server
+    last_activity: Instant,
+    /// When we last sent a PING
+    last_ping: Instant,
+    /// Token and send time of the PING we are waiting on
+    pending: Option<(String, Instant)>,
+    /// Counter used to make PING tokens unique
+    counter: u64,
+}
+
+impl Default for Keepalive {
+    fn default() -> Self {
+        Self::new(DEFAULT_PING_TIMEOUT, Instant::now())
+    }
+}
+
+impl Keepalive {
+    /// Keepalive for a connection opened at `now`
+    pub fn new(timeout: Duration, now: Instant) -> Self {
+        Self {
+            timeout,
+            last_activity: now,
+            last_ping: now,
+            pending: None,
+            counter: 0,
+        }
+    }
+
+    /// Start over for a connection freshly opened at `now`
+    pub fn reset(&mut self, timeout: Duration, now: Instant) {
+        let counter = self.counter;
+        *self = Self::new(timeout, now);
+        self.counter = counter;
+    }
+
+    /// Record that something was received from the server at `now`
+    pub fn on_activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    /// If `message`, received at `now`, is the PONG for our outstanding
+    /// PING, return the round trip
+    pub fn observe_pong(&mut self, message: &Message, now: Instant) -> Option<Duration> {
+        let Command::PONG(first, second) = &message.command else {
+            return None;
+        };
+        let (token, sent) = self.pending.as_ref()?;
+        // Servers echo the token as the last parameter: `PONG <server> :<token>`
+        if first == token || second.as_deref() == Some(token.as_str()) {
+            let lag = now.saturating_duration_since(*sent);
+            self.pending = None;
+            Some(lag)
+        } else {
+            None
+        }
+    }
+
+    /// Check the link at `now`. PINGs are only sent when `can_ping` (i.e.
+    /// registered), but the timeout applies at any stage of the connection.
+    pub fn poll(&mut self, can_ping: bool, now: Instant) -> KeepaliveCheck {
+        let silence = now.saturating_duration_since(self.last_activity);
+        if silence >= self.timeout {
+            return KeepaliveCheck::TimedOut(silence);
+        }
+
+        if can_ping && now.saturating_duration_since(self.last_ping) >= self.ping_interval() {
+            self.counter += 1;
+            let token = format!("slirc-{}", self.counter);
+            self.pending = Some((token.clone(), now));
+            self.last_ping = now;
+            return KeepaliveCheck::SendPing(token);
+        }
+
+        KeepaliveCheck::Idle
+    }
+
+    /// When `poll` next has something to do
+    pub fn next_deadline(&self, can_ping: bool) -> Instant {
+        let timeout_at = self.last_activity + self.timeout;
+        if can_ping {
+            timeout_at.min(self.last_ping + self.ping_interval())
+        } else {
+            timeout_at
+        }
+    }
+
+    /// Ping often enough that at least one PING fits inside the timeout
+    fn ping_interval(&self) -> Duration {
+        PING_INTERVAL.min(self.timeout / 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ping_then_matching_pong_measures_lag() {
+        let start = Instant::now();
+        let mut keepalive = Keepalive::new(Duration::from_millis(200), start);
+        assert_eq!(keepalive.poll(true, start), KeepaliveCheck::Idle);
+
+        let later = start + Duration::from_millis(110);
+        keepalive.on_activity(later);
+        let KeepaliveCheck::SendPing(token) = keepalive.poll(true, later) else {
+            panic!("expected a PING");
+        };
+
+        let back = later + Duration::from_millis(25);
+        let other = Message::from(Command::PONG("irc.example.com".into(), Some("x".into())));
+        assert!(keepalive.observe_pong(&other, back).is_none());
+
+        let pong = Message::from(Command::PONG("irc.example.com".into(), Some(token)));
+        assert_eq!(
+            keepalive.observe_pong(&pong, back),
+            Some(Duration::from_millis(25))
+        );
+        // Only the first matching PONG counts
+        assert!(keepalive.observe_pong(&pong, back).is_none());
+    }
+
+    #[test]
+    fn test_no_ping_before_registration() {
+        let start = Instant::now();
+        let mut keepalive = Keepalive::new(Duration::from_millis(100), start);
+        let later = start + Duration::from_millis(60);
+        keepalive.on_activity(later);
+        assert_eq!(keepalive.poll(false, later), KeepaliveCheck::Idle);
+    }
+
+    #[test]
+    fn test_next_deadline_ignores_ping_before_registration() {
+        let keepalive = Keepalive::new(Duration::from_secs(120), Instant::now());
+        assert!(keepalive.next_deadline(true) < keepalive.next_deadline(false));
+    }
+
+    #[test]
+    fn test_silence_times_out() {
+        let start = Instant::now();
+        let mut keepalive = Keepalive::new(Duration::from_millis(50), start);
+        let later = start + Duration::from_millis(60);
+        assert_eq!(
+            keepalive.poll(true, later),
+            KeepaliveCheck::TimedOut(Duration::from_millis(60))
+        );
+
+        keepalive.reset(Duration::from_millis(50), later);
+        assert!(!matches!(
+            keepalive.poll(true, later),
+            KeepaliveCheck::TimedOut(_)
+        ));
+    }
+}
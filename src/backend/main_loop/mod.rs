@@ -1,102 +1,315 @@
 //! Backend main event loop with CAP negotiation and message routing.
+//!
+//! `run_backend` acts as a supervisor: it receives network-tagged actions from
+//! the UI and forwards each one to a dedicated connection task for that
+//! network, spawning the task on first use. Every task owns its own transport
+//! and registration state, so connections to different networks never
+//! interfere with each other.
+//!
+//! Nothing polls: each task sleeps in `tokio::select!` until an action, a
+//! server line or a timer (keepalive / reconnect) is ready, and every event
+//! sent to the UI wakes it with `request_repaint`.
 
+pub mod batch;
 pub mod connection;
+pub mod echo;
 pub mod handlers;
+pub mod history;
+pub mod keepalive;
+pub mod mode_lists;
+pub mod multiline;
+pub mod nick_fallback;
+pub mod reconnect;
+pub mod send_queue;
+pub mod split;
 pub mod state;
+pub mod whois;
 
 pub use state::{PendingRegistration, RegistrationState, ServerCaps};
 
-use crate::protocol::{BackendAction, GuiEvent};
+use crate::backend::sender::EventSender;
+use crate::protocol::{BackendAction, GuiEvent, NetworkAction, NetworkEvent, NetworkId};
 use crossbeam_channel::{Receiver, Sender};
-use slirc_proto::Transport;
-use std::time::Duration;
+use eframe::egui;
+use keepalive::{Keepalive, KeepaliveCheck};
+use reconnect::{ConnectionParams, ReconnectState};
+use send_queue::SendQueue;
+use slirc_proto::{Command, Message, Transport};
+use std::collections::HashMap;
+use std::time::Instant;
 use tokio::runtime::Runtime;
-use tokio::time::timeout;
+use tokio::sync::mpsc;
 
-/// Run the backend event loop on a tokio runtime
-pub fn run_backend(action_rx: Receiver<BackendAction>, event_tx: Sender<GuiEvent>) {
+/// Run the backend event loop on a tokio runtime.
+/// `repaint` is woken whenever an event is sent to the UI.
+pub fn run_backend(
+    action_rx: Receiver<NetworkAction>,
+    event_tx: Sender<NetworkEvent>,
+    repaint: egui::Context,
+) {
     // Create a Tokio runtime for this thread
     let rt = match Runtime::new() {
         Ok(rt) => rt,
         Err(e) => {
-            let _ = event_tx.send(GuiEvent::Error(format!(
-                "Failed to create Tokio runtime: {}",
-                e
-            )));
+            let _ = event_tx.send(NetworkEvent::new(
+                NetworkId::new(),
+                GuiEvent::Error(format!("Failed to create Tokio runtime: {}", e)),
+            ));
+            repaint.request_repaint();
             return;
         }
     };
 
     rt.block_on(async move {
-        let mut transport: Option<Transport> = None;
-        let mut current_nick = String::new();
+        // Bridge the UI's blocking channel into the runtime; the bridge ends
+        // (closing `bridge_rx`) once the UI drops its sender.
+        let (bridge_tx, mut bridge_rx) = mpsc::unbounded_channel::<NetworkAction>();
+        tokio::task::spawn_blocking(move || {
+            while let Ok(action) = action_rx.recv() {
+                if bridge_tx.send(action).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // One action channel per network connection task
+        let mut networks: HashMap<NetworkId, mpsc::UnboundedSender<BackendAction>> = HashMap::new();
+
+        // Dispatch actions from the UI to their network's task as they arrive
+        while let Some(NetworkAction { network, action }) = bridge_rx.recv().await {
+            let tx = networks
+                .entry(network.clone())
+                .or_insert_with(|| spawn_network(network.clone(), &event_tx, &repaint));
+            if let Err(mpsc::error::SendError(action)) = tx.send(action) {
+                // The task has exited; start a fresh one for this network
+                let new_tx = spawn_network(network.clone(), &event_tx, &repaint);
+                let _ = new_tx.send(action);
+                networks.insert(network, new_tx);
+            }
+        }
+        // UI is gone, shut the backend down
+    });
+}
+
+/// Spawn the connection task for a network and return its action sender
+fn spawn_network(
+    network: NetworkId,
+    event_tx: &Sender<NetworkEvent>,
+    repaint: &egui::Context,
+) -> mpsc::UnboundedSender<BackendAction> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let events = EventSender::new(network, event_tx.clone(), repaint.clone());
+    tokio::spawn(run_network(rx, events));
+    tx
+}
+
+/// What woke the connection task up
+enum Wake {
+    /// An action from the UI (`None` once the supervisor is gone)
+    Action(Option<BackendAction>),
+    /// A line from the server
+    Message(Message),
+    /// The server closed the connection, or reading failed
+    Closed(Option<String>),
+    /// A keepalive, reconnect or flood-control deadline passed
+    Timer,
+}
+
+/// Connection task for a single network
+async fn run_network(mut action_rx: mpsc::UnboundedReceiver<BackendAction>, event_tx: EventSender) {
+    let mut transport: Option<Transport> = None;
+    let mut current_nick = String::new();
 
-        // Connection state for auto-reconnect
-        let mut last_connection_params: Option<(String, u16, String, String, String, bool, bool)> = None;
+    // Connection state for auto-reconnect
+    let mut last_connection_params: Option<ConnectionParams> = None;
+    let mut reconnect = ReconnectState::default();
 
-        // CAP negotiation state machine
-        let mut reg_state = RegistrationState::Registered; // Start as registered (no connection)
-        let mut server_caps = ServerCaps::default();
-        let mut pending_reg: Option<PendingRegistration> = None;
+    // Client PINGs for lag measurement and dead-link detection
+    let mut keepalive = Keepalive::default();
 
-        loop {
-            // Check for actions from the UI (non-blocking)
-            while let Ok(action) = action_rx.try_recv() {
+    // Outgoing lines, rate-limited to stay under the server's flood limits
+    let mut send_queue = SendQueue::default();
+
+    // Our own prefix, pending echoes and replies being collected
+    let mut conn = ConnectionScoped::default();
+
+    // CAP negotiation state machine
+    let mut reg_state = RegistrationState::Registered; // Start as registered (no connection)
+    let mut server_caps = ServerCaps::default();
+    let mut pending_reg: Option<PendingRegistration> = None;
+
+    loop {
+        // Next timer: keepalive and flood control while connected, the pending
+        // reconnect otherwise
+        let deadline = if transport.is_some() {
+            let keepalive_at = keepalive.next_deadline(reg_state == RegistrationState::Registered);
+            Some(
+                [
+                    send_queue.next_ready(Instant::now()),
+                    conn.echoes.next_deadline(),
+                ]
+                .into_iter()
+                .flatten()
+                .fold(keepalive_at, Instant::min),
+            )
+        } else {
+            reconnect.next_attempt
+        };
+
+        let wake = tokio::select! {
+            action = action_rx.recv() => Wake::Action(action),
+            wake = read_next(&mut transport) => wake,
+            _ = sleep_until(deadline) => Wake::Timer,
+        };
+
+        let mut connection_lost = false;
+        match wake {
+            Wake::Action(Some(action)) => {
                 handlers::handle_backend_action(
                     action,
                     &mut transport,
                     &mut current_nick,
                     &mut last_connection_params,
+                    &mut reconnect,
+                    &mut keepalive,
+                    &mut send_queue,
+                    &mut conn,
                     &mut reg_state,
                     &mut server_caps,
                     &mut pending_reg,
                     &event_tx,
-                ).await;
+                )
+                .await;
             }
-
-            // Read from the network (with short timeout so we can check for actions)
-            if let Some(ref mut t) = transport {
-                match timeout(Duration::from_millis(50), t.read_message()).await {
-                    Ok(Ok(Some(message))) => {
-                        handlers::handle_server_message(
-                            message,
-                            t,
-                            &mut current_nick,
-                            &mut reg_state,
-                            &mut server_caps,
-                            &mut pending_reg,
-                            &event_tx,
-                        ).await;
+            // Supervisor is gone, shut this connection down
+            Wake::Action(None) => return,
+            Wake::Message(message) => {
+                if let Some(t) = transport.as_mut() {
+                    let now = Instant::now();
+                    keepalive.on_activity(now);
+                    if let Some(lag) = keepalive.observe_pong(&message, now) {
+                        let _ = event_tx.send(GuiEvent::Lag(lag));
                     }
-                    Ok(Ok(None)) => {
-                        // Connection closed
-                        transport = None;
-                        let _ = event_tx
-                            .send(GuiEvent::Disconnected("Connection closed by server".into()));
-
-                        // Note: Auto-reconnect would trigger here if last_connection_params.6 is true
-                        // For now, just notify the user
-                        if let Some(params) = &last_connection_params {
-                            if params.6 { // auto_reconnect flag
-                                let _ = event_tx.send(GuiEvent::RawMessage(
-                                    "Connection lost. Auto-reconnect enabled (manual reconnect required for now).".to_string()
-                                ));
-                            }
+                    let mapping = server_caps.support.case_mapping();
+                    reconnect.observe(&message, &current_nick, mapping);
+                    conn.self_prefix.observe(&message, &current_nick, mapping);
+                    let was_registered = reg_state == RegistrationState::Registered;
+
+                    handlers::handle_server_message(
+                        message,
+                        t,
+                        &mut send_queue,
+                        &mut conn.echoes,
+                        &mut conn.batches,
+                        &mut conn.history,
+                        &mut conn.whois,
+                        &mut conn.mode_lists,
+                        &mut current_nick,
+                        &mut reg_state,
+                        &mut server_caps,
+                        &mut pending_reg,
+                        &event_tx,
+                    )
+                    .await;
+
+                    if !was_registered && reg_state == RegistrationState::Registered {
+                        // Registration succeeded: reset the backoff and restore channels
+                        reconnect.cancel();
+                        for channel in &reconnect.channels {
+                            send_queue.push(Message::join(channel));
                         }
                     }
-                    Ok(Err(e)) => {
-                        let _ = event_tx.send(GuiEvent::Error(format!("Read error: {:?}", e)));
-                        transport = None;
-                        let _ = event_tx.send(GuiEvent::Disconnected("Read error".into()));
-                    }
-                    Err(_) => {
-                        // Timeout - this is normal, just loop
-                    }
                 }
-            } else {
-                // No connection, sleep a bit to avoid busy-looping
-                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            Wake::Closed(None) => {
+                let _ = event_tx.send(GuiEvent::Disconnected("Connection closed by server".into()));
+                connection_lost = true;
+            }
+            Wake::Closed(Some(e)) => {
+                let _ = event_tx.send(GuiEvent::Error(format!("Read error: {}", e)));
+                let _ = event_tx.send(GuiEvent::Disconnected("Read error".into()));
+                connection_lost = true;
+            }
+            Wake::Timer => {}
+        }
+
+        // Timers are checked after every wakeup; they are no-ops until due
+        if let (Some(t), false) = (transport.as_mut(), connection_lost) {
+            let registered = reg_state == RegistrationState::Registered;
+            match keepalive.poll(registered, Instant::now()) {
+                KeepaliveCheck::SendPing(token) => {
+                    // Sent ahead of queued lines so the lag reflects the link only
+                    send_queue.push_priority(Message::from(Command::PING(token, None)));
+                }
+                KeepaliveCheck::TimedOut(silence) => {
+                    let _ = event_tx.send(GuiEvent::Disconnected(format!(
+                        "Ping timeout: {} seconds",
+                        silence.as_secs()
+                    )));
+                    connection_lost = true;
+                }
+                KeepaliveCheck::Idle => {}
+            }
+
+            if !connection_lost {
+                for event in conn.echoes.expire() {
+                    let _ = event_tx.send(event);
+                }
+                send_queue.flush(t, &event_tx).await;
             }
         }
-    });
+
+        if connection_lost {
+            transport = None;
+            send_queue.clear();
+            send_queue.report_depth(&event_tx);
+            conn.reset("Connection lost", &event_tx);
+            if last_connection_params
+                .as_ref()
+                .is_some_and(|p| p.auto_reconnect)
+            {
+                handlers::schedule_reconnect(&mut reconnect, &event_tx);
+            }
+        } else if transport.is_none() && reconnect.is_due() {
+            reconnect.next_attempt = None;
+            if let Some(params) = &last_connection_params {
+                let connected = handlers::connect(
+                    params,
+                    reconnect.attempt,
+                    &mut transport,
+                    &mut keepalive,
+                    &mut send_queue,
+                    &mut current_nick,
+                    &mut reg_state,
+                    &mut server_caps,
+                    &mut pending_reg,
+                    &event_tx,
+                )
+                .await;
+                if !connected {
+                    handlers::schedule_reconnect(&mut reconnect, &event_tx);
+                }
+            }
+        }
+    }
+}
+
+/// Read the next line from the server, or wait forever if not connected
+async fn read_next(transport: &mut Option<Transport>) -> Wake {
+    let Some(t) = transport.as_mut() else {
+        return std::future::pending().await;
+    };
+    match t.read_message().await {
+        Ok(Some(message)) => Wake::Message(message),
+        Ok(None) => Wake::Closed(None),
+        Err(e) => Wake::Closed(Some(format!("{:?}", e))),
+    }
+}
+
+/// Sleep until `deadline`, or forever if there is none
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(at) => tokio::time::sleep_until(at.into()).await,
+        None => std::future::pending().await,
+    }
 }
@@ -0,0 +1,136 @@
+//! Channel mask lists: the entries of a ban, exception, invite exception or
+//! quiet list are collected into one `GuiEvent::ModeList`, sent when the
+//! list's end numeric arrives.
+
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use slirc_proto::{Command, Message};
+
+use crate::channel_modes::ModeListEntry;
+use crate::irc_name::CaseMapping;
+use crate::protocol::GuiEvent;
+
+/// Lists still being received, by folded channel and mode letter
+#[derive(Debug, Default)]
+pub struct ModeLists {
+    pending: HashMap<(String, char), Vec<ModeListEntry>>,
+}
+
+impl ModeLists {
+    /// Collect a list numeric; returns the whole list at its end
+    pub fn observe(&mut self, message: &Message, mapping: CaseMapping) -> Option<GuiEvent> {
+        let Command::Response(code, args) = &message.command else {
+            return None;
+        };
+        // Every reply is `<me> <channel> ...`; quiets also name their mode
+        let channel = args.get(1)?;
+        let (mode, fields, end) = match code.code() {
+            // RPL_BANLIST / RPL_ENDOFBANLIST
+            367 => ('b', &args[2..], false),
+            368 => ('b', &args[2..], true),
+            // RPL_EXCEPTLIST / RPL_ENDOFEXCEPTLIST
+            348 => ('e', &args[2..], false),
+            349 => ('e', &args[2..], true),
+            // RPL_INVITELIST / RPL_ENDOFINVITELIST
+            346 => ('I', &args[2..], false),
+            347 => ('I', &args[2..], true),
+            // RPL_QUIETLIST / RPL_ENDOFQUIETLIST
+            728 | 729 => {
+                let mode = args.get(2)?.chars().next()?;
+                (mode, &args[3..], code.code() == 729)
+            }
+            _ => return None,
+        };
+        let key = (mapping.fold(channel), mode);
+
+        if end {
+            // An empty list has only the end numeric
+            let entries = self.pending.remove(&key).unwrap_or_default();
+            return Some(GuiEvent::ModeList {
+                channel: channel.clone(),
+                mode,
+                entries,
+            });
+        }
+
+        // <mask> [<setter> <set time>]
+        let mask = fields.first()?.clone();
+        self.pending.entry(key).or_default().push(ModeListEntry {
+            mask,
+            set_by: fields.get(1).cloned(),
+            set_at: fields
+                .get(2)
+                .and_then(|secs| secs.parse().ok())
+                .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+        });
+        None
+    }
+
+    /// Forget partial lists, e.g. after the connection dropped
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_util::parse;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_list_collected_until_end() {
+        let mut lists = ModeLists::default();
+        let lines = [
+            ":srv 367 me #Chan *!*@spam op!u@h 1709294400",
+            ":srv 728 me #chan q *!*@loud op 1709294400",
+            ":srv 367 me #chan bad!*@*",
+        ];
+        for line in lines {
+            assert!(lists.observe(&parse(line), CaseMapping::Rfc1459).is_none());
+        }
+
+        let Some(GuiEvent::ModeList {
+            channel,
+            mode: 'b',
+            entries,
+        }) = lists.observe(
+            &parse(":srv 368 me #chan :End of Channel Ban List"),
+            CaseMapping::Rfc1459,
+        )
+        else {
+            panic!("RPL_ENDOFBANLIST should finish the list");
+        };
+        assert_eq!(channel, "#chan");
+        assert_eq!(
+            entries,
+            [
+                ModeListEntry {
+                    mask: "*!*@spam".into(),
+                    set_by: Some("op!u@h".into()),
+                    set_at: Some(Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()),
+                },
+                ModeListEntry {
+                    mask: "bad!*@*".into(),
+                    set_by: None,
+                    set_at: None,
+                },
+            ]
+        );
+
+        assert!(matches!(
+            lists.observe(&parse(":srv 729 me #chan q :End of Channel Quiet List"), CaseMapping::Rfc1459),
+            Some(GuiEvent::ModeList { mode: 'q', entries, .. }) if entries.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_empty_list_reported() {
+        let mut lists = ModeLists::default();
+        assert!(matches!(
+            lists.observe(&parse(":srv 349 me #chan :End of Channel Exception List"), CaseMapping::Rfc1459),
+            Some(GuiEvent::ModeList { mode: 'e', entries, .. }) if entries.is_empty()
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+//! IRCv3 `draft/multiline`: sending several lines as one logical message.
+//!
+//! The lines are wrapped in `BATCH +ref draft/multiline <target>` ...
+//! `BATCH -ref`, each PRIVMSG tagged with `batch=ref`. Lines longer than the
+//! 512-byte limit are split and the continuations tagged
+//! `draft/multiline-concat`. A new batch is started whenever the server's
+//! `max-bytes`/`max-lines` limits would be exceeded.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use slirc_proto::{Message, Tag};
+
+use super::split::split_message;
+
+/// Capability name
+pub const MULTILINE_CAP: &str = "draft/multiline";
+
+/// Tag marking a line that continues the previous one without a newline
+const CONCAT_TAG: &str = "draft/multiline-concat";
+
+/// Counter used to make batch references unique
+static NEXT_REFERENCE: AtomicU64 = AtomicU64::new(1);
+
+/// Limits advertised in the capability value, e.g. `max-bytes=4096,max-lines=24`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MultilineLimits {
+    /// Maximum bytes of the combined message (lines joined with `\n`)
+    pub max_bytes: usize,
+    /// Maximum number of PRIVMSGs in one batch, if limited
+    pub max_lines: Option<usize>,
+}
+
+impl MultilineLimits {
+    /// Parse the capability value; `max-bytes` is mandatory
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut max_bytes = None;
+        let mut max_lines = None;
+        for item in value.split(',') {
+            match item.split_once('=') {
+                Some(("max-bytes", n)) => max_bytes = n.parse().ok(),
+                Some(("max-lines", n)) => max_lines = n.parse().ok(),
+                _ => {}
+            }
+        }
+        Some(Self {
+            max_bytes: max_bytes.filter(|&n| n > 0)?,
+            max_lines: max_lines.filter(|&n| n > 0),
+        })
+    }
+}
+
+/// Build the BATCH and PRIVMSG lines that send `lines` to `target`, each
+/// PRIVMSG's text being at most `budget` bytes. Every batch comes back as
+/// its own list, from `BATCH +ref` to `BATCH -ref`.
+pub fn build_batches(
+    target: &str,
+    lines: &[String],
+    budget: usize,
+    limits: MultilineLimits,
+) -> Vec<Vec<Message>> {
+    let mut batches = Vec::new();
+    let mut messages = Vec::new();
+    let mut batch: Option<Batch> = None;
+
+    for line in lines {
+        for (i, piece) in split_message(line, budget).into_iter().enumerate() {
+            let concat = i > 0;
+            let added = piece.len() + usize::from(!concat);
+
+            if let Some(open) = &batch {
+                let full = open.bytes + added > limits.max_bytes
+                    || limits.max_lines.is_some_and(|max| open.lines >= max);
+                if full {
+                    messages.extend(batch.take().map(|b| b.close()));
+                    batches.push(std::mem::take(&mut messages));
+                }
+            }
+
+            let open = batch.get_or_insert_with(|| {
+                let batch = Batch::open();
+                if let Ok(start) = Message::new(
+                    None,
+                    "BATCH",
+                    vec![&format!("+{}", batch.reference), MULTILINE_CAP, target],
+                ) {
+                    messages.push(start);
+                }
+                batch
+            });
+
+            let mut tags = vec![Tag("batch".to_string(), Some(open.reference.clone()))];
+            // The first line of a batch can't continue anything
+            if concat && open.lines > 0 {
+                tags.push(Tag(CONCAT_TAG.to_string(), None));
+            }
+            let mut privmsg = Message::privmsg(target, &piece);
+            privmsg.tags = Some(tags);
+            messages.push(privmsg);
+
+            open.bytes += added;
+            open.lines += 1;
+        }
+    }
+
+    if let Some(open) = batch {
+        messages.push(open.close());
+        batches.push(messages);
+    }
+    batches
+}
+
+/// A batch being built
+struct Batch {
+    reference: String,
+    bytes: usize,
+    lines: usize,
+}
+
+impl Batch {
+    fn open() -> Self {
+        let n = NEXT_REFERENCE.fetch_add(1, Ordering::Relaxed);
+        Self {
+            reference: format!("ml{}", n),
+            bytes: 0,
+            lines: 0,
+        }
+    }
+
+    fn close(self) -> Message {
+        Message::new(None, "BATCH", vec![&format!("-{}", self.reference)])
+            .expect("BATCH -ref is a valid message")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch_commands(batches: &[Vec<Message>]) -> Vec<String> {
+        batches
+            .iter()
+            .flatten()
+            .map(|m| m.to_string())
+            .filter(|line| line.starts_with("BATCH"))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!(
+            MultilineLimits::parse("max-bytes=4096,max-lines=24"),
+            Some(MultilineLimits {
+                max_bytes: 4096,
+                max_lines: Some(24)
+            })
+        );
+        assert_eq!(
+            MultilineLimits::parse("max-bytes=40000"),
+            Some(MultilineLimits {
+                max_bytes: 40000,
+                max_lines: None
+            })
+        );
+        assert_eq!(MultilineLimits::parse("max-lines=10"), None);
+    }
+
+    #[test]
+    fn test_single_batch() {
+        let lines = vec!["one".to_string(), "two".to_string(), "three".to_string()];
+        let limits = MultilineLimits {
+            max_bytes: 4096,
+            max_lines: None,
+        };
+        let batches = build_batches("#chan", &lines, 400, limits);
+        assert_eq!(batches.len(), 1);
+        let messages = &batches[0];
+        // BATCH +ref, three PRIVMSGs, BATCH -ref
+        assert_eq!(messages.len(), 5);
+        assert_eq!(batch_commands(&batches).len(), 2);
+        for privmsg in &messages[1..4] {
+            let tags = privmsg.tags.as_ref().expect("batch tag");
+            assert_eq!(tags.len(), 1);
+            assert_eq!(tags[0].0, "batch");
+        }
+    }
+
+    #[test]
+    fn test_max_lines_starts_new_batch() {
+        let lines: Vec<String> = (0..5).map(|i| format!("line {}", i)).collect();
+        let limits = MultilineLimits {
+            max_bytes: 4096,
+            max_lines: Some(2),
+        };
+        let batches = build_batches("#chan", &lines, 400, limits);
+        // Three batches of 2 + 2 + 1 lines, each opened and closed
+        let sizes: Vec<usize> = batches.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [4, 4, 3]);
+        assert_eq!(batch_commands(&batches).len(), 6);
+        for batch in &batches {
+            assert!(batch[0].to_string().starts_with("BATCH +"));
+            assert!(batch[batch.len() - 1].to_string().starts_with("BATCH -"));
+        }
+    }
+
+    #[test]
+    fn test_long_line_continuations_are_concat() {
+        let lines = vec!["word ".repeat(30).trim_end().to_string()];
+        let limits = MultilineLimits {
+            max_bytes: 4096,
+            max_lines: None,
+        };
+        let batches = build_batches("#chan", &lines, 40, limits);
+        let messages = &batches[0];
+        let privmsgs = &messages[1..messages.len() - 1];
+        assert!(privmsgs.len() > 1);
+        assert_eq!(privmsgs[0].tags.as_ref().unwrap().len(), 1);
+        for privmsg in &privmsgs[1..] {
+            let tags = privmsg.tags.as_ref().unwrap();
+            assert!(tags.iter().any(|t| t.0 == CONCAT_TAG));
+        }
+    }
+}
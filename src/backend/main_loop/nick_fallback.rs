@@ -0,0 +1,79 @@
+//! Picking another nickname when ours is rejected during registration.
+//!
+//! Until 001 arrives the server won't let us in without a nick, so on
+//! 432/433/436 we try the network's configured alternates in order, then the
+//! nick with `_` appended, then with a digit appended.
+
+/// Digits tried after the `_` suffixes
+const MAX_DIGIT: u32 = 9;
+
+/// Nicks still to try for the current registration
+#[derive(Debug, Clone, Default)]
+pub struct NickFallback {
+    /// Candidates in the order they are tried
+    candidates: Vec<String>,
+    /// Index of the next candidate
+    next: usize,
+}
+
+impl NickFallback {
+    /// Build the candidate list for `nick` and its configured alternates
+    pub fn new(nick: &str, alternates: &[String]) -> Self {
+        let mut candidates: Vec<String> = Vec::new();
+        let generated = [format!("{}_", nick), format!("{}__", nick)]
+            .into_iter()
+            .chain((1..=MAX_DIGIT).map(|n| format!("{}{}", nick, n)));
+        for candidate in alternates
+            .iter()
+            .map(|alt| alt.trim().to_string())
+            .chain(generated)
+        {
+            if !candidate.is_empty() && candidate != nick && !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+        Self {
+            candidates,
+            next: 0,
+        }
+    }
+
+    /// Next nick to try, or `None` once every candidate was rejected
+    pub fn next_nick(&mut self) -> Option<String> {
+        let nick = self.candidates.get(self.next)?.clone();
+        self.next += 1;
+        Some(nick)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alternates_tried_first() {
+        let mut fallback = NickFallback::new("alice", &["alicia".into(), " ali ".into()]);
+        assert_eq!(fallback.next_nick().as_deref(), Some("alicia"));
+        assert_eq!(fallback.next_nick().as_deref(), Some("ali"));
+        assert_eq!(fallback.next_nick().as_deref(), Some("alice_"));
+        assert_eq!(fallback.next_nick().as_deref(), Some("alice__"));
+        assert_eq!(fallback.next_nick().as_deref(), Some("alice1"));
+    }
+
+    #[test]
+    fn test_candidates_run_out() {
+        let mut fallback = NickFallback::new("bob", &[]);
+        let tried: Vec<String> = std::iter::from_fn(|| fallback.next_nick()).collect();
+        assert_eq!(tried.len(), 2 + MAX_DIGIT as usize);
+        assert_eq!(tried.last().map(String::as_str), Some("bob9"));
+        assert_eq!(fallback.next_nick(), None);
+    }
+
+    #[test]
+    fn test_skips_duplicates_and_own_nick() {
+        let mut fallback =
+            NickFallback::new("carol", &["carol".into(), "carol_".into(), "".into()]);
+        assert_eq!(fallback.next_nick().as_deref(), Some("carol_"));
+        assert_eq!(fallback.next_nick().as_deref(), Some("carol__"));
+    }
+}
@@ -0,0 +1,201 @@
+//! Automatic reconnection with jittered exponential backoff.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+use slirc_proto::{Command, Message};
+
+use super::nick_fallback::NickFallback;
+use crate::irc_name::CaseMapping;
+
+/// Delay before the first reconnect attempt
+const BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// Upper bound for the backoff delay
+const MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// Parameters of the last requested connection, kept for reconnects
+#[derive(Debug, Clone)]
+pub struct ConnectionParams {
+    /// Servers to rotate through, the requested server first
+    pub servers: Vec<(String, u16)>,
+    pub nickname: String,
+    pub alt_nicks: Vec<String>,
+    pub username: String,
+    pub realname: String,
+    pub use_tls: bool,
+    pub auto_reconnect: bool,
+    pub ping_timeout: Duration,
+    pub flood_burst: u32,
+    pub flood_refill: Duration,
+    pub sasl_password: Option<String>,
+}
+
+impl ConnectionParams {
+    /// Server to use for the given attempt (attempt 0 is the requested server)
+    pub fn server_for_attempt(&self, attempt: u32) -> &(String, u16) {
+        &self.servers[attempt as usize % self.servers.len()]
+    }
+
+    /// Nicks to try, alternates first, when ours is rejected while registering
+    pub fn nick_fallback(&self) -> NickFallback {
+        NickFallback::new(&self.nickname, &self.alt_nicks)
+    }
+}
+
+/// Reconnect bookkeeping for a single network connection
+#[derive(Debug, Default)]
+pub struct ReconnectState {
+    /// Consecutive attempts since the last successful registration
+    pub attempt: u32,
+    /// When the next attempt is due, if one is scheduled
+    pub next_attempt: Option<Instant>,
+    /// Channels we are in, rejoined after reconnecting
+    pub channels: Vec<String>,
+}
+
+impl ReconnectState {
+    /// Schedule the next attempt, returning its number and delay
+    pub fn schedule(&mut self) -> (u32, Duration) {
+        self.attempt += 1;
+        let delay = backoff_delay(self.attempt);
+        self.next_attempt = Some(Instant::now() + delay);
+        (self.attempt, delay)
+    }
+
+    /// Whether a scheduled attempt is due
+    pub fn is_due(&self) -> bool {
+        self.next_attempt.is_some_and(|at| Instant::now() >= at)
+    }
+
+    /// Stop any pending attempt and reset the backoff
+    pub fn cancel(&mut self) {
+        self.attempt = 0;
+        self.next_attempt = None;
+    }
+
+    /// Track our own JOIN/PART/KICK so open channels can be restored later.
+    /// Nicks and channels compare under the server's `mapping`.
+    pub fn observe(&mut self, message: &Message, current_nick: &str, mapping: CaseMapping) {
+        let from_us = message
+            .source_nickname()
+            .is_some_and(|nick| mapping.names_equal(nick, current_nick));
+        match &message.command {
+            Command::JOIN(channel, _, _) if from_us => {
+                if !self
+                    .channels
+                    .iter()
+                    .any(|c| mapping.names_equal(c, channel))
+                {
+                    self.channels.push(channel.clone());
+                }
+            }
+            Command::PART(channel, _) if from_us => {
+                self.channels.retain(|c| !mapping.names_equal(c, channel));
+            }
+            Command::KICK(channel, nick, _) if mapping.names_equal(nick, current_nick) => {
+                self.channels.retain(|c| !mapping.names_equal(c, channel));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Backoff delay for an attempt: doubles from `BASE_DELAY` up to `MAX_DELAY`,
+/// with "equal jitter" (a random value between half and all of the delay)
+/// so that many clients dropped at once don't reconnect in lockstep.
+pub fn backoff_delay(attempt: u32) -> Duration {
+    let exp = attempt.saturating_sub(1).min(16);
+    let delay = BASE_DELAY.saturating_mul(1 << exp).min(MAX_DELAY);
+    let half = delay / 2;
+    let jitter_ms = random_u64() % (half.as_millis() as u64 + 1);
+    half + Duration::from_millis(jitter_ms)
+}
+
+/// Cheap random number from the std hasher's per-instance random keys
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_util::parse;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        for attempt in 1..=20 {
+            let full = BASE_DELAY
+                .saturating_mul(1 << (attempt - 1).min(16))
+                .min(MAX_DELAY);
+            let delay = backoff_delay(attempt);
+            assert!(delay >= full / 2, "attempt {} too short", attempt);
+            assert!(delay <= full, "attempt {} too long", attempt);
+        }
+        assert!(backoff_delay(100) <= MAX_DELAY);
+    }
+
+    #[test]
+    fn test_schedule_and_cancel() {
+        let mut state = ReconnectState::default();
+        let (attempt, _) = state.schedule();
+        assert_eq!(attempt, 1);
+        assert!(state.next_attempt.is_some());
+        let (attempt, _) = state.schedule();
+        assert_eq!(attempt, 2);
+
+        state.cancel();
+        assert_eq!(state.attempt, 0);
+        assert!(!state.is_due());
+    }
+
+    fn params(alt_nicks: &[&str]) -> ConnectionParams {
+        ConnectionParams {
+            servers: vec![("a.example".into(), 6667), ("b.example".into(), 6697)],
+            nickname: "nick".into(),
+            alt_nicks: alt_nicks.iter().map(|nick| nick.to_string()).collect(),
+            username: "user".into(),
+            realname: "Real".into(),
+            use_tls: false,
+            auto_reconnect: true,
+            ping_timeout: Duration::from_secs(120),
+            flood_burst: 5,
+            flood_refill: Duration::from_secs(2),
+            sasl_password: None,
+        }
+    }
+
+    #[test]
+    fn test_channels_tracked_under_casemapping() {
+        let mut state = ReconnectState::default();
+        let mapping = CaseMapping::Rfc1459;
+        state.observe(&parse(":Nick[a]!u@h JOIN #chan[1]"), "nick{a}", mapping);
+        state.observe(&parse(":nick{a}!u@h JOIN #CHAN{1}"), "nick{a}", mapping);
+        assert_eq!(state.channels, ["#chan[1]"]);
+        state.observe(
+            &parse(":op!u@h KICK #Chan{1} NICK[A] :bye"),
+            "nick{a}",
+            mapping,
+        );
+        assert!(state.channels.is_empty());
+    }
+
+    #[test]
+    fn test_server_rotation() {
+        let params = params(&[]);
+        assert_eq!(params.server_for_attempt(0).0, "a.example");
+        assert_eq!(params.server_for_attempt(1).0, "b.example");
+        assert_eq!(params.server_for_attempt(2).0, "a.example");
+    }
+
+    #[test]
+    fn test_alt_nicks_tried_before_generated() {
+        let mut fallback = params(&["nick_away", "nick"]).nick_fallback();
+        assert_eq!(fallback.next_nick().as_deref(), Some("nick_away"));
+        assert_eq!(fallback.next_nick().as_deref(), Some("nick_"));
+
+        let mut fallback = params(&[]).nick_fallback();
+        assert_eq!(fallback.next_nick().as_deref(), Some("nick_"));
+    }
+}
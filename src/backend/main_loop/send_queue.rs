@@ -0,0 +1,256 @@
+//! Outgoing send queue with token-bucket flood control.
+//!
+//! Servers disconnect clients that send too much too fast ("Excess Flood").
+//! Everything the user triggers goes through this queue: up to `burst` lines
+//! go out at once, after which one line is released per `refill` interval.
+//! A BATCH is released as a whole for one token, so the server never holds
+//! it open waiting for the rest. PONG and QUIT jump the queue and are never
+//! held back.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use slirc_proto::{Message, Transport};
+
+use crate::backend::sender::EventSender;
+use crate::config::{DEFAULT_FLOOD_BURST, DEFAULT_FLOOD_REFILL_MS};
+use crate::protocol::GuiEvent;
+
+/// Rate-limited outgoing queue for a single connection
+#[derive(Debug)]
+pub struct SendQueue {
+    /// Lines sent ahead of everything else, ignoring the rate limit
+    priority: VecDeque<Message>,
+    /// Rate-limited units: a single line, or a whole BATCH
+    queue: VecDeque<Vec<Message>>,
+    /// Rest of the unit being sent; goes out without waiting for tokens
+    sending: VecDeque<Message>,
+    /// Bucket capacity
+    burst: u32,
+    /// Time to regain one token
+    refill: Duration,
+    /// Tokens currently available
+    tokens: u32,
+    /// When the last token was added (or the bucket was last full)
+    last_refill: Instant,
+    /// Queue depth last reported to the UI
+    reported_depth: usize,
+}
+
+impl Default for SendQueue {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_FLOOD_BURST,
+            Duration::from_millis(DEFAULT_FLOOD_REFILL_MS),
+        )
+    }
+}
+
+impl SendQueue {
+    pub fn new(burst: u32, refill: Duration) -> Self {
+        let burst = burst.max(1);
+        Self {
+            priority: VecDeque::new(),
+            queue: VecDeque::new(),
+            sending: VecDeque::new(),
+            burst,
+            refill: refill.max(Duration::from_millis(1)),
+            tokens: burst,
+            last_refill: Instant::now(),
+            reported_depth: 0,
+        }
+    }
+
+    /// Start over for a freshly opened connection
+    pub fn reset(&mut self, burst: u32, refill: Duration) {
+        let reported_depth = self.reported_depth;
+        *self = Self::new(burst, refill);
+        self.reported_depth = reported_depth;
+    }
+
+    /// Queue a rate-limited line
+    pub fn push(&mut self, message: Message) {
+        self.queue.push_back(vec![message]);
+    }
+
+    /// Queue lines that must go out back to back (a BATCH), for one token
+    pub fn push_batch(&mut self, messages: Vec<Message>) {
+        if !messages.is_empty() {
+            self.queue.push_back(messages);
+        }
+    }
+
+    /// Queue a line that must go out before anything else (PONG, QUIT)
+    pub fn push_priority(&mut self, message: Message) {
+        self.priority.push_back(message);
+    }
+
+    /// Drop everything still waiting (connection closed)
+    pub fn clear(&mut self) {
+        self.priority.clear();
+        self.queue.clear();
+        self.sending.clear();
+    }
+
+    /// Number of rate-limited lines waiting to be sent
+    pub fn len(&self) -> usize {
+        self.sending.len() + self.queue.iter().map(Vec::len).sum::<usize>()
+    }
+
+    /// Whether nothing is waiting
+    pub fn is_empty(&self) -> bool {
+        self.priority.is_empty() && self.sending.is_empty() && self.queue.is_empty()
+    }
+
+    /// Next line that may be sent at `now`, if any
+    pub fn pop_ready(&mut self, now: Instant) -> Option<Message> {
+        self.refill_tokens(now);
+        if let Some(message) = self.priority.pop_front() {
+            // Priority lines still use up a token if one is available
+            self.take_token(now);
+            return Some(message);
+        }
+        // The rest of a batch already paid for
+        if let Some(message) = self.sending.pop_front() {
+            return Some(message);
+        }
+        if self.tokens > 0 {
+            let mut unit = self.queue.pop_front()?.into_iter();
+            let message = unit.next()?;
+            self.sending.extend(unit);
+            self.take_token(now);
+            return Some(message);
+        }
+        None
+    }
+
+    /// When the next held-back line may be sent, if any are waiting
+    pub fn next_ready(&self, now: Instant) -> Option<Instant> {
+        if !self.priority.is_empty() || !self.sending.is_empty() {
+            return Some(now);
+        }
+        if self.queue.is_empty() {
+            return None;
+        }
+        if self.tokens > 0 {
+            Some(now)
+        } else {
+            Some(self.last_refill + self.refill)
+        }
+    }
+
+    /// Write every line the bucket currently allows, then tell the UI if the
+    /// queue depth changed
+    pub async fn flush(&mut self, transport: &mut Transport, event_tx: &EventSender) {
+        while let Some(message) = self.pop_ready(Instant::now()) {
+            if let Err(e) = transport.write_message(&message).await {
+                let _ = event_tx.send(GuiEvent::Error(format!("Failed to send: {}", e)));
+                break;
+            }
+        }
+        self.report_depth(event_tx);
+    }
+
+    /// Send `GuiEvent::SendQueueDepth` if the depth changed since last time
+    pub fn report_depth(&mut self, event_tx: &EventSender) {
+        let depth = self.len();
+        if depth != self.reported_depth {
+            self.reported_depth = depth;
+            let _ = event_tx.send(GuiEvent::SendQueueDepth(depth));
+        }
+    }
+
+    /// Use up one token, if any are left
+    fn take_token(&mut self, now: Instant) {
+        if self.tokens == self.burst {
+            // Leaving a full bucket: refill time starts counting now
+            self.last_refill = now;
+        }
+        self.tokens = self.tokens.saturating_sub(1);
+    }
+
+    /// Add the tokens earned since the last refill
+    fn refill_tokens(&mut self, now: Instant) {
+        if self.tokens >= self.burst {
+            return;
+        }
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let earned = (elapsed.as_nanos() / self.refill.as_nanos()) as u32;
+        if earned > 0 {
+            self.tokens = (self.tokens + earned).min(self.burst);
+            self.last_refill += self.refill * earned;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slirc_proto::Command;
+
+    fn line(text: &str) -> Message {
+        Message::privmsg("#test", text)
+    }
+
+    #[test]
+    fn test_burst_then_rate_limited() {
+        let mut queue = SendQueue::new(3, Duration::from_millis(50));
+        for i in 0..5 {
+            queue.push(line(&i.to_string()));
+        }
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(queue.pop_ready(start).is_some());
+        }
+        assert!(queue.pop_ready(start).is_none());
+        assert_eq!(queue.len(), 2);
+        let next = queue.next_ready(start).unwrap();
+        assert_eq!(next, start + Duration::from_millis(50));
+
+        assert!(queue.pop_ready(next - Duration::from_millis(1)).is_none());
+        assert!(queue.pop_ready(next).is_some());
+        assert!(queue.pop_ready(next).is_none());
+    }
+
+    #[test]
+    fn test_priority_bypasses_limit() {
+        let mut queue = SendQueue::new(1, Duration::from_secs(60));
+        let now = Instant::now();
+        queue.push(line("first"));
+        queue.push(line("second"));
+        assert!(queue.pop_ready(now).is_some());
+        assert!(queue.pop_ready(now).is_none());
+
+        queue.push_priority(Message::quit());
+        let next = queue.pop_ready(now).expect("priority line should be sent");
+        assert!(matches!(next.command, Command::QUIT(_)));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn test_batch_sent_as_one_unit() {
+        let mut queue = SendQueue::new(1, Duration::from_secs(60));
+        let now = Instant::now();
+        queue.push_batch((0..4).map(|i| line(&i.to_string())).collect());
+        queue.push(line("after"));
+        assert_eq!(queue.len(), 5);
+
+        for _ in 0..4 {
+            assert!(queue.pop_ready(now).is_some());
+        }
+        // The batch used the only token
+        assert!(queue.pop_ready(now).is_none());
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut queue = SendQueue::default();
+        queue.push(line("x"));
+        queue.push_priority(Message::quit());
+        queue.clear();
+        assert!(queue.is_empty());
+        assert!(queue.next_ready(Instant::now()).is_none());
+    }
+}
@@ -0,0 +1,334 @@
+//! Splitting outgoing text to fit the 512-byte IRC line limit.
+//!
+//! The server relays our lines to other clients as
+//! `:nick!user@host PRIVMSG <target> :<text>\r\n`, and that whole line must fit
+//! in 512 bytes, so the text budget depends on our own prefix and the target.
+//! Text is split on word boundaries where possible (never inside a UTF-8
+//! character or a formatting code); CTCP ACTION wrapping is repeated on every
+//! piece and active formatting is re-applied at the start of continuations.
+
+use slirc_proto::{Command, Message, Prefix};
+
+use crate::irc_name::CaseMapping;
+
+/// Maximum length of an IRC line including the trailing CRLF
+pub const MAX_LINE_LEN: usize = 512;
+
+/// Assumed username length while our real one is unknown (`~` + USERLEN 10)
+const UNKNOWN_USER_LEN: usize = 11;
+
+/// Assumed hostname length while our real one is unknown (max DNS label chain)
+const UNKNOWN_HOST_LEN: usize = 63;
+
+/// Never split into pieces smaller than this, whatever the budget says
+const MIN_BUDGET: usize = 32;
+
+const CTCP_ACTION_START: &str = "\x01ACTION ";
+const CTCP_DELIM: char = '\x01';
+
+/// Our own `user@host` as the server sees it, learned from 001, 396
+/// (RPL_HOSTHIDDEN), CHGHOST and the prefix of our own JOINs.
+#[derive(Debug, Default, Clone)]
+pub struct SelfPrefix {
+    pub user: Option<String>,
+    pub host: Option<String>,
+}
+
+impl SelfPrefix {
+    /// Update from a server message; our nick compares under `mapping`
+    pub fn observe(&mut self, message: &Message, current_nick: &str, mapping: CaseMapping) {
+        let is_us = |nick: &str| mapping.names_equal(nick, current_nick);
+        match &message.command {
+            // "Welcome to the Example IRC Network nick!user@host"
+            Command::Response(code, args) if code.code() == 1 => {
+                let mask = args.last().and_then(|text| text.split_whitespace().last());
+                if let Some((_, user_host)) = mask.and_then(|m| m.split_once('!')) {
+                    if let Some((user, host)) = user_host.split_once('@') {
+                        self.user = Some(user.to_string());
+                        self.host = Some(host.to_string());
+                    }
+                }
+            }
+            // 396 <nick> <host> :is now your displayed host
+            Command::Response(code, args) if code.code() == 396 => {
+                if let Some(host) = args.get(1) {
+                    self.host = Some(host.clone());
+                }
+            }
+            // :nick!old@old CHGHOST <user> <host>
+            Command::Raw(cmd, args)
+                if cmd.eq_ignore_ascii_case("CHGHOST")
+                    && message.source_nickname().is_some_and(is_us) =>
+            {
+                if let [user, host] = args.as_slice() {
+                    self.user = Some(user.clone());
+                    self.host = Some(host.clone());
+                }
+            }
+            _ => {
+                // Any message from us carries our full prefix
+                if let Some(Prefix::Nickname(nick, user, host)) = &message.prefix {
+                    if is_us(nick) && !user.is_empty() && !host.is_empty() {
+                        self.user = Some(user.clone());
+                        self.host = Some(host.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Forget everything (new connection)
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Length of `:nick!user@host ` as relayed by the server
+    pub fn relayed_len(&self, nick: &str) -> usize {
+        let user = self.user.as_ref().map_or(UNKNOWN_USER_LEN, |u| u.len());
+        let host = self.host.as_ref().map_or(UNKNOWN_HOST_LEN, |h| h.len());
+        1 + nick.len() + 1 + user + 1 + host + 1
+    }
+
+    /// Bytes available for the text of `<command> <target> :<text>`
+    pub fn text_budget(&self, nick: &str, command: &str, target: &str) -> usize {
+        let overhead = self.relayed_len(nick) + command.len() + 1 + target.len() + 2 + 2;
+        MAX_LINE_LEN.saturating_sub(overhead).max(MIN_BUDGET)
+    }
+}
+
+/// Split message text into pieces of at most `budget` bytes.
+/// A CTCP ACTION (`\x01ACTION ...\x01`) is split into several ACTIONs.
+pub fn split_message(text: &str, budget: usize) -> Vec<String> {
+    if text.len() <= budget {
+        return vec![text.to_string()];
+    }
+
+    if let Some(inner) = text
+        .strip_prefix(CTCP_ACTION_START)
+        .map(|rest| rest.strip_suffix(CTCP_DELIM).unwrap_or(rest))
+    {
+        let wrap_len = CTCP_ACTION_START.len() + 1;
+        return split_formatted(inner, budget.saturating_sub(wrap_len))
+            .into_iter()
+            .map(|piece| format!("{}{}{}", CTCP_ACTION_START, piece, CTCP_DELIM))
+            .collect();
+    }
+
+    split_formatted(text, budget)
+}
+
+/// Formatting state carried over from one piece to the next
+#[derive(Debug, Default, Clone)]
+struct Format {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strikethrough: bool,
+    monospace: bool,
+    reverse: bool,
+    /// The last color code (`\x03fg[,bg]` or `\x04RRGGBB[,RRGGBB]`)
+    color: Option<String>,
+}
+
+impl Format {
+    /// Update the state for a formatting code token
+    fn apply(&mut self, code: &str) {
+        match code.as_bytes()[0] {
+            0x02 => self.bold = !self.bold,
+            0x1D => self.italic = !self.italic,
+            0x1F => self.underline = !self.underline,
+            0x1E => self.strikethrough = !self.strikethrough,
+            0x11 => self.monospace = !self.monospace,
+            0x16 => self.reverse = !self.reverse,
+            0x0F => *self = Self::default(),
+            // A bare color code resets the color
+            0x03 | 0x04 if code.len() == 1 => self.color = None,
+            // Pad to two digits so a digit following the re-applied code
+            // on the next line isn't read as part of it
+            0x03 => {
+                let padded: Vec<String> =
+                    code[1..].split(',').map(|n| format!("{:0>2}", n)).collect();
+                self.color = Some(format!("\x03{}", padded.join(",")));
+            }
+            0x04 => self.color = Some(code.to_string()),
+            _ => {}
+        }
+    }
+
+    /// Codes that recreate this state at the start of a new line
+    fn prefix(&self) -> String {
+        let mut out = String::new();
+        for (on, code) in [
+            (self.bold, '\x02'),
+            (self.italic, '\x1D'),
+            (self.underline, '\x1F'),
+            (self.strikethrough, '\x1E'),
+            (self.monospace, '\x11'),
+            (self.reverse, '\x16'),
+        ] {
+            if on {
+                out.push(code);
+            }
+        }
+        if let Some(color) = &self.color {
+            out.push_str(color);
+        }
+        out
+    }
+}
+
+/// Split text with formatting codes into pieces of at most `budget` bytes
+fn split_formatted(text: &str, budget: usize) -> Vec<String> {
+    let budget = budget.max(MIN_BUDGET);
+    let mut lines = Vec::new();
+    let mut format = Format::default();
+    let mut current = String::new();
+    // Bytes at the start of `current` that only re-apply formatting
+    let mut carried = 0;
+    // Byte offset of the last space in `current`, and the format at that point
+    let mut last_space: Option<(usize, Format)> = None;
+
+    for token in tokens(text) {
+        while current.len() + token.len() > budget && current.len() > carried {
+            match last_space.take() {
+                // Break at the last word boundary
+                Some((at, format_at)) if at > carried => {
+                    let rest = current[at + 1..].to_string();
+                    current.truncate(at);
+                    lines.push(std::mem::take(&mut current));
+                    current = format_at.prefix();
+                    carried = current.len();
+                    current.push_str(&rest);
+                }
+                // One long word: break right here
+                _ => {
+                    lines.push(std::mem::take(&mut current));
+                    current = format.prefix();
+                    carried = current.len();
+                }
+            }
+        }
+
+        if is_format_code(token) {
+            format.apply(token);
+        } else if token == " " {
+            last_space = Some((current.len(), format.clone()));
+        }
+        current.push_str(token);
+    }
+
+    if current.len() > carried || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+fn is_format_code(token: &str) -> bool {
+    matches!(
+        token.as_bytes()[0],
+        0x02 | 0x03 | 0x04 | 0x0F | 0x11 | 0x16 | 0x1D | 0x1E | 0x1F
+    )
+}
+
+/// Break text into single characters and whole formatting codes, so that
+/// splitting between tokens never cuts a UTF-8 sequence or a color code
+fn tokens(text: &str) -> Vec<&str> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        let len = match bytes[i] {
+            0x03 => 1 + color_args_len(&bytes[i + 1..], |b| b.is_ascii_digit(), 2),
+            0x04 => 1 + color_args_len(&bytes[i + 1..], |b| b.is_ascii_hexdigit(), 6),
+            _ => text[i..].chars().next().map_or(1, char::len_utf8),
+        };
+        tokens.push(&text[i..i + len]);
+        i += len;
+    }
+    tokens
+}
+
+/// Length of the `fg[,bg]` arguments following a color code
+fn color_args_len(rest: &[u8], is_digit: fn(&u8) -> bool, max: usize) -> usize {
+    let fg = rest.iter().take(max).take_while(|b| is_digit(b)).count();
+    if fg == 0 {
+        return 0;
+    }
+    if rest.get(fg) == Some(&b',') {
+        let bg = rest[fg + 1..]
+            .iter()
+            .take(max)
+            .take_while(|b| is_digit(b))
+            .count();
+        if bg > 0 {
+            return fg + 1 + bg;
+        }
+    }
+    fg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_text_is_untouched() {
+        assert_eq!(split_message("hello", 100), vec!["hello"]);
+    }
+
+    #[test]
+    fn test_splits_on_word_boundaries() {
+        let text = "alpha beta gamma delta epsilon zeta eta theta iota kappa";
+        let pieces = split_message(text, 32);
+        assert!(pieces.len() > 1);
+        for piece in &pieces {
+            assert!(piece.len() <= 32);
+            assert!(!piece.starts_with(' ') && !piece.ends_with(' '));
+        }
+        assert_eq!(pieces.join(" "), text);
+    }
+
+    #[test]
+    fn test_never_splits_utf8() {
+        let text = "日本語".repeat(30);
+        let pieces = split_message(&text, 32);
+        assert!(pieces.iter().all(|p| p.len() <= 32));
+        assert_eq!(pieces.concat(), text);
+    }
+
+    #[test]
+    fn test_action_wrapping_survives() {
+        let text = format!("\x01ACTION {}\x01", "waves ".repeat(20).trim_end());
+        let pieces = split_message(&text, 60);
+        assert!(pieces.len() > 1);
+        for piece in &pieces {
+            assert!(piece.len() <= 60);
+            assert!(piece.starts_with("\x01ACTION "));
+            assert!(piece.ends_with('\x01'));
+        }
+    }
+
+    #[test]
+    fn test_formatting_carried_over() {
+        let text = format!("\x02\x0304,01{}", "word ".repeat(20).trim_end());
+        let pieces = split_message(&text, 40);
+        assert!(pieces.len() > 1);
+        for piece in &pieces[1..] {
+            assert!(piece.starts_with("\x02\x0304,01"), "{:?}", piece);
+        }
+        // The color code itself is never cut
+        assert!(pieces[0].starts_with("\x02\x0304,01w"));
+    }
+
+    #[test]
+    fn test_budget_from_prefix() {
+        let mut prefix = SelfPrefix::default();
+        let unknown = prefix.text_budget("nick", "PRIVMSG", "#chan");
+        prefix.user = Some("~u".into());
+        prefix.host = Some("h.example".into());
+        let known = prefix.text_budget("nick", "PRIVMSG", "#chan");
+        assert!(known > unknown);
+        // ":nick!~u@h.example PRIVMSG #chan :" + CRLF
+        assert_eq!(known, 512 - ":nick!~u@h.example PRIVMSG #chan :\r\n".len());
+    }
+}
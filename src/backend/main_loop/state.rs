@@ -1,5 +1,16 @@
-//! State types for connection registration and CAP negotiation.
+//! State types for connection registration, CAP negotiation and the
+//! bookkeeping that lasts for one connection.
 
+use super::batch::Batches;
+use super::echo::PendingEchoes;
+use super::history::ChatHistory;
+use super::mode_lists::ModeLists;
+use super::multiline::MultilineLimits;
+use super::nick_fallback::NickFallback;
+use super::split::SelfPrefix;
+use super::whois::WhoisReplies;
+use crate::backend::sender::EventSender;
+use crate::server_support::ServerSupport;
 use slirc_proto::sasl::SaslMechanism;
 use std::collections::HashSet;
 
@@ -38,8 +49,12 @@ pub struct ServerCaps {
     pub enabled: HashSet<String>,
     /// SASL mechanisms if server advertised "sasl=PLAIN,EXTERNAL,..."
     pub sasl_mechanisms: Vec<SaslMechanism>,
+    /// Limits from "draft/multiline=max-bytes=...,max-lines=..."
+    pub multiline: Option<MultilineLimits>,
     /// Whether we're still receiving multi-line CAP LS (* prefix)
     pub cap_ls_more: bool,
+    /// Features from RPL_ISUPPORT (005), built up as the lines arrive
+    pub support: ServerSupport,
 }
 
 /// Pending registration info saved while doing CAP negotiation
@@ -49,4 +64,67 @@ pub struct PendingRegistration {
     pub username: String,
     pub realname: String,
     pub sasl_password: Option<String>,
+    /// Nicks to try if the current one is rejected before 001
+    pub nick_fallback: NickFallback,
+    /// The nick the UI knows us by, to tell it if 001 gives us another
+    pub known_nick: String,
+}
+
+/// Replies being collected and lines in flight on the current connection.
+/// None of it carries over to the next connection.
+#[derive(Debug, Default)]
+pub struct ConnectionScoped {
+    /// Our own user@host, for splitting long messages to fit the line limit
+    pub self_prefix: SelfPrefix,
+    /// Our own lines waiting for the server's echo-message copy
+    pub echoes: PendingEchoes,
+    /// Open server batches
+    pub batches: Batches,
+    /// Our outstanding scrollback requests
+    pub history: ChatHistory,
+    /// WHOIS replies being collected
+    pub whois: WhoisReplies,
+    /// Ban/exception/invex/quiet lists being collected
+    pub mode_lists: ModeLists,
+}
+
+impl ConnectionScoped {
+    /// Forget the connection that ended; lines still waiting for their echo
+    /// are reported as failed with `reason`
+    pub fn reset(&mut self, reason: &str, event_tx: &EventSender) {
+        self.self_prefix.clear();
+        self.batches.clear();
+        self.history.clear();
+        self.whois.clear();
+        self.mode_lists.clear();
+        for event in self.echoes.fail_all(reason) {
+            let _ = event_tx.send(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_util::{drain_events, event_sender};
+    use crate::protocol::GuiEvent;
+
+    #[test]
+    fn test_reset_fails_pending_echoes() {
+        let (sender, rx) = event_sender();
+        let mut conn = ConnectionScoped::default();
+        conn.echoes.push("#chan");
+
+        conn.reset("Disconnected", &sender);
+        assert!(matches!(
+            drain_events(&rx).as_slice(),
+            [GuiEvent::OwnMessageFailed { target, reason, .. }]
+                if target == "#chan" && reason == "Disconnected"
+        ));
+        assert!(conn.echoes.next_deadline().is_none());
+
+        // Nothing is left for a second reset to report
+        conn.reset("Connection lost", &sender);
+        assert!(rx.try_recv().is_err());
+    }
 }
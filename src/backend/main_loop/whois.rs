@@ -0,0 +1,191 @@
+//! WHOIS replies: the numerics of one reply are collected into a single
+//! `GuiEvent::WhoisResult`, sent when RPL_ENDOFWHOIS (318) arrives.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::DateTime;
+use slirc_proto::{Command, Message};
+
+use crate::irc_name::CaseMapping;
+use crate::protocol::{GuiEvent, WhoisInfo};
+
+/// WHOIS replies still being received, by nick folded under the server's casemapping
+#[derive(Debug, Default)]
+pub struct WhoisReplies {
+    pending: HashMap<String, WhoisInfo>,
+}
+
+impl WhoisReplies {
+    /// Collect a WHOIS numeric; returns the finished result on RPL_ENDOFWHOIS
+    pub fn observe(&mut self, message: &Message, mapping: CaseMapping) -> Option<GuiEvent> {
+        let Command::Response(code, args) = &message.command else {
+            return None;
+        };
+        // Every reply is `<me> <nick> ...`
+        let nick = args.get(1)?;
+        let key = mapping.fold(nick);
+        let arg = |i: usize| args.get(i).cloned();
+
+        let info = match code.code() {
+            // RPL_ENDOFWHOIS; nothing was collected if the nick doesn't exist
+            318 => return self.pending.remove(&key).map(GuiEvent::WhoisResult),
+            // RPL_WHOISUSER <user> <host> * :<realname> starts a fresh reply
+            311 => {
+                self.pending.insert(
+                    key,
+                    WhoisInfo {
+                        nick: nick.clone(),
+                        user: arg(2).unwrap_or_default(),
+                        host: arg(3).unwrap_or_default(),
+                        realname: arg(5).unwrap_or_default(),
+                        ..WhoisInfo::default()
+                    },
+                );
+                return None;
+            }
+            312 | 313 | 317 | 319 | 330 | 338 | 671 | 276 => {
+                self.pending.entry(key).or_insert_with(|| WhoisInfo {
+                    nick: nick.clone(),
+                    ..WhoisInfo::default()
+                })
+            }
+            _ => return None,
+        };
+
+        match code.code() {
+            // RPL_WHOISSERVER <server> :<info>
+            312 => {
+                info.server = arg(2);
+                info.server_info = arg(3);
+            }
+            // RPL_WHOISOPERATOR
+            313 => info.oper = true,
+            // RPL_WHOISIDLE <idle secs> <signon> :seconds idle, signon time
+            317 => {
+                info.idle = arg(2)
+                    .and_then(|secs| secs.parse().ok())
+                    .map(Duration::from_secs);
+                info.signon = arg(3)
+                    .and_then(|secs| secs.parse().ok())
+                    .and_then(|secs| DateTime::from_timestamp(secs, 0));
+            }
+            // RPL_WHOISCHANNELS :<channels>, possibly split over several lines
+            319 => {
+                let channels = arg(2).unwrap_or_default();
+                info.channels
+                    .extend(channels.split_whitespace().map(str::to_string));
+            }
+            // RPL_WHOISACCOUNT <account> :is logged in as
+            330 => info.account = arg(2),
+            // RPL_WHOISACTUALLY [<user@host>] <ip> :<text>
+            338 => {
+                let fields = &args[2..args.len().saturating_sub(1).max(2)];
+                info.actual_host = Some(fields.join(" ")).filter(|h| !h.is_empty());
+            }
+            // RPL_WHOISSECURE
+            671 => info.secure = true,
+            // RPL_WHOISCERTFP :has client certificate fingerprint <fp>
+            276 => {
+                info.certfp = args
+                    .last()
+                    .and_then(|text| text.split_whitespace().last())
+                    .map(str::to_string);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Forget partial replies, e.g. after the connection dropped
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_util::parse;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_reply_collected_until_end() {
+        let mut whois = WhoisReplies::default();
+        let lines = [
+            ":srv 311 me Alice alice host.example * :Alice Liddell",
+            ":srv 319 me Alice :@#rust +#irc",
+            ":srv 319 me Alice :#offtopic",
+            ":srv 312 me Alice irc.example.net :Example server",
+            ":srv 313 me Alice :is an IRC operator",
+            ":srv 671 me Alice :is using a secure connection",
+            ":srv 330 me Alice alice_acct :is logged in as",
+            ":srv 338 me Alice alice@10.0.0.1 10.0.0.1 :Actual user@host, Actual IP",
+            ":srv 276 me Alice :has client certificate fingerprint abc123",
+            ":srv 317 me Alice 125 1709294400 :seconds idle, signon time",
+        ];
+        for line in lines {
+            assert!(whois.observe(&parse(line), CaseMapping::Rfc1459).is_none());
+        }
+
+        let Some(GuiEvent::WhoisResult(info)) = whois.observe(
+            &parse(":srv 318 me alice :End of /WHOIS list."),
+            CaseMapping::Rfc1459,
+        ) else {
+            panic!("RPL_ENDOFWHOIS should finish the reply");
+        };
+        assert_eq!(info.nick, "Alice");
+        assert_eq!(info.user, "alice");
+        assert_eq!(info.host, "host.example");
+        assert_eq!(info.realname, "Alice Liddell");
+        assert_eq!(info.channels, ["@#rust", "+#irc", "#offtopic"]);
+        assert_eq!(info.server.as_deref(), Some("irc.example.net"));
+        assert_eq!(info.server_info.as_deref(), Some("Example server"));
+        assert_eq!(info.account.as_deref(), Some("alice_acct"));
+        assert_eq!(info.actual_host.as_deref(), Some("alice@10.0.0.1 10.0.0.1"));
+        assert_eq!(info.certfp.as_deref(), Some("abc123"));
+        assert_eq!(info.idle, Some(Duration::from_secs(125)));
+        assert_eq!(
+            info.signon,
+            Some(Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap())
+        );
+        assert!(info.oper && info.secure);
+
+        // The reply is finished: another end has nothing to report
+        assert!(whois
+            .observe(
+                &parse(":srv 318 me Alice :End of /WHOIS list."),
+                CaseMapping::Rfc1459
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_nick_folded_under_casemapping() {
+        let mut whois = WhoisReplies::default();
+        let user = parse(":srv 311 me Dan[m] dan host.example * :Dan");
+        assert!(whois.observe(&user, CaseMapping::Rfc1459).is_none());
+        let end = parse(":srv 318 me dan{m} :End of /WHOIS list.");
+        assert!(matches!(
+            whois.observe(&end, CaseMapping::Rfc1459),
+            Some(GuiEvent::WhoisResult(info)) if info.nick == "Dan[m]"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_nick_gives_no_result() {
+        let mut whois = WhoisReplies::default();
+        assert!(whois
+            .observe(
+                &parse(":srv 401 me nobody :No such nick/channel"),
+                CaseMapping::Rfc1459
+            )
+            .is_none());
+        assert!(whois
+            .observe(
+                &parse(":srv 318 me nobody :End of /WHOIS list."),
+                CaseMapping::Rfc1459
+            )
+            .is_none());
+    }
+}
@@ -4,9 +4,13 @@
 /// - `connection`: TLS and TCP connection establishment
 /// - `handlers`: IRC message routing and event generation
 /// - `main_loop`: Core event loop and CAP negotiation state machine
+/// - `sender`: Network-tagged event channel used by connection tasks
 mod connection;
 mod handlers;
 mod main_loop;
+mod sender;
+#[cfg(test)]
+mod test_util;
 
 // Re-export the main backend entry points
 pub use main_loop::run_backend;
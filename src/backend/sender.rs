@@ -0,0 +1,36 @@
+//! Network-tagged event sender for per-network connection tasks.
+//!
+//! Every connection task owns an `EventSender` so handlers can keep emitting
+//! plain `GuiEvent`s while the UI still learns which network they belong to.
+//! Sending also wakes the UI, so it doesn't need to poll for events.
+
+use crossbeam_channel::{SendError, Sender};
+use eframe::egui;
+
+use crate::protocol::{GuiEvent, NetworkEvent, NetworkId};
+
+/// Sends `GuiEvent`s to the UI, tagging each with the owning network.
+#[derive(Clone)]
+pub struct EventSender {
+    network: NetworkId,
+    tx: Sender<NetworkEvent>,
+    repaint: egui::Context,
+}
+
+impl EventSender {
+    pub fn new(network: NetworkId, tx: Sender<NetworkEvent>, repaint: egui::Context) -> Self {
+        Self {
+            network,
+            tx,
+            repaint,
+        }
+    }
+
+    /// Send an event to the UI, tagged with this sender's network, and wake it
+    pub fn send(&self, event: GuiEvent) -> Result<(), SendError<NetworkEvent>> {
+        self.tx
+            .send(NetworkEvent::new(self.network.clone(), event))?;
+        self.repaint.request_repaint();
+        Ok(())
+    }
+}
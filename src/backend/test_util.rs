@@ -0,0 +1,24 @@
+//! Fixtures shared by the backend's unit tests
+
+use crossbeam_channel::Receiver;
+use slirc_proto::Message;
+
+use super::sender::EventSender;
+use crate::protocol::{GuiEvent, NetworkEvent};
+
+/// Parse a line as the server would send it
+pub fn parse(line: &str) -> Message {
+    line.parse().expect("valid IRC line")
+}
+
+/// An event sender for the `net` connection and the receiving end
+pub fn event_sender() -> (EventSender, Receiver<NetworkEvent>) {
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let sender = EventSender::new("net".into(), tx, eframe::egui::Context::default());
+    (sender, rx)
+}
+
+/// Events sent so far, without their network tag
+pub fn drain_events(rx: &Receiver<NetworkEvent>) -> Vec<GuiEvent> {
+    rx.try_iter().map(|e| e.event).collect()
+}
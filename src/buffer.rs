@@ -1,18 +1,45 @@
-use crate::protocol::UserInfo;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::channel_modes::ChannelModes;
+use crate::protocol::{HistoryAnchor, MessageTags, TypingState, UserInfo, REPLY_TAG};
 
 /// Maximum messages to keep in a buffer before trimming
 const MAX_BUFFER_MESSAGES: usize = 2000;
 /// Number of oldest messages to remove when trimming
 const BUFFER_TRIM_COUNT: usize = 500;
+/// Room for older scrollback loaded from the server, on top of
+/// `MAX_BUFFER_MESSAGES`
+const MAX_SCROLLBACK_MESSAGES: usize = 2000;
+/// An `active` typing notification lapses after this long without another
+const TYPING_ACTIVE_TIMEOUT: Duration = Duration::from_secs(6);
+/// A `paused` typing notification lapses after this long
+const TYPING_PAUSED_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Represents a rendered message with timestamp, sender info, and styled text
 #[derive(Clone, Debug)]
 pub struct RenderedMessage {
-    pub timestamp: String,
+    /// When the message was sent; formatted for display by the renderer
+    pub timestamp: DateTime<Utc>,
     pub sender: String,
     pub text: String,
     /// Message type for special rendering
     pub msg_type: MessageType,
+    /// Whether the server has relayed our own message back yet
+    pub delivery: Delivery,
+    /// IRCv3 tags from the server (`msgid`, `account`, client tags)
+    pub tags: MessageTags,
+    /// Emoji reactions (`+draft/react`), in the order first used
+    pub reactions: Vec<Reaction>,
+}
+
+/// Everyone who reacted to a message with the same emoji
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reaction {
+    pub emoji: String,
+    /// Nicks that reacted, earliest first
+    pub nicks: Vec<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -21,19 +48,35 @@ pub enum MessageType {
     Action, // /me messages
     Join,
     Part,
+    Kick,
     Quit,
     NickChange,
     Topic,
     Notice,
 }
 
+/// Delivery state of our own messages when the server supports `echo-message`
+#[derive(Clone, Debug, PartialEq, Default)]
+pub enum Delivery {
+    /// Shown as sent (everything that isn't waiting or failed)
+    #[default]
+    Delivered,
+    /// Waiting for the server's echo, with the backend's id for the line
+    Pending(u64),
+    /// The server rejected the line or never echoed it
+    Failed(String),
+}
+
 impl RenderedMessage {
-    pub fn new(timestamp: String, sender: String, text: String) -> Self {
+    pub fn new(timestamp: DateTime<Utc>, sender: String, text: String) -> Self {
         Self {
             timestamp,
             sender,
             text,
             msg_type: MessageType::Normal,
+            delivery: Delivery::Delivered,
+            tags: MessageTags::default(),
+            reactions: Vec::new(),
         }
     }
 
@@ -41,6 +84,92 @@ impl RenderedMessage {
         self.msg_type = msg_type;
         self
     }
+
+    pub fn with_delivery(mut self, delivery: Delivery) -> Self {
+        self.delivery = delivery;
+        self
+    }
+
+    pub fn with_tags(mut self, tags: MessageTags) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// `msgid` of the message this one replies to (`+draft/reply`)
+    pub fn reply_to(&self) -> Option<&str> {
+        self.tags.client_tag(REPLY_TAG).filter(|id| !id.is_empty())
+    }
+
+    /// Add `nick`'s reaction, or take it back when `added` is false
+    pub fn apply_reaction(&mut self, emoji: &str, nick: &str, added: bool) {
+        let index = self.reactions.iter().position(|r| r.emoji == emoji);
+        match (index, added) {
+            (Some(i), true) => {
+                let nicks = &mut self.reactions[i].nicks;
+                if !nicks.iter().any(|n| n == nick) {
+                    nicks.push(nick.to_string());
+                }
+            }
+            (None, true) => self.reactions.push(Reaction {
+                emoji: emoji.to_string(),
+                nicks: vec![nick.to_string()],
+            }),
+            (Some(i), false) => {
+                self.reactions[i].nicks.retain(|n| n != nick);
+                if self.reactions[i].nicks.is_empty() {
+                    self.reactions.remove(i);
+                }
+            }
+            (None, false) => {}
+        }
+    }
+
+    /// Whether `other` is the same message, e.g. seen live and again in history
+    fn is_same(&self, other: &RenderedMessage) -> bool {
+        match (&self.tags.msgid, &other.tags.msgid) {
+            (Some(a), Some(b)) => a == b,
+            // Without ids, fall back to what the server told us about it
+            _ => {
+                self.timestamp == other.timestamp
+                    && self.sender == other.sender
+                    && self.text == other.text
+            }
+        }
+    }
+}
+
+/// Someone's latest typing notification in a buffer
+#[derive(Clone, Debug)]
+pub struct Typist {
+    pub nick: String,
+    pub state: TypingState,
+    /// When the notification arrived
+    pub since: Instant,
+}
+
+impl Typist {
+    fn expired(&self, now: Instant) -> bool {
+        let timeout = match self.state {
+            TypingState::Active => TYPING_ACTIVE_TIMEOUT,
+            TypingState::Paused => TYPING_PAUSED_TIMEOUT,
+            TypingState::Done => Duration::ZERO,
+        };
+        now.duration_since(self.since) >= timeout
+    }
+}
+
+/// Progress of loading scrollback from the server into a buffer
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum HistoryState {
+    /// Nothing requested yet
+    #[default]
+    NotLoaded,
+    /// A request is in flight
+    Loading,
+    /// Loaded some; older messages may be available
+    Idle,
+    /// The server has nothing older (or doesn't support history)
+    Exhausted,
 }
 
 /// Represents a single buffer (channel, query, or system)
@@ -51,16 +180,35 @@ pub struct ChannelBuffer {
     pub users: Vec<UserInfo>,
     /// Topic of the channel
     pub topic: String,
+    /// Who set the topic, and when, if the server told us
+    pub topic_set_by: Option<String>,
+    pub topic_set_at: Option<DateTime<Utc>>,
+    /// We were kicked: the buffer stays open to show why, but we aren't in
+    /// the channel any more
+    pub left: bool,
     /// Number of unread messages
     pub unread_count: usize,
     /// Whether there is a highlight/mention in unread messages
     pub has_highlight: bool,
-    /// Channel modes (e.g., "mtn" for +m+t+n)
-    pub channel_modes: String,
+    /// Channel modes, including key, limit and any fetched mask lists
+    pub channel_modes: ChannelModes,
     /// Whether notifications are muted for this channel
     pub notifications_muted: bool,
-    /// List of pinned message IDs (indices in messages vec)
-    pub pinned_messages: Vec<usize>,
+    /// Server ids (`msgid`) of pinned messages
+    pub pinned_messages: Vec<String>,
+    /// Scrollback loading progress
+    pub history: HistoryState,
+    /// How many of the oldest messages were loaded as scrollback; trimming
+    /// leaves them alone so a page being read doesn't vanish
+    pub scrollback: usize,
+    /// Typing notifications from other users
+    pub typing: Vec<Typist>,
+    /// Time of the last message read here, shared with our other clients
+    /// through `draft/read-marker`
+    pub read_marker: Option<DateTime<Utc>>,
+    /// Where the "new messages" divider goes: the read marker as it was
+    /// when the buffer was opened
+    pub unread_divider: Option<DateTime<Utc>>,
 }
 
 impl ChannelBuffer {
@@ -69,11 +217,19 @@ impl ChannelBuffer {
             messages: Vec::new(),
             users: Vec::new(),
             topic: String::new(),
+            topic_set_by: None,
+            topic_set_at: None,
+            left: false,
             unread_count: 0,
             has_highlight: false,
-            channel_modes: String::new(),
+            channel_modes: ChannelModes::default(),
             notifications_muted: false,
             pinned_messages: Vec::new(),
+            history: HistoryState::NotLoaded,
+            scrollback: 0,
+            typing: Vec::new(),
+            read_marker: None,
+            unread_divider: None,
         }
     }
 
@@ -85,9 +241,146 @@ impl ChannelBuffer {
                 self.has_highlight = true;
             }
         }
-        // Trim old messages if buffer gets too large
-        if self.messages.len() > MAX_BUFFER_MESSAGES {
-            self.messages.drain(0..BUFFER_TRIM_COUNT);
+        // Trim the oldest live messages if the buffer gets too large
+        if self.messages.len().saturating_sub(self.scrollback) > MAX_BUFFER_MESSAGES {
+            let start = self.scrollback;
+            self.messages.drain(start..start + BUFFER_TRIM_COUNT);
+        }
+    }
+
+    /// Our own message still waiting for the echo with this id
+    pub fn find_pending_mut(&mut self, id: u64) -> Option<&mut RenderedMessage> {
+        self.messages
+            .iter_mut()
+            .rev()
+            .find(|m| m.delivery == Delivery::Pending(id))
+    }
+
+    /// Mutable access to the message with server id `msgid`
+    pub fn find_by_msgid_mut(&mut self, msgid: &str) -> Option<&mut RenderedMessage> {
+        self.messages
+            .iter_mut()
+            .rev()
+            .find(|m| m.tags.msgid.as_deref() == Some(msgid))
+    }
+
+    /// The message with server id `msgid`, if it is still in the buffer
+    pub fn find_by_msgid(&self, msgid: &str) -> Option<&RenderedMessage> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.tags.msgid.as_deref() == Some(msgid))
+    }
+
+    /// Merge a page of scrollback into the buffer, skipping messages we
+    /// already have. Messages older than ours go in front, in page order;
+    /// newer ones are slotted in by time. Messages we had are never moved,
+    /// so live lines and lines waiting for their echo keep their place.
+    ///
+    /// Returns how many were put in front, which is where the message that
+    /// was oldest before now sits.
+    pub fn insert_history(&mut self, history: Vec<RenderedMessage>) -> usize {
+        let oldest = self.messages.first().map(|m| m.timestamp);
+        let mut front: Vec<RenderedMessage> = Vec::new();
+        for msg in history {
+            if self.messages.iter().chain(&front).any(|m| m.is_same(&msg)) {
+                continue;
+            }
+            match oldest {
+                Some(oldest) if msg.timestamp > oldest => {
+                    let index = self
+                        .messages
+                        .partition_point(|m| m.timestamp <= msg.timestamp);
+                    self.messages.insert(index, msg);
+                }
+                _ => front.push(msg),
+            }
+        }
+
+        let added = front.len();
+        self.messages.splice(0..0, front);
+        self.scrollback += added;
+
+        // Over the cap, drop the oldest of what we had rather than the page
+        // just loaded
+        let limit = MAX_BUFFER_MESSAGES + MAX_SCROLLBACK_MESSAGES;
+        if self.messages.len() > limit {
+            let excess = self.messages.len() - limit;
+            self.messages.drain(added..added + excess);
+            self.scrollback = self.scrollback.saturating_sub(excess).max(added);
+        }
+        added
+    }
+
+    /// Whether scrolling to the top should ask the server for older messages
+    pub fn can_load_older(&self) -> bool {
+        matches!(self.history, HistoryState::NotLoaded | HistoryState::Idle)
+            && !self.messages.is_empty()
+            && self.scrollback < MAX_SCROLLBACK_MESSAGES
+    }
+
+    /// Request anchor for the messages before the oldest one we have
+    pub fn older_history_anchor(&self) -> Option<HistoryAnchor> {
+        let oldest = self.messages.first()?;
+        Some(HistoryAnchor::Before {
+            msgid: oldest.tags.msgid.clone(),
+            time: oldest.timestamp,
+        })
+    }
+
+    /// Record a typing notification; `done` (or a message, see
+    /// `stop_typing`) removes the nick
+    pub fn set_typing(&mut self, nick: &str, state: TypingState, now: Instant) {
+        self.typing.retain(|t| t.nick != nick && !t.expired(now));
+        if state != TypingState::Done {
+            self.typing.push(Typist {
+                nick: nick.to_string(),
+                state,
+                since: now,
+            });
+        }
+    }
+
+    /// `nick` sent a message, which ends their typing
+    pub fn stop_typing(&mut self, nick: &str) {
+        self.typing.retain(|t| t.nick != nick);
+    }
+
+    /// Nicks actively typing that haven't timed out, in the order they started
+    pub fn typing_nicks(&self, now: Instant) -> Vec<&str> {
+        self.typing
+            .iter()
+            .filter(|t| t.state == TypingState::Active && !t.expired(now))
+            .map(|t| t.nick.as_str())
+            .collect()
+    }
+
+    /// The buffer is being viewed: everything in it is read. `opened` is
+    /// true on the first frame it is shown, which places the divider.
+    /// Returns the new read marker if it moved, to pass on to the server.
+    pub fn mark_read(&mut self, opened: bool) -> Option<DateTime<Utc>> {
+        self.clear_unread();
+        let latest = self.messages.iter().map(|m| m.timestamp).max()?;
+        if opened {
+            self.unread_divider = self.read_marker.filter(|read| *read < latest);
+        }
+        if self.read_marker.is_some_and(|read| read >= latest) {
+            return None;
+        }
+        self.read_marker = Some(latest);
+        Some(latest)
+    }
+
+    /// Another of our clients read up to `time`; it never moves backwards
+    pub fn apply_read_marker(&mut self, time: DateTime<Utc>) {
+        if self.read_marker.is_some_and(|read| read >= time) {
+            return;
+        }
+        self.read_marker = Some(time);
+        let unread = self.messages.iter().filter(|m| m.timestamp > time).count();
+        self.unread_count = self.unread_count.min(unread);
+        if self.unread_count == 0 {
+            self.has_highlight = false;
         }
     }
 
@@ -100,6 +393,7 @@ impl ChannelBuffer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
 
     #[test]
     fn test_add_message_unread_and_trim() {
@@ -107,7 +401,7 @@ mod tests {
         // Add unread messages
         for i in 0..10 {
             buf.add_message(
-                RenderedMessage::new(format!("10:{:02}", i), "alice".into(), format!("msg{}", i)),
+                RenderedMessage::new(Utc::now(), "alice".into(), format!("msg{}", i)),
                 false,
                 false,
             );
@@ -117,7 +411,7 @@ mod tests {
         // Trim by adding many messages until we exceed MAX_BUFFER_MESSAGES
         for i in 0..(MAX_BUFFER_MESSAGES + 10) {
             buf.add_message(
-                RenderedMessage::new(format!("11:{:02}", i), "bob".into(), "X".into()),
+                RenderedMessage::new(Utc::now(), "bob".into(), "X".into()),
                 true,
                 false,
             );
@@ -130,7 +424,7 @@ mod tests {
     fn test_clear_unread() {
         let mut buf = ChannelBuffer::new();
         buf.add_message(
-            RenderedMessage::new("12:00".into(), "a".into(), "hello".into()),
+            RenderedMessage::new(Utc::now(), "a".into(), "hello".into()),
             false,
             false,
         );
@@ -139,4 +433,206 @@ mod tests {
         assert_eq!(buf.unread_count, 0);
         assert!(!buf.has_highlight);
     }
+
+    #[test]
+    fn test_find_pending() {
+        let mut buf = ChannelBuffer::new();
+        buf.add_message(
+            RenderedMessage::new(Utc::now(), "me".into(), "hello".into())
+                .with_delivery(Delivery::Pending(7)),
+            true,
+            false,
+        );
+        assert!(buf.find_pending_mut(3).is_none());
+        let msg = buf.find_pending_mut(7).expect("pending message");
+        msg.delivery = Delivery::Delivered;
+        assert!(buf.find_pending_mut(7).is_none());
+    }
+
+    #[test]
+    fn test_insert_history_dedupes_and_orders() {
+        let at = |min| Utc.with_ymd_and_hms(2024, 3, 1, 12, min, 0).unwrap();
+        let msgid = |id: &str| MessageTags {
+            msgid: Some(id.into()),
+            ..Default::default()
+        };
+        let mut buf = ChannelBuffer::new();
+        buf.add_message(
+            RenderedMessage::new(at(5), "alice".into(), "live".into()),
+            true,
+            false,
+        );
+
+        let history = vec![
+            RenderedMessage::new(at(1), "bob".into(), "old".into()).with_tags(msgid("m1")),
+            // Same line we already saw live, found again without an id
+            RenderedMessage::new(at(5), "alice".into(), "live".into()),
+        ];
+        assert_eq!(buf.insert_history(history), 1);
+        assert_eq!(buf.messages[0].text, "old");
+        assert_eq!(buf.messages[1].text, "live");
+
+        // A second page repeating m1 adds nothing
+        let again =
+            vec![RenderedMessage::new(at(1), "bob".into(), "old".into()).with_tags(msgid("m1"))];
+        assert_eq!(buf.insert_history(again), 0);
+        assert_eq!(
+            buf.older_history_anchor(),
+            Some(HistoryAnchor::Before {
+                msgid: Some("m1".into()),
+                time: at(1),
+            })
+        );
+    }
+
+    #[test]
+    fn test_full_page_into_full_buffer() {
+        let at = |secs| Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap();
+        let mut buf = ChannelBuffer::new();
+        for i in 0..MAX_BUFFER_MESSAGES as i64 {
+            buf.add_message(
+                RenderedMessage::new(at(1000 + i), "alice".into(), format!("live{}", i)),
+                true,
+                false,
+            );
+        }
+        let page: Vec<_> = (0..50)
+            .map(|i| RenderedMessage::new(at(i), "bob".into(), format!("old{}", i)))
+            .collect();
+        assert_eq!(buf.insert_history(page), 50);
+        assert_eq!(buf.messages.len(), MAX_BUFFER_MESSAGES + 50);
+        assert_eq!(buf.messages[0].text, "old0");
+        assert_eq!(buf.messages[50].text, "live0");
+
+        // The next live line trims live lines, not the page being read
+        buf.add_message(
+            RenderedMessage::new(at(5000), "alice".into(), "new".into()),
+            true,
+            false,
+        );
+        assert_eq!(buf.messages[49].text, "old49");
+        assert_eq!(buf.messages[50].text, format!("live{}", BUFFER_TRIM_COUNT));
+        assert_eq!(buf.messages.last().unwrap().text, "new");
+        assert_eq!(
+            buf.messages.len(),
+            MAX_BUFFER_MESSAGES + 51 - BUFFER_TRIM_COUNT
+        );
+    }
+
+    #[test]
+    fn test_history_does_not_move_live_lines() {
+        let at = |min| Utc.with_ymd_and_hms(2024, 3, 1, 12, min, 0).unwrap();
+        let mut buf = ChannelBuffer::new();
+        // Our line, stamped by our clock, still waiting for its echo
+        buf.add_message(
+            RenderedMessage::new(at(10), "me".into(), "pending".into())
+                .with_delivery(Delivery::Pending(1)),
+            true,
+            false,
+        );
+        buf.add_message(
+            RenderedMessage::new(at(9), "alice".into(), "live".into()),
+            true,
+            false,
+        );
+        let page = vec![
+            RenderedMessage::new(at(1), "bob".into(), "old".into()),
+            RenderedMessage::new(at(11), "carol".into(), "newer".into()),
+        ];
+        assert_eq!(buf.insert_history(page), 1);
+        let texts: Vec<&str> = buf.messages.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, ["old", "pending", "live", "newer"]);
+    }
+
+    #[test]
+    fn test_reply_finds_parent() {
+        let mut buf = ChannelBuffer::new();
+        let parent = RenderedMessage::new(Utc::now(), "alice".into(), "question?".into())
+            .with_tags(MessageTags {
+                msgid: Some("p1".into()),
+                ..Default::default()
+            });
+        let reply = RenderedMessage::new(Utc::now(), "bob".into(), "answer".into()).with_tags(
+            MessageTags {
+                client: vec![(REPLY_TAG.into(), "p1".into())],
+                ..Default::default()
+            },
+        );
+        buf.add_message(parent, true, false);
+        buf.add_message(reply, true, false);
+
+        let reply = &buf.messages[1];
+        assert_eq!(reply.reply_to(), Some("p1"));
+        assert_eq!(buf.find_by_msgid("p1").unwrap().text, "question?");
+        assert!(buf.find_by_msgid("gone").is_none());
+        assert!(buf.messages[0].reply_to().is_none());
+    }
+
+    #[test]
+    fn test_reactions_aggregate_and_toggle() {
+        let mut msg = RenderedMessage::new(Utc::now(), "alice".into(), "ship it".into());
+        msg.apply_reaction("👍", "bob", true);
+        msg.apply_reaction("👍", "carol", true);
+        msg.apply_reaction("👍", "bob", true);
+        msg.apply_reaction("🎉", "bob", true);
+        assert_eq!(msg.reactions.len(), 2);
+        assert_eq!(msg.reactions[0].nicks, vec!["bob", "carol"]);
+
+        msg.apply_reaction("👍", "bob", false);
+        assert_eq!(msg.reactions[0].nicks, vec!["carol"]);
+        msg.apply_reaction("🎉", "bob", false);
+        assert_eq!(msg.reactions.len(), 1);
+        msg.apply_reaction("😂", "bob", false);
+        assert_eq!(msg.reactions.len(), 1);
+    }
+
+    #[test]
+    fn test_typing_expires() {
+        let mut buf = ChannelBuffer::new();
+        let start = Instant::now();
+        buf.set_typing("alice", TypingState::Active, start);
+        buf.set_typing("bob", TypingState::Paused, start);
+        assert_eq!(buf.typing_nicks(start), vec!["alice"]);
+
+        // Active lapses after 6s; paused is kept (but not shown) for 30s
+        let later = start + Duration::from_secs(7);
+        assert!(buf.typing_nicks(later).is_empty());
+        buf.set_typing("carol", TypingState::Active, later);
+        assert_eq!(buf.typing.len(), 2);
+
+        buf.set_typing("carol", TypingState::Done, later);
+        buf.set_typing("bob", TypingState::Active, later);
+        buf.stop_typing("bob");
+        assert!(buf.typing.is_empty());
+    }
+
+    #[test]
+    fn test_read_marker() {
+        let at = |m| Utc.with_ymd_and_hms(2024, 1, 1, 12, m, 0).unwrap();
+        let mut buf = ChannelBuffer::new();
+        for m in 0..4 {
+            let msg = RenderedMessage::new(at(m), "alice".into(), format!("{}", m));
+            buf.add_message(msg, false, m == 3);
+        }
+        assert_eq!(buf.unread_count, 4);
+
+        // Read elsewhere up to the second message
+        buf.apply_read_marker(at(1));
+        assert_eq!(buf.unread_count, 2);
+        assert!(buf.has_highlight);
+        // Older markers are ignored
+        buf.apply_read_marker(at(0));
+        assert_eq!(buf.read_marker, Some(at(1)));
+
+        // Opening it reads the rest, with the divider where we were
+        assert_eq!(buf.mark_read(true), Some(at(3)));
+        assert_eq!(buf.unread_divider, Some(at(1)));
+        assert_eq!(buf.unread_count, 0);
+        assert!(!buf.has_highlight);
+        assert_eq!(buf.mark_read(false), None);
+
+        // Reopened with nothing new: no divider
+        buf.mark_read(true);
+        assert_eq!(buf.unread_divider, None);
+    }
 }
@@ -0,0 +1,350 @@
+//! Channel mode state: flags, key, limit and the mask lists.
+//!
+//! A channel's modes are filled from RPL_CHANNELMODEIS (324) after joining
+//! and kept current from live MODE lines. List modes (bans, exceptions,
+//! invite exceptions, quiets) are only known once their list has been
+//! requested (367/348/346/728), and are then updated by MODE as well.
+//! Which letters take parameters comes from the server's ISUPPORT.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+
+use crate::server_support::{ChannelModeKind, ServerSupport};
+
+/// One `+x [arg]` or `-x [arg]` step of a MODE line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeChange {
+    pub added: bool,
+    pub mode: char,
+    pub arg: Option<String>,
+}
+
+impl ModeChange {
+    /// Split a mode string and its arguments (`+kl-t`, `[secret, 10]`) into
+    /// single changes. Which modes consume an argument follows ISUPPORT;
+    /// unknown modes are taken to be flags.
+    pub fn parse(modes: &str, args: &[String], support: &ServerSupport) -> Vec<ModeChange> {
+        let mut args = args.iter();
+        let mut added = true;
+        let mut changes = Vec::new();
+        for mode in modes.chars() {
+            match mode {
+                '+' => added = true,
+                '-' => added = false,
+                _ => {
+                    let takes_arg = match support.mode_kind(mode) {
+                        Some(
+                            ChannelModeKind::List
+                            | ChannelModeKind::AlwaysParam
+                            | ChannelModeKind::Prefix,
+                        ) => true,
+                        Some(ChannelModeKind::SetParam) => added,
+                        Some(ChannelModeKind::Flag) | None => false,
+                    };
+                    let arg = if takes_arg {
+                        args.next().cloned()
+                    } else {
+                        None
+                    };
+                    changes.push(ModeChange { added, mode, arg });
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// A MODE line's mode string (`+kl-t`) and arguments (`secret`, `10`)
+pub fn mode_line(changes: &[ModeChange]) -> (String, Vec<&str>) {
+    let mut modes = String::new();
+    let mut args = Vec::new();
+    let mut sign = None;
+    for change in changes {
+        if sign != Some(change.added) {
+            modes.push(if change.added { '+' } else { '-' });
+            sign = Some(change.added);
+        }
+        modes.push(change.mode);
+        args.extend(change.arg.as_deref());
+    }
+    (modes, args)
+}
+
+/// Changes as they appear on a MODE line, e.g. `+kl-t secret 10`
+pub fn format_mode_changes(changes: &[ModeChange]) -> String {
+    let (modes, args) = mode_line(changes);
+    std::iter::once(modes.as_str())
+        .chain(args)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Split changes into MODE lines with at most `max_params` changes that
+/// take a parameter each (ISUPPORT `MODES`; `None` = no limit)
+pub fn batch_mode_changes(changes: &[ModeChange], max_params: Option<usize>) -> Vec<&[ModeChange]> {
+    let mut batches = Vec::new();
+    let (mut start, mut params) = (0, 0);
+    for (i, change) in changes.iter().enumerate() {
+        if change.arg.is_none() {
+            continue;
+        }
+        if max_params.is_some_and(|max| params >= max.max(1)) {
+            batches.push(&changes[start..i]);
+            start = i;
+            params = 0;
+        }
+        params += 1;
+    }
+    if start < changes.len() {
+        batches.push(&changes[start..]);
+    }
+    batches
+}
+
+/// A ban, exception, invite exception or quiet mask
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModeListEntry {
+    pub mask: String,
+    /// Who set it, if the server said
+    pub set_by: Option<String>,
+    /// When it was set, if the server said
+    pub set_at: Option<DateTime<Utc>>,
+}
+
+/// Modes of one channel
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelModes {
+    /// Plain modes that are set (`m`, `t`, ...)
+    pub flags: BTreeSet<char>,
+    /// Channel key (+k); empty if the server hides it from us
+    pub key: Option<String>,
+    /// User limit (+l)
+    pub limit: Option<u32>,
+    /// Other modes set with a parameter (e.g. `+f`, `+j`), by letter
+    pub params: BTreeMap<char, String>,
+    /// Mask lists by mode letter; a list is missing until it is fetched
+    pub lists: BTreeMap<char, Vec<ModeListEntry>>,
+}
+
+impl ChannelModes {
+    /// Whether `mode` is set, as a flag or with a parameter
+    pub fn is_set(&self, mode: char) -> bool {
+        match mode {
+            'k' => self.key.is_some(),
+            'l' => self.limit.is_some(),
+            _ => self.flags.contains(&mode) || self.params.contains_key(&mode),
+        }
+    }
+
+    /// The set modes as one string, e.g. `+klnt`; empty if none are set
+    pub fn mode_string(&self) -> String {
+        let mut letters: Vec<char> = self
+            .flags
+            .iter()
+            .chain(self.params.keys())
+            .copied()
+            .chain(self.key.as_ref().map(|_| 'k'))
+            .chain(self.limit.map(|_| 'l'))
+            .collect();
+        if letters.is_empty() {
+            return String::new();
+        }
+        letters.sort_unstable();
+        std::iter::once('+').chain(letters).collect()
+    }
+
+    /// Apply one live change made by `set_by` at `time`. Membership modes
+    /// are not channel state and are ignored.
+    pub fn apply(
+        &mut self,
+        change: &ModeChange,
+        support: &ServerSupport,
+        set_by: &str,
+        time: DateTime<Utc>,
+    ) {
+        let ModeChange { added, mode, arg } = change;
+        match support.mode_kind(*mode) {
+            Some(ChannelModeKind::Prefix) => {}
+            Some(ChannelModeKind::List) => {
+                let Some(mask) = arg else {
+                    return;
+                };
+                // Only lists we have fetched are kept, so they stay complete
+                let Some(list) = self.lists.get_mut(mode) else {
+                    return;
+                };
+                let mapping = support.case_mapping();
+                list.retain(|entry| !mapping.names_equal(&entry.mask, mask));
+                if *added {
+                    list.push(ModeListEntry {
+                        mask: mask.clone(),
+                        set_by: Some(set_by.to_string()),
+                        set_at: Some(time),
+                    });
+                }
+            }
+            _ => match (*mode, *added) {
+                ('k', true) => self.key = Some(arg.clone().unwrap_or_default()),
+                ('k', false) => self.key = None,
+                ('l', true) => self.limit = arg.as_deref().and_then(|n| n.parse().ok()),
+                ('l', false) => self.limit = None,
+                (mode, true) => match arg {
+                    Some(arg) => {
+                        self.params.insert(mode, arg.clone());
+                    }
+                    None => {
+                        self.flags.insert(mode);
+                    }
+                },
+                (mode, false) => {
+                    self.flags.remove(&mode);
+                    self.params.remove(&mode);
+                }
+            },
+        }
+    }
+
+    /// Replace everything but the lists with the modes of RPL_CHANNELMODEIS
+    pub fn reset(&mut self, changes: &[ModeChange], support: &ServerSupport) {
+        let lists = std::mem::take(&mut self.lists);
+        *self = Self {
+            lists,
+            ..Self::default()
+        };
+        for change in changes {
+            self.apply(change, support, "", Utc::now());
+        }
+    }
+
+    /// Entries of list mode `mode`; empty if not fetched
+    pub fn list(&self, mode: char) -> &[ModeListEntry] {
+        self.lists.get(&mode).map_or(&[][..], Vec::as_slice)
+    }
+
+    /// Take a freshly fetched list
+    pub fn set_list(&mut self, mode: char, entries: Vec<ModeListEntry>) {
+        self.lists.insert(mode, entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn support() -> ServerSupport {
+        let mut support = ServerSupport::default();
+        support.apply(&["PREFIX=(ov)@+", "CHANMODES=beIq,k,fl,imnrst"]);
+        support
+    }
+
+    fn change(added: bool, mode: char, arg: Option<&str>) -> ModeChange {
+        ModeChange {
+            added,
+            mode,
+            arg: arg.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_follows_chanmodes() {
+        let args: Vec<String> = ["secret", "10", "*!*@bad", "alice"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let changes = ModeChange::parse("+kln-lb+o", &args, &support());
+        assert_eq!(
+            changes,
+            [
+                change(true, 'k', Some("secret")),
+                change(true, 'l', Some("10")),
+                change(true, 'n', None),
+                // -l takes no parameter
+                change(false, 'l', None),
+                change(false, 'b', Some("*!*@bad")),
+                change(true, 'o', Some("alice")),
+            ]
+        );
+        assert_eq!(
+            format_mode_changes(&changes),
+            "+kln-lb+o secret 10 *!*@bad alice"
+        );
+    }
+
+    #[test]
+    fn test_batches_respect_modes_limit() {
+        let changes = [
+            change(true, 'm', None),
+            change(true, 'b', Some("a!*@*")),
+            change(true, 'b', Some("b!*@*")),
+            change(false, 'b', Some("c!*@*")),
+            change(false, 't', None),
+            change(true, 'l', Some("10")),
+        ];
+        let lines: Vec<String> = batch_mode_changes(&changes, Some(2))
+            .into_iter()
+            .map(format_mode_changes)
+            .collect();
+        assert_eq!(lines, ["+mbb a!*@* b!*@*", "-bt+l c!*@* 10"]);
+        assert_eq!(batch_mode_changes(&changes, None).len(), 1);
+        assert!(batch_mode_changes(&[], Some(3)).is_empty());
+    }
+
+    #[test]
+    fn test_apply_and_reset() {
+        let support = support();
+        let time = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let mut modes = ChannelModes::default();
+        modes.reset(
+            &ModeChange::parse("+ntkl", &["secret".into(), "50".into()], &support),
+            &support,
+        );
+        assert_eq!(modes.mode_string(), "+klnt");
+        assert_eq!(modes.key.as_deref(), Some("secret"));
+        assert_eq!(modes.limit, Some(50));
+
+        modes.apply(&change(false, 'k', Some("secret")), &support, "op", time);
+        modes.apply(&change(true, 'f', Some("#overflow")), &support, "op", time);
+        modes.apply(&change(false, 't', None), &support, "op", time);
+        assert_eq!(modes.mode_string(), "+fln");
+        assert!(modes.is_set('f') && !modes.is_set('k'));
+
+        // 324 replaces the modes
+        modes.reset(&ModeChange::parse("+m", &[], &support), &support);
+        assert_eq!(modes.mode_string(), "+m");
+        assert_eq!(modes.limit, None);
+    }
+
+    #[test]
+    fn test_lists_track_fetched_only() {
+        let support = support();
+        let time = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let mut modes = ChannelModes::default();
+        // Not fetched yet: a partial list would look complete
+        modes.apply(&change(true, 'b', Some("*!*@a")), &support, "op", time);
+        assert!(modes.lists.get(&'b').is_none());
+
+        modes.set_list(
+            'b',
+            vec![ModeListEntry {
+                mask: "*!*@old".into(),
+                set_by: None,
+                set_at: None,
+            }],
+        );
+        modes.apply(&change(true, 'b', Some("*!*@new")), &support, "op", time);
+        modes.apply(&change(false, 'b', Some("*!*@OLD")), &support, "op", time);
+        assert_eq!(
+            modes.list('b'),
+            [ModeListEntry {
+                mask: "*!*@new".into(),
+                set_by: Some("op".into()),
+                set_at: Some(time),
+            }]
+        );
+        // Kept across 324
+        modes.reset(&[], &support);
+        assert_eq!(modes.list('b').len(), 1);
+    }
+}
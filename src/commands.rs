@@ -3,18 +3,21 @@
 use crossbeam_channel::Sender;
 use slirc_proto::ctcp::Ctcp;
 
-use crate::protocol::BackendAction;
+use crate::protocol::{BackendAction, NetworkAction};
+use crate::server_support::ServerSupport;
 use crate::validation;
 
 /// Handle user commands starting with '/'.
+/// Commands are sent to `network` (the network of the active buffer).
 /// Returns true if the input was a command (and should be cleared), false otherwise.
 pub fn handle_user_command(
     message_input: &str,
+    network: &str,
     active_buffer: &str,
-    buffers: &std::collections::HashMap<String, crate::buffer::ChannelBuffer>,
-    action_tx: &Sender<BackendAction>,
+    buffers: &std::collections::HashMap<crate::irc_name::IrcName, crate::buffer::ChannelBuffer>,
+    support: &ServerSupport,
+    action_tx: &Sender<NetworkAction>,
     system_log: &mut Vec<String>,
-    connection_nickname: &mut String,
 ) -> bool {
     let s = message_input.trim();
     if !s.starts_with('/') {
@@ -25,36 +28,29 @@ pub fn handle_user_command(
     let cmdline = s[1..].trim();
     let mut parts = cmdline.split_whitespace();
     let cmd = parts.next().unwrap_or("").to_lowercase();
+    let send = |action: BackendAction| action_tx.send(NetworkAction::new(network, action));
 
     match cmd.as_str() {
         "join" | "j" => {
             if let Some(chan) = parts.next() {
-                let channel = if chan.starts_with('#') || chan.starts_with('&') {
-                    chan.to_string()
-                } else {
-                    format!("#{}", chan)
-                };
+                let channel = support.channel_name(chan);
 
                 // Validate channel name
-                if let Err(e) = validation::validate_channel_name(&channel) {
+                if let Err(e) = validation::validate_channel_name(&channel, support) {
                     system_log.push(format!("Invalid channel name: {}", e));
                     return true;
                 }
 
-                let _ = action_tx.send(BackendAction::Join(channel));
+                let _ = send(BackendAction::Join(channel));
             } else {
                 system_log.push("Usage: /join <channel>".into());
             }
         }
         "part" | "p" => {
             if let Some(chan) = parts.next() {
-                let channel = if chan.starts_with('#') || chan.starts_with('&') {
-                    chan.to_string()
-                } else {
-                    format!("#{}", chan)
-                };
+                let channel = support.channel_name(chan);
                 let reason = parts.collect::<Vec<_>>().join(" ");
-                let _ = action_tx.send(BackendAction::Part {
+                let _ = send(BackendAction::Part {
                     channel,
                     message: if reason.is_empty() {
                         None
@@ -64,10 +60,10 @@ pub fn handle_user_command(
                 });
             } else {
                 // If no channel was provided, part the active buffer if it's a channel
-                if active_buffer.starts_with('#') || active_buffer.starts_with('&') {
+                if support.is_channel(active_buffer) {
                     let channel = active_buffer.to_string();
                     let reason = parts.collect::<Vec<_>>().join(" ");
-                    let _ = action_tx.send(BackendAction::Part {
+                    let _ = send(BackendAction::Part {
                         channel,
                         message: if reason.is_empty() {
                             None
@@ -87,12 +83,29 @@ pub fn handle_user_command(
                     system_log.push("Usage: /msg <target> <message>".into());
                 } else {
                     let target = target.to_string();
-                    let _ = action_tx.send(BackendAction::SendMessage { target, text });
+                    let _ = send(BackendAction::SendMessage {
+                        target,
+                        text,
+                        client_tags: Vec::new(),
+                    });
                 }
             } else {
                 system_log.push("Usage: /msg <target> <message>".into());
             }
         }
+        "notice" => {
+            if let Some(target) = parts.next() {
+                let text = parts.collect::<Vec<_>>().join(" ");
+                if text.is_empty() {
+                    system_log.push("Usage: /notice <target> <message>".into());
+                } else {
+                    let target = target.to_string();
+                    let _ = send(BackendAction::SendNotice { target, text });
+                }
+            } else {
+                system_log.push("Usage: /notice <target> <message>".into());
+            }
+        }
         "me" => {
             let text = parts.collect::<Vec<_>>().join(" ");
             if text.is_empty() {
@@ -103,9 +116,10 @@ pub fn handle_user_command(
                 // Send to active buffer
                 if active_buffer != "System" {
                     let target = active_buffer.to_string();
-                    let _ = action_tx.send(BackendAction::SendMessage {
+                    let _ = send(BackendAction::SendMessage {
                         target,
                         text: action_text,
+                        client_tags: Vec::new(),
                     });
                 } else {
                     system_log.push("/me can only be used in a channel or PM".into());
@@ -114,7 +128,7 @@ pub fn handle_user_command(
         }
         "whois" | "w" => {
             if let Some(target) = parts.next() {
-                let _ = action_tx.send(BackendAction::Whois(target.to_string()));
+                let _ = send(BackendAction::Whois(target.to_string()));
             } else {
                 system_log.push("Usage: /whois <nick>".into());
             }
@@ -122,9 +136,9 @@ pub fn handle_user_command(
         "topic" | "t" => {
             // If no argument provided, show current topic for active buffer
             let new_topic = parts.collect::<Vec<_>>().join(" ");
-            if active_buffer.starts_with('#') || active_buffer.starts_with('&') {
+            if support.is_channel(active_buffer) {
                 if new_topic.is_empty() {
-                    if let Some(buffer) = buffers.get(active_buffer) {
+                    if let Some(buffer) = buffers.get(&support.irc_name(active_buffer)) {
                         if buffer.topic.is_empty() {
                             system_log.push(format!("No topic set for {}", active_buffer));
                         } else {
@@ -133,7 +147,7 @@ pub fn handle_user_command(
                         }
                     }
                 } else {
-                    let _ = action_tx.send(BackendAction::SetTopic {
+                    let _ = send(BackendAction::SetTopic {
                         channel: active_buffer.to_string(),
                         topic: new_topic,
                     });
@@ -145,8 +159,8 @@ pub fn handle_user_command(
         "kick" | "k" => {
             if let Some(nick) = parts.next() {
                 let reason = parts.collect::<Vec<_>>().join(" ");
-                if active_buffer.starts_with('#') || active_buffer.starts_with('&') {
-                    let _ = action_tx.send(BackendAction::Kick {
+                if support.is_channel(active_buffer) {
+                    let _ = send(BackendAction::Kick {
                         channel: active_buffer.to_string(),
                         nick: nick.to_string(),
                         reason: if reason.is_empty() {
@@ -164,27 +178,26 @@ pub fn handle_user_command(
         }
         "nick" => {
             if let Some(newnick) = parts.next() {
-                // Update locally and send to server
-                *connection_nickname = newnick.to_string();
-                let _ = action_tx.send(BackendAction::Nick(newnick.to_string()));
+                // Our nick only changes once the server confirms it
+                let _ = send(BackendAction::Nick(newnick.to_string()));
             } else {
                 system_log.push("Usage: /nick <newnick>".into());
             }
         }
         "quit" | "exit" => {
             let reason = parts.collect::<Vec<_>>().join(" ");
-            let _ = action_tx.send(BackendAction::Quit(if reason.is_empty() {
+            let _ = send(BackendAction::Quit(if reason.is_empty() {
                 None
             } else {
                 Some(reason)
             }));
         }
         "list" => {
-            let _ = action_tx.send(BackendAction::List);
+            let _ = send(BackendAction::List);
             system_log.push("Requesting channel list from server...".into());
         }
         "help" => {
-            system_log.push("Supported commands: /join, /part, /msg, /me, /nick, /quit, /whois, /topic, /kick, /list".into());
+            system_log.push("Supported commands: /join, /part, /msg, /notice, /me, /nick, /quit, /whois, /topic, /kick, /list".into());
         }
         unknown => {
             system_log.push(format!("Unknown command: /{}", unknown));
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Local, Utc};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -6,6 +7,25 @@ use std::path::PathBuf;
 
 // Default configuration
 pub const DEFAULT_SERVER: &str = "irc.slirc.net:6667";
+pub const DEFAULT_PING_TIMEOUT_SECS: u64 = 120;
+pub const DEFAULT_FLOOD_BURST: u32 = 5;
+pub const DEFAULT_FLOOD_REFILL_MS: u64 = 2000;
+
+fn default_ping_timeout_secs() -> u64 {
+    DEFAULT_PING_TIMEOUT_SECS
+}
+
+fn default_flood_burst() -> u32 {
+    DEFAULT_FLOOD_BURST
+}
+
+fn default_flood_refill_ms() -> u64 {
+    DEFAULT_FLOOD_REFILL_MS
+}
+
+fn default_send_typing() -> bool {
+    true
+}
 
 /// Represents a saved IRC network with connection settings
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -13,6 +33,8 @@ pub struct Network {
     pub name: String,
     pub servers: Vec<String>, // e.g. ["irc.libera.chat:6667", "irc.libera.chat:6697"]
     pub nick: String,
+    #[serde(default)]
+    pub alt_nicks: Vec<String>, // Tried in order if the nick is taken at connect time
     pub auto_connect: bool,
     pub favorite_channels: Vec<String>, // Auto-join channels
     #[serde(default, skip_serializing)]
@@ -21,6 +43,14 @@ pub struct Network {
     pub use_tls: bool, // Whether to use TLS for this network
     #[serde(default)]
     pub auto_reconnect: bool, // Whether to automatically reconnect on connection loss
+    #[serde(default = "default_ping_timeout_secs")]
+    pub ping_timeout_secs: u64, // Silence before the connection is considered dead
+    #[serde(default = "default_flood_burst")]
+    pub flood_burst: u32, // Lines that may be sent back to back
+    #[serde(default = "default_flood_refill_ms")]
+    pub flood_refill_ms: u64, // Time to regain one line after the burst
+    #[serde(default = "default_send_typing")]
+    pub send_typing: bool, // Send +typing notifications while composing
 }
 
 impl Default for Network {
@@ -29,11 +59,69 @@ impl Default for Network {
             name: "Default".to_string(),
             servers: vec![DEFAULT_SERVER.to_string()],
             nick: "slirc_user".to_string(),
+            alt_nicks: vec![],
             auto_connect: false,
             favorite_channels: vec![],
             nickserv_password: None,
             use_tls: false,
             auto_reconnect: true, // Enable auto-reconnect by default for resilience
+            ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+            flood_burst: DEFAULT_FLOOD_BURST,
+            flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+            send_typing: true,
+        }
+    }
+}
+
+/// Clock used for message timestamps
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TimestampStyle {
+    /// 14:05
+    #[default]
+    Hour24,
+    /// 2:05 PM
+    Hour12,
+    /// 5m ago
+    Relative,
+}
+
+/// How message timestamps are shown in the message view
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct TimestampFormat {
+    pub style: TimestampStyle,
+    pub show_seconds: bool,
+}
+
+impl Default for TimestampFormat {
+    fn default() -> Self {
+        Self {
+            style: TimestampStyle::Hour24,
+            show_seconds: true,
+        }
+    }
+}
+
+impl TimestampFormat {
+    /// Format `time` in local time; relative times are measured from `now`
+    pub fn format(&self, time: &DateTime<Utc>, now: DateTime<Utc>) -> String {
+        let local = time.with_timezone(&Local);
+        match (self.style, self.show_seconds) {
+            (TimestampStyle::Hour24, true) => local.format("%H:%M:%S").to_string(),
+            (TimestampStyle::Hour24, false) => local.format("%H:%M").to_string(),
+            (TimestampStyle::Hour12, true) => local.format("%-I:%M:%S %p").to_string(),
+            (TimestampStyle::Hour12, false) => local.format("%-I:%M %p").to_string(),
+            (TimestampStyle::Relative, _) => {
+                // Clock skew can put a server-time stamp slightly in the future
+                let secs = (now - *time).num_seconds().max(0);
+                match secs {
+                    0..=59 if self.show_seconds => format!("{}s ago", secs),
+                    0..=59 => "just now".to_string(),
+                    60..=3599 => format!("{}m ago", secs / 60),
+                    3600..=86399 => format!("{}h ago", secs / 3600),
+                    _ => format!("{}d ago", secs / 86400),
+                }
+            }
         }
     }
 }
@@ -60,16 +148,21 @@ impl ConnectionConfig {
     /// Parse server address into (host, port) tuple
     /// Uses port 6697 for TLS, 6667 for plaintext by default
     pub fn parse_server(&self) -> (String, u16) {
-        let parts: Vec<&str> = self.server.split(':').collect();
-        let host = parts[0].to_string();
-        let port: u16 = parts
-            .get(1)
-            .and_then(|p| p.parse().ok())
-            .unwrap_or(if self.use_tls { 6697 } else { 6667 });
-        (host, port)
+        parse_server_address(&self.server, if self.use_tls { 6697 } else { 6667 })
     }
 }
 
+/// Parse a `host[:port]` server address, falling back to `default_port`
+pub fn parse_server_address(addr: &str, default_port: u16) -> (String, u16) {
+    let parts: Vec<&str> = addr.split(':').collect();
+    let host = parts[0].to_string();
+    let port: u16 = parts
+        .get(1)
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(default_port);
+    (host, port)
+}
+
 /// Load a NickServ password from the system keyring (for future NickServ support).
 pub fn load_nickserv_password(network_name: &str) -> Option<String> {
     use keyring::Entry;
@@ -87,6 +180,8 @@ pub struct Settings {
     pub theme: String,
     #[serde(default)]
     pub networks: Vec<Network>,
+    #[serde(default)]
+    pub timestamp_format: TimestampFormat,
 }
 
 pub fn settings_path() -> Option<PathBuf> {
@@ -116,3 +211,62 @@ pub fn save_settings(settings: &Settings) -> std::io::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_clock_formats() {
+        let time = Utc::now();
+        let with_seconds = TimestampFormat::default().format(&time, time);
+        assert_eq!(with_seconds.len(), "00:00:00".len());
+        let no_seconds = TimestampFormat {
+            style: TimestampStyle::Hour24,
+            show_seconds: false,
+        };
+        assert_eq!(no_seconds.format(&time, time).len(), "00:00".len());
+        let twelve_hour = TimestampFormat {
+            style: TimestampStyle::Hour12,
+            show_seconds: false,
+        };
+        let formatted = twelve_hour.format(&time, time);
+        assert!(formatted.ends_with("AM") || formatted.ends_with("PM"));
+    }
+
+    #[test]
+    fn test_relative_format() {
+        let now = Utc::now();
+        let relative = TimestampFormat {
+            style: TimestampStyle::Relative,
+            show_seconds: false,
+        };
+        assert_eq!(relative.format(&now, now), "just now");
+        assert_eq!(
+            relative.format(&(now + Duration::seconds(5)), now),
+            "just now"
+        );
+        assert_eq!(
+            relative.format(&(now - Duration::minutes(5)), now),
+            "5m ago"
+        );
+        assert_eq!(relative.format(&(now - Duration::hours(3)), now), "3h ago");
+        assert_eq!(relative.format(&(now - Duration::days(2)), now), "2d ago");
+        let with_seconds = TimestampFormat {
+            show_seconds: true,
+            ..relative
+        };
+        assert_eq!(
+            with_seconds.format(&(now - Duration::seconds(42)), now),
+            "42s ago"
+        );
+    }
+
+    #[test]
+    fn test_settings_without_timestamp_format() {
+        let json = r#"{"server":"","nick":"","default_channel":"","history":[],"theme":""}"#;
+        let settings: Settings = serde_json::from_str(json).expect("old settings load");
+        assert_eq!(settings.timestamp_format, TimestampFormat::default());
+    }
+}
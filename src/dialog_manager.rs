@@ -6,10 +6,12 @@
 
 use eframe::egui::Context;
 
+use crate::channel_modes::ChannelModes;
 use crate::config::Network;
+use crate::server_support::ServerSupport;
 use crate::ui::dialogs::{
-    ChannelBrowserDialog, ChannelListItem, DialogAction, HelpDialog, NetworkManagerDialog,
-    NickChangeDialog, TopicEditorDialog,
+    ChannelBrowserDialog, ChannelListItem, ChannelSettingsDialog, DialogAction, HelpDialog,
+    NetworkManagerDialog, NickChangeDialog, PasteConfirmDialog, TopicEditorDialog,
 };
 
 /// Manages all application dialogs in one place.
@@ -21,6 +23,8 @@ pub struct DialogManager {
     pub topic_editor_dialog: Option<TopicEditorDialog>,
     pub network_manager_dialog: Option<NetworkManagerDialog>,
     pub channel_browser_dialog: Option<ChannelBrowserDialog>,
+    pub paste_confirm_dialog: Option<PasteConfirmDialog>,
+    pub channel_settings_dialog: Option<ChannelSettingsDialog>,
 }
 
 impl DialogManager {
@@ -32,6 +36,8 @@ impl DialogManager {
             topic_editor_dialog: None,
             network_manager_dialog: None,
             channel_browser_dialog: None,
+            paste_confirm_dialog: None,
+            channel_settings_dialog: None,
         }
     }
 
@@ -40,6 +46,15 @@ impl DialogManager {
         self.nick_change_dialog = Some(NickChangeDialog::new(current_nick));
     }
 
+    /// Reopen the nick change dialog after the server rejected a nick.
+    pub fn open_nick_rejected(&mut self, current_nick: &str, rejected_nick: &str, reason: &str) {
+        self.nick_change_dialog = Some(NickChangeDialog::rejected(
+            current_nick,
+            rejected_nick,
+            reason,
+        ));
+    }
+
     /// Open the topic editor dialog for the given channel.
     pub fn open_topic_editor(&mut self, channel: &str, current_topic: &str) {
         self.topic_editor_dialog = Some(TopicEditorDialog::new(channel, current_topic));
@@ -55,6 +70,49 @@ impl DialogManager {
         self.channel_browser_dialog = Some(ChannelBrowserDialog::new());
     }
 
+    /// Open the paste confirmation dialog for lines about to be sent.
+    pub fn open_paste_confirm(
+        &mut self,
+        network: &str,
+        target: &str,
+        text: &str,
+        lines: Vec<String>,
+    ) {
+        self.paste_confirm_dialog = Some(PasteConfirmDialog::new(network, target, text, lines));
+    }
+
+    /// Open the channel settings dialog for a channel's current modes.
+    pub fn open_channel_settings(
+        &mut self,
+        network: &str,
+        channel: &str,
+        modes: &ChannelModes,
+        support: &ServerSupport,
+        can_edit: bool,
+    ) -> &ChannelSettingsDialog {
+        self.channel_settings_dialog
+            .insert(ChannelSettingsDialog::new(
+                network, channel, modes, support, can_edit,
+            ))
+    }
+
+    /// Refresh the channel settings dialog if it shows this channel.
+    pub fn update_channel_settings(
+        &mut self,
+        network: &str,
+        channel: &str,
+        support: &ServerSupport,
+        modes: &ChannelModes,
+    ) {
+        if let Some(ref mut dialog) = self.channel_settings_dialog {
+            if dialog.network == network
+                && support.case_mapping().names_equal(&dialog.channel, channel)
+            {
+                dialog.update(modes);
+            }
+        }
+    }
+
     /// Toggle the help dialog.
     pub fn toggle_help(&mut self) {
         self.help_dialog.toggle();
@@ -153,6 +211,36 @@ impl DialogManager {
             self.channel_browser_dialog = None;
         }
 
+        // Paste confirmation dialog
+        let mut close_paste_confirm = false;
+        if let Some(ref mut dialog) = self.paste_confirm_dialog {
+            let (action, still_open) = dialog.render(ctx);
+            if let Some(action) = action {
+                actions.push(action);
+            }
+            if !still_open {
+                close_paste_confirm = true;
+            }
+        }
+        if close_paste_confirm {
+            self.paste_confirm_dialog = None;
+        }
+
+        // Channel settings dialog
+        let mut close_channel_settings = false;
+        if let Some(ref mut dialog) = self.channel_settings_dialog {
+            let (action, still_open) = dialog.render(ctx);
+            if let Some(action) = action {
+                actions.push(action);
+            }
+            if !still_open {
+                close_channel_settings = true;
+            }
+        }
+        if close_channel_settings {
+            self.channel_settings_dialog = None;
+        }
+
         (actions, networks_to_save)
     }
 }
@@ -174,6 +262,8 @@ mod tests {
         assert!(dm.topic_editor_dialog.is_none());
         assert!(dm.network_manager_dialog.is_none());
         assert!(dm.channel_browser_dialog.is_none());
+        assert!(dm.paste_confirm_dialog.is_none());
+        assert!(dm.channel_settings_dialog.is_none());
     }
 
     #[test]
@@ -203,4 +293,29 @@ mod tests {
         dm.open_channel_browser();
         assert!(dm.channel_browser_dialog.is_some());
     }
+
+    #[test]
+    fn test_open_paste_confirm() {
+        let mut dm = DialogManager::new();
+        dm.open_paste_confirm("libera", "#test", "a\nb", vec!["a".into(), "b".into()]);
+        let dialog = dm.paste_confirm_dialog.as_ref().expect("dialog open");
+        assert_eq!(dialog.lines.len(), 2);
+    }
+
+    #[test]
+    fn test_channel_settings_follow_their_channel() {
+        let mut dm = DialogManager::new();
+        let support = ServerSupport::default();
+        dm.open_channel_settings("libera", "#Test", &ChannelModes::default(), &support, true);
+
+        let mut modes = ChannelModes::default();
+        modes.flags.insert('m');
+        dm.update_channel_settings("libera", "#other", &support, &modes);
+        let dialog = dm.channel_settings_dialog.as_ref().expect("dialog open");
+        assert!(dialog.flags.contains(&('m', false)));
+
+        dm.update_channel_settings("libera", "#test", &support, &modes);
+        let dialog = dm.channel_settings_dialog.as_ref().expect("dialog open");
+        assert!(dialog.flags.contains(&('m', true)));
+    }
 }
@@ -1,42 +1,90 @@
 //! Backend event processing (incoming IRC messages, user lists, topics, etc.).
 
-use chrono::Local;
+use chrono::{DateTime, Local, Utc};
 use slirc_proto::ctcp::{Ctcp, CtcpKind};
 
-use crate::buffer::{MessageType, RenderedMessage};
-use crate::protocol::{GuiEvent, UserInfo};
-use crate::state::ClientState;
+use crate::buffer::{Delivery, HistoryState, MessageType, RenderedMessage};
+use crate::channel_modes::format_mode_changes;
+use crate::protocol::{GuiEvent, MessageTags, UserInfo};
+use crate::server_support::ServerSupport;
+use crate::state::{ClientState, PendingInvite, SYSTEM_BUFFER};
+use crate::ui::messages::contains_mention;
 
 /// Process a single event from the backend.
 ///
-/// Updates `state` based on the incoming event. Returns `Some(new_nick)` if
-/// the server confirmed a nick change (caller should update UI nick field).
-pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<String> {
+/// Updates the session for `network` based on the incoming event. Returns
+/// `Some(new_nick)` if the server confirmed a nick change (caller should
+/// update UI nick field).
+pub fn process_single_event(
+    state: &mut ClientState,
+    network: &str,
+    event: GuiEvent,
+) -> Option<String> {
     match event {
         GuiEvent::Connected => {
-            state.is_connected = true;
+            let session = state.session_mut(network);
+            session.is_connected = true;
+            session.reconnect_at = None;
+            let server_name = session.server_name.clone();
             let ts = Local::now().format("%H:%M:%S").to_string();
-            state
-                .system_log
-                .push(format!("[{}] ✓ Connected and registered!", ts));
-            // Expand the server in the network list and show a status toast
-            state.expanded_networks.insert(state.server_name.clone());
+            state.system_log.push(format!(
+                "[{}] ✓ Connected and registered to {}!",
+                ts, network
+            ));
+            // Expand the network in the sidebar tree and show a status toast
+            state.expanded_networks.insert(network.to_string());
             state.status_messages.push((
-                format!("Connected to {}", state.server_name),
+                format!("Connected to {}", server_name),
                 std::time::Instant::now(),
             ));
             None
         }
 
         GuiEvent::Disconnected(reason) => {
-            state.is_connected = false;
+            let session = state.session_mut(network);
+            session.is_connected = false;
+            session.reconnect_at = None;
+            session.lag = None;
+            session.send_queue_depth = 0;
+            // Scrollback requests in flight died with the connection
+            for buffer in session.buffers.values_mut() {
+                if buffer.history == HistoryState::Loading {
+                    buffer.history = HistoryState::NotLoaded;
+                }
+            }
             let ts = Local::now().format("%H:%M:%S").to_string();
-            state
-                .system_log
-                .push(format!("[{}] ✗ Disconnected: {}", ts, reason));
-            state
-                .status_messages
-                .push(("Disconnected".into(), std::time::Instant::now()));
+            state.system_log.push(format!(
+                "[{}] ✗ Disconnected from {}: {}",
+                ts, network, reason
+            ));
+            state.status_messages.push((
+                format!("Disconnected from {}", network),
+                std::time::Instant::now(),
+            ));
+            None
+        }
+
+        GuiEvent::Reconnecting { attempt, delay } => {
+            state.session_mut(network).reconnect_at =
+                Some((attempt, std::time::Instant::now() + delay));
+            let ts = Local::now().format("%H:%M:%S").to_string();
+            state.system_log.push(format!(
+                "[{}] ↻ Reconnecting to {} in {}s (attempt {})",
+                ts,
+                network,
+                delay.as_secs(),
+                attempt
+            ));
+            None
+        }
+
+        GuiEvent::Lag(lag) => {
+            state.session_mut(network).lag = Some(lag);
+            None
+        }
+
+        GuiEvent::SendQueueDepth(depth) => {
+            state.session_mut(network).send_queue_depth = depth;
             None
         }
 
@@ -50,27 +98,33 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
         }
 
         GuiEvent::NickChanged { old, new } => {
-            // Update user lists in all buffers where the old nick existed
-            for (buffer_name, buffer) in state.buffers.iter_mut() {
-                if buffer.users.iter().any(|u| u.nick == old) {
+            // Update user lists in all of this network's buffers where the old nick existed
+            let active = state.active_network == network;
+            let active_buffer = state.active_buffer.clone();
+            let session = state.session_mut(network);
+            let cm = session.support.case_mapping();
+            for (buffer_name, buffer) in session.buffers.iter_mut() {
+                if buffer.users.iter().any(|u| cm.names_equal(&u.nick, &old)) {
                     for user in buffer.users.iter_mut() {
-                        if user.nick == old {
+                        if cm.names_equal(&user.nick, &old) {
                             user.nick = new.clone();
                         }
                     }
-                    let ts = Local::now().format("%H:%M:%S").to_string();
                     let nick_msg = RenderedMessage::new(
-                        ts.clone(),
+                        Utc::now(),
                         "*".into(),
                         format!("{} is now known as {}", old, new),
                     )
                     .with_type(MessageType::NickChange);
-                    let is_active = *buffer_name == state.active_buffer;
+                    let is_active = active && cm.names_equal(buffer_name.as_str(), &active_buffer);
                     buffer.add_message(nick_msg, is_active, false);
                 }
             }
-            // Update our_nick in state
-            state.our_nick = new.clone();
+            // Only the server's echo of our own NICK changes our nick
+            if !cm.names_equal(&session.our_nick, &old) {
+                return None;
+            }
+            session.our_nick = new.clone();
             let ts = Local::now().format("%H:%M:%S").to_string();
             state
                 .system_log
@@ -79,6 +133,18 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
             Some(new)
         }
 
+        GuiEvent::NickError { nick, reason } => {
+            let ts = Local::now().format("%H:%M:%S").to_string();
+            state
+                .system_log
+                .push(format!("[{}] ⚠ Cannot use nick {}: {}", ts, nick, reason));
+            state.status_messages.push((
+                format!("Nick {} rejected: {}", nick, reason),
+                std::time::Instant::now(),
+            ));
+            None
+        }
+
         GuiEvent::RawMessage(msg) => {
             let ts = Local::now().format("%H:%M:%S").to_string();
             state.system_log.push(format!("[{}] {}", ts, msg));
@@ -93,63 +159,80 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
             target,
             sender,
             text,
+            time,
+            tags,
         } => {
-            // If it's a PM, the target is the sender (for display)
-            let buffer_name = if target.starts_with('#') || target.starts_with('&') {
-                target.clone()
-            } else {
-                // Private message - use sender as buffer name
-                sender.clone()
-            };
+            add_chat_message(
+                state,
+                network,
+                &target,
+                &sender,
+                &text,
+                time,
+                tags,
+                Delivery::Delivered,
+            );
+            None
+        }
 
-            let ts = Local::now().format("%H:%M:%S").to_string();
-            let mention = text.contains(&state.our_nick);
-            let is_own_msg = sender == state.our_nick;
-            let active = state.active_buffer.clone();
-
-            // Create buffer first
-            let buffer = state.ensure_buffer(&buffer_name);
-            let is_active = active == buffer_name;
-            // Use slirc_proto's CTCP parser to detect ACTION messages
-            let msg_type = if let Some(ctcp) = Ctcp::parse(&text) {
-                if matches!(ctcp.kind, CtcpKind::Action) {
-                    MessageType::Action
-                } else {
-                    MessageType::Normal
-                }
-            } else if sender.starts_with('-') && sender.ends_with('-') {
-                // Backend marks NOTICE messages with -<sender>- as a sender string
-                MessageType::Notice
-            } else {
-                MessageType::Normal
-            };
-            let msg =
-                RenderedMessage::new(ts.clone(), sender.clone(), text.clone()).with_type(msg_type);
-            buffer.add_message(msg, is_active || is_own_msg, mention);
+        GuiEvent::OwnMessagePending {
+            id,
+            target,
+            sender,
+            text,
+            tags,
+        } => {
+            add_chat_message(
+                state,
+                network,
+                &target,
+                &sender,
+                &text,
+                Utc::now(),
+                tags,
+                Delivery::Pending(id),
+            );
+            None
+        }
 
-            // Keep user list updated if a new nick speaks
-            if (buffer_name.starts_with('#') || buffer_name.starts_with('&'))
-                && !buffer.users.iter().any(|u| u.nick == sender)
+        GuiEvent::OwnMessageEchoed {
+            id,
+            target,
+            text,
+            tags,
+        } => {
+            if let Some(msg) = state
+                .sessions
+                .get_mut(network)
+                .and_then(|session| session.buffer_mut(&target))
+                .and_then(|buffer| buffer.find_pending_mut(id))
             {
-                buffer.users.push(UserInfo {
-                    nick: sender.clone(),
-                    prefix: None,
-                });
-                crate::ui::sort_users(&mut buffer.users[..]);
+                // The server's copy is authoritative (it may have been altered)
+                msg.text = text;
+                msg.tags = tags;
+                msg.delivery = Delivery::Delivered;
+                if let Some(ref logger) = state.logger {
+                    logger.log(crate::logging::LogEntry {
+                        network: network.to_string(),
+                        channel: target.clone(),
+                        timestamp: msg.timestamp,
+                        nick: msg.sender.clone(),
+                        message: msg.text.clone(),
+                    });
+                }
             }
+            None
+        }
 
-            // Log to file (non-blocking) - done after buffer operations to avoid borrow conflicts
-            if let Some(ref logger) = state.logger {
-                logger.log(crate::logging::LogEntry {
-                    network: state.server_name.clone(),
-                    channel: buffer_name.clone(),
-                    timestamp: ts.clone(),
-                    nick: sender.clone(),
-                    message: text.clone(),
-                });
+        GuiEvent::OwnMessageFailed { id, target, reason } => {
+            if let Some(msg) = state
+                .sessions
+                .get_mut(network)
+                .and_then(|session| session.buffer_mut(&target))
+                .and_then(|buffer| buffer.find_pending_mut(id))
+            {
+                msg.delivery = Delivery::Failed(reason);
             }
-
-            // Unread/highlight handled by ChannelBuffer::add_message
             None
         }
 
@@ -161,10 +244,12 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
             state
                 .status_messages
                 .push((format!("Joined {}", channel), std::time::Instant::now()));
-            let buffer = state.ensure_buffer(&channel);
-            buffer.clear_unread();
-            buffer.has_highlight = false;
-            state.active_buffer = channel;
+            let cm = state.support(network).case_mapping();
+            state
+                .pending_invites
+                .retain(|i| !(i.network == network && cm.names_equal(&i.channel, &channel)));
+            state.ensure_buffer(network, &channel).left = false;
+            state.switch_to_buffer(network, &channel);
             None
         }
 
@@ -176,27 +261,153 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
             state
                 .status_messages
                 .push((format!("Left {}", channel), std::time::Instant::now()));
-            state.buffers.remove(&channel);
-            state.buffers_order.retain(|b| *b != channel);
-            if state.active_buffer == channel {
-                state.active_buffer = "System".into();
+            let was_active = state.is_active(network, &channel);
+            state.session_mut(network).remove_buffer(&channel);
+            if was_active {
+                state.active_buffer = SYSTEM_BUFFER.into();
+            }
+            None
+        }
+
+        GuiEvent::Kicked {
+            channel,
+            by,
+            reason,
+        } => {
+            let ts = Local::now().format("%H:%M:%S").to_string();
+            let reason = reason.map(|r| format!(" ({})", r)).unwrap_or_default();
+            state.system_log.push(format!(
+                "[{}] ⊘ Kicked from {} by {}{}",
+                ts, channel, by, reason
+            ));
+            state.status_messages.push((
+                format!("Kicked from {} by {}", channel, by),
+                std::time::Instant::now(),
+            ));
+            // Keep the buffer so the reason stays readable
+            let is_active = state.is_active(network, &channel);
+            if let Some(buffer) = state.session_mut(network).buffer_mut(&channel) {
+                let kick_msg = RenderedMessage::new(
+                    Utc::now(),
+                    by.clone(),
+                    format!("You were kicked by {}{}", by, reason),
+                )
+                .with_type(MessageType::Kick);
+                buffer.add_message(kick_msg, is_active, true);
+                buffer.users.clear();
+                buffer.typing.clear();
+                buffer.left = true;
             }
             None
         }
 
-        GuiEvent::UserJoined { channel, nick } => {
-            let is_active = state.active_buffer == channel;
-            let buffer = state.ensure_buffer(&channel);
+        GuiEvent::Invited { channel, by } => {
             let ts = Local::now().format("%H:%M:%S").to_string();
-            let join_msg = RenderedMessage::new(ts.clone(), "→".into(), format!("{} joined", nick))
+            state
+                .system_log
+                .push(format!("[{}] ✉ {} invited you to {}", ts, by, channel));
+            let invite = PendingInvite {
+                network: network.to_string(),
+                channel,
+                by,
+            };
+            let cm = state.support(network).case_mapping();
+            if !state
+                .pending_invites
+                .iter()
+                .any(|i| i.network == invite.network && cm.names_equal(&i.channel, &invite.channel))
+            {
+                state.pending_invites.push(invite);
+            }
+            None
+        }
+
+        GuiEvent::History { target, messages } => {
+            let Some(buffer) = state
+                .sessions
+                .get_mut(network)
+                .and_then(|session| session.buffer_mut(&target))
+            else {
+                return None;
+            };
+            buffer.history = if messages.is_empty() {
+                HistoryState::Exhausted
+            } else {
+                HistoryState::Idle
+            };
+            let history = messages
+                .into_iter()
+                .map(|m| {
+                    let msg_type = chat_message_type(&m.sender, &m.text);
+                    RenderedMessage::new(m.time, m.sender, m.text)
+                        .with_type(msg_type)
+                        .with_tags(m.tags)
+                })
+                .collect();
+            buffer.insert_history(history);
+            None
+        }
+
+        GuiEvent::Typing {
+            target,
+            sender,
+            state: typing,
+        } => {
+            let session = state.session_mut(network);
+            // Our own notifications come back with echo-message
+            if !session.names_equal(&sender, &session.our_nick) {
+                let buffer_name = chat_buffer_name(&session.support, &target, &sender, false);
+                if let Some(buffer) = session.buffer_mut(&buffer_name) {
+                    buffer.set_typing(&sender, typing, std::time::Instant::now());
+                }
+            }
+            None
+        }
+
+        GuiEvent::ReadMarker { target, time } => {
+            if let Some(buffer) = state.session_mut(network).buffer_mut(&target) {
+                buffer.apply_read_marker(time);
+            }
+            None
+        }
+
+        GuiEvent::Reaction {
+            target,
+            sender,
+            msgid,
+            reaction,
+            added,
+        } => {
+            let session = state.session_mut(network);
+            let is_own = session.names_equal(&sender, &session.our_nick);
+            let buffer_name = chat_buffer_name(&session.support, &target, &sender, is_own);
+            // Reactions to messages we don't have (any more) are dropped
+            if let Some(msg) = session
+                .buffer_mut(&buffer_name)
+                .and_then(|buffer| buffer.find_by_msgid_mut(&msgid))
+            {
+                msg.apply_reaction(&reaction, &sender, added);
+            }
+            None
+        }
+
+        GuiEvent::UserJoined {
+            channel,
+            nick,
+            time,
+        } => {
+            let is_active = state.is_active(network, &channel);
+            let cm = state.support(network).case_mapping();
+            let buffer = state.ensure_buffer(network, &channel);
+            let join_msg = RenderedMessage::new(time, "→".into(), format!("{} joined", nick))
                 .with_type(MessageType::Join);
             buffer.add_message(join_msg, is_active, false);
-            if !buffer.users.iter().any(|u| u.nick == nick) {
+            if !buffer.users.iter().any(|u| cm.names_equal(&u.nick, &nick)) {
                 buffer.users.push(UserInfo {
                     nick: nick.clone(),
                     prefix: None,
                 });
-                crate::ui::sort_users(&mut buffer.users[..]);
+                sort_channel_users(state, network, &channel);
             }
             // Unread handled by add_message
             None
@@ -206,42 +417,90 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
             channel,
             nick,
             message,
+            time,
         } => {
-            let is_active = state.active_buffer == channel;
-            let buffer = state.ensure_buffer(&channel);
+            let is_active = state.is_active(network, &channel);
+            let cm = state.support(network).case_mapping();
+            let buffer = state.ensure_buffer(network, &channel);
             let msg = message.map(|m| format!(" ({})", m)).unwrap_or_default();
-            let ts = Local::now().format("%H:%M:%S").to_string();
-            let part_msg =
-                RenderedMessage::new(ts.clone(), "←".into(), format!("{} left{}", nick, msg))
-                    .with_type(MessageType::Part);
+            let part_msg = RenderedMessage::new(time, "←".into(), format!("{} left{}", nick, msg))
+                .with_type(MessageType::Part);
             buffer.add_message(part_msg, is_active, false);
-            buffer.users.retain(|u| u.nick != nick);
+            buffer.users.retain(|u| !cm.names_equal(&u.nick, &nick));
             // Unread handled by add_message
             None
         }
 
-        GuiEvent::UserQuit { nick, message } => {
-            // Remove the user from all channels and add quit message
-            let active = state.active_buffer.clone();
+        GuiEvent::UserKicked {
+            channel,
+            nick,
+            by,
+            reason,
+            time,
+        } => {
+            let is_active = state.is_active(network, &channel);
+            let cm = state.support(network).case_mapping();
+            let buffer = state.ensure_buffer(network, &channel);
+            let reason = reason.map(|r| format!(" ({})", r)).unwrap_or_default();
+            let kick_msg = RenderedMessage::new(
+                time,
+                by.clone(),
+                format!("{} was kicked by {}{}", nick, by, reason),
+            )
+            .with_type(MessageType::Kick);
+            buffer.add_message(kick_msg, is_active, false);
+            buffer.users.retain(|u| !cm.names_equal(&u.nick, &nick));
+            buffer.stop_typing(&nick);
+            None
+        }
+
+        GuiEvent::UserQuit {
+            nick,
+            message,
+            time,
+        } => {
+            // Remove the user from all of this network's channels and add quit message
+            let active = state.active_network == network;
+            let active_buffer = state.active_buffer.clone();
             let msg = message.map(|m| format!(" ({})", m)).unwrap_or_default();
-            let ts = Local::now().format("%H:%M:%S").to_string();
 
-            for (channel_name, buffer) in state.buffers.iter_mut() {
-                if buffer.users.iter().any(|u| u.nick == nick) {
-                    let quit_msg = RenderedMessage::new(
-                        ts.clone(),
-                        "⇐".into(),
-                        format!("{} quit{}", nick, msg),
-                    )
-                    .with_type(MessageType::Quit);
-                    let is_active = active == *channel_name;
+            let session = state.session_mut(network);
+            let cm = session.support.case_mapping();
+            for (channel_name, buffer) in session.buffers.iter_mut() {
+                if buffer.users.iter().any(|u| cm.names_equal(&u.nick, &nick)) {
+                    let quit_msg =
+                        RenderedMessage::new(time, "⇐".into(), format!("{} quit{}", nick, msg))
+                            .with_type(MessageType::Quit);
+                    let is_active = active && cm.names_equal(channel_name.as_str(), &active_buffer);
                     buffer.add_message(quit_msg, is_active, false);
-                    buffer.users.retain(|u| u.nick != nick);
+                    buffer.users.retain(|u| !cm.names_equal(&u.nick, &nick));
                 }
             }
             None
         }
 
+        GuiEvent::WhoisResult(info) => {
+            // Shown in the buffer being looked at, or the system log
+            let lines = crate::ui::whois_card::whois_lines(&info);
+            if state.active_network == network && state.active_buffer != SYSTEM_BUFFER {
+                let name = state.active_buffer.clone();
+                let whois_msg = RenderedMessage::new(Utc::now(), "WHOIS".into(), lines.join("\n"))
+                    .with_type(MessageType::Notice);
+                state
+                    .ensure_buffer(network, &name)
+                    .add_message(whois_msg, true, false);
+            } else {
+                let ts = Local::now().format("%H:%M:%S").to_string();
+                for line in lines {
+                    state.system_log.push(format!("[{}] {}", ts, line));
+                }
+            }
+            let session = state.session_mut(network);
+            let nick = session.irc_name(&info.nick);
+            session.whois.insert(nick, info);
+            None
+        }
+
         GuiEvent::Motd(line) => {
             let ts = Local::now().format("%H:%M:%S").to_string();
             // Clean up MOTD line formatting a bit for readability
@@ -255,22 +514,57 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
         }
 
         GuiEvent::Topic { channel, topic } => {
-            let active = state.active_buffer.clone();
-            let buffer = state.ensure_buffer(&channel);
+            let is_active = state.is_active(network, &channel);
+            let buffer = state.ensure_buffer(network, &channel);
             buffer.topic = topic.clone();
-            let ts = Local::now().format("%H:%M:%S").to_string();
+            // RPL_TOPICWHOTIME follows with the setter
+            buffer.topic_set_by = None;
+            buffer.topic_set_at = None;
             let topic_msg =
-                RenderedMessage::new(ts.clone(), "*".into(), format!("Topic: {}", topic))
+                RenderedMessage::new(Utc::now(), "*".into(), format!("Topic: {}", topic))
                     .with_type(MessageType::Topic);
-            buffer.add_message(topic_msg, active == channel, false);
+            buffer.add_message(topic_msg, is_active, false);
             // Unread handled by add_message
             None
         }
 
+        GuiEvent::TopicChanged {
+            channel,
+            topic,
+            set_by,
+            time,
+        } => {
+            let is_active = state.is_active(network, &channel);
+            let buffer = state.ensure_buffer(network, &channel);
+            let text = if topic.is_empty() {
+                format!("{} cleared the topic", set_by)
+            } else {
+                format!("{} changed the topic to: {}", set_by, topic)
+            };
+            buffer.topic = topic;
+            buffer.topic_set_by = Some(set_by.clone());
+            buffer.topic_set_at = Some(time);
+            let topic_msg = RenderedMessage::new(time, set_by, text).with_type(MessageType::Topic);
+            buffer.add_message(topic_msg, is_active, false);
+            None
+        }
+
+        GuiEvent::TopicWhoTime {
+            channel,
+            set_by,
+            set_at,
+        } => {
+            if let Some(buffer) = state.session_mut(network).buffer_mut(&channel) {
+                buffer.topic_set_by = Some(set_by);
+                buffer.topic_set_at = Some(set_at);
+            }
+            None
+        }
+
         GuiEvent::Names { channel, names } => {
-            let buffer = state.ensure_buffer(&channel);
+            let buffer = state.ensure_buffer(network, &channel);
             buffer.users = names;
-            crate::ui::sort_users(&mut buffer.users[..]);
+            sort_channel_users(state, network, &channel);
             None
         }
 
@@ -280,10 +574,15 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
             prefix,
             added,
         } => {
-            let buffer = state.ensure_buffer(&channel);
+            let cm = state.support(network).case_mapping();
+            let buffer = state.ensure_buffer(network, &channel);
             // Find the user and update the prefix; if the user isn't present,
             // add them (some servers may send MODE before a NAMES refresh).
-            if let Some(user) = buffer.users.iter_mut().find(|u| u.nick == nick) {
+            if let Some(user) = buffer
+                .users
+                .iter_mut()
+                .find(|u| cm.names_equal(&u.nick, &nick))
+            {
                 if added {
                     user.prefix = prefix;
                 } else if user.prefix == prefix {
@@ -295,53 +594,59 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
                     prefix,
                 });
             }
-            crate::ui::sort_users(&mut buffer.users[..]);
+            sort_channel_users(state, network, &channel);
             None
         }
 
         GuiEvent::ChannelMode {
             channel,
-            modes,
+            changes,
             set_by,
+            time,
         } => {
             // Check if this is the active buffer before mutable borrow
-            let is_active = state.active_buffer == channel;
-
-            let buffer = state.ensure_buffer(&channel);
-
-            // Update channel_modes string
-            // Parse modes: +m-t means add m, remove t
-            let mut adding = true;
-            for ch in modes.chars() {
-                match ch {
-                    '+' => adding = true,
-                    '-' => adding = false,
-                    mode => {
-                        if adding {
-                            if !buffer.channel_modes.contains(mode) {
-                                buffer.channel_modes.push(mode);
-                            }
-                        } else {
-                            buffer.channel_modes = buffer.channel_modes.replace(mode, "");
-                        }
-                    }
-                }
+            let is_active = state.is_active(network, &channel);
+
+            let session = state.session_mut(network);
+            let support = session.support.clone();
+            let buffer = session.ensure_buffer(&channel);
+            for change in &changes {
+                buffer.channel_modes.apply(change, &support, &set_by, time);
             }
-            // Keep modes sorted for consistent display
-            let mut chars: Vec<char> = buffer.channel_modes.chars().collect();
-            chars.sort();
-            buffer.channel_modes = chars.into_iter().collect();
 
             // Log to buffer
-            let ts = Local::now().format("%H:%M:%S").to_string();
-            let mode_msg =
-                RenderedMessage::new(ts, "*".into(), format!("{} set mode {}", set_by, modes))
-                    .with_type(MessageType::Normal); // Using Normal as Mode type doesn't exist yet
+            let mode_msg = RenderedMessage::new(
+                time,
+                "*".into(),
+                format!("{} set mode {}", set_by, format_mode_changes(&changes)),
+            )
+            .with_type(MessageType::Normal); // Using Normal as Mode type doesn't exist yet
             buffer.add_message(mode_msg, is_active, false);
 
             None
         }
 
+        GuiEvent::ChannelModeIs { channel, changes } => {
+            let session = state.session_mut(network);
+            let support = session.support.clone();
+            session
+                .ensure_buffer(&channel)
+                .channel_modes
+                .reset(&changes, &support);
+            None
+        }
+
+        GuiEvent::ModeList {
+            channel,
+            mode,
+            entries,
+        } => {
+            if let Some(buffer) = state.session_mut(network).buffer_mut(&channel) {
+                buffer.channel_modes.set_list(mode, entries);
+            }
+            None
+        }
+
         // Channel list events are handled in app.rs before calling this function
         GuiEvent::ChannelListItem { .. } | GuiEvent::ChannelListEnd => {
             // No-op: filtered by app.rs process_events
@@ -349,15 +654,23 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
         }
 
         // Server info from ISUPPORT - log network name
-        GuiEvent::ServerInfo {
-            network,
-            casemapping,
-        } => {
+        GuiEvent::ServerInfo(support) => {
             let ts = Local::now().format("%H:%M:%S").to_string();
-            if let Some(net) = network {
+            let session = state.session_mut(network);
+            // 005 arrives over several lines; log names only when they appear
+            let new_network = support
+                .network
+                .clone()
+                .filter(|net| session.support.network.as_ref() != Some(net));
+            let new_casemapping = support
+                .casemapping
+                .clone()
+                .filter(|cm| session.support.casemapping.as_ref() != Some(cm));
+            session.set_support(support);
+            if let Some(net) = new_network {
                 state.system_log.push(format!("[{}] Network: {}", ts, net));
             }
-            if let Some(cm) = casemapping {
+            if let Some(cm) = new_casemapping {
                 state
                     .system_log
                     .push(format!("[{}] Casemapping: {}", ts, cm));
@@ -380,6 +693,103 @@ pub fn process_single_event(state: &mut ClientState, event: GuiEvent) -> Option<
     }
 }
 
+/// Add a PRIVMSG/NOTICE line to its channel or query buffer.
+/// Lines that are still pending are logged once the server echoes them.
+#[allow(clippy::too_many_arguments)]
+fn add_chat_message(
+    state: &mut ClientState,
+    network: &str,
+    target: &str,
+    sender: &str,
+    text: &str,
+    time: DateTime<Utc>,
+    tags: MessageTags,
+    delivery: Delivery,
+) {
+    let session = state.session_mut(network);
+    let cm = session.support.case_mapping();
+    // NOTICE senders are marked as -nick-
+    let is_own_msg = cm.names_equal(sender.trim_matches('-'), &session.our_nick);
+
+    let buffer_name = chat_buffer_name(&session.support, target, sender, is_own_msg);
+
+    let is_active = state.is_active(network, &buffer_name);
+    let session = state.session_mut(network);
+    let mention = contains_mention(text, &session.our_nick, cm);
+    let is_channel = session.support.is_channel(&buffer_name);
+
+    // Create buffer first
+    let buffer = session.ensure_buffer(&buffer_name);
+    let msg_type = chat_message_type(sender, text);
+    let pending = delivery != Delivery::Delivered;
+    let msg = RenderedMessage::new(time, sender.to_string(), text.to_string())
+        .with_type(msg_type)
+        .with_tags(tags)
+        .with_delivery(delivery);
+    buffer.add_message(msg, is_active || is_own_msg, mention);
+    buffer.stop_typing(sender);
+
+    // Keep user list updated if a new nick speaks
+    if is_channel && !buffer.users.iter().any(|u| cm.names_equal(&u.nick, sender)) {
+        buffer.users.push(UserInfo {
+            nick: sender.to_string(),
+            prefix: None,
+        });
+        sort_channel_users(state, network, &buffer_name);
+    }
+
+    // Log to file (non-blocking) - done after buffer operations to avoid borrow conflicts
+    if let (Some(logger), false) = (state.logger.as_ref(), pending) {
+        logger.log(crate::logging::LogEntry {
+            network: network.to_string(),
+            channel: buffer_name,
+            timestamp: time,
+            nick: sender.to_string(),
+            message: text.to_string(),
+        });
+    }
+}
+
+/// Buffer for a line sent to `target`. A PM goes in the buffer of the
+/// other party: the sender, or the target for our own messages. Lines for
+/// part of a channel (STATUSMSG, e.g. `@#chan`) go in the channel.
+fn chat_buffer_name(support: &ServerSupport, target: &str, sender: &str, is_own: bool) -> String {
+    let channel = target.trim_start_matches(|c| support.statusmsg.contains(c));
+    if support.is_channel(channel) {
+        channel.to_string()
+    } else if is_own {
+        target.to_string()
+    } else {
+        sender.to_string()
+    }
+}
+
+/// Re-sort a channel's user list by the network's prefix ranks
+fn sort_channel_users(state: &mut ClientState, network: &str, channel: &str) {
+    let session = state.session_mut(network);
+    let key = session.irc_name(channel);
+    if let Some(buffer) = session.buffers.get_mut(&key) {
+        crate::ui::sort_users(&mut buffer.users[..], &session.support);
+    }
+}
+
+/// How to show a PRIVMSG/NOTICE line: /me actions and notices stand out
+fn chat_message_type(sender: &str, text: &str) -> MessageType {
+    // Use slirc_proto's CTCP parser to detect ACTION messages
+    if let Some(ctcp) = Ctcp::parse(text) {
+        if matches!(ctcp.kind, CtcpKind::Action) {
+            MessageType::Action
+        } else {
+            MessageType::Normal
+        }
+    } else if sender.starts_with('-') && sender.ends_with('-') {
+        // Backend marks NOTICE messages with -<sender>- as a sender string
+        MessageType::Notice
+    } else {
+        MessageType::Normal
+    }
+}
+
 /// Clean MOTD line formatting (strips common prefixes).
 pub fn clean_motd_line(line: &str) -> String {
     let mut s = line.trim_start();
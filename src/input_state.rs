@@ -4,8 +4,29 @@
 //! following the pattern used by modern IRC clients like Halloy.
 
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use crate::buffer::ChannelBuffer;
+use crate::irc_name::IrcName;
+use crate::protocol::{TypingState, REPLY_TAG};
+use crate::server_support::ServerSupport;
+
+/// Minimum time between two `active` typing notifications
+const TYPING_ACTIVE_INTERVAL: Duration = Duration::from_secs(3);
+/// Time without edits after which we say we paused
+const TYPING_PAUSE_AFTER: Duration = Duration::from_secs(5);
+
+/// A message the user is composing a reply to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplyTarget {
+    /// Network and buffer the message is in; the reply is only sent there
+    pub network: String,
+    pub buffer: String,
+    /// Server id of the message (`msgid`)
+    pub msgid: String,
+    pub sender: String,
+    pub text: String,
+}
 
 /// Manages all input-related state for the IRC client.
 ///
@@ -42,6 +63,102 @@ pub struct InputState {
 
     /// Last input text (for detecting changes that should reset completion)
     pub last_input_text: String,
+
+    /// Message the next line replies to, if any
+    pub replying_to: Option<ReplyTarget>,
+
+    /// Our own typing notifications
+    pub typing: TypingNotifier,
+}
+
+/// A typing notification to send: network, target and state
+pub type TypingUpdate = (String, String, TypingState);
+
+/// The last typing notification we sent
+#[derive(Debug, Clone)]
+struct SentTyping {
+    network: String,
+    target: String,
+    state: TypingState,
+    at: Instant,
+}
+
+/// Decides when to send `+typing` notifications as the input changes,
+/// throttled as the IRCv3 typing spec asks.
+#[derive(Debug, Default)]
+pub struct TypingNotifier {
+    sent: Option<SentTyping>,
+    /// Input at the previous update, to notice edits
+    last_text: String,
+    last_edit: Option<Instant>,
+}
+
+impl TypingNotifier {
+    /// Look at the input while `target` on `network` is shown and return the
+    /// notifications to send. `target` is `None` where typing must not be
+    /// reported (the System buffer, or sending is turned off).
+    pub fn update(
+        &mut self,
+        network: &str,
+        target: Option<&str>,
+        text: &str,
+        now: Instant,
+    ) -> Vec<TypingUpdate> {
+        let mut updates = Vec::new();
+        let edited = text != self.last_text;
+        if edited {
+            self.last_text = text.to_string();
+            self.last_edit = Some(now);
+        }
+        // Commands aren't messages, so typing one isn't "typing"
+        let composing = !text.trim().is_empty() && !text.starts_with('/');
+
+        // Left the buffer, or cleared the input: we're done there
+        if let Some(sent) = &self.sent {
+            let moved = target.map_or(true, |t| sent.network != network || sent.target != t);
+            if moved || !composing {
+                updates.push((sent.network.clone(), sent.target.clone(), TypingState::Done));
+                self.sent = None;
+            }
+        }
+        let Some(target) = target.filter(|_| composing) else {
+            return updates;
+        };
+
+        let state = match &self.sent {
+            None if edited => Some(TypingState::Active),
+            Some(sent) if edited => (sent.state != TypingState::Active
+                || now.duration_since(sent.at) >= TYPING_ACTIVE_INTERVAL)
+                .then_some(TypingState::Active),
+            Some(sent) if sent.state == TypingState::Active => self
+                .last_edit
+                .is_some_and(|at| now.duration_since(at) >= TYPING_PAUSE_AFTER)
+                .then_some(TypingState::Paused),
+            _ => None,
+        };
+        if let Some(state) = state {
+            updates.push((network.to_string(), target.to_string(), state));
+            self.sent = Some(SentTyping {
+                network: network.to_string(),
+                target: target.to_string(),
+                state,
+                at: now,
+            });
+        }
+        updates
+    }
+
+    /// Whether a later update may need to send `paused`
+    pub fn is_active(&self) -> bool {
+        self.sent
+            .as_ref()
+            .is_some_and(|sent| sent.state == TypingState::Active)
+    }
+
+    /// Our message was sent; receivers stop showing us typing on their own
+    pub fn message_sent(&mut self) {
+        self.sent = None;
+    }
 }
 
 impl InputState {
@@ -54,7 +171,7 @@ impl InputState {
     ///
     /// Supports:
     /// - IRC commands (starting with /)
-    /// - Channel names (starting with # or &)
+    /// - Channel names (starting with one of the network's CHANTYPES)
     /// - User nicknames from active buffer
     /// - @mentions
     pub fn collect_completions(
@@ -62,7 +179,8 @@ impl InputState {
         prefix: &str,
         buffers_order: &[String],
         active_buffer: &str,
-        buffers: &HashMap<String, ChannelBuffer>,
+        buffers: &HashMap<IrcName, ChannelBuffer>,
+        support: &ServerSupport,
     ) -> Vec<String> {
         let mut matches: Vec<String> = Vec::new();
         let mut search_prefix = prefix;
@@ -71,8 +189,8 @@ impl InputState {
         // Command completion when prefix starts with /
         if prefix.starts_with('/') {
             let commands = vec![
-                "/join", "/j", "/part", "/p", "/msg", "/privmsg", "/me", "/whois", "/w", "/topic",
-                "/t", "/kick", "/k", "/nick", "/quit", "/exit", "/help",
+                "/join", "/j", "/part", "/p", "/msg", "/privmsg", "/notice", "/me", "/whois", "/w",
+                "/topic", "/t", "/kick", "/k", "/nick", "/quit", "/exit", "/help",
             ];
             for cmd in commands {
                 if cmd.starts_with(prefix) {
@@ -85,7 +203,7 @@ impl InputState {
             keep_lead = "@";
         }
 
-        if prefix.starts_with('#') || prefix.starts_with('&') {
+        if support.is_channel(prefix) {
             // channel completions
             for b in buffers_order {
                 if b.starts_with(prefix) {
@@ -94,7 +212,7 @@ impl InputState {
             }
         } else if !prefix.starts_with('/') {
             // user completions from active buffer (skip if completing commands)
-            if let Some(buffer) = buffers.get(active_buffer) {
+            if let Some(buffer) = buffers.get(&support.irc_name(active_buffer)) {
                 for u in &buffer.users {
                     if u.nick.starts_with(search_prefix) {
                         matches.push(format!("{}{}", keep_lead, u.nick.clone()));
@@ -181,6 +299,34 @@ impl InputState {
         }
     }
 
+    /// Split the message input into the lines to send.
+    ///
+    /// The newline Enter leaves at the end is dropped. Returns `None` if the
+    /// input is a single line.
+    pub fn multiline_lines(&self) -> Option<Vec<String>> {
+        let text = self.message_input.trim_end_matches(['\r', '\n']);
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        (lines.len() > 1).then_some(lines)
+    }
+
+    /// The reply in progress, if it was started in `buffer` on `network`
+    pub fn reply_in(&self, network: &str, buffer: &str) -> Option<&ReplyTarget> {
+        self.replying_to
+            .as_ref()
+            .filter(|reply| reply.network == network && reply.buffer == buffer)
+    }
+
+    /// Client tags for a message sent to `buffer` on `network`: a
+    /// `+draft/reply` tag when replying there. The reply is finished either way.
+    pub fn take_reply_tags(&mut self, network: &str, buffer: &str) -> Vec<(String, String)> {
+        let tags = self
+            .reply_in(network, buffer)
+            .map(|reply| vec![(REPLY_TAG.to_string(), reply.msgid.clone())])
+            .unwrap_or_default();
+        self.replying_to = None;
+        tags
+    }
+
     /// Navigate up in command history.
     #[allow(dead_code)]
     pub fn history_up(&mut self) {
@@ -293,4 +439,91 @@ mod tests {
         input.apply_completion("/join", 0, 3);
         assert_eq!(input.message_input, "/join ");
     }
+
+    #[test]
+    fn test_multiline_lines() {
+        let mut input = InputState::new();
+        input.message_input = "hello\n".into();
+        assert_eq!(input.multiline_lines(), None);
+
+        input.message_input = "fn main() {\r\n    run();\n}\n".into();
+        assert_eq!(
+            input.multiline_lines(),
+            Some(vec![
+                "fn main() {".to_string(),
+                "    run();".to_string(),
+                "}".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_reply_tags_only_for_reply_buffer() {
+        let mut input = InputState::new();
+        input.replying_to = Some(ReplyTarget {
+            network: "net".into(),
+            buffer: "#chan".into(),
+            msgid: "m1".into(),
+            sender: "alice".into(),
+            text: "hi".into(),
+        });
+        assert!(input.reply_in("net", "#other").is_none());
+        assert!(input.reply_in("net", "#chan").is_some());
+
+        assert_eq!(
+            input.take_reply_tags("net", "#chan"),
+            vec![(REPLY_TAG.to_string(), "m1".to_string())]
+        );
+        assert!(input.replying_to.is_none());
+        assert!(input.take_reply_tags("net", "#chan").is_empty());
+    }
+
+    #[test]
+    fn test_typing_notifications_throttled() {
+        let mut typing = TypingNotifier::default();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let active = vec![("net".into(), "#chan".into(), TypingState::Active)];
+
+        assert!(typing.update("net", Some("#chan"), "", at(0)).is_empty());
+        assert_eq!(typing.update("net", Some("#chan"), "h", at(0)), active);
+        // More keystrokes within 3 seconds send nothing new
+        assert!(typing.update("net", Some("#chan"), "he", at(1)).is_empty());
+        assert_eq!(typing.update("net", Some("#chan"), "hel", at(3)), active);
+        // Idle for 5 seconds: paused, once
+        assert!(typing.update("net", Some("#chan"), "hel", at(7)).is_empty());
+        assert_eq!(
+            typing.update("net", Some("#chan"), "hel", at(8)),
+            vec![("net".into(), "#chan".into(), TypingState::Paused)]
+        );
+        assert!(typing
+            .update("net", Some("#chan"), "hel", at(20))
+            .is_empty());
+        // Clearing the input says done
+        assert_eq!(
+            typing.update("net", Some("#chan"), "", at(21)),
+            vec![("net".into(), "#chan".into(), TypingState::Done)]
+        );
+    }
+
+    #[test]
+    fn test_typing_done_when_leaving_or_sending() {
+        let mut typing = TypingNotifier::default();
+        let now = Instant::now();
+        typing.update("net", Some("#chan"), "hi", now);
+        assert_eq!(
+            typing.update("net", Some("#other"), "hi", now),
+            vec![("net".into(), "#chan".into(), TypingState::Done)]
+        );
+
+        // Sending turned off: nothing is reported for the new text
+        assert!(typing.update("net", None, "hi there", now).is_empty());
+
+        typing.update("net", Some("#chan"), "hello", now);
+        typing.message_sent();
+        assert!(typing.update("net", Some("#chan"), "", now).is_empty());
+        assert!(typing
+            .update("net", Some("#chan"), "/join #x", now)
+            .is_empty());
+    }
 }
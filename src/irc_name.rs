@@ -0,0 +1,162 @@
+//! Casemapping-aware nick and channel names.
+//!
+//! IRC compares names case-insensitively under the server's CASEMAPPING.
+//! With `rfc1459` (the default when a server announces none) `[]\~` are the
+//! upper-case forms of `{}|^`, so `Alice[m]` and `alice{m}` are one nick.
+//! `IrcName` keeps a name as first seen for display and compares, hashes
+//! and borrows as its folded form, so maps keyed by it can be looked up
+//! with `&str` keys that are already folded.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// How a server folds the case of nicks and channel names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaseMapping {
+    /// Only `A-Z` fold to `a-z`
+    Ascii,
+    /// `A-Z[]\~` fold to `a-z{}|^`
+    #[default]
+    Rfc1459,
+    /// `A-Z[]\` fold to `a-z{}|` (`~` and `^` stay distinct)
+    StrictRfc1459,
+}
+
+impl CaseMapping {
+    /// The mapping for an ISUPPORT CASEMAPPING value. Unknown mappings
+    /// (e.g. `rfc7613`) fold at least ASCII.
+    pub fn from_name(name: Option<&str>) -> Self {
+        match name.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("rfc1459") => Self::Rfc1459,
+            Some("strict-rfc1459") => Self::StrictRfc1459,
+            Some(_) => Self::Ascii,
+        }
+    }
+
+    /// Lower-case form of one character
+    pub fn fold_char(self, c: char) -> char {
+        match (self, c) {
+            (Self::Rfc1459 | Self::StrictRfc1459, '[') => '{',
+            (Self::Rfc1459 | Self::StrictRfc1459, ']') => '}',
+            (Self::Rfc1459 | Self::StrictRfc1459, '\\') => '|',
+            (Self::Rfc1459, '~') => '^',
+            _ => c.to_ascii_lowercase(),
+        }
+    }
+
+    /// Lower-case form of a name. Only ASCII changes, so byte offsets in
+    /// the result match the original.
+    pub fn fold(self, name: &str) -> String {
+        name.chars().map(|c| self.fold_char(c)).collect()
+    }
+
+    /// Whether two names are the same under this mapping
+    pub fn names_equal(self, a: &str, b: &str) -> bool {
+        a.len() == b.len()
+            && a.chars()
+                .zip(b.chars())
+                .all(|(x, y)| self.fold_char(x) == self.fold_char(y))
+    }
+}
+
+/// A nick or channel name that compares under a casemapping
+#[derive(Debug, Clone)]
+pub struct IrcName {
+    /// As first seen, for display
+    name: String,
+    /// Folded, for comparison
+    key: String,
+}
+
+impl IrcName {
+    pub fn new(name: &str, mapping: CaseMapping) -> Self {
+        Self {
+            name: name.to_string(),
+            key: mapping.fold(name),
+        }
+    }
+
+    /// The name in its original case
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    /// The folded name this one compares as
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The same name folded under another mapping
+    pub fn remap(&self, mapping: CaseMapping) -> Self {
+        Self::new(&self.name, mapping)
+    }
+}
+
+impl PartialEq for IrcName {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for IrcName {}
+
+impl Hash for IrcName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl Borrow<str> for IrcName {
+    fn borrow(&self) -> &str {
+        &self.key
+    }
+}
+
+impl fmt::Display for IrcName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_fold_per_mapping() {
+        assert_eq!(CaseMapping::Rfc1459.fold("Alice[m]\\~"), "alice{m}|^");
+        assert_eq!(CaseMapping::StrictRfc1459.fold("Alice[m]\\~"), "alice{m}|~");
+        assert_eq!(CaseMapping::Ascii.fold("Alice[m]\\~"), "alice[m]\\~");
+        assert!(CaseMapping::Rfc1459.names_equal("#Rust", "#rust"));
+        assert!(CaseMapping::Rfc1459.names_equal("nick[away]", "NICK{AWAY}"));
+        assert!(!CaseMapping::Ascii.names_equal("nick[away]", "nick{away}"));
+        assert!(!CaseMapping::Rfc1459.names_equal("alice", "alice_"));
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(CaseMapping::from_name(None), CaseMapping::Rfc1459);
+        assert_eq!(CaseMapping::from_name(Some("ascii")), CaseMapping::Ascii);
+        assert_eq!(
+            CaseMapping::from_name(Some("strict-rfc1459")),
+            CaseMapping::StrictRfc1459
+        );
+        assert_eq!(CaseMapping::from_name(Some("rfc7613")), CaseMapping::Ascii);
+    }
+
+    #[test]
+    fn test_irc_name_keys() {
+        let mut map = HashMap::new();
+        map.insert(IrcName::new("#Rust", CaseMapping::Rfc1459), 1);
+        map.insert(IrcName::new("#rust", CaseMapping::Rfc1459), 2);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map["#rust"], 2);
+        // The first spelling is kept for display
+        let (name, _) = map.iter().next().unwrap();
+        assert_eq!(name.as_str(), "#Rust");
+        assert_eq!(name.to_string(), "#Rust");
+        assert_eq!(name.key(), "#rust");
+    }
+}
@@ -5,14 +5,17 @@
 pub mod app;
 pub mod backend;
 pub mod buffer;
+pub mod channel_modes;
 pub mod commands;
 pub mod config;
 pub mod dialog_manager;
 pub mod events;
 pub mod fonts;
 pub mod input_state;
+pub mod irc_name;
 pub mod logging;
 pub mod protocol;
+pub mod server_support;
 pub mod state;
 pub mod ui;
 pub mod validation;
@@ -4,7 +4,7 @@
 //! Logs are stored in XDG_DATA_HOME/slirc-client/logs/ with the structure:
 //! logs/network/channel/YYYY-MM-DD.log
 
-use chrono::Local;
+use chrono::{DateTime, Local, Utc};
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use std::collections::HashMap;
 use std::fs::{self, File, OpenOptions};
@@ -17,7 +17,7 @@ use std::thread;
 pub struct LogEntry {
     pub network: String,
     pub channel: String,
-    pub timestamp: String,
+    pub timestamp: DateTime<Utc>,
     pub nick: String,
     pub message: String,
 }
@@ -79,8 +79,9 @@ fn write_log_entry(
     log_dir: &std::path::Path,
     entry: &LogEntry,
 ) -> Result<(), String> {
-    // Build path: logs/network/channel/YYYY-MM-DD.log
-    let date = Local::now().format("%Y-%m-%d").to_string();
+    // Build path: logs/network/channel/YYYY-MM-DD.log, dated by the message
+    let local = entry.timestamp.with_timezone(&Local);
+    let date = local.format("%Y-%m-%d").to_string();
     let sanitized_network = sanitize_filename(&entry.network);
     let sanitized_channel = sanitize_filename(&entry.channel);
 
@@ -109,7 +110,9 @@ fn write_log_entry(
     writeln!(
         writer,
         "[{}] <{}> {}",
-        entry.timestamp, entry.nick, entry.message
+        local.format("%H:%M:%S"),
+        entry.nick,
+        entry.message
     )
     .map_err(|e| format!("Failed to write log entry: {}", e))?;
 
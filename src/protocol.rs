@@ -1,3 +1,44 @@
+use chrono::{DateTime, Utc};
+
+use crate::channel_modes::{ModeChange, ModeListEntry};
+use crate::server_support::ServerSupport;
+
+/// Identifies a network session. Saved networks use their configured name;
+/// quick connects use the server host they were started with.
+pub type NetworkId = String;
+
+/// A `BackendAction` addressed to a specific network connection.
+#[derive(Debug, Clone)]
+pub struct NetworkAction {
+    pub network: NetworkId,
+    pub action: BackendAction,
+}
+
+impl NetworkAction {
+    pub fn new(network: impl Into<NetworkId>, action: BackendAction) -> Self {
+        Self {
+            network: network.into(),
+            action,
+        }
+    }
+}
+
+/// A `GuiEvent` tagged with the network connection it originated from.
+#[derive(Debug, Clone)]
+pub struct NetworkEvent {
+    pub network: NetworkId,
+    pub event: GuiEvent,
+}
+
+impl NetworkEvent {
+    pub fn new(network: impl Into<NetworkId>, event: GuiEvent) -> Self {
+        Self {
+            network: network.into(),
+            event,
+        }
+    }
+}
+
 /// Actions sent from the UI to the Backend
 #[derive(Debug, Clone)]
 pub enum BackendAction {
@@ -5,17 +46,31 @@ pub enum BackendAction {
     Connect {
         server: String,
         port: u16,
+        /// Other server addresses (`host[:port]`) to rotate through when reconnecting
+        fallback_servers: Vec<String>,
         nickname: String,
+        /// Nicks to try, in order, if `nickname` is rejected during registration
+        alt_nicks: Vec<String>,
         username: String,
         realname: String,
         use_tls: bool,
         auto_reconnect: bool,
+        /// Seconds without any data from the server before the link is considered dead
+        ping_timeout_secs: u64,
+        /// Flood control: lines that may be sent back to back
+        flood_burst: u32,
+        /// Flood control: milliseconds to regain one line after the burst
+        flood_refill_ms: u64,
         /// Optional SASL password for authentication
         sasl_password: Option<String>,
     },
     /// Disconnect from the server
     #[allow(dead_code)]
     Disconnect,
+    /// Skip the remaining backoff delay and reconnect immediately
+    ReconnectNow,
+    /// Stop a pending automatic reconnect
+    CancelReconnect,
     /// Join a channel
     Join(String),
     /// Part (leave) a channel
@@ -27,8 +82,24 @@ pub enum BackendAction {
     Nick(String),
     /// Quit the server
     Quit(Option<String>),
-    /// Send a message to a target (channel or user)
-    SendMessage { target: String, text: String },
+    /// Send a message to a target (channel or user); long text is split
+    /// into several lines by the backend. `client_tags` (e.g. `+draft/reply`)
+    /// go on every line when the server supports `message-tags`.
+    SendMessage {
+        target: String,
+        text: String,
+        client_tags: Vec<(String, String)>,
+    },
+    /// Send a NOTICE to a target (channel or user)
+    SendNotice { target: String, text: String },
+    /// Send several lines to a target, as a `draft/multiline` batch when
+    /// the server supports it and one message per line otherwise.
+    /// `client_tags` go with the message as a whole, e.g. `+draft/reply`.
+    SendMultiline {
+        target: String,
+        lines: Vec<String>,
+        client_tags: Vec<(String, String)>,
+    },
     /// Set the topic for a channel
     SetTopic { channel: String, topic: String },
     /// Request WHOIS information for a nick
@@ -47,6 +118,149 @@ pub enum BackendAction {
     },
     /// Request channel list from server
     List,
+    /// Ask for a channel's modes (answered by `GuiEvent::ChannelModeIs`)
+    /// and for the mask lists of the list modes in `lists`
+    /// (`GuiEvent::ModeList`)
+    QueryModes { channel: String, lists: Vec<char> },
+    /// Change channel modes, sent in as few MODE lines as the server's
+    /// ISUPPORT `MODES` limit allows
+    SetChannelModes {
+        channel: String,
+        changes: Vec<ModeChange>,
+    },
+    /// Fetch scrollback for a channel or query with `draft/chathistory`;
+    /// answered by `GuiEvent::History`
+    FetchHistory {
+        target: String,
+        anchor: HistoryAnchor,
+    },
+    /// Add (or with `add: false`, take back) an emoji reaction to the
+    /// message `msgid` in `target`, sent as a `TAGMSG`
+    React {
+        target: String,
+        msgid: String,
+        reaction: String,
+        add: bool,
+    },
+    /// Tell `target` whether we are typing, sent as a `TAGMSG`
+    Typing { target: String, state: TypingState },
+    /// Tell our other clients we have read `target` up to `time` (`MARKREAD`)
+    MarkRead { target: String, time: DateTime<Utc> },
+}
+
+/// Where a scrollback request starts
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryAnchor {
+    /// The most recent messages (on join)
+    Latest,
+    /// Messages older than the oldest one we have
+    Before {
+        msgid: Option<String>,
+        time: DateTime<Utc>,
+    },
+}
+
+/// Client tag naming the `msgid` of the message being replied to
+pub const REPLY_TAG: &str = "+draft/reply";
+
+/// Client tag carrying an emoji reaction to the message in `+draft/reply`
+pub const REACT_TAG: &str = "+draft/react";
+
+/// Client tag taking back an earlier `+draft/react`
+pub const UNREACT_TAG: &str = "+draft/unreact";
+
+/// Client tag carrying a typing notification
+pub const TYPING_TAG: &str = "+typing";
+
+/// Value of the `+typing` tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypingState {
+    /// Composing a message right now
+    Active,
+    /// Has unsent text but stopped typing
+    Paused,
+    /// Cleared the input without sending
+    Done,
+}
+
+impl TypingState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TypingState::Active => "active",
+            TypingState::Paused => "paused",
+            TypingState::Done => "done",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "active" => Some(TypingState::Active),
+            "paused" => Some(TypingState::Paused),
+            "done" => Some(TypingState::Done),
+            _ => None,
+        }
+    }
+}
+
+/// IRCv3 message tags carried from the server to the UI
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MessageTags {
+    /// Server-assigned id (`msgid`), stable across history and echoes
+    pub msgid: Option<String>,
+    /// Services account of the sender (`account`)
+    pub account: Option<String>,
+    /// `labeled-response` label, when this answers one of our commands
+    pub label: Option<String>,
+    /// Client-only tags (`+draft/reply`, `+draft/react`, ...) with the `+`
+    /// kept; a tag sent without a value has an empty one
+    pub client: Vec<(String, String)>,
+}
+
+impl MessageTags {
+    /// Value of the client-only tag `name` (e.g. `+draft/reply`)
+    pub fn client_tag(&self, name: &str) -> Option<&str> {
+        self.client
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A message from the server's chat history
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryMessage {
+    pub sender: String,
+    pub text: String,
+    pub time: DateTime<Utc>,
+    pub tags: MessageTags,
+}
+
+/// Everything a WHOIS reply told us about a user
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WhoisInfo {
+    pub nick: String,
+    pub user: String,
+    pub host: String,
+    pub realname: String,
+    /// Server the user is on, and its description
+    pub server: Option<String>,
+    pub server_info: Option<String>,
+    /// Time since the user last spoke
+    pub idle: Option<std::time::Duration>,
+    /// When the user connected
+    pub signon: Option<DateTime<Utc>>,
+    /// Channels with the user's prefix (e.g. `@#rust`)
+    pub channels: Vec<String>,
+    /// Services account the user is logged in to
+    pub account: Option<String>,
+    /// Real host or IP, shown to opers (RPL_WHOISACTUALLY)
+    pub actual_host: Option<String>,
+    /// Client certificate fingerprint (RPL_WHOISCERTFP)
+    pub certfp: Option<String>,
+    /// Connected over TLS
+    pub secure: bool,
+    /// IRC operator
+    pub oper: bool,
 }
 
 /// Events sent from the Backend to the UI
@@ -56,30 +270,111 @@ pub enum GuiEvent {
     Connected,
     /// Disconnected from server
     Disconnected(String),
+    /// Connection lost; an automatic reconnect is scheduled after `delay`
+    Reconnecting {
+        attempt: u32,
+        delay: std::time::Duration,
+    },
+    /// Round-trip time of the latest client PING
+    Lag(std::time::Duration),
+    /// Number of outgoing lines held back by flood control
+    SendQueueDepth(usize),
     /// Connection error
     Error(String),
+    /// A complete WHOIS reply
+    WhoisResult(WhoisInfo),
     /// A message was received (for any target)
     MessageReceived {
         target: String,
         sender: String,
         text: String,
+        /// When the server says the message was sent (`server-time`), or
+        /// when it arrived
+        time: DateTime<Utc>,
+        tags: MessageTags,
+    },
+    /// One of our own lines, shown until the server's `echo-message` copy
+    /// arrives (`OwnMessageEchoed`) or it fails (`OwnMessageFailed`)
+    OwnMessagePending {
+        id: u64,
+        target: String,
+        sender: String,
+        text: String,
+        tags: MessageTags,
+    },
+    /// The server relayed a pending line back; `text` and `tags` are the
+    /// server's version
+    OwnMessageEchoed {
+        id: u64,
+        target: String,
+        text: String,
+        tags: MessageTags,
+    },
+    /// A pending line was rejected by the server or never echoed
+    OwnMessageFailed {
+        id: u64,
+        target: String,
+        reason: String,
     },
     /// We successfully joined a channel
     JoinedChannel(String),
     /// We left a channel
     PartedChannel(String),
+    /// Scrollback for a buffer, oldest first; empty once there is no more
+    History {
+        target: String,
+        messages: Vec<HistoryMessage>,
+    },
+    /// `sender` started, paused or stopped typing to `target`
+    Typing {
+        target: String,
+        sender: String,
+        state: TypingState,
+    },
+    /// We (on any of our clients) have read `target` up to `time`
+    ReadMarker { target: String, time: DateTime<Utc> },
+    /// `sender` reacted to (or took back a reaction to) message `msgid`
+    Reaction {
+        target: String,
+        sender: String,
+        msgid: String,
+        reaction: String,
+        added: bool,
+    },
     /// Someone joined a channel we're in
-    UserJoined { channel: String, nick: String },
+    UserJoined {
+        channel: String,
+        nick: String,
+        time: DateTime<Utc>,
+    },
+    /// We were kicked from a channel
+    Kicked {
+        channel: String,
+        by: String,
+        reason: Option<String>,
+    },
+    /// Someone else was kicked from a channel we're in
+    UserKicked {
+        channel: String,
+        nick: String,
+        by: String,
+        reason: Option<String>,
+        time: DateTime<Utc>,
+    },
+    /// `by` invited us to `channel`
+    Invited { channel: String, by: String },
     /// Someone left a channel we're in
     UserParted {
         channel: String,
         nick: String,
         message: Option<String>,
+        time: DateTime<Utc>,
     },
     /// A user quit from the server (affects all channels they were in)
     UserQuit {
         nick: String,
         message: Option<String>,
+        time: DateTime<Utc>,
     },
     /// A user mode was changed in a channel (e.g. +o/-o) — used to update
     /// the nickname prefix in the UI.
@@ -89,11 +384,26 @@ pub enum GuiEvent {
         prefix: Option<char>,
         added: bool,
     },
-    /// Channel mode changed (e.g., +m, -t)
+    /// `set_by` changed channel modes (e.g. +m, -t, +k key, +b mask);
+    /// membership modes arrive as `UserMode` instead
     ChannelMode {
         channel: String,
-        modes: String,
+        changes: Vec<ModeChange>,
         set_by: String,
+        time: DateTime<Utc>,
+    },
+    /// All modes currently set on a channel (RPL_CHANNELMODEIS), list
+    /// modes excepted
+    ChannelModeIs {
+        channel: String,
+        changes: Vec<ModeChange>,
+    },
+    /// A channel's complete ban (`b`), exception (`e`), invite exception
+    /// (`I`) or quiet (`q`) list
+    ModeList {
+        channel: String,
+        mode: char,
+        entries: Vec<ModeListEntry>,
     },
     /// Raw server message for the system log
     RawMessage(String),
@@ -101,14 +411,29 @@ pub enum GuiEvent {
     Motd(String),
     /// Topic for a channel
     Topic { channel: String, topic: String },
+    /// `set_by` changed the topic while we were in the channel (TOPIC)
+    TopicChanged {
+        channel: String,
+        topic: String,
+        set_by: String,
+        time: DateTime<Utc>,
+    },
+    /// Who set the current topic and when (RPL_TOPICWHOTIME)
+    TopicWhoTime {
+        channel: String,
+        set_by: String,
+        set_at: DateTime<Utc>,
+    },
     /// Names list for a channel. Each name contains any mode prefix that was
     /// included in the NAMES reply (e.g. `@`, `+`, `%`, `~`, `&`).
     Names {
         channel: String,
         names: Vec<UserInfo>,
     },
-    /// Notification that the nick changed locally
+    /// A nick changed (ours only once the server has confirmed it)
     NickChanged { old: String, new: String },
+    /// The server rejected a nick change after registration (432/433/436/437)
+    NickError { nick: String, reason: String },
     /// Channel list entry from server (RPL_LIST 322)
     ChannelListItem {
         channel: String,
@@ -117,11 +442,8 @@ pub enum GuiEvent {
     },
     /// End of channel list (RPL_LISTEND 323)
     ChannelListEnd,
-    /// Server capabilities negotiated (network name, casemapping, etc.)
-    ServerInfo {
-        network: Option<String>,
-        casemapping: Option<String>,
-    },
+    /// Server features from RPL_ISUPPORT so far (prefixes, channel types, limits)
+    ServerInfo(ServerSupport),
     /// SASL authentication result
     SaslResult { success: bool, message: String },
 }
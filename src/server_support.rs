@@ -0,0 +1,393 @@
+//! Server features announced in RPL_ISUPPORT (005).
+//!
+//! Each connection builds a `ServerSupport` from the 005 tokens it receives
+//! and shares a copy with the UI (`GuiEvent::ServerInfo`), so membership
+//! prefixes, channel types and limits come from the server rather than
+//! being hardcoded. Until the server says otherwise the RFC 1459 defaults
+//! apply.
+
+use std::collections::HashMap;
+
+use crate::irc_name::{CaseMapping, IrcName};
+
+/// How a channel mode letter takes parameters (the CHANMODES groups plus
+/// the PREFIX modes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelModeKind {
+    /// Type A: a list of masks (`b`, `e`, `I`); parameter when listing or changing
+    List,
+    /// Type B: always has a parameter (`k`)
+    AlwaysParam,
+    /// Type C: parameter only when set (`l`)
+    SetParam,
+    /// Type D: a plain flag (`m`, `t`, ...)
+    Flag,
+    /// A membership mode from PREFIX (`o`, `v`, ...); parameter is a nick
+    Prefix,
+}
+
+/// Server features from RPL_ISUPPORT
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerSupport {
+    /// Membership (mode, prefix) pairs, highest rank first
+    pub prefixes: Vec<(char, char)>,
+    /// CHANMODES groups A, B, C and D
+    pub chanmodes: [String; 4],
+    /// Characters a channel name may start with
+    pub chantypes: String,
+    /// Prefixes that can be put before a channel to message only those members
+    pub statusmsg: String,
+    /// Longest nickname the server accepts
+    pub nicklen: Option<usize>,
+    /// Longest channel name the server accepts
+    pub channellen: Option<usize>,
+    /// Longest topic the server accepts
+    pub topiclen: Option<usize>,
+    /// Parameterized modes per MODE command (`None` = unlimited)
+    pub modes: Option<usize>,
+    /// Targets per command, keyed by upper-case command (`None` = unlimited)
+    pub targmax: HashMap<String, Option<usize>>,
+    /// Network name, if announced
+    pub network: Option<String>,
+    /// Nick/channel casemapping, if announced
+    pub casemapping: Option<String>,
+    /// Most messages one CHATHISTORY request may ask for (`None` = unlimited)
+    pub chathistory: Option<usize>,
+}
+
+impl Default for ServerSupport {
+    fn default() -> Self {
+        Self {
+            prefixes: vec![('o', '@'), ('v', '+')],
+            chanmodes: [
+                "b".to_string(),
+                "k".to_string(),
+                "l".to_string(),
+                "imnpst".to_string(),
+            ],
+            chantypes: "#&".to_string(),
+            statusmsg: String::new(),
+            nicklen: None,
+            channellen: None,
+            topiclen: None,
+            modes: Some(3),
+            targmax: HashMap::new(),
+            network: None,
+            casemapping: None,
+            chathistory: None,
+        }
+    }
+}
+
+impl ServerSupport {
+    /// Apply the tokens of one 005 line (without our nick and the trailing
+    /// "are supported by this server"). `-TOKEN` restores the default.
+    pub fn apply(&mut self, tokens: &[&str]) {
+        let defaults = Self::default();
+        for token in tokens {
+            if let Some(name) = token.strip_prefix('-') {
+                self.reset(name, &defaults);
+                continue;
+            }
+            let (name, value) = match token.split_once('=') {
+                Some((name, value)) => (name, Some(unescape(value))),
+                None => (*token, None),
+            };
+            let value = value.as_deref();
+            match name.to_ascii_uppercase().as_str() {
+                "PREFIX" => {
+                    self.prefixes = value.and_then(parse_prefix).unwrap_or_default();
+                }
+                "CHANMODES" => {
+                    let mut groups = value.unwrap_or_default().split(',');
+                    self.chanmodes =
+                        std::array::from_fn(|_| groups.next().unwrap_or_default().to_string());
+                }
+                "CHANTYPES" => self.chantypes = value.unwrap_or_default().to_string(),
+                "STATUSMSG" => self.statusmsg = value.unwrap_or_default().to_string(),
+                "NICKLEN" => self.nicklen = value.and_then(|v| v.parse().ok()),
+                "CHANNELLEN" => self.channellen = value.and_then(|v| v.parse().ok()),
+                "TOPICLEN" => self.topiclen = value.and_then(|v| v.parse().ok()),
+                "MODES" => self.modes = value.and_then(|v| v.parse().ok()),
+                "TARGMAX" => {
+                    self.targmax = value
+                        .unwrap_or_default()
+                        .split(',')
+                        .filter_map(|entry| entry.split_once(':'))
+                        .map(|(cmd, max)| (cmd.to_ascii_uppercase(), max.parse().ok()))
+                        .collect();
+                }
+                "NETWORK" => self.network = value.map(str::to_string),
+                "CASEMAPPING" => self.casemapping = value.map(str::to_string),
+                // 0 means no limit
+                "CHATHISTORY" => {
+                    self.chathistory = value.and_then(|v| v.parse().ok()).filter(|&n| n > 0);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Put a negated token back to how it was before the server set it
+    fn reset(&mut self, name: &str, defaults: &Self) {
+        match name.to_ascii_uppercase().as_str() {
+            "PREFIX" => self.prefixes = defaults.prefixes.clone(),
+            "CHANMODES" => self.chanmodes = defaults.chanmodes.clone(),
+            "CHANTYPES" => self.chantypes = defaults.chantypes.clone(),
+            "STATUSMSG" => self.statusmsg = defaults.statusmsg.clone(),
+            "NICKLEN" => self.nicklen = defaults.nicklen,
+            "CHANNELLEN" => self.channellen = defaults.channellen,
+            "TOPICLEN" => self.topiclen = defaults.topiclen,
+            "MODES" => self.modes = defaults.modes,
+            "TARGMAX" => self.targmax = defaults.targmax.clone(),
+            "NETWORK" => self.network = None,
+            "CASEMAPPING" => self.casemapping = None,
+            "CHATHISTORY" => self.chathistory = None,
+            _ => {}
+        }
+    }
+
+    /// How nicks and channel names compare on this server
+    pub fn case_mapping(&self) -> CaseMapping {
+        CaseMapping::from_name(self.casemapping.as_deref())
+    }
+
+    /// `name` as a key that compares under this server's casemapping
+    pub fn irc_name(&self, name: &str) -> IrcName {
+        IrcName::new(name, self.case_mapping())
+    }
+
+    /// Whether `name` is a channel (starts with one of CHANTYPES)
+    pub fn is_channel(&self, name: &str) -> bool {
+        name.chars()
+            .next()
+            .is_some_and(|c| self.chantypes.contains(c))
+    }
+
+    /// `name` as a channel: unchanged if it already is one, otherwise with
+    /// the first channel type (usually `#`) in front
+    pub fn channel_name(&self, name: &str) -> String {
+        match self.chantypes.chars().next() {
+            Some(chantype) if !self.is_channel(name) => format!("{}{}", chantype, name),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Split a NAMES entry such as `@+alice` into its highest prefix and
+    /// the nick (all prefixes are stripped when multi-prefix is enabled)
+    pub fn split_prefix<'a>(&self, entry: &'a str) -> (Option<char>, &'a str) {
+        let nick = entry.trim_start_matches(|c| self.is_prefix(c));
+        let prefix = entry[..entry.len() - nick.len()]
+            .chars()
+            .max_by_key(|c| self.prefix_rank(Some(*c)));
+        (prefix, nick)
+    }
+
+    /// Whether `c` is a membership prefix symbol
+    pub fn is_prefix(&self, c: char) -> bool {
+        self.prefixes.iter().any(|&(_, symbol)| symbol == c)
+    }
+
+    /// Rank of a membership prefix: 0 for none (or unknown), then counting
+    /// up to the highest prefix
+    pub fn prefix_rank(&self, prefix: Option<char>) -> usize {
+        prefix
+            .and_then(|c| self.prefixes.iter().position(|&(_, symbol)| symbol == c))
+            .map_or(0, |index| self.prefixes.len() - index)
+    }
+
+    /// Prefix symbol for a membership mode letter (`o` -> `@`)
+    pub fn prefix_for_mode(&self, mode: char) -> Option<char> {
+        self.prefixes
+            .iter()
+            .find(|&&(letter, _)| letter == mode)
+            .map(|&(_, symbol)| symbol)
+    }
+
+    /// Mode letter for a membership prefix symbol (`@` -> `o`)
+    pub fn mode_for_prefix(&self, prefix: char) -> Option<char> {
+        self.prefixes
+            .iter()
+            .find(|&&(_, symbol)| symbol == prefix)
+            .map(|&(letter, _)| letter)
+    }
+
+    /// Whether someone with `prefix` holds membership mode `mode` or a
+    /// higher one. A mode the server doesn't have falls back to op (`o`).
+    pub fn is_at_least(&self, prefix: Option<char>, mode: char) -> bool {
+        let required = self
+            .prefix_for_mode(mode)
+            .or_else(|| self.prefix_for_mode('o'));
+        match required {
+            Some(required) => self.prefix_rank(prefix) >= self.prefix_rank(Some(required)),
+            None => false,
+        }
+    }
+
+    /// How channel mode `mode` takes parameters, if the server knows it
+    pub fn mode_kind(&self, mode: char) -> Option<ChannelModeKind> {
+        if self.prefix_for_mode(mode).is_some() {
+            return Some(ChannelModeKind::Prefix);
+        }
+        let kinds = [
+            ChannelModeKind::List,
+            ChannelModeKind::AlwaysParam,
+            ChannelModeKind::SetParam,
+            ChannelModeKind::Flag,
+        ];
+        self.chanmodes
+            .iter()
+            .zip(kinds)
+            .find(|(group, _)| group.contains(mode))
+            .map(|(_, kind)| kind)
+    }
+
+    /// Most targets `command` accepts at once (`None` = no limit announced)
+    pub fn max_targets(&self, command: &str) -> Option<usize> {
+        self.targmax
+            .get(&command.to_ascii_uppercase())
+            .copied()
+            .flatten()
+    }
+}
+
+/// Parse a PREFIX value such as `(ov)@+`; an empty value means no prefixes
+fn parse_prefix(value: &str) -> Option<Vec<(char, char)>> {
+    if value.is_empty() {
+        return Some(Vec::new());
+    }
+    let (modes, symbols) = value.strip_prefix('(')?.split_once(')')?;
+    if modes.chars().count() != symbols.chars().count() {
+        return None;
+    }
+    Some(modes.chars().zip(symbols.chars()).collect())
+}
+
+/// Undo the `\xHH` escapes ISUPPORT values use for spaces and the like
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(index) = rest.find("\\x") {
+        out.push_str(&rest[..index]);
+        let escaped = rest
+            .get(index + 2..index + 4)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                out.push_str("\\x");
+                rest = &rest[index + 2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn support(tokens: &[&str]) -> ServerSupport {
+        let mut support = ServerSupport::default();
+        support.apply(tokens);
+        support
+    }
+
+    #[test]
+    fn test_defaults() {
+        let support = ServerSupport::default();
+        assert!(support.is_channel("#rust"));
+        assert!(support.is_channel("&local"));
+        assert!(!support.is_channel("alice"));
+        assert!(!support.is_channel(""));
+        assert_eq!(support.split_prefix("@alice"), (Some('@'), "alice"));
+        assert_eq!(support.split_prefix("%alice"), (None, "%alice"));
+        assert_eq!(support.modes, Some(3));
+        assert_eq!(support.mode_kind('b'), Some(ChannelModeKind::List));
+        assert_eq!(support.mode_kind('o'), Some(ChannelModeKind::Prefix));
+    }
+
+    #[test]
+    fn test_apply_tokens() {
+        let support = support(&[
+            "PREFIX=(qaohv)~&@%+",
+            "CHANMODES=beI,k,l,imnpstrCT",
+            "CHANTYPES=#!+",
+            "STATUSMSG=@+",
+            "NICKLEN=31",
+            "CHANNELLEN=64",
+            "TOPICLEN=390",
+            "MODES=4",
+            "TARGMAX=PRIVMSG:4,NOTICE:4,JOIN:",
+            "NETWORK=Example\\x20Net",
+            "CASEMAPPING=rfc1459",
+            "CHATHISTORY=100",
+        ]);
+        assert_eq!(support.prefixes.len(), 5);
+        assert_eq!(support.chanmodes[3], "imnpstrCT");
+        assert!(support.is_channel("!ABCDEchan"));
+        assert!(support.is_channel("+modeless"));
+        assert!(!support.is_channel("&local"));
+        assert_eq!(support.statusmsg, "@+");
+        assert_eq!(support.nicklen, Some(31));
+        assert_eq!(support.channellen, Some(64));
+        assert_eq!(support.topiclen, Some(390));
+        assert_eq!(support.modes, Some(4));
+        assert_eq!(support.max_targets("privmsg"), Some(4));
+        assert_eq!(support.max_targets("JOIN"), None);
+        assert_eq!(support.network.as_deref(), Some("Example Net"));
+        assert_eq!(support.casemapping.as_deref(), Some("rfc1459"));
+        assert_eq!(support.chathistory, Some(100));
+        assert_eq!(support.mode_kind('e'), Some(ChannelModeKind::List));
+        assert_eq!(support.mode_kind('k'), Some(ChannelModeKind::AlwaysParam));
+        assert_eq!(support.mode_kind('l'), Some(ChannelModeKind::SetParam));
+        assert_eq!(support.mode_kind('C'), Some(ChannelModeKind::Flag));
+        assert_eq!(support.mode_kind('q'), Some(ChannelModeKind::Prefix));
+        assert_eq!(support.mode_kind('Z'), None);
+
+        // Negation restores the default; MODES without a value is unlimited
+        let support = self::support(&["CHANTYPES=!", "-CHANTYPES", "MODES"]);
+        assert_eq!(support.chantypes, "#&");
+        assert_eq!(support.modes, None);
+    }
+
+    #[test]
+    fn test_prefix_ranks() {
+        let support = support(&["PREFIX=(qaohv)~&@%+"]);
+        assert!(support.prefix_rank(Some('~')) > support.prefix_rank(Some('@')));
+        assert!(support.prefix_rank(Some('@')) > support.prefix_rank(Some('+')));
+        assert!(support.prefix_rank(Some('+')) > support.prefix_rank(None));
+        assert_eq!(support.prefix_rank(Some('!')), 0);
+        assert_eq!(support.prefix_for_mode('h'), Some('%'));
+        assert_eq!(support.mode_for_prefix('&'), Some('a'));
+        assert!(support.is_at_least(Some('&'), 'o'));
+        assert!(support.is_at_least(Some('%'), 'h'));
+        assert!(!support.is_at_least(Some('+'), 'h'));
+
+        // Multi-prefix NAMES entries keep the highest prefix
+        assert_eq!(support.split_prefix("+@alice"), (Some('@'), "alice"));
+        assert_eq!(support.split_prefix("bob"), (None, "bob"));
+
+        // Without halfops, the halfop check asks for op
+        let plain = ServerSupport::default();
+        assert!(!plain.is_at_least(Some('+'), 'h'));
+        assert!(plain.is_at_least(Some('@'), 'h'));
+    }
+
+    #[test]
+    fn test_custom_prefix_and_channel_name() {
+        let support = support(&["PREFIX=(Yov)!@+", "CHANTYPES=+"]);
+        assert_eq!(support.split_prefix("!alice"), (Some('!'), "alice"));
+        assert!(support.is_at_least(Some('!'), 'o'));
+        assert_eq!(support.channel_name("rust"), "+rust");
+        assert_eq!(support.channel_name("+rust"), "+rust");
+
+        let none = self::support(&["PREFIX="]);
+        assert!(none.prefixes.is_empty());
+        assert_eq!(none.split_prefix("@alice"), (None, "@alice"));
+    }
+}
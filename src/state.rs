@@ -3,35 +3,169 @@
 //! `ClientState` holds all data that represents the IRC session:
 //! buffers, networks, connection status, etc. This separation allows
 //! UI components to receive state as a parameter rather than owning it.
+//!
+//! Each connected network has its own `NetworkSession` with independent
+//! buffers and connection status. The "System" buffer is global and is not
+//! owned by any session.
 
 use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
+use once_cell::sync::Lazy;
+
 use crate::buffer::ChannelBuffer;
 use crate::config::Network;
+use crate::irc_name::IrcName;
 use crate::logging::Logger;
+use crate::protocol::{NetworkId, WhoisInfo};
+use crate::server_support::ServerSupport;
 
-/// Core application state for the IRC client.
-///
-/// This struct contains all session data, separated from UI concerns.
-/// It is owned by `SlircApp` and passed to UI components as needed.
+/// Name of the global system buffer.
+pub const SYSTEM_BUFFER: &str = "System";
+
+/// Shared empty buffer map, for when no network session is active.
+pub fn no_buffers() -> &'static HashMap<IrcName, ChannelBuffer> {
+    static NO_BUFFERS: Lazy<HashMap<IrcName, ChannelBuffer>> = Lazy::new(HashMap::new);
+    &NO_BUFFERS
+}
+
+/// Server features assumed for networks without a session.
+pub fn default_support() -> &'static ServerSupport {
+    static DEFAULT_SUPPORT: Lazy<ServerSupport> = Lazy::new(ServerSupport::default);
+    &DEFAULT_SUPPORT
+}
+
+/// An invitation to a channel, waiting for the user to accept or decline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingInvite {
+    pub network: NetworkId,
+    pub channel: String,
+    /// Nick of whoever sent the invite.
+    pub by: String,
+}
+
+/// Per-network session state (one per connection).
 #[derive(Default)]
-pub struct ClientState {
-    /// Whether we are currently connected to a server.
+pub struct NetworkSession {
+    /// Whether this network is currently connected.
     pub is_connected: bool,
 
     /// The server we are connected to (for logging/display).
     pub server_name: String,
 
-    /// Our current nickname on the server (updated on NickChanged).
+    /// Our current nickname on this network (updated on NickChanged).
     pub our_nick: String,
 
-    /// Message buffers keyed by channel/query name.
-    pub buffers: HashMap<String, ChannelBuffer>,
+    /// Message buffers keyed by channel/query name under the network's
+    /// casemapping (the key keeps the name as first seen).
+    pub buffers: HashMap<IrcName, ChannelBuffer>,
 
-    /// Ordered list of buffer names (for sidebar display).
+    /// Ordered list of buffer names as displayed (for the sidebar).
     pub buffers_order: Vec<String>,
 
+    /// Pending automatic reconnect: attempt number and when it will happen.
+    pub reconnect_at: Option<(u32, std::time::Instant)>,
+
+    /// Round-trip time of the latest client PING, if measured yet.
+    pub lag: Option<std::time::Duration>,
+
+    /// Outgoing lines held back by flood control.
+    pub send_queue_depth: usize,
+
+    /// Latest WHOIS reply for each nick.
+    pub whois: HashMap<IrcName, WhoisInfo>,
+
+    /// Server features from ISUPPORT (prefixes, channel types, limits).
+    pub support: ServerSupport,
+}
+
+impl NetworkSession {
+    /// Create an empty, disconnected session.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `name` as it compares on this network.
+    pub fn irc_name(&self, name: &str) -> IrcName {
+        self.support.irc_name(name)
+    }
+
+    /// Whether two nicks or channel names are the same on this network.
+    pub fn names_equal(&self, a: &str, b: &str) -> bool {
+        self.support.case_mapping().names_equal(a, b)
+    }
+
+    /// The buffer for a channel or query, in any case.
+    pub fn buffer(&self, name: &str) -> Option<&ChannelBuffer> {
+        self.buffers.get(&self.irc_name(name))
+    }
+
+    /// Mutable buffer for a channel or query, in any case.
+    pub fn buffer_mut(&mut self, name: &str) -> Option<&mut ChannelBuffer> {
+        let key = self.irc_name(name);
+        self.buffers.get_mut(&key)
+    }
+
+    /// The displayed name of the buffer `name` refers to, if it exists.
+    pub fn buffer_name(&self, name: &str) -> Option<&str> {
+        self.buffers
+            .get_key_value(&self.irc_name(name))
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Get a mutable reference to a buffer, creating it if needed.
+    pub fn ensure_buffer(&mut self, name: &str) -> &mut ChannelBuffer {
+        let key = self.irc_name(name);
+        if !self.buffers.contains_key(&key) {
+            self.buffers_order.push(name.to_string());
+        }
+        self.buffers.entry(key).or_insert_with(ChannelBuffer::new)
+    }
+
+    /// Remove a buffer and its sidebar entry.
+    pub fn remove_buffer(&mut self, name: &str) {
+        let mapping = self.support.case_mapping();
+        self.buffers.remove(&self.irc_name(name));
+        self.buffers_order.retain(|b| !mapping.names_equal(b, name));
+    }
+
+    /// Take new server features. If the casemapping changed, names are
+    /// re-keyed; of buffers whose names now collide only one is kept.
+    pub fn set_support(&mut self, support: ServerSupport) {
+        let mapping = support.case_mapping();
+        let remap = mapping != self.support.case_mapping();
+        self.support = support;
+        if !remap {
+            return;
+        }
+        for (key, buffer) in std::mem::take(&mut self.buffers) {
+            self.buffers.entry(key.remap(mapping)).or_insert(buffer);
+        }
+        let mut seen = HashSet::new();
+        self.buffers_order
+            .retain(|name| seen.insert(mapping.fold(name)));
+        self.whois = std::mem::take(&mut self.whois)
+            .into_iter()
+            .map(|(nick, info)| (nick.remap(mapping), info))
+            .collect();
+    }
+}
+
+/// Core application state for the IRC client.
+///
+/// This struct contains all session data, separated from UI concerns.
+/// It is owned by `SlircApp` and passed to UI components as needed.
+#[derive(Default)]
+pub struct ClientState {
+    /// Per-network sessions keyed by network id.
+    pub sessions: HashMap<NetworkId, NetworkSession>,
+
+    /// Ordered list of network ids (for sidebar display).
+    pub sessions_order: Vec<NetworkId>,
+
+    /// Network of the active buffer (or the last used network while in "System").
+    pub active_network: NetworkId,
+
     /// Currently active/visible buffer.
     pub active_buffer: String,
 
@@ -47,10 +181,13 @@ pub struct ClientState {
     /// Status toast messages with creation time (auto-expire).
     pub status_messages: Vec<(String, Instant)>,
 
+    /// Channel invites not yet answered, oldest first.
+    pub pending_invites: Vec<PendingInvite>,
+
     /// Chat logger for persisting messages to disk.
     pub logger: Option<Logger>,
 
-    /// Collapsed sections in sidebar (for Phase 3 collapsible sections)
+    /// Collapsed sections in sidebar (network ids whose buffers are hidden)
     pub collapsed_sections: std::collections::HashSet<String>,
 
     /// Channel search/filter text (for Phase 3 search feature)
@@ -60,87 +197,183 @@ pub struct ClientState {
 impl ClientState {
     /// Create a new ClientState with default values.
     pub fn new() -> Self {
-        let mut state = Self {
-            is_connected: false,
-            server_name: String::new(),
-            our_nick: String::new(),
-            buffers: HashMap::new(),
-            buffers_order: vec!["System".into()],
-            active_buffer: "System".into(),
+        Self {
+            sessions: HashMap::new(),
+            sessions_order: Vec::new(),
+            active_network: NetworkId::new(),
+            active_buffer: SYSTEM_BUFFER.into(),
             networks: Vec::new(),
             system_log: vec!["Welcome to SLIRC!".into()],
             expanded_networks: HashSet::new(),
             status_messages: Vec::new(),
+            pending_invites: Vec::new(),
             logger: Logger::new().ok(),
             collapsed_sections: HashSet::new(),
             channel_filter: String::new(),
-        };
+        }
+    }
 
-        // Create the System buffer
-        state.buffers.insert("System".into(), ChannelBuffer::new());
+    /// Get the session for a network, if it exists.
+    pub fn session(&self, network: &str) -> Option<&NetworkSession> {
+        self.sessions.get(network)
+    }
 
-        state
+    /// Get a mutable reference to a network's session, creating it if needed.
+    ///
+    /// The first session created becomes the active network.
+    pub fn session_mut(&mut self, network: &str) -> &mut NetworkSession {
+        if !self.sessions.contains_key(network) {
+            self.sessions
+                .insert(network.to_string(), NetworkSession::new());
+            self.sessions_order.push(network.to_string());
+            if self.active_network.is_empty() {
+                self.active_network = network.to_string();
+            }
+        }
+        self.sessions
+            .get_mut(network)
+            .expect("Session should exist")
     }
 
-    /// Get a mutable reference to a buffer, creating it if needed.
-    pub fn ensure_buffer(&mut self, name: &str) -> &mut ChannelBuffer {
-        if !self.buffers.contains_key(name) {
-            self.buffers.insert(name.to_string(), ChannelBuffer::new());
-            self.buffers_order.push(name.to_string());
+    /// Session of the active network, if any.
+    pub fn active_session(&self) -> Option<&NetworkSession> {
+        self.sessions.get(&self.active_network)
+    }
+
+    /// Mutable session of the active network, if any.
+    pub fn active_session_mut(&mut self) -> Option<&mut NetworkSession> {
+        self.sessions.get_mut(&self.active_network)
+    }
+
+    /// Buffers of the active network (empty if no network is active).
+    pub fn active_buffers(&self) -> &HashMap<IrcName, ChannelBuffer> {
+        self.active_session()
+            .map(|s| &s.buffers)
+            .unwrap_or_else(no_buffers)
+    }
+
+    /// The active buffer, unless it is the "System" buffer.
+    pub fn active_channel_buffer(&self) -> Option<&ChannelBuffer> {
+        self.active_session()?.buffer(&self.active_buffer)
+    }
+
+    /// Mutable active buffer, unless it is the "System" buffer.
+    pub fn active_channel_buffer_mut(&mut self) -> Option<&mut ChannelBuffer> {
+        let name = self.active_buffer.clone();
+        self.active_session_mut()?.buffer_mut(&name)
+    }
+
+    /// Server features of a network (the defaults if it has no session).
+    pub fn support(&self, network: &str) -> &ServerSupport {
+        self.session(network)
+            .map(|s| &s.support)
+            .unwrap_or_else(default_support)
+    }
+
+    /// Server features of the active network.
+    pub fn active_support(&self) -> &ServerSupport {
+        self.support(&self.active_network)
+    }
+
+    /// Whether the active network is connected.
+    pub fn is_connected(&self) -> bool {
+        self.active_session().is_some_and(|s| s.is_connected)
+    }
+
+    /// Our nickname on the active network (empty if none).
+    pub fn our_nick(&self) -> &str {
+        self.active_session()
+            .map(|s| s.our_nick.as_str())
+            .unwrap_or("")
+    }
+
+    /// Whether the given buffer on the given network is the one being viewed.
+    /// Names compare under the network's casemapping.
+    pub fn is_active(&self, network: &str, buffer: &str) -> bool {
+        if self.active_network != network {
+            return false;
+        }
+        if self.active_buffer == SYSTEM_BUFFER || buffer == SYSTEM_BUFFER {
+            return self.active_buffer == buffer;
         }
-        self.buffers.get_mut(name).expect("Buffer should exist")
+        self.support(network)
+            .case_mapping()
+            .names_equal(&self.active_buffer, buffer)
+    }
+
+    /// Get a mutable reference to a buffer on a network, creating both if needed.
+    pub fn ensure_buffer(&mut self, network: &str, name: &str) -> &mut ChannelBuffer {
+        self.session_mut(network).ensure_buffer(name)
+    }
+
+    /// All buffers in sidebar order: "System" first, then each network's buffers.
+    fn buffer_cycle(&self) -> Vec<(NetworkId, String)> {
+        let mut cycle = vec![(self.active_network.clone(), SYSTEM_BUFFER.to_string())];
+        for network in &self.sessions_order {
+            if let Some(session) = self.sessions.get(network) {
+                for name in &session.buffers_order {
+                    cycle.push((network.clone(), name.clone()));
+                }
+            }
+        }
+        cycle
+    }
+
+    /// Position of the active buffer within `buffer_cycle`.
+    fn active_cycle_index(&self, cycle: &[(NetworkId, String)]) -> Option<usize> {
+        if self.active_buffer == SYSTEM_BUFFER {
+            return Some(0);
+        }
+        cycle.iter().position(|(n, b)| self.is_active(n, b))
     }
 
     /// Switch to the next buffer in order.
     pub fn next_buffer(&mut self) {
-        if let Some(current_idx) = self
-            .buffers_order
-            .iter()
-            .position(|b| b == &self.active_buffer)
-        {
-            let next_idx = (current_idx + 1) % self.buffers_order.len();
-            if let Some(next_buffer) = self.buffers_order.get(next_idx) {
-                self.active_buffer = next_buffer.clone();
-                if let Some(buffer) = self.buffers.get_mut(next_buffer) {
-                    buffer.clear_unread();
-                    buffer.has_highlight = false;
-                }
+        let cycle = self.buffer_cycle();
+        if let Some(current_idx) = self.active_cycle_index(&cycle) {
+            let next_idx = (current_idx + 1) % cycle.len();
+            if let Some((network, name)) = cycle.get(next_idx) {
+                self.switch_to_buffer(network, name);
             }
         }
     }
 
     /// Switch to the previous buffer in order.
     pub fn prev_buffer(&mut self) {
-        if let Some(current_idx) = self
-            .buffers_order
-            .iter()
-            .position(|b| b == &self.active_buffer)
-        {
+        let cycle = self.buffer_cycle();
+        if let Some(current_idx) = self.active_cycle_index(&cycle) {
             let prev_idx = if current_idx == 0 {
-                self.buffers_order.len() - 1
+                cycle.len() - 1
             } else {
                 current_idx - 1
             };
-            if let Some(prev_buffer) = self.buffers_order.get(prev_idx) {
-                self.active_buffer = prev_buffer.clone();
-                if let Some(buffer) = self.buffers.get_mut(prev_buffer) {
-                    buffer.clear_unread();
-                    buffer.has_highlight = false;
-                }
+            if let Some((network, name)) = cycle.get(prev_idx) {
+                self.switch_to_buffer(network, name);
             }
         }
     }
 
-    /// Switch to a specific buffer by name.
-    #[allow(dead_code)]
-    pub fn switch_to_buffer(&mut self, name: &str) {
-        if self.buffers.contains_key(name) {
-            self.active_buffer = name.to_string();
-            if let Some(buffer) = self.buffers.get_mut(name) {
-                buffer.clear_unread();
-                buffer.has_highlight = false;
-            }
+    /// Switch to a specific buffer on a network.
+    ///
+    /// Switching to "System" keeps the current active network. The buffer
+    /// may be named in any case; it is shown as it was first named.
+    pub fn switch_to_buffer(&mut self, network: &str, name: &str) {
+        if name == SYSTEM_BUFFER {
+            self.active_buffer = SYSTEM_BUFFER.into();
+            return;
         }
+        let Some(session) = self.sessions.get_mut(network) else {
+            return;
+        };
+        let Some(display) = session.buffer_name(name).map(str::to_string) else {
+            return;
+        };
+        if let Some(buffer) = session.buffer_mut(name) {
+            buffer.clear_unread();
+            buffer.has_highlight = false;
+        }
+        self.active_network = network.to_string();
+        self.active_buffer = display;
     }
 
     /// Purge status messages older than the given duration.
@@ -154,42 +387,93 @@ impl ClientState {
 mod tests {
     use super::*;
 
+    const NET: &str = "testnet";
+
     #[test]
     fn test_client_state_new() {
         let state = ClientState::new();
-        assert!(!state.is_connected);
-        assert!(state.server_name.is_empty());
-        assert!(state.our_nick.is_empty());
-        assert!(state.buffers.contains_key("System"));
+        assert!(!state.is_connected());
+        assert!(state.our_nick().is_empty());
+        assert!(state.sessions.is_empty());
         assert_eq!(state.active_buffer, "System");
-        assert_eq!(state.buffers_order, vec!["System"]);
+        assert!(state.active_buffers().is_empty());
     }
 
     #[test]
     fn test_connection_state() {
         let mut state = ClientState::new();
-        state.server_name = "irc.example.net:6667".to_string();
-        state.our_nick = "testuser".to_string();
-        state.is_connected = true;
-
-        assert_eq!(state.server_name, "irc.example.net:6667");
-        assert_eq!(state.our_nick, "testuser");
-        assert!(state.is_connected);
+        let session = state.session_mut(NET);
+        session.server_name = "irc.example.net:6667".to_string();
+        session.our_nick = "testuser".to_string();
+        session.is_connected = true;
+
+        assert_eq!(state.active_network, NET);
+        assert_eq!(
+            state.active_session().unwrap().server_name,
+            "irc.example.net:6667"
+        );
+        assert_eq!(state.our_nick(), "testuser");
+        assert!(state.is_connected());
     }
 
     #[test]
     fn test_ensure_buffer() {
         let mut state = ClientState::new();
-        state.ensure_buffer("#test");
-        assert!(state.buffers.contains_key("#test"));
-        assert!(state.buffers_order.contains(&"#test".to_string()));
+        state.ensure_buffer(NET, "#test");
+        let session = state.session(NET).unwrap();
+        assert!(session.buffers.contains_key("#test"));
+        assert!(session.buffers_order.contains(&"#test".to_string()));
+    }
+
+    #[test]
+    fn test_buffers_follow_casemapping() {
+        let mut state = ClientState::new();
+        state.ensure_buffer(NET, "#Rust[dev]");
+        state.ensure_buffer(NET, "#rust{dev}");
+        let session = state.session(NET).unwrap();
+        assert_eq!(session.buffers.len(), 1);
+        assert_eq!(session.buffers_order, vec!["#Rust[dev]"]);
+
+        // The first spelling is kept however the buffer is named later
+        state.switch_to_buffer(NET, "#RUST{DEV}");
+        assert_eq!(state.active_buffer, "#Rust[dev]");
+        assert!(state.is_active(NET, "#rust[dev]"));
+
+        // Under ascii, brackets and braces are different characters
+        let mut support = ServerSupport::default();
+        support.apply(&["CASEMAPPING=ascii"]);
+        let session = state.session_mut(NET);
+        session.set_support(support);
+        assert!(session.buffer("#RUST[DEV]").is_some());
+        assert!(session.buffer("#rust{dev}").is_none());
+
+        session.remove_buffer("#rust[dev]");
+        assert!(session.buffers.is_empty());
+        assert!(session.buffers_order.is_empty());
+    }
+
+    #[test]
+    fn test_sessions_are_independent() {
+        let mut state = ClientState::new();
+        state.ensure_buffer("net1", "#rust");
+        state.ensure_buffer("net2", "#rust");
+        state.ensure_buffer("net2", "#other");
+
+        assert_eq!(state.sessions_order, vec!["net1", "net2"]);
+        assert_eq!(state.session("net1").unwrap().buffers.len(), 1);
+        assert_eq!(state.session("net2").unwrap().buffers.len(), 2);
+
+        state.session_mut("net2").remove_buffer("#rust");
+        assert!(state.session("net1").unwrap().buffers.contains_key("#rust"));
+        assert!(!state.session("net2").unwrap().buffers.contains_key("#rust"));
     }
 
     #[test]
     fn test_next_prev_buffer() {
         let mut state = ClientState::new();
-        state.ensure_buffer("#chan1");
-        state.ensure_buffer("#chan2");
+        state.ensure_buffer(NET, "#chan1");
+        state.ensure_buffer(NET, "#chan2");
+        state.ensure_buffer("othernet", "#chan3");
 
         assert_eq!(state.active_buffer, "System");
         state.next_buffer();
@@ -197,21 +481,31 @@ mod tests {
         state.next_buffer();
         assert_eq!(state.active_buffer, "#chan2");
         state.next_buffer();
+        assert_eq!(state.active_buffer, "#chan3");
+        assert_eq!(state.active_network, "othernet");
+        state.next_buffer();
         assert_eq!(state.active_buffer, "System"); // wrap around
 
+        state.prev_buffer();
+        assert_eq!(state.active_buffer, "#chan3");
         state.prev_buffer();
         assert_eq!(state.active_buffer, "#chan2");
+        assert_eq!(state.active_network, NET);
     }
 
     #[test]
     fn test_switch_to_buffer() {
         let mut state = ClientState::new();
-        state.ensure_buffer("#test");
-        state.switch_to_buffer("#test");
+        state.ensure_buffer(NET, "#test");
+        state.switch_to_buffer(NET, "#test");
         assert_eq!(state.active_buffer, "#test");
 
         // Switching to non-existent buffer does nothing
-        state.switch_to_buffer("#nonexistent");
+        state.switch_to_buffer(NET, "#nonexistent");
         assert_eq!(state.active_buffer, "#test");
+
+        // Same name on an unknown network does nothing either
+        state.switch_to_buffer("othernet", "#test");
+        assert_eq!(state.active_network, NET);
     }
 }
@@ -3,6 +3,7 @@
 //! This follows the immediate-mode GUI pattern where dialogs return results
 //! that the main app processes, avoiding callback hell and borrow checker issues.
 
+use crate::channel_modes::ModeChange;
 use crate::config::Network;
 
 /// Actions that dialogs can return to the main application.
@@ -29,4 +30,27 @@ pub enum DialogAction {
         channel: String,
         topic: String,
     },
+
+    // Invite toast: join the channel, or just dismiss the invite
+    AnswerInvite {
+        network: String,
+        channel: String,
+        accept: bool,
+    },
+
+    // Paste confirmation
+    SendMultiline {
+        network: String,
+        target: String,
+        /// The pasted input the lines came from
+        text: String,
+        lines: Vec<String>,
+    },
+
+    // Channel settings
+    SetChannelModes {
+        network: String,
+        channel: String,
+        changes: Vec<ModeChange>,
+    },
 }
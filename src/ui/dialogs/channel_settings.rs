@@ -0,0 +1,571 @@
+//! Channel settings dialog - edit channel modes and mask lists.
+
+use std::collections::BTreeMap;
+
+use chrono::Local;
+use eframe::egui;
+
+use super::DialogAction;
+use crate::channel_modes::{ChannelModes, ModeChange, ModeListEntry};
+use crate::server_support::{ChannelModeKind, ServerSupport};
+
+/// Plain modes offered as checkboxes, when the server has them
+const SIMPLE_MODES: [(char, &str); 6] = [
+    ('m', "Moderated (+m)"),
+    ('t', "Only operators change the topic (+t)"),
+    ('n', "No messages from outside (+n)"),
+    ('s', "Secret (+s)"),
+    ('i', "Invite only (+i)"),
+    ('r', "Registered users only (+r)"),
+];
+
+/// Mask lists offered for editing, when the server has them
+const LIST_MODES: [(char, &str); 4] = [
+    ('b', "Bans"),
+    ('e', "Exceptions"),
+    ('I', "Invite exceptions"),
+    ('q', "Quiets"),
+];
+
+/// Self-contained channel settings dialog state.
+pub struct ChannelSettingsDialog {
+    /// Network the channel is on
+    pub network: String,
+    /// The channel whose modes we're editing
+    pub channel: String,
+    /// Modes as the server last reported them
+    modes: ChannelModes,
+    /// Simple modes the server has, each with its edited state
+    pub flags: Vec<(char, bool)>,
+    /// Whether a key (+k) should be set
+    pub key_enabled: bool,
+    /// The key to set; hidden keys show as empty
+    pub key_input: String,
+    /// Whether a user limit (+l) should be set
+    pub limit_enabled: bool,
+    /// The user limit to set
+    pub limit_input: String,
+    /// List modes the server has
+    lists: Vec<char>,
+    /// Mask being typed for each list
+    new_masks: BTreeMap<char, String>,
+    /// Masks added to or removed from the lists, not applied yet
+    pub list_edits: Vec<ModeChange>,
+    /// Whether we may change anything (channel operator)
+    can_edit: bool,
+}
+
+impl ChannelSettingsDialog {
+    /// Create a channel settings dialog showing `modes`
+    pub fn new(
+        network: &str,
+        channel: &str,
+        modes: &ChannelModes,
+        support: &ServerSupport,
+        can_edit: bool,
+    ) -> Self {
+        let mut dialog = Self {
+            network: network.to_string(),
+            channel: channel.to_string(),
+            modes: modes.clone(),
+            flags: SIMPLE_MODES
+                .iter()
+                .filter(|(mode, _)| support.mode_kind(*mode) == Some(ChannelModeKind::Flag))
+                .map(|&(mode, _)| (mode, false))
+                .collect(),
+            key_enabled: false,
+            key_input: String::new(),
+            limit_enabled: false,
+            limit_input: String::new(),
+            lists: LIST_MODES
+                .iter()
+                .filter(|(mode, _)| support.mode_kind(*mode) == Some(ChannelModeKind::List))
+                .map(|&(mode, _)| mode)
+                .collect(),
+            new_masks: BTreeMap::new(),
+            list_edits: Vec::new(),
+            can_edit,
+        };
+        dialog.reset_inputs();
+        dialog
+    }
+
+    /// List modes to fetch for this dialog
+    pub fn list_modes(&self) -> Vec<char> {
+        self.lists.clone()
+    }
+
+    /// Take the server's current modes. Edits in progress are kept.
+    pub fn update(&mut self, modes: &ChannelModes) {
+        let edited = self.is_modified();
+        self.modes = modes.clone();
+        if !edited {
+            self.reset_inputs();
+        }
+    }
+
+    /// Whether anything differs from the server's modes, counting a limit
+    /// that is still being typed
+    pub fn is_modified(&self) -> bool {
+        !self.changes().is_empty() || !self.limit_valid()
+    }
+
+    /// Fill the inputs from the server's modes
+    fn reset_inputs(&mut self) {
+        for (mode, on) in &mut self.flags {
+            *on = self.modes.is_set(*mode);
+        }
+        self.key_enabled = self.modes.key.is_some();
+        self.key_input = self.modes.key.clone().unwrap_or_default();
+        self.limit_enabled = self.modes.limit.is_some();
+        self.limit_input = self
+            .modes
+            .limit
+            .map(|limit| limit.to_string())
+            .unwrap_or_default();
+    }
+
+    /// The mode changes that turn the server's modes into the edited ones
+    pub fn changes(&self) -> Vec<ModeChange> {
+        let mut changes = Vec::new();
+        for &(mode, on) in &self.flags {
+            if on != self.modes.is_set(mode) {
+                changes.push(ModeChange {
+                    added: on,
+                    mode,
+                    arg: None,
+                });
+            }
+        }
+
+        // A new key replaces the old one, which has to be removed first
+        let key = self.key_input.trim();
+        let new_key = (self.key_enabled && !key.is_empty()).then_some(key);
+        let old_key = self.modes.key.as_deref();
+        let key_changed = match (old_key, new_key) {
+            // A key we can't see is kept unless it is turned off
+            (Some(""), None) => !self.key_enabled,
+            (old, new) => old != new,
+        };
+        if key_changed {
+            if let Some(old) = old_key {
+                changes.push(ModeChange {
+                    added: false,
+                    mode: 'k',
+                    arg: Some(if old.is_empty() { "*" } else { old }.to_string()),
+                });
+            }
+            if let Some(new) = new_key {
+                changes.push(ModeChange {
+                    added: true,
+                    mode: 'k',
+                    arg: Some(new.to_string()),
+                });
+            }
+        }
+
+        let limit = match self.limit_input.trim().parse::<u32>() {
+            _ if !self.limit_enabled => None,
+            Ok(limit) => Some(limit),
+            // Not a number (yet): the current limit stays
+            Err(_) => self.modes.limit,
+        };
+        if limit != self.modes.limit {
+            changes.push(ModeChange {
+                added: limit.is_some(),
+                mode: 'l',
+                arg: limit.map(|limit| limit.to_string()),
+            });
+        }
+
+        changes.extend(self.list_edits.iter().cloned());
+        changes
+    }
+
+    /// The limit is turned off or holds a number
+    fn limit_valid(&self) -> bool {
+        !self.limit_enabled || self.limit_input.trim().parse::<u32>().is_ok()
+    }
+
+    /// Render the channel settings dialog.
+    /// Returns `Some(DialogAction::SetChannelModes)` when the user applies
+    /// their edits.
+    ///
+    /// The second return value indicates if the dialog is still open.
+    pub fn render(&mut self, ctx: &egui::Context) -> (Option<DialogAction>, bool) {
+        let mut action: Option<DialogAction> = None;
+        let mut window_open = true;
+        let mut should_close = false;
+
+        egui::Window::new(format!("Channel Settings: {}", self.channel))
+            .open(&mut window_open)
+            .resizable(true)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                if !self.can_edit {
+                    ui.label(
+                        egui::RichText::new("Only channel operators can change these settings")
+                            .small()
+                            .weak(),
+                    );
+                    ui.add_space(4.0);
+                }
+
+                ui.add_enabled_ui(self.can_edit, |ui| {
+                    self.render_modes(ui);
+                });
+
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        for mode in self.lists.clone() {
+                            self.render_list(ui, mode);
+                        }
+                    });
+
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    let changes = self.changes();
+                    let can_apply = self.can_edit && self.limit_valid() && !changes.is_empty();
+                    if ui
+                        .add_enabled(can_apply, egui::Button::new("Apply"))
+                        .clicked()
+                    {
+                        action = Some(DialogAction::SetChannelModes {
+                            network: self.network.clone(),
+                            channel: self.channel.clone(),
+                            changes,
+                        });
+                        // The server's MODE reply updates the lists
+                        self.list_edits.clear();
+                    }
+
+                    if ui.button("Close").clicked() {
+                        should_close = true;
+                    }
+
+                    if can_apply {
+                        ui.label(
+                            egui::RichText::new("(modified)")
+                                .small()
+                                .color(egui::Color32::YELLOW),
+                        );
+                    }
+                });
+
+                // Close on Escape
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    should_close = true;
+                }
+            });
+
+        let still_open = window_open && !should_close;
+        (action, still_open)
+    }
+
+    /// Checkboxes for the simple modes, then key and limit
+    fn render_modes(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("channel_settings_modes")
+            .num_columns(2)
+            .spacing([16.0, 4.0])
+            .show(ui, |ui| {
+                for (i, (mode, on)) in self.flags.iter_mut().enumerate() {
+                    let label = SIMPLE_MODES
+                        .iter()
+                        .find(|(m, _)| m == mode)
+                        .map_or("", |(_, label)| *label);
+                    ui.checkbox(on, label);
+                    if i % 2 == 1 {
+                        ui.end_row();
+                    }
+                }
+            });
+
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.key_enabled, "Key (+k)");
+            ui.add_enabled(
+                self.key_enabled,
+                egui::TextEdit::singleline(&mut self.key_input).desired_width(160.0),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.limit_enabled, "User limit (+l)");
+            ui.add_enabled(
+                self.limit_enabled,
+                egui::TextEdit::singleline(&mut self.limit_input).desired_width(60.0),
+            );
+            if !self.limit_valid() {
+                ui.label(
+                    egui::RichText::new("Enter a number")
+                        .small()
+                        .color(egui::Color32::RED),
+                );
+            }
+        });
+    }
+
+    /// A mask list with who set each entry and when, plus staged edits
+    fn render_list(&mut self, ui: &mut egui::Ui, mode: char) {
+        let title = LIST_MODES
+            .iter()
+            .find(|(m, _)| *m == mode)
+            .map_or("", |(_, title)| *title);
+        let fetched = self.modes.lists.contains_key(&mode);
+        let entries: Vec<ModeListEntry> = self.modes.list(mode).to_vec();
+        let header = if fetched {
+            format!("{} ({})", title, entries.len())
+        } else {
+            title.to_string()
+        };
+
+        egui::CollapsingHeader::new(header)
+            .id_salt(("channel_settings_list", mode))
+            .default_open(mode == 'b')
+            .show(ui, |ui| {
+                if !fetched {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Loading…");
+                    });
+                }
+
+                egui::Grid::new(("channel_settings_entries", mode))
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for entry in &entries {
+                            let staged = self.staged(mode, &entry.mask);
+                            let mask = egui::RichText::new(&entry.mask).monospace();
+                            ui.label(if staged.is_some() {
+                                mask.strikethrough()
+                            } else {
+                                mask
+                            });
+                            ui.label(entry.set_by.as_deref().unwrap_or("—"));
+                            ui.label(
+                                entry
+                                    .set_at
+                                    .map(|at| {
+                                        at.with_timezone(&Local)
+                                            .format("%Y-%m-%d %H:%M")
+                                            .to_string()
+                                    })
+                                    .unwrap_or_else(|| "—".to_string()),
+                            );
+                            match staged {
+                                Some(index) => {
+                                    if ui.small_button("Keep").clicked() {
+                                        self.list_edits.remove(index);
+                                    }
+                                }
+                                None => {
+                                    if self.can_edit && ui.small_button("Remove").clicked() {
+                                        self.list_edits.push(ModeChange {
+                                            added: false,
+                                            mode,
+                                            arg: Some(entry.mask.clone()),
+                                        });
+                                    }
+                                }
+                            }
+                            ui.end_row();
+                        }
+
+                        // Masks added here but not applied yet
+                        let mut unstage = None;
+                        for (index, edit) in self.list_edits.iter().enumerate() {
+                            if edit.mode != mode || !edit.added {
+                                continue;
+                            }
+                            ui.label(
+                                egui::RichText::new(edit.arg.as_deref().unwrap_or_default())
+                                    .monospace()
+                                    .italics(),
+                            );
+                            ui.label("(new)");
+                            ui.label("");
+                            if ui.small_button("Undo").clicked() {
+                                unstage = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                        if let Some(index) = unstage {
+                            self.list_edits.remove(index);
+                        }
+                    });
+
+                if self.can_edit {
+                    ui.horizontal(|ui| {
+                        let mask = self.new_masks.entry(mode).or_default();
+                        let input = ui.add(
+                            egui::TextEdit::singleline(mask)
+                                .hint_text("nick!user@host")
+                                .desired_width(220.0),
+                        );
+                        let submitted =
+                            input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        let valid = list_mask(mask).is_some();
+                        let add = ui.add_enabled(valid, egui::Button::new("Add")).clicked();
+                        if (add || submitted) && valid {
+                            let mask = list_mask(&std::mem::take(mask)).map(str::to_string);
+                            self.list_edits.push(ModeChange {
+                                added: true,
+                                mode,
+                                arg: mask,
+                            });
+                        } else if mask.trim().contains(char::is_whitespace) {
+                            ui.label(
+                                egui::RichText::new("No spaces in a mask")
+                                    .small()
+                                    .color(egui::Color32::RED),
+                            );
+                        }
+                    });
+                }
+            });
+    }
+
+    /// Index of the staged removal of `mask` from list `mode`, if any
+    fn staged(&self, mode: char, mask: &str) -> Option<usize> {
+        self.list_edits
+            .iter()
+            .position(|edit| edit.mode == mode && !edit.added && edit.arg.as_deref() == Some(mask))
+    }
+}
+
+/// The mask typed into a list's entry field, if it can be sent: a space
+/// would split it into several MODE arguments
+fn list_mask(input: &str) -> Option<&str> {
+    let mask = input.trim();
+    (!mask.is_empty() && !mask.contains(char::is_whitespace)).then_some(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn support() -> ServerSupport {
+        let mut support = ServerSupport::default();
+        support.apply(&["CHANMODES=beIq,k,fl,imnrst"]);
+        support
+    }
+
+    fn change(added: bool, mode: char, arg: Option<&str>) -> ModeChange {
+        ModeChange {
+            added,
+            mode,
+            arg: arg.map(str::to_string),
+        }
+    }
+
+    fn dialog(modes: &ChannelModes) -> ChannelSettingsDialog {
+        ChannelSettingsDialog::new("libera", "#rust", modes, &support(), true)
+    }
+
+    #[test]
+    fn test_settings_start_unmodified() {
+        let modes = ChannelModes {
+            flags: ['n', 't'].into_iter().collect(),
+            key: Some("secret".into()),
+            limit: Some(20),
+            ..ChannelModes::default()
+        };
+        let dialog = dialog(&modes);
+        assert!(!dialog.is_modified());
+        assert_eq!(dialog.key_input, "secret");
+        assert_eq!(dialog.limit_input, "20");
+        let flags: Vec<char> = dialog.flags.iter().map(|(mode, _)| *mode).collect();
+        assert_eq!(flags, ['m', 't', 'n', 's', 'i', 'r']);
+        assert_eq!(dialog.list_modes(), ['b', 'e', 'I', 'q']);
+    }
+
+    #[test]
+    fn test_edits_become_mode_changes() {
+        let modes = ChannelModes {
+            flags: ['n'].into_iter().collect(),
+            key: Some("old".into()),
+            ..ChannelModes::default()
+        };
+        let mut dialog = dialog(&modes);
+        dialog.flags.iter_mut().for_each(|(mode, on)| {
+            if *mode == 'n' || *mode == 'm' {
+                *on = !*on;
+            }
+        });
+        dialog.key_input = "new".into();
+        dialog.limit_enabled = true;
+        dialog.limit_input = "30".into();
+        dialog.list_edits.push(change(true, 'b', Some("*!*@spam")));
+
+        assert_eq!(
+            dialog.changes(),
+            [
+                change(true, 'm', None),
+                change(false, 'n', None),
+                change(false, 'k', Some("old")),
+                change(true, 'k', Some("new")),
+                change(true, 'l', Some("30")),
+                change(true, 'b', Some("*!*@spam")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hidden_key_kept_unless_turned_off() {
+        let modes = ChannelModes {
+            key: Some(String::new()),
+            ..ChannelModes::default()
+        };
+        let mut dialog = dialog(&modes);
+        assert!(dialog.key_enabled);
+        assert!(dialog.changes().is_empty());
+
+        dialog.key_enabled = false;
+        assert_eq!(dialog.changes(), [change(false, 'k', Some("*"))]);
+    }
+
+    #[test]
+    fn test_invalid_limit_keeps_current_one() {
+        let modes = ChannelModes {
+            limit: Some(20),
+            ..ChannelModes::default()
+        };
+        let mut dialog = dialog(&modes);
+        dialog.limit_input = "5a".into();
+        assert!(!dialog.limit_valid());
+        assert!(dialog.changes().is_empty());
+        assert!(dialog.is_modified());
+
+        dialog.limit_enabled = false;
+        assert!(dialog.limit_valid());
+        assert_eq!(dialog.changes(), [change(false, 'l', None)]);
+    }
+
+    #[test]
+    fn test_masks_with_spaces_rejected() {
+        assert_eq!(list_mask("  *!*@spam "), Some("*!*@spam"));
+        assert_eq!(list_mask("*!*@a *!*@b"), None);
+        assert_eq!(list_mask("   "), None);
+    }
+
+    #[test]
+    fn test_update_keeps_edits() {
+        let mut dialog = dialog(&ChannelModes::default());
+        let mut modes = ChannelModes::default();
+        modes.flags.insert('t');
+        dialog.update(&modes);
+        assert!(dialog.flags.contains(&('t', true)));
+
+        dialog.limit_enabled = true;
+        dialog.limit_input = "5".into();
+        modes.flags.insert('m');
+        dialog.update(&modes);
+        assert!(dialog.flags.contains(&('m', false)));
+        assert_eq!(
+            dialog.changes(),
+            [change(false, 'm', None), change(true, 'l', Some("5"))]
+        );
+    }
+}
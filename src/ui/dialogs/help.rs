@@ -58,6 +58,7 @@ impl HelpDialog {
                 ui.label("  /part [#channel] - leave a channel");
                 ui.label("  /nick <nick> - change nick");
                 ui.label("  /msg <nick> <text> - send private message");
+                ui.label("  /notice <target> <text> - send a notice");
                 ui.label("  /me <text> - send CTCP ACTION");
                 ui.label("  /whois <nick> - request WHOIS");
                 ui.label("  /topic [text] - view/set channel topic");
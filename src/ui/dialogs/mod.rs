@@ -15,17 +15,21 @@
 
 mod actions;
 mod channel_browser;
+mod channel_settings;
 mod help;
 mod network;
 mod nick_change;
+mod paste_confirm;
 mod status_toasts;
 mod topic_editor;
 
 // Re-export dialog types and actions
 pub use actions::DialogAction;
 pub use channel_browser::{ChannelBrowserDialog, ChannelListItem};
+pub use channel_settings::ChannelSettingsDialog;
 pub use help::HelpDialog;
 pub use network::NetworkManagerDialog;
 pub use nick_change::NickChangeDialog;
-pub use status_toasts::render_status_toasts;
+pub use paste_confirm::PasteConfirmDialog;
+pub use status_toasts::{render_invite_toasts, render_status_toasts};
 pub use topic_editor::TopicEditorDialog;
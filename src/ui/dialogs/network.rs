@@ -4,18 +4,45 @@ use eframe::egui;
 use std::collections::HashSet;
 
 use super::DialogAction;
-use crate::config::Network;
+use crate::config::{
+    Network, DEFAULT_FLOOD_BURST, DEFAULT_FLOOD_REFILL_MS, DEFAULT_PING_TIMEOUT_SECS,
+};
 
 /// Form state for creating/editing a network
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct NetworkForm {
     pub name: String,
     pub servers: String, // Comma-separated
     pub nick: String,
+    pub alt_nicks: String, // Comma-separated
     pub auto_connect: bool,
     pub favorite_channels: String, // Comma-separated
     pub nickserv_password: String,
     pub use_tls: bool,
+    pub ping_timeout_secs: u64, // 0 = default
+    pub flood_burst: u32,       // 0 = default
+    pub flood_refill_ms: u64,   // 0 = default
+    pub send_typing: bool,
+}
+
+impl Default for NetworkForm {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            servers: String::new(),
+            nick: String::new(),
+            alt_nicks: String::new(),
+            auto_connect: false,
+            favorite_channels: String::new(),
+            nickserv_password: String::new(),
+            use_tls: false,
+            ping_timeout_secs: 0,
+            flood_burst: 0,
+            flood_refill_ms: 0,
+            // Match Network::default() and the config's serde default
+            send_typing: true,
+        }
+    }
 }
 
 impl NetworkForm {
@@ -25,10 +52,15 @@ impl NetworkForm {
             name: network.name.clone(),
             servers: network.servers.join(", "),
             nick: network.nick.clone(),
+            alt_nicks: network.alt_nicks.join(", "),
             auto_connect: network.auto_connect,
             favorite_channels: network.favorite_channels.join(", "),
             nickserv_password: network.nickserv_password.clone().unwrap_or_default(),
             use_tls: network.use_tls,
+            ping_timeout_secs: network.ping_timeout_secs,
+            flood_burst: network.flood_burst,
+            flood_refill_ms: network.flood_refill_ms,
+            send_typing: network.send_typing,
         }
     }
 
@@ -41,6 +73,13 @@ impl NetworkForm {
             .filter(|s| !s.is_empty())
             .collect();
 
+        let alt_nicks: Vec<String> = self
+            .alt_nicks
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
         let favorite_channels: Vec<String> = self
             .favorite_channels
             .split(',')
@@ -52,6 +91,7 @@ impl NetworkForm {
             name: self.name.clone(),
             servers,
             nick: self.nick.clone(),
+            alt_nicks,
             auto_connect: self.auto_connect,
             favorite_channels,
             nickserv_password: if self.nickserv_password.is_empty() {
@@ -61,6 +101,22 @@ impl NetworkForm {
             },
             use_tls: self.use_tls,
             auto_reconnect: true,
+            ping_timeout_secs: if self.ping_timeout_secs == 0 {
+                DEFAULT_PING_TIMEOUT_SECS
+            } else {
+                self.ping_timeout_secs
+            },
+            flood_burst: if self.flood_burst == 0 {
+                DEFAULT_FLOOD_BURST
+            } else {
+                self.flood_burst
+            },
+            flood_refill_ms: if self.flood_refill_ms == 0 {
+                DEFAULT_FLOOD_REFILL_MS
+            } else {
+                self.flood_refill_ms
+            },
+            send_typing: self.send_typing,
         }
     }
 
@@ -109,7 +165,13 @@ impl NetworkManagerDialog {
     /// Start adding a new network
     fn start_add(&mut self) {
         self.editing_index = None;
-        self.form = NetworkForm::default();
+        self.form = NetworkForm {
+            ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+            flood_burst: DEFAULT_FLOOD_BURST,
+            flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+            send_typing: true,
+            ..NetworkForm::default()
+        };
     }
 
     /// Cancel editing
@@ -242,11 +304,16 @@ impl NetworkManagerDialog {
                                 name: "Libera.Chat".to_string(),
                                 servers: vec!["irc.libera.chat:6697".to_string()],
                                 nick: "slirc_user".to_string(),
+                                alt_nicks: vec![],
                                 auto_connect: false,
                                 favorite_channels: vec!["#slirc".to_string()],
                                 nickserv_password: None,
                                 use_tls: true,
                                 auto_reconnect: true,
+                                ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+                                flood_burst: DEFAULT_FLOOD_BURST,
+                                flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+                                send_typing: true,
                             });
                             self.modified = true;
                         }
@@ -256,11 +323,16 @@ impl NetworkManagerDialog {
                                 name: "OFTC".to_string(),
                                 servers: vec!["irc.oftc.net:6697".to_string()],
                                 nick: "slirc_user".to_string(),
+                                alt_nicks: vec![],
                                 auto_connect: false,
                                 favorite_channels: vec![],
                                 nickserv_password: None,
                                 use_tls: true,
                                 auto_reconnect: true,
+                                ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+                                flood_burst: DEFAULT_FLOOD_BURST,
+                                flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+                                send_typing: true,
                             });
                             self.modified = true;
                         }
@@ -270,11 +342,16 @@ impl NetworkManagerDialog {
                                 name: "EFnet".to_string(),
                                 servers: vec!["irc.choopa.net:9999".to_string()],
                                 nick: "slirc_user".to_string(),
+                                alt_nicks: vec![],
                                 auto_connect: false,
                                 favorite_channels: vec![],
                                 nickserv_password: None,
                                 use_tls: true,
                                 auto_reconnect: true,
+                                ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+                                flood_burst: DEFAULT_FLOOD_BURST,
+                                flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+                                send_typing: true,
                             });
                             self.modified = true;
                         }
@@ -284,11 +361,16 @@ impl NetworkManagerDialog {
                                 name: "Rizon".to_string(),
                                 servers: vec!["irc.rizon.net:6697".to_string()],
                                 nick: "slirc_user".to_string(),
+                                alt_nicks: vec![],
                                 auto_connect: false,
                                 favorite_channels: vec![],
                                 nickserv_password: None,
                                 use_tls: true,
                                 auto_reconnect: true,
+                                ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+                                flood_burst: DEFAULT_FLOOD_BURST,
+                                flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+                                send_typing: true,
                             });
                             self.modified = true;
                         }
@@ -336,6 +418,20 @@ impl NetworkManagerDialog {
                             ui.text_edit_singleline(&mut self.form.nick);
                             ui.end_row();
 
+                            ui.label("Alternate Nicks:");
+                            ui.text_edit_singleline(&mut self.form.alt_nicks);
+                            ui.end_row();
+
+                            ui.label("");
+                            ui.label(
+                                egui::RichText::new(
+                                    "(Comma-separated, tried if the nick is taken)",
+                                )
+                                .small()
+                                .weak(),
+                            );
+                            ui.end_row();
+
                             ui.label("Favorite Channels:");
                             ui.text_edit_singleline(&mut self.form.favorite_channels);
                             ui.end_row();
@@ -362,11 +458,37 @@ impl NetworkManagerDialog {
                                     .weak(),
                             );
                             ui.end_row();
+
+                            ui.label("Ping Timeout:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.form.ping_timeout_secs)
+                                    .range(30..=900)
+                                    .suffix(" s"),
+                            );
+                            ui.end_row();
+
+                            ui.label("Flood Control:");
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut self.form.flood_burst)
+                                        .range(1..=50)
+                                        .suffix(" lines"),
+                                );
+                                ui.label("then one per");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.form.flood_refill_ms)
+                                        .range(100..=10_000)
+                                        .speed(50)
+                                        .suffix(" ms"),
+                                );
+                            });
+                            ui.end_row();
                         });
 
                     ui.add_space(4.0);
                     ui.checkbox(&mut self.form.auto_connect, "Auto-connect on startup");
                     ui.checkbox(&mut self.form.use_tls, "🔒 Use TLS/SSL encryption");
+                    ui.checkbox(&mut self.form.send_typing, "Let others see when I'm typing");
 
                     ui.separator();
                     ui.horizontal(|ui| {
@@ -444,6 +566,13 @@ mod tests {
         assert!(!form.is_valid());
     }
 
+    #[test]
+    fn test_new_network_sends_typing_by_default() {
+        let form = NetworkForm::default();
+        assert!(form.send_typing);
+        assert_eq!(form.send_typing, Network::default().send_typing);
+    }
+
     #[test]
     fn test_network_form_from_network() {
         let network = Network {
@@ -453,11 +582,16 @@ mod tests {
                 "irc2.test.com:6697".to_string(),
             ],
             nick: "testuser".to_string(),
+            alt_nicks: vec!["testuser_".to_string()],
             auto_connect: true,
             favorite_channels: vec!["#test".to_string(), "#rust".to_string()],
             nickserv_password: Some("secret".to_string()),
             use_tls: true,
             auto_reconnect: true,
+            ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+            flood_burst: DEFAULT_FLOOD_BURST,
+            flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+            send_typing: true,
         };
 
         let form = NetworkForm::from_network(&network);
@@ -465,6 +599,7 @@ mod tests {
         assert_eq!(form.name, "TestNet");
         assert_eq!(form.servers, "irc.test.com:6667, irc2.test.com:6697");
         assert_eq!(form.nick, "testuser");
+        assert_eq!(form.alt_nicks, "testuser_");
         assert!(form.auto_connect);
         assert_eq!(form.favorite_channels, "#test, #rust");
         assert_eq!(form.nickserv_password, "secret");
@@ -477,10 +612,15 @@ mod tests {
             name: "NewNet".to_string(),
             servers: "irc.new.com:6667, irc2.new.com:6697".to_string(),
             nick: "newuser".to_string(),
+            alt_nicks: "newuser_, nu".to_string(),
             auto_connect: false,
             favorite_channels: "#new, #test".to_string(),
             nickserv_password: String::new(),
             use_tls: false,
+            ping_timeout_secs: 0,
+            flood_burst: 0,
+            flood_refill_ms: 0,
+            send_typing: false,
         };
 
         let network = form.to_network();
@@ -491,10 +631,15 @@ mod tests {
             vec!["irc.new.com:6667", "irc2.new.com:6697"]
         );
         assert_eq!(network.nick, "newuser");
+        assert_eq!(network.alt_nicks, vec!["newuser_", "nu"]);
         assert!(!network.auto_connect);
         assert_eq!(network.favorite_channels, vec!["#new", "#test"]);
         assert!(network.nickserv_password.is_none());
         assert!(!network.use_tls);
+        assert_eq!(network.ping_timeout_secs, DEFAULT_PING_TIMEOUT_SECS);
+        assert_eq!(network.flood_burst, DEFAULT_FLOOD_BURST);
+        assert_eq!(network.flood_refill_ms, DEFAULT_FLOOD_REFILL_MS);
+        assert!(!network.send_typing);
     }
 
     #[test]
@@ -518,11 +663,16 @@ mod tests {
             name: "Net1".to_string(),
             servers: vec!["irc.net1.com:6667".to_string()],
             nick: "user1".to_string(),
+            alt_nicks: vec![],
             auto_connect: false,
             favorite_channels: vec![],
             nickserv_password: None,
             use_tls: false,
             auto_reconnect: true,
+            ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+            flood_burst: DEFAULT_FLOOD_BURST,
+            flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+            send_typing: true,
         }];
 
         let dialog = NetworkManagerDialog::new(networks.clone());
@@ -538,11 +688,16 @@ mod tests {
             name: "Net1".to_string(),
             servers: vec!["irc.net1.com:6667".to_string()],
             nick: "user1".to_string(),
+            alt_nicks: vec![],
             auto_connect: false,
             favorite_channels: vec![],
             nickserv_password: None,
             use_tls: false,
             auto_reconnect: true,
+            ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+            flood_burst: DEFAULT_FLOOD_BURST,
+            flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+            send_typing: true,
         }];
 
         let dialog = NetworkManagerDialog::new(networks);
@@ -12,6 +12,8 @@ pub struct NickChangeDialog {
     pub nick_input: String,
     /// The current nickname (for display/comparison)
     current_nick: String,
+    /// Why the server rejected the last nick we asked for, if it did
+    pub error: Option<String>,
 }
 
 impl NickChangeDialog {
@@ -21,6 +23,17 @@ impl NickChangeDialog {
             open: true,
             nick_input: current_nick.to_string(),
             current_nick: current_nick.to_string(),
+            error: None,
+        }
+    }
+
+    /// Create the dialog after the server rejected `rejected_nick`,
+    /// showing the reason and the rejected nick for editing
+    pub fn rejected(current_nick: &str, rejected_nick: &str, reason: &str) -> Self {
+        Self {
+            nick_input: rejected_nick.to_string(),
+            error: Some(reason.to_string()),
+            ..Self::new(current_nick)
         }
     }
 
@@ -38,6 +51,14 @@ impl NickChangeDialog {
             .open(&mut still_open)
             .resizable(false)
             .show(ctx, |ui| {
+                if let Some(error) = &self.error {
+                    ui.label(
+                        egui::RichText::new(format!("⚠ {}", error))
+                            .color(ui.visuals().error_fg_color),
+                    );
+                    ui.add_space(4.0);
+                }
+
                 ui.label("New nickname:");
 
                 let response =
@@ -106,6 +127,15 @@ mod tests {
         assert_eq!(dialog.current_nick, "testuser");
     }
 
+    #[test]
+    fn test_nick_change_dialog_rejected() {
+        let dialog = NickChangeDialog::rejected("testuser", "taken", "Nickname is already in use");
+        assert!(dialog.open);
+        assert_eq!(dialog.nick_input, "taken");
+        assert_eq!(dialog.current_nick, "testuser");
+        assert_eq!(dialog.error.as_deref(), Some("Nickname is already in use"));
+    }
+
     #[test]
     fn test_nick_change_dialog_is_open() {
         let mut dialog = NickChangeDialog::new("testuser");
@@ -0,0 +1,114 @@
+//! Paste confirmation dialog - confirm sending multi-line input.
+
+use eframe::egui;
+
+use super::DialogAction;
+
+/// Maximum number of lines shown in the preview
+const PREVIEW_LINES: usize = 10;
+
+/// Self-contained paste confirmation dialog state.
+pub struct PasteConfirmDialog {
+    /// Network the lines will be sent on
+    pub network: String,
+    /// Channel or nick the lines will be sent to
+    pub target: String,
+    /// The pasted input, as it was when the dialog opened
+    pub text: String,
+    /// The lines to send, in order
+    pub lines: Vec<String>,
+}
+
+impl PasteConfirmDialog {
+    /// Create a new paste confirmation dialog
+    pub fn new(network: &str, target: &str, text: &str, lines: Vec<String>) -> Self {
+        Self {
+            network: network.to_string(),
+            target: target.to_string(),
+            text: text.to_string(),
+            lines,
+        }
+    }
+
+    /// Render the paste confirmation dialog.
+    /// Returns `Some(DialogAction::SendMultiline)` if the user confirmed.
+    ///
+    /// The second return value indicates if the dialog is still open.
+    pub fn render(&mut self, ctx: &egui::Context) -> (Option<DialogAction>, bool) {
+        let mut action: Option<DialogAction> = None;
+        let mut window_open = true;
+        let mut should_close = false;
+
+        egui::Window::new("Send Multiple Lines")
+            .open(&mut window_open)
+            .resizable(true)
+            .collapsible(false)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Send {} lines to {}?",
+                    self.lines.len(),
+                    self.target
+                ));
+                ui.add_space(4.0);
+
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for line in self.lines.iter().take(PREVIEW_LINES) {
+                            ui.label(egui::RichText::new(line).monospace());
+                        }
+                        let hidden = self.lines.len().saturating_sub(PREVIEW_LINES);
+                        if hidden > 0 {
+                            ui.label(
+                                egui::RichText::new(format!("… and {} more", hidden))
+                                    .small()
+                                    .weak(),
+                            );
+                        }
+                    });
+
+                ui.add_space(8.0);
+
+                ui.horizontal(|ui| {
+                    let send_label = format!("Send {} lines", self.lines.len());
+                    if ui.button(send_label).clicked() {
+                        action = Some(DialogAction::SendMultiline {
+                            network: self.network.clone(),
+                            target: self.target.clone(),
+                            text: std::mem::take(&mut self.text),
+                            lines: std::mem::take(&mut self.lines),
+                        });
+                        should_close = true;
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        should_close = true;
+                    }
+                });
+
+                // Close on Escape
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    should_close = true;
+                }
+            });
+
+        let still_open = window_open && !should_close;
+        (action, still_open)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paste_confirm_creation() {
+        let lines = vec!["fn main() {".to_string(), "}".to_string()];
+        let dialog = PasteConfirmDialog::new("libera", "#rust", "fn main() {\n}", lines.clone());
+        assert_eq!(dialog.network, "libera");
+        assert_eq!(dialog.target, "#rust");
+        assert_eq!(dialog.text, "fn main() {\n}");
+        assert_eq!(dialog.lines, lines);
+    }
+}
@@ -2,6 +2,9 @@
 
 use eframe::egui;
 
+use super::DialogAction;
+use crate::state::PendingInvite;
+
 /// Render floating status toasts (top-right corner).
 ///
 /// # Arguments
@@ -29,6 +32,53 @@ pub fn render_status_toasts(ctx: &egui::Context, status_messages: &[(String, std
         });
 }
 
+/// Render a card for each unanswered channel invite (bottom-right corner)
+/// with buttons to join or decline.
+///
+/// Returns the user's answer, if they gave one this frame.
+pub fn render_invite_toasts(
+    ctx: &egui::Context,
+    invites: &[PendingInvite],
+) -> Option<DialogAction> {
+    if invites.is_empty() {
+        return None;
+    }
+
+    let mut answer = None;
+    egui::Area::new(egui::Id::new("invite_toast_area"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -80.0]) // Above the input
+        .show(ctx, |ui| {
+            for invite in invites {
+                egui::Frame::new()
+                    .fill(egui::Color32::from_rgba_unmultiplied(30, 30, 30, 230))
+                    .corner_radius(6.0)
+                    .inner_margin(egui::Margin::symmetric(12, 8))
+                    .show(ui, |ui| {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{} invited you to {}",
+                                invite.by, invite.channel
+                            ))
+                            .color(egui::Color32::WHITE),
+                        );
+                        ui.horizontal(|ui| {
+                            let join = ui.button("Join").clicked();
+                            let decline = ui.button("Decline").clicked();
+                            if join || decline {
+                                answer = Some(DialogAction::AnswerInvite {
+                                    network: invite.network.clone(),
+                                    channel: invite.channel.clone(),
+                                    accept: join,
+                                });
+                            }
+                        });
+                    });
+                ui.add_space(6.0);
+            }
+        });
+    answer
+}
+
 #[cfg(test)]
 mod tests {
     // Status toasts are purely UI, tested via integration tests
@@ -1,7 +1,9 @@
 //! Traditional horizontal menu bar (Discord/Slack-inspired with IRC-specific menus)
 //! File, Edit, View, Server, Window, Help
 
-use crate::protocol::BackendAction;
+use crate::config::{TimestampFormat, TimestampStyle};
+use crate::protocol::{BackendAction, NetworkAction};
+use crate::server_support::ServerSupport;
 use eframe::egui;
 
 /// Actions that the menu can request
@@ -13,17 +15,21 @@ pub enum MenuAction {
 }
 
 /// Render the traditional horizontal menu bar
+/// Server actions apply to `network` (the active network).
 /// Returns Some(MenuAction) if an action was requested
 #[allow(clippy::too_many_arguments)]
 pub fn render_menu_bar(
     ctx: &egui::Context,
     ui: &mut egui::Ui,
     is_connected: bool,
+    network: &str,
     active_buffer: &str,
+    support: &ServerSupport,
     show_channel_list: &mut bool,
     show_user_list: &mut bool,
+    timestamp_format: &mut TimestampFormat,
     quick_switcher: &mut crate::ui::quick_switcher::QuickSwitcher,
-    action_tx: &crossbeam_channel::Sender<BackendAction>,
+    action_tx: &crossbeam_channel::Sender<NetworkAction>,
 ) -> Option<MenuAction> {
     let mut menu_action: Option<MenuAction> = None;
 
@@ -32,8 +38,8 @@ pub fn render_menu_bar(
         ui.menu_button("File", |ui| {
             ui.horizontal(|ui| {
                 if ui
-                    .add_enabled(!is_connected, egui::Button::new("Connect..."))
-                    .on_hover_text("Connect to IRC server")
+                    .button("Connect...")
+                    .on_hover_text("Connect to an IRC network")
                     .clicked()
                 {
                     menu_action = Some(MenuAction::NetworkManager);
@@ -50,8 +56,10 @@ pub fn render_menu_bar(
                     .on_hover_text("Disconnect from current server")
                     .clicked()
                 {
-                    let _ =
-                        action_tx.send(BackendAction::Quit(Some("User disconnected".to_string())));
+                    let _ = action_tx.send(NetworkAction::new(
+                        network,
+                        BackendAction::Quit(Some("User disconnected".to_string())),
+                    ));
                     ui.close_menu();
                 }
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -172,11 +180,33 @@ pub fn render_menu_bar(
                     ui.label(egui::RichText::new("Ctrl+U").weak().small());
                 });
             });
+
+            ui.separator();
+
+            ui.menu_button("Timestamps", |ui| {
+                ui.radio_value(
+                    &mut timestamp_format.style,
+                    TimestampStyle::Hour24,
+                    "24-hour",
+                );
+                ui.radio_value(
+                    &mut timestamp_format.style,
+                    TimestampStyle::Hour12,
+                    "12-hour",
+                );
+                ui.radio_value(
+                    &mut timestamp_format.style,
+                    TimestampStyle::Relative,
+                    "Relative (5m ago)",
+                );
+                ui.separator();
+                ui.checkbox(&mut timestamp_format.show_seconds, "Show Seconds");
+            });
         });
 
         // Server Menu (IRC-specific)
         ui.menu_button("Server", |ui| {
-            let in_channel = active_buffer != "System" && active_buffer.starts_with('#');
+            let in_channel = support.is_channel(active_buffer);
 
             ui.add_enabled_ui(is_connected, |ui| {
                 ui.horizontal(|ui| {
@@ -203,10 +233,13 @@ pub fn render_menu_bar(
                     .on_hover_text("Leave current channel")
                     .clicked()
                 {
-                    let _ = action_tx.send(BackendAction::Part {
-                        channel: active_buffer.to_string(),
-                        message: None,
-                    });
+                    let _ = action_tx.send(NetworkAction::new(
+                        network,
+                        BackendAction::Part {
+                            channel: active_buffer.to_string(),
+                            message: None,
+                        },
+                    ));
                     ui.close_menu();
                 }
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -223,7 +256,7 @@ pub fn render_menu_bar(
                         .on_hover_text("List all channels on server")
                         .clicked()
                     {
-                        let _ = action_tx.send(BackendAction::List);
+                        let _ = action_tx.send(NetworkAction::new(network, BackendAction::List));
                         menu_action = Some(MenuAction::ChannelBrowser);
                         ui.close_menu();
                     }
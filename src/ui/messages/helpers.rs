@@ -1,30 +1,36 @@
 //! Helper utilities for message processing.
 
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+use crate::irc_name::CaseMapping;
+
 /// Check if a message contains a mention of the given nickname.
 ///
 /// # Mention Detection Rules
 ///
 /// A mention is detected when:
 /// 1. The nickname appears as a complete word (not part of another word)
-/// 2. Case-insensitive matching (alice matches ALICE, Alice, etc.)
+/// 2. Case-insensitive matching under the server casemapping (alice matches
+///    ALICE, Alice, etc.; with rfc1459 `nick[m]` also matches `NICK{M}`)
 /// 3. Common IRC mention formats: "nick:", "nick,", "@nick"
 ///
 /// # Examples
 ///
 /// ```ignore
-/// contains_mention("Hey alice, how are you?", "alice") == true
-/// contains_mention("Hey ALICE: check this out", "alice") == true
-/// contains_mention("Hey @alice", "alice") == true
-/// contains_mention("alice's message", "alice") == true  // apostrophe is word boundary
-/// contains_mention("malice aforethought", "alice") == false  // alice is inside word
+/// contains_mention("Hey alice, how are you?", "alice", mapping) == true
+/// contains_mention("Hey ALICE: check this out", "alice", mapping) == true
+/// contains_mention("Hey @alice", "alice", mapping) == true
+/// contains_mention("alice's message", "alice", mapping) == true  // apostrophe is word boundary
+/// contains_mention("malice aforethought", "alice", mapping) == false  // alice is inside word
 /// ```
-pub(crate) fn contains_mention(text: &str, nickname: &str) -> bool {
+pub(crate) fn contains_mention(text: &str, nickname: &str, mapping: CaseMapping) -> bool {
     if nickname.is_empty() {
         return false;
     }
 
-    let text_lower = text.to_lowercase();
-    let nick_lower = nickname.to_lowercase();
+    // Folding only touches ASCII, so positions in text_lower index text too
+    let text_lower = mapping.fold(text);
+    let nick_lower = mapping.fold(nickname);
 
     // Find all occurrences of the nickname
     let mut search_start = 0;
@@ -54,126 +60,182 @@ pub(crate) fn contains_mention(text: &str, nickname: &str) -> bool {
 /// Messages from the same sender within 5 minutes are grouped together.
 pub(crate) const GROUP_TIME_GAP_SECONDS: u32 = 300;
 
-/// Parse a timestamp string "HH:MM:SS" into total seconds since midnight.
-/// Returns None if parsing fails.
-pub(crate) fn parse_timestamp_seconds(ts: &str) -> Option<u32> {
-    let parts: Vec<&str> = ts.split(':').collect();
-    if parts.len() != 3 {
-        return None;
-    }
-    let hours: u32 = parts[0].parse().ok()?;
-    let minutes: u32 = parts[1].parse().ok()?;
-    let seconds: u32 = parts[2].parse().ok()?;
-    Some(hours * 3600 + minutes * 60 + seconds)
+/// Check if two message times are within the grouping window (5 minutes).
+/// Order doesn't matter: history replayed with `server-time` may be older
+/// than what is already shown.
+pub(crate) fn timestamps_within_window(ts1: &DateTime<Utc>, ts2: &DateTime<Utc>) -> bool {
+    (*ts2 - *ts1).num_seconds().abs() <= i64::from(GROUP_TIME_GAP_SECONDS)
 }
 
-/// Check if two timestamps are within the grouping window (5 minutes).
-/// Handles midnight wraparound.
-pub(crate) fn timestamps_within_window(ts1: &str, ts2: &str) -> bool {
-    let Some(secs1) = parse_timestamp_seconds(ts1) else {
-        return false;
-    };
-    let Some(secs2) = parse_timestamp_seconds(ts2) else {
-        return false;
-    };
+/// Full local date and time, for hover text on timestamps.
+pub(crate) fn full_timestamp(ts: &DateTime<Utc>) -> String {
+    ts.with_timezone(&Local)
+        .format("%A, %B %-d, %Y %H:%M:%S")
+        .to_string()
+}
 
-    // Calculate difference, handling midnight wraparound
-    let diff = if secs2 >= secs1 {
-        secs2 - secs1
+/// Label for the divider above the first message of `day`.
+pub(crate) fn day_label(day: NaiveDate, today: NaiveDate) -> String {
+    if day == today {
+        "Today".to_string()
+    } else if today.pred_opt() == Some(day) {
+        "Yesterday".to_string()
     } else {
-        // Midnight crossed: add 24 hours to second timestamp
-        (secs2 + 86400) - secs1
-    };
+        day.format("%A, %B %-d, %Y").to_string()
+    }
+}
+
+/// Characters of a replied-to message shown in the quote above a reply.
+const REPLY_SNIPPET_CHARS: usize = 80;
 
-    diff <= GROUP_TIME_GAP_SECONDS
+/// One-line preview of a message for reply quotes: whitespace collapsed,
+/// control codes dropped and long text cut off with an ellipsis.
+pub(crate) fn reply_snippet(text: &str) -> String {
+    let clean: String = text.chars().filter(|c| !c.is_control()).collect();
+    let line = clean.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= REPLY_SNIPPET_CHARS {
+        return line;
+    }
+    let mut cut: String = line.chars().take(REPLY_SNIPPET_CHARS).collect();
+    cut.truncate(cut.trim_end().len());
+    cut.push('…');
+    cut
+}
+
+/// The "is typing…" line shown above the input, if anyone is typing.
+pub(crate) fn typing_line(nicks: &[&str]) -> Option<String> {
+    match nicks {
+        [] => None,
+        [nick] => Some(format!("{nick} is typing…")),
+        [first, second] => Some(format!("{first} and {second} are typing…")),
+        [first, second, third] => Some(format!("{first}, {second} and {third} are typing…")),
+        _ => Some("Several people are typing…".to_string()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
 
-    #[test]
-    fn test_parse_timestamp_seconds() {
-        assert_eq!(parse_timestamp_seconds("00:00:00"), Some(0));
-        assert_eq!(parse_timestamp_seconds("00:00:01"), Some(1));
-        assert_eq!(parse_timestamp_seconds("00:01:00"), Some(60));
-        assert_eq!(parse_timestamp_seconds("01:00:00"), Some(3600));
-        assert_eq!(parse_timestamp_seconds("12:30:45"), Some(45045));
-        assert_eq!(parse_timestamp_seconds("23:59:59"), Some(86399));
-
-        // Invalid formats
-        assert_eq!(parse_timestamp_seconds("invalid"), None);
-        assert_eq!(parse_timestamp_seconds("12:30"), None);
-        assert_eq!(parse_timestamp_seconds(""), None);
-        assert_eq!(parse_timestamp_seconds("aa:bb:cc"), None);
-    }
+    const RFC: CaseMapping = CaseMapping::Rfc1459;
 
     #[test]
     fn test_timestamps_within_window() {
+        let at = |h, m, sec| Utc.with_ymd_and_hms(2024, 1, 1, h, m, sec).unwrap();
+
         // Same timestamp
-        assert!(timestamps_within_window("12:00:00", "12:00:00"));
+        assert!(timestamps_within_window(&at(12, 0, 0), &at(12, 0, 0)));
 
         // Within 5 minutes
-        assert!(timestamps_within_window("12:00:00", "12:04:59"));
-        assert!(timestamps_within_window("12:00:00", "12:05:00"));
+        assert!(timestamps_within_window(&at(12, 0, 0), &at(12, 4, 59)));
+        assert!(timestamps_within_window(&at(12, 0, 0), &at(12, 5, 0)));
 
         // Just outside 5 minutes
-        assert!(!timestamps_within_window("12:00:00", "12:05:01"));
-        assert!(!timestamps_within_window("12:00:00", "12:10:00"));
+        assert!(!timestamps_within_window(&at(12, 0, 0), &at(12, 5, 1)));
+        assert!(!timestamps_within_window(&at(12, 0, 0), &at(12, 10, 0)));
 
-        // Test midnight wraparound
-        assert!(timestamps_within_window("23:58:00", "00:01:00")); // 3 minutes across midnight
+        // Across midnight: 3 minutes apart
+        let before_midnight = at(23, 58, 0);
+        let after_midnight = Utc.with_ymd_and_hms(2024, 1, 2, 0, 1, 0).unwrap();
+        assert!(timestamps_within_window(&before_midnight, &after_midnight));
+
+        // Same time of day, a day apart
+        let next_day = Utc.with_ymd_and_hms(2024, 1, 2, 12, 0, 0).unwrap();
+        assert!(!timestamps_within_window(&at(12, 0, 0), &next_day));
+    }
+
+    #[test]
+    fn test_day_label() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        assert_eq!(day_label(today, today), "Today");
+        let yesterday = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(day_label(yesterday, today), "Yesterday");
+        let older = NaiveDate::from_ymd_opt(2024, 2, 27).unwrap();
+        assert_eq!(day_label(older, today), "Tuesday, February 27, 2024");
+    }
 
-        // Invalid timestamps should return false
-        assert!(!timestamps_within_window("invalid", "12:00:00"));
-        assert!(!timestamps_within_window("12:00:00", "invalid"));
+    #[test]
+    fn test_reply_snippet() {
+        assert_eq!(reply_snippet("hello\n  \x02world\x02"), "hello world");
+        let long = "word ".repeat(40);
+        let snippet = reply_snippet(&long);
+        assert!(snippet.ends_with("word…"));
+        assert_eq!(snippet.chars().count(), REPLY_SNIPPET_CHARS);
     }
 
     #[test]
     fn test_contains_mention_basic() {
         // Basic word boundary matches
-        assert!(contains_mention("hey alice how are you", "alice"));
-        assert!(contains_mention("alice: check this out", "alice"));
-        assert!(contains_mention("hello alice!", "alice"));
+        assert!(contains_mention("hey alice how are you", "alice", RFC));
+        assert!(contains_mention("alice: check this out", "alice", RFC));
+        assert!(contains_mention("hello alice!", "alice", RFC));
 
         // Case insensitivity
-        assert!(contains_mention("Hey ALICE, how are you?", "alice"));
-        assert!(contains_mention("Hey Alice, how are you?", "alice"));
-        assert!(contains_mention("hey alice, how are you?", "ALICE"));
+        assert!(contains_mention("Hey ALICE, how are you?", "alice", RFC));
+        assert!(contains_mention("Hey Alice, how are you?", "alice", RFC));
+        assert!(contains_mention("hey alice, how are you?", "ALICE", RFC));
 
         // @ mentions
-        assert!(contains_mention("@alice check this out", "alice"));
-        assert!(contains_mention("hey @alice", "alice"));
+        assert!(contains_mention("@alice check this out", "alice", RFC));
+        assert!(contains_mention("hey @alice", "alice", RFC));
     }
 
     #[test]
     fn test_contains_mention_word_boundaries() {
         // Should NOT match when nick is inside another word
-        assert!(!contains_mention("malice aforethought", "alice"));
-        assert!(!contains_mention("bobcat is cute", "bob"));
-        assert!(!contains_mention("jacoby is here", "jacob"));
+        assert!(!contains_mention("malice aforethought", "alice", RFC));
+        assert!(!contains_mention("bobcat is cute", "bob", RFC));
+        assert!(!contains_mention("jacoby is here", "jacob", RFC));
 
         // Should match at start/end of text
-        assert!(contains_mention("alice", "alice"));
-        assert!(contains_mention("hi alice", "alice"));
-        assert!(contains_mention("alice says hi", "alice"));
+        assert!(contains_mention("alice", "alice", RFC));
+        assert!(contains_mention("hi alice", "alice", RFC));
+        assert!(contains_mention("alice says hi", "alice", RFC));
 
         // Should match with punctuation
-        assert!(contains_mention("alice's message", "alice"));
-        assert!(contains_mention("(alice)", "alice"));
-        assert!(contains_mention("[alice]", "alice"));
+        assert!(contains_mention("alice's message", "alice", RFC));
+        assert!(contains_mention("(alice)", "alice", RFC));
+        assert!(contains_mention("[alice]", "alice", RFC));
+    }
+
+    #[test]
+    fn test_contains_mention_casemapping() {
+        assert!(contains_mention("ping NICK{M}: hi", "nick[m]", RFC));
+        assert!(contains_mention("ping nick^", "Nick~", RFC));
+        assert!(!contains_mention(
+            "ping nick{m}",
+            "nick[m]",
+            CaseMapping::Ascii
+        ));
+        // Non-ASCII before the nick doesn't shift the match
+        assert!(contains_mention("İstanbul alice", "alice", RFC));
     }
 
     #[test]
     fn test_contains_mention_edge_cases() {
         // Empty nickname should not match
-        assert!(!contains_mention("hello world", ""));
+        assert!(!contains_mention("hello world", "", RFC));
 
         // Empty text should not match
-        assert!(!contains_mention("", "alice"));
+        assert!(!contains_mention("", "alice", RFC));
 
         // Single character nick
-        assert!(contains_mention("hey x what's up", "x"));
-        assert!(!contains_mention("hex is cool", "x")); // x inside word
+        assert!(contains_mention("hey x what's up", "x", RFC));
+        assert!(!contains_mention("hex is cool", "x", RFC)); // x inside word
+    }
+
+    #[test]
+    fn test_typing_line() {
+        assert_eq!(typing_line(&[]), None);
+        assert_eq!(typing_line(&["alice"]).unwrap(), "alice is typing…");
+        assert_eq!(
+            typing_line(&["alice", "bob"]).unwrap(),
+            "alice and bob are typing…"
+        );
+        assert_eq!(
+            typing_line(&["alice", "bob", "carol", "dave"]).unwrap(),
+            "Several people are typing…"
+        );
     }
 }
@@ -6,4 +6,5 @@ mod helpers;
 mod render;
 
 // Re-export public API
-pub use render::render_messages;
+pub(crate) use helpers::{contains_mention, reply_snippet, typing_line};
+pub use render::{render_messages, MessageAction, ScrollTarget};
@@ -1,24 +1,85 @@
 //! Main message rendering logic with grouping and avatars.
 
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
 use eframe::egui::{self, Color32};
 use slirc_proto::ctcp::Ctcp;
 
-use crate::buffer::{ChannelBuffer, MessageType, RenderedMessage};
+use crate::buffer::{ChannelBuffer, Delivery, HistoryState, MessageType, RenderedMessage};
+use crate::config::{TimestampFormat, TimestampStyle};
+use crate::irc_name::CaseMapping;
 use crate::ui::theme::{self, SlircTheme};
 
 use super::format::render_message_text;
-use super::helpers::{contains_mention, timestamps_within_window};
+use super::helpers::{
+    contains_mention, day_label, full_timestamp, reply_snippet, timestamps_within_window,
+};
+
+/// Actions requested by the message view
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageAction {
+    /// The view reached the oldest loaded message: fetch older scrollback
+    LoadOlderHistory,
+    /// User chose to reply to a message
+    Reply {
+        msgid: String,
+        sender: String,
+        text: String,
+    },
+    /// User clicked a reply's quote: bring the original message into view
+    JumpToMessage(String),
+    /// User asked to see the first message after the read marker
+    JumpToFirstUnread,
+    /// User added (or with `add: false`, took back) a reaction to a message
+    React {
+        msgid: String,
+        reaction: String,
+        add: bool,
+    },
+}
+
+/// Where the message view should scroll on the next frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScrollTarget {
+    /// The message with this `msgid`, flashed once it is in view
+    Message(String),
+    /// The "New messages" divider
+    FirstUnread,
+}
+
+/// Reactions offered next to Reply when hovering a message
+const QUICK_REACTIONS: [&str; 5] = ["👍", "❤", "😂", "🎉", "👀"];
+
+/// How long a message stays highlighted after jumping to it, in seconds
+const JUMP_HIGHLIGHT_SECS: f64 = 1.5;
+
+/// State shared by every message while the list is drawn
+struct Interaction<'a> {
+    /// What to scroll into view this frame
+    scroll_to: Option<&'a ScrollTarget>,
+    /// Where the "New messages" divider was drawn, if it was
+    unread_divider: Option<egui::Rect>,
+    /// What the user asked for, if anything
+    action: Option<MessageAction>,
+    /// How the server folds nicks, for spotting mentions
+    case_mapping: CaseMapping,
+}
 
 /// Render the central message panel with message list.
 /// Topic bar is rendered separately by ui::topic_bar module.
+/// `buffer` is the active buffer's contents, `None` for "System".
+/// `scroll_to` is a message or the unread divider to bring into view.
+#[allow(clippy::too_many_arguments)]
 pub fn render_messages(
-    _ctx: &egui::Context,
+    ctx: &egui::Context,
     ui: &mut egui::Ui,
     active_buffer: &str,
-    buffers: &std::collections::HashMap<String, ChannelBuffer>,
+    buffer: Option<&ChannelBuffer>,
     system_log: &[String],
     nickname: &str,
-) {
+    case_mapping: CaseMapping,
+    timestamp_format: TimestampFormat,
+    scroll_to: Option<&ScrollTarget>,
+) -> Option<MessageAction> {
     let dark_mode = ui.style().visuals.dark_mode;
     let theme = if dark_mode {
         SlircTheme::dark()
@@ -26,8 +87,21 @@ pub fn render_messages(
         SlircTheme::light()
     };
 
+    // Relative timestamps age without any new events arriving
+    if timestamp_format.style == TimestampStyle::Relative {
+        let interval = if timestamp_format.show_seconds { 1 } else { 30 };
+        ctx.request_repaint_after(std::time::Duration::from_secs(interval));
+    }
+
+    let mut interaction = Interaction {
+        scroll_to,
+        unread_divider: None,
+        action: None,
+        case_mapping,
+    };
+
     // Messages area with improved styling
-    egui::ScrollArea::vertical()
+    let output = egui::ScrollArea::vertical()
         .auto_shrink([false; 2])
         .stick_to_bottom(true)
         .show(ui, |ui| {
@@ -35,12 +109,53 @@ pub fn render_messages(
 
             if active_buffer == "System" {
                 render_system_log(ui, system_log, &theme);
-            } else if let Some(buffer) = buffers.get(active_buffer) {
-                render_grouped_messages(ui, buffer, nickname, &theme);
+            } else if let Some(buffer) = buffer {
+                render_grouped_messages(
+                    ui,
+                    buffer,
+                    nickname,
+                    timestamp_format,
+                    &mut interaction,
+                    &theme,
+                );
             }
 
             ui.add_space(8.0);
         });
+
+    // Scrolled past the divider: offer a way back to it
+    if interaction
+        .unread_divider
+        .is_some_and(|divider| divider.bottom() < output.inner_rect.top())
+    {
+        let pill = egui::Rect::from_center_size(
+            egui::pos2(output.inner_rect.center().x, output.inner_rect.top() + 18.0),
+            egui::vec2(180.0, 24.0),
+        );
+        let jump = egui::Button::new(
+            egui::RichText::new("↑ Jump to first unread")
+                .size(12.0)
+                .color(Color32::WHITE),
+        )
+        .fill(theme.error)
+        .corner_radius(12.0);
+        if ui.put(pill, jump).on_hover_text("Ctrl+Shift+U").clicked() {
+            interaction.action = Some(MessageAction::JumpToFirstUnread);
+        }
+    }
+
+    if interaction.action.is_some() {
+        return interaction.action;
+    }
+
+    // Older scrollback is wanted when the user scrolls up past the oldest
+    // message, or when what we have doesn't fill the view yet
+    let buffer = buffer?;
+    let scrolled_to_top =
+        output.state.offset.y <= 0.0 && ui.input(|i| i.smooth_scroll_delta.y > 0.0);
+    let fits = output.content_size.y <= output.inner_rect.height();
+    (buffer.can_load_older() && (scrolled_to_top || fits))
+        .then_some(MessageAction::LoadOlderHistory)
 }
 
 /// Render system log with modern styling
@@ -58,12 +173,18 @@ fn render_system_log(ui: &mut egui::Ui, system_log: &[String], theme: &SlircThem
 struct MessageGroup<'a> {
     sender: &'a str,
     messages: Vec<&'a RenderedMessage>,
-    first_timestamp: &'a str,
+    first_timestamp: DateTime<Utc>,
     is_system: bool,
 }
 
-/// Group messages by sender for modern display
-fn group_messages(messages: &[RenderedMessage]) -> Vec<MessageGroup<'_>> {
+/// Group messages by sender for modern display.
+/// Groups never span two days of `tz` or the read marker `divider`, so a
+/// divider can go between them.
+fn group_messages<'a, Tz: TimeZone>(
+    messages: &'a [RenderedMessage],
+    divider: Option<DateTime<Utc>>,
+    tz: &Tz,
+) -> Vec<MessageGroup<'a>> {
     let mut groups: Vec<MessageGroup<'_>> = Vec::new();
 
     for msg in messages {
@@ -71,6 +192,7 @@ fn group_messages(messages: &[RenderedMessage]) -> Vec<MessageGroup<'_>> {
             msg.msg_type,
             MessageType::Join
                 | MessageType::Part
+                | MessageType::Kick
                 | MessageType::Quit
                 | MessageType::NickChange
                 | MessageType::Topic
@@ -81,7 +203,7 @@ fn group_messages(messages: &[RenderedMessage]) -> Vec<MessageGroup<'_>> {
             groups.push(MessageGroup {
                 sender: &msg.sender,
                 messages: vec![msg],
-                first_timestamp: &msg.timestamp,
+                first_timestamp: msg.timestamp,
                 is_system: true,
             });
             continue;
@@ -91,6 +213,8 @@ fn group_messages(messages: &[RenderedMessage]) -> Vec<MessageGroup<'_>> {
         // - Same sender
         // - Compatible message type
         // - Within 5-minute time window from the *last* message in the group
+        // - On the same day
+        // - On the same side of the read marker
         let should_group = groups.last().is_some_and(|last| {
             if last.is_system || last.sender != msg.sender {
                 return false;
@@ -105,9 +229,10 @@ fn group_messages(messages: &[RenderedMessage]) -> Vec<MessageGroup<'_>> {
             let last_msg_ts = last
                 .messages
                 .last()
-                .map(|m| m.timestamp.as_str())
-                .unwrap_or(last.first_timestamp);
-            timestamps_within_window(last_msg_ts, &msg.timestamp)
+                .map_or(last.first_timestamp, |m| m.timestamp);
+            timestamps_within_window(&last_msg_ts, &msg.timestamp)
+                && same_day(&last_msg_ts, &msg.timestamp, tz)
+                && !divider.is_some_and(|read| last_msg_ts <= read && msg.timestamp > read)
         });
 
         if should_group {
@@ -116,7 +241,7 @@ fn group_messages(messages: &[RenderedMessage]) -> Vec<MessageGroup<'_>> {
             groups.push(MessageGroup {
                 sender: &msg.sender,
                 messages: vec![msg],
-                first_timestamp: &msg.timestamp,
+                first_timestamp: msg.timestamp,
                 is_system: false,
             });
         }
@@ -125,28 +250,117 @@ fn group_messages(messages: &[RenderedMessage]) -> Vec<MessageGroup<'_>> {
     groups
 }
 
-/// Render messages with grouping and avatars
+/// Whether two times fall on the same calendar day in `tz`
+fn same_day<Tz: TimeZone>(a: &DateTime<Utc>, b: &DateTime<Utc>, tz: &Tz) -> bool {
+    a.with_timezone(tz).date_naive() == b.with_timezone(tz).date_naive()
+}
+
+/// Render messages with grouping and avatars, with a divider at each new day
 fn render_grouped_messages(
     ui: &mut egui::Ui,
     buffer: &ChannelBuffer,
     nickname: &str,
+    timestamp_format: TimestampFormat,
+    interaction: &mut Interaction<'_>,
     theme: &SlircTheme,
 ) {
-    let groups = group_messages(&buffer.messages);
+    if buffer.history == HistoryState::Loading {
+        ui.horizontal(|ui| {
+            ui.add_space(52.0);
+            ui.spinner();
+            ui.label(
+                egui::RichText::new("Loading older messages…")
+                    .size(12.0)
+                    .color(theme.text_muted),
+            );
+        });
+    }
+
+    let groups = group_messages(&buffer.messages, buffer.unread_divider, &Local);
+    let now = Utc::now();
+    let today = now.with_timezone(&Local).date_naive();
+    let mut last_day: Option<NaiveDate> = None;
+    let mut divider = buffer.unread_divider;
 
     for group in groups {
+        let day = group.first_timestamp.with_timezone(&Local).date_naive();
+        if last_day != Some(day) {
+            render_day_divider(ui, &day_label(day, today), theme);
+            last_day = Some(day);
+        }
+        // First group after the read marker
+        if divider.is_some_and(|read| group.first_timestamp > read) {
+            let rect = render_unread_divider(ui, theme);
+            if interaction.scroll_to == Some(&ScrollTarget::FirstUnread) {
+                ui.scroll_to_rect(rect, Some(egui::Align::TOP));
+            }
+            interaction.unread_divider = Some(rect);
+            divider = None;
+        }
+
+        let time = timestamp_format.format(&group.first_timestamp, now);
         if group.is_system {
             // Render system message (join/part/etc) compactly
-            render_system_message(ui, group.messages[0], theme);
+            render_system_message(ui, group.messages[0], &time, theme);
         } else {
             // Render message group with avatar
-            render_message_group(ui, &group, buffer, nickname, theme);
+            render_message_group(
+                ui,
+                &group,
+                buffer,
+                nickname,
+                timestamp_format,
+                now,
+                interaction,
+                theme,
+            );
         }
     }
 }
 
+/// Render a centered date label between two rules
+fn render_day_divider(ui: &mut egui::Ui, label: &str, theme: &SlircTheme) {
+    ui.add_space(16.0);
+    let (rect, _) =
+        ui.allocate_exact_size(egui::vec2(ui.available_width(), 16.0), egui::Sense::hover());
+    let text_rect = ui.painter().text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        label,
+        egui::FontId::new(11.0, egui::FontFamily::Proportional),
+        theme.text_muted,
+    );
+    let stroke = egui::Stroke::new(1.0, theme.surface[3]);
+    let y = rect.center().y;
+    ui.painter()
+        .hline(rect.left() + 16.0..=text_rect.left() - 8.0, y, stroke);
+    ui.painter()
+        .hline(text_rect.right() + 8.0..=rect.right() - 16.0, y, stroke);
+}
+
+/// Render the "New messages" rule at the read marker, returning where it is
+fn render_unread_divider(ui: &mut egui::Ui, theme: &SlircTheme) -> egui::Rect {
+    ui.add_space(8.0);
+    let (rect, _) =
+        ui.allocate_exact_size(egui::vec2(ui.available_width(), 16.0), egui::Sense::hover());
+    let text_rect = ui.painter().text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        "New messages",
+        egui::FontId::new(11.0, egui::FontFamily::Proportional),
+        theme.error,
+    );
+    let stroke = egui::Stroke::new(1.0, theme.error);
+    let y = rect.center().y;
+    ui.painter()
+        .hline(rect.left() + 16.0..=text_rect.left() - 8.0, y, stroke);
+    ui.painter()
+        .hline(text_rect.right() + 8.0..=rect.right() - 16.0, y, stroke);
+    rect
+}
+
 /// Render a system message (join, part, quit, etc.)
-fn render_system_message(ui: &mut egui::Ui, msg: &RenderedMessage, theme: &SlircTheme) {
+fn render_system_message(ui: &mut egui::Ui, msg: &RenderedMessage, time: &str, theme: &SlircTheme) {
     let (icon, color, text) = match &msg.msg_type {
         MessageType::Join => (
             "→",
@@ -165,6 +379,7 @@ fn render_system_message(ui: &mut egui::Ui, msg: &RenderedMessage, theme: &Slirc
         ),
         MessageType::NickChange => ("~", theme.info, format!("{} {}", msg.sender, msg.text)),
         MessageType::Topic => ("★", theme.info, msg.text.clone()),
+        MessageType::Kick => ("⊘", theme.error, msg.text.clone()),
         _ => ("•", theme.text_muted, msg.text.clone()),
     };
 
@@ -178,21 +393,22 @@ fn render_system_message(ui: &mut egui::Ui, msg: &RenderedMessage, theme: &Slirc
                 .color(theme.text_muted)
                 .italics(),
         );
-        ui.label(
-            egui::RichText::new(&msg.timestamp)
-                .size(10.0)
-                .color(theme.text_muted),
-        );
+        ui.label(egui::RichText::new(time).size(10.0).color(theme.text_muted))
+            .on_hover_text(full_timestamp(&msg.timestamp));
     });
     ui.add_space(4.0);
 }
 
 /// Render a group of messages from the same sender
+#[allow(clippy::too_many_arguments)]
 fn render_message_group(
     ui: &mut egui::Ui,
     group: &MessageGroup<'_>,
     buffer: &ChannelBuffer,
     nickname: &str,
+    timestamp_format: TimestampFormat,
+    now: DateTime<Utc>,
+    interaction: &mut Interaction<'_>,
     theme: &SlircTheme,
 ) {
     // Add spacing between groups (cozy layout)
@@ -230,10 +446,11 @@ fn render_message_group(
 
                 // Timestamp
                 ui.label(
-                    egui::RichText::new(group.first_timestamp)
+                    egui::RichText::new(timestamp_format.format(&group.first_timestamp, now))
                         .size(11.0)
                         .color(theme.text_muted),
-                );
+                )
+                .on_hover_text(full_timestamp(&group.first_timestamp));
             });
 
             ui.add_space(2.0);
@@ -244,23 +461,90 @@ fn render_message_group(
                     ui.add_space(2.0); // Tighter spacing within group
                 }
 
-                ui.horizontal(|ui| {
+                if let Some(parent) = msg.reply_to() {
+                    if render_reply_quote(ui, parent, buffer, theme) {
+                        interaction.action = Some(MessageAction::JumpToMessage(parent.to_string()));
+                    }
+                }
+
+                let row = ui.horizontal(|ui| {
                     // Message content
                     ui.vertical(|ui| {
-                        let mention = contains_mention(&msg.text, nickname);
+                        let mention =
+                            contains_mention(&msg.text, nickname, interaction.case_mapping);
                         render_message_content(ui, msg, buffer, mention, theme);
                     });
 
+                    // Our own line waiting for, or never getting, the server's echo
+                    match &msg.delivery {
+                        Delivery::Delivered => {}
+                        Delivery::Pending(_) => {
+                            ui.label(egui::RichText::new("…").size(11.0).color(theme.text_muted))
+                                .on_hover_text("Waiting for the server");
+                        }
+                        Delivery::Failed(reason) => {
+                            ui.label(
+                                egui::RichText::new("⚠ not delivered")
+                                    .size(11.0)
+                                    .color(theme.error),
+                            )
+                            .on_hover_text(reason);
+                        }
+                    }
+
                     // Timestamp (faint, shown on hover)
                     if i > 0 {
                         let timestamp_response = ui.label(
-                            egui::RichText::new(&msg.timestamp)
+                            egui::RichText::new(timestamp_format.format(&msg.timestamp, now))
                                 .size(10.0)
                                 .color(Color32::from_white_alpha(40)),
                         );
-                        timestamp_response.on_hover_text(&msg.timestamp);
+                        timestamp_response.on_hover_text(full_timestamp(&msg.timestamp));
+                    }
+
+                    // Reply and react buttons while the row is hovered (need the server's id)
+                    if let Some(msgid) = &msg.tags.msgid {
+                        if ui.rect_contains_pointer(ui.max_rect()) {
+                            for emoji in QUICK_REACTIONS {
+                                let quick = ui.add(
+                                    egui::Label::new(egui::RichText::new(emoji).size(12.0))
+                                        .sense(egui::Sense::click()),
+                                );
+                                if quick.clicked() {
+                                    interaction.action = Some(MessageAction::React {
+                                        msgid: msgid.clone(),
+                                        reaction: emoji.to_string(),
+                                        add: !has_reacted(msg, emoji, nickname),
+                                    });
+                                }
+                            }
+                            let reply = ui.add(
+                                egui::Label::new(
+                                    egui::RichText::new("↩ Reply")
+                                        .size(11.0)
+                                        .color(theme.text_muted),
+                                )
+                                .sense(egui::Sense::click()),
+                            );
+                            if reply.clicked() {
+                                interaction.action = Some(MessageAction::Reply {
+                                    msgid: msgid.clone(),
+                                    sender: msg.sender.clone(),
+                                    text: msg.text.clone(),
+                                });
+                            }
+                        }
                     }
                 });
+
+                if let Some(msgid) = msg.tags.msgid.as_deref() {
+                    if !msg.reactions.is_empty() {
+                        if let Some(action) = render_reactions(ui, msg, msgid, nickname, theme) {
+                            interaction.action = Some(action);
+                        }
+                    }
+                    highlight_jump_target(ui, &row.response, msgid, interaction, theme);
+                }
             }
         });
     });
@@ -279,6 +563,123 @@ fn render_message_group(
     }
 }
 
+/// Whether `nickname` has reacted to `msg` with `emoji`
+fn has_reacted(msg: &RenderedMessage, emoji: &str, nickname: &str) -> bool {
+    msg.reactions
+        .iter()
+        .any(|r| r.emoji == emoji && r.nicks.iter().any(|n| n == nickname))
+}
+
+/// Reaction chips under a message; clicking one toggles our own reaction
+fn render_reactions(
+    ui: &mut egui::Ui,
+    msg: &RenderedMessage,
+    msgid: &str,
+    nickname: &str,
+    theme: &SlircTheme,
+) -> Option<MessageAction> {
+    let mut action = None;
+    ui.horizontal_wrapped(|ui| {
+        for reaction in &msg.reactions {
+            let ours = reaction.nicks.iter().any(|n| n == nickname);
+            let (fill, stroke) = if ours {
+                (
+                    theme.accent.gamma_multiply(0.25),
+                    egui::Stroke::new(1.0, theme.accent),
+                )
+            } else {
+                (theme.surface[2], egui::Stroke::NONE)
+            };
+            let label = format!("{} {}", reaction.emoji, reaction.nicks.len());
+            let chip = ui
+                .add(
+                    egui::Button::new(egui::RichText::new(label).size(12.0))
+                        .fill(fill)
+                        .stroke(stroke)
+                        .corner_radius(10.0),
+                )
+                .on_hover_text(reaction.nicks.join(", "));
+            if chip.clicked() {
+                action = Some(MessageAction::React {
+                    msgid: msgid.to_string(),
+                    reaction: reaction.emoji.clone(),
+                    add: !ours,
+                });
+            }
+        }
+    });
+    action
+}
+
+/// Quote of the message a reply answers; returns true when clicked
+fn render_reply_quote(
+    ui: &mut egui::Ui,
+    parent_id: &str,
+    buffer: &ChannelBuffer,
+    theme: &SlircTheme,
+) -> bool {
+    let Some(parent) = buffer.find_by_msgid(parent_id) else {
+        ui.label(
+            egui::RichText::new("↪ Original message not loaded")
+                .size(12.0)
+                .color(theme.text_muted)
+                .italics(),
+        );
+        return false;
+    };
+    let quote = ui.horizontal(|ui| {
+        ui.label(egui::RichText::new("↪").size(12.0).color(theme.text_muted));
+        ui.label(
+            egui::RichText::new(&parent.sender)
+                .size(12.0)
+                .strong()
+                .color(theme::nick_color(&parent.sender)),
+        );
+        ui.label(
+            egui::RichText::new(reply_snippet(&parent.text))
+                .size(12.0)
+                .color(theme.text_secondary),
+        );
+    });
+    quote
+        .response
+        .interact(egui::Sense::click())
+        .on_hover_cursor(egui::CursorIcon::PointingHand)
+        .on_hover_text("Jump to message")
+        .clicked()
+}
+
+/// Scroll to the message we were asked to jump to, and flash it briefly
+fn highlight_jump_target(
+    ui: &egui::Ui,
+    row: &egui::Response,
+    msgid: &str,
+    interaction: &Interaction<'_>,
+    theme: &SlircTheme,
+) {
+    let key = egui::Id::new("message_jump_highlight");
+    let now = ui.input(|i| i.time);
+    if matches!(interaction.scroll_to, Some(ScrollTarget::Message(id)) if id == msgid) {
+        row.scroll_to_me(Some(egui::Align::Center));
+        ui.ctx()
+            .data_mut(|d| d.insert_temp(key, (msgid.to_string(), now)));
+    }
+    let Some((target, since)) = ui.ctx().data(|d| d.get_temp::<(String, f64)>(key)) else {
+        return;
+    };
+    let elapsed = now - since;
+    if target != msgid || elapsed > JUMP_HIGHLIGHT_SECS {
+        return;
+    }
+    let fade = 1.0 - (elapsed / JUMP_HIGHLIGHT_SECS) as f32;
+    ui.painter().rect_filled(
+        row.rect.expand(2.0),
+        4.0,
+        theme.accent.gamma_multiply(0.25 * fade),
+    );
+    ui.ctx().request_repaint();
+}
+
 /// Render the content of a single message
 fn render_message_content(
     ui: &mut egui::Ui,
@@ -354,6 +755,11 @@ fn render_message_content(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol::MessageTags;
+
+    fn at(hour: u32, min: u32, sec: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, hour, min, sec).unwrap()
+    }
 
     #[test]
     fn test_group_messages_time_gap() {
@@ -361,27 +767,36 @@ mod tests {
 
         let messages = vec![
             RenderedMessage {
-                timestamp: "12:00:00".to_string(),
+                timestamp: at(12, 0, 0),
                 sender: "alice".to_string(),
                 text: "Hello".to_string(),
                 msg_type: MessageType::Normal,
+                delivery: Delivery::Delivered,
+                tags: MessageTags::default(),
+                reactions: Vec::new(),
             },
             RenderedMessage {
-                timestamp: "12:02:00".to_string(),
+                timestamp: at(12, 2, 0),
                 sender: "alice".to_string(),
                 text: "Still here".to_string(),
                 msg_type: MessageType::Normal,
+                delivery: Delivery::Delivered,
+                tags: MessageTags::default(),
+                reactions: Vec::new(),
             },
             // 10 minute gap - should start new group
             RenderedMessage {
-                timestamp: "12:12:00".to_string(),
+                timestamp: at(12, 12, 0),
                 sender: "alice".to_string(),
                 text: "Back again".to_string(),
                 msg_type: MessageType::Normal,
+                delivery: Delivery::Delivered,
+                tags: MessageTags::default(),
+                reactions: Vec::new(),
             },
         ];
 
-        let groups = group_messages(&messages);
+        let groups = group_messages(&messages, None, &Utc);
 
         assert_eq!(groups.len(), 2);
         assert_eq!(groups[0].messages.len(), 2); // First two messages grouped
@@ -394,26 +809,35 @@ mod tests {
 
         let messages = vec![
             RenderedMessage {
-                timestamp: "12:00:00".to_string(),
+                timestamp: at(12, 0, 0),
                 sender: "alice".to_string(),
                 text: "Hello".to_string(),
                 msg_type: MessageType::Normal,
+                delivery: Delivery::Delivered,
+                tags: MessageTags::default(),
+                reactions: Vec::new(),
             },
             RenderedMessage {
-                timestamp: "12:00:30".to_string(),
+                timestamp: at(12, 0, 30),
                 sender: "bob".to_string(),
                 text: "Hi!".to_string(),
                 msg_type: MessageType::Normal,
+                delivery: Delivery::Delivered,
+                tags: MessageTags::default(),
+                reactions: Vec::new(),
             },
             RenderedMessage {
-                timestamp: "12:01:00".to_string(),
+                timestamp: at(12, 1, 0),
                 sender: "alice".to_string(),
                 text: "How are you?".to_string(),
                 msg_type: MessageType::Normal,
+                delivery: Delivery::Delivered,
+                tags: MessageTags::default(),
+                reactions: Vec::new(),
             },
         ];
 
-        let groups = group_messages(&messages);
+        let groups = group_messages(&messages, None, &Utc);
 
         // Each sender should get their own group
         assert_eq!(groups.len(), 3);
@@ -421,4 +845,49 @@ mod tests {
         assert_eq!(groups[1].sender, "bob");
         assert_eq!(groups[2].sender, "alice");
     }
+
+    #[test]
+    fn test_group_messages_split_at_midnight() {
+        use crate::buffer::MessageType;
+
+        let messages = vec![
+            RenderedMessage {
+                timestamp: at(23, 59, 0),
+                sender: "alice".to_string(),
+                text: "Almost midnight".to_string(),
+                msg_type: MessageType::Normal,
+                delivery: Delivery::Delivered,
+                tags: MessageTags::default(),
+                reactions: Vec::new(),
+            },
+            // One minute later, but on the next day
+            RenderedMessage {
+                timestamp: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+                sender: "alice".to_string(),
+                text: "Happy new day".to_string(),
+                msg_type: MessageType::Normal,
+                delivery: Delivery::Delivered,
+                tags: MessageTags::default(),
+                reactions: Vec::new(),
+            },
+        ];
+
+        let groups = group_messages(&messages, None, &Utc);
+
+        assert_eq!(groups.len(), 2);
+        assert!(same_day(&at(0, 0, 0), &at(23, 59, 59), &Utc));
+    }
+
+    #[test]
+    fn test_group_messages_split_at_read_marker() {
+        let messages: Vec<RenderedMessage> = (0..3)
+            .map(|m| RenderedMessage::new(at(12, m, 0), "alice".to_string(), m.to_string()))
+            .collect();
+
+        let groups = group_messages(&messages, Some(at(12, 1, 0)), &Utc);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].messages.len(), 2);
+        assert_eq!(groups[1].first_timestamp, at(12, 2, 0));
+    }
 }
@@ -19,6 +19,7 @@ pub mod shortcuts;
 pub mod theme;
 pub mod toolbar;
 pub mod topic_bar;
+pub mod whois_card;
 
 // Re-export commonly used items
 pub use panels::sort_users;
@@ -1,21 +1,19 @@
-//! Channel/buffer list panel rendering with search, collapsible sections, and unread badges.
+//! Channel/buffer list panel rendering with search, a network → channel tree, and unread badges.
 
-use crate::buffer::ChannelBuffer;
+use crate::protocol::NetworkId;
+use crate::state::{ClientState, NetworkSession, SYSTEM_BUFFER};
 use crate::ui::theme::SlircTheme;
 use eframe::egui::{self, Color32, Stroke};
-use std::collections::HashMap;
 
 /// Render the left channel list panel.
-#[allow(clippy::too_many_arguments)]
+///
+/// Buffers are grouped under their network; each network node can be
+/// collapsed (tracked in `state.collapsed_sections` by network id).
 pub fn render_channel_list(
     ctx: &egui::Context,
-    buffers: &HashMap<String, ChannelBuffer>,
-    buffers_order: &[String],
-    active_buffer: &mut String,
+    state: &mut ClientState,
     context_menu_visible: &mut bool,
-    context_menu_target: &mut Option<String>,
-    collapsed_sections: &mut std::collections::HashSet<String>,
-    channel_filter: &mut String,
+    context_menu_target: &mut Option<(NetworkId, String)>,
 ) {
     let dark_mode = ctx.style().visuals.dark_mode;
     let theme = if dark_mode {
@@ -61,22 +59,23 @@ pub fn render_channel_list(
             ui.add_space(8.0);
 
             // Search/filter input (Phase 3)
-            if buffers_order.len() > 3 {
+            let total_buffers: usize = state.sessions.values().map(|s| s.buffers_order.len()).sum();
+            if total_buffers > 3 {
                 ui.add_space(4.0);
                 ui.horizontal(|ui| {
                     ui.add_space(16.0);
                     let search_response = ui.add_sized(
                         egui::vec2(ui.available_width() - 32.0, 32.0),
-                        egui::TextEdit::singleline(channel_filter)
+                        egui::TextEdit::singleline(&mut state.channel_filter)
                             .hint_text("🔍 Search channels...")
                             .desired_width(f32::INFINITY),
                     );
 
                     // Clear button when text present
-                    if !channel_filter.is_empty() {
+                    if !state.channel_filter.is_empty() {
                         ui.add_space(-28.0);
                         if ui.small_button("✕").clicked() {
-                            channel_filter.clear();
+                            state.channel_filter.clear();
                         }
                     }
 
@@ -88,233 +87,108 @@ pub fn render_channel_list(
                 ui.add_space(8.0);
             }
 
-            // Channel list with collapsible sections (Phase 3)
+            let mut selected: Option<(NetworkId, String)> = None;
+
+            // Network tree: one collapsible node per network with its channels and DMs
             egui::ScrollArea::vertical()
                 .auto_shrink([false; 2])
                 .show(ui, |ui| {
-                    // Filter and categorize buffers
-                    let filtered_buffers: Vec<&String> = buffers_order
-                        .iter()
-                        .filter(|name| {
-                            if channel_filter.is_empty() {
-                                true
-                            } else {
-                                name.to_lowercase().contains(&channel_filter.to_lowercase())
-                            }
-                        })
-                        .collect();
-
-                    let mut channels = Vec::new();
-                    let mut dms = Vec::new();
-                    let mut system = Vec::new();
-
-                    for name in filtered_buffers {
-                        if name.as_str() == "System" {
-                            system.push(name);
-                        } else if name.starts_with('#') || name.starts_with('&') {
-                            channels.push(name);
-                        } else {
-                            dms.push(name);
+                    let filter = state.channel_filter.to_lowercase();
+                    let mut any_match = false;
+
+                    for network in &state.sessions_order {
+                        let Some(session) = state.sessions.get(network) else {
+                            continue;
+                        };
+
+                        // Channels first, then private messages
+                        let (channels, dms) = filter_buffers(session, &filter);
+                        if !filter.is_empty() && channels.is_empty() && dms.is_empty() {
+                            continue;
                         }
-                    }
+                        any_match = true;
 
-                    // CHANNELS section (collapsible - Phase 3)
-                    if !channels.is_empty() {
-                        let channels_collapsed = collapsed_sections.contains("channels");
-                        ui.add_space(4.0);
-
-                        let header_response = ui
-                            .horizontal(|ui| {
-                                ui.add_space(16.0);
-                                let caret = if channels_collapsed { "▶" } else { "▼" };
-                                ui.label(
-                                    egui::RichText::new(caret).size(9.0).color(theme.text_muted),
-                                );
-                                ui.add_space(4.0);
-                                ui.label(
-                                    egui::RichText::new("CHANNELS")
-                                        .size(11.0)
-                                        .strong()
-                                        .color(theme.text_muted),
-                                );
-                            })
-                            .response;
-
-                        if header_response.clicked() {
-                            if channels_collapsed {
-                                collapsed_sections.remove("channels");
-                            } else {
-                                collapsed_sections.insert("channels".to_string());
-                            }
-                        }
+                        // Never hide matches while filtering
+                        let collapsed =
+                            filter.is_empty() && state.collapsed_sections.contains(network);
+                        let unread: usize = session.buffers.values().map(|b| b.unread_count).sum();
+                        let has_highlight = session.buffers.values().any(|b| b.has_highlight);
 
-                        ui.add_space(6.0);
-                        ui.horizontal(|ui| {
-                            ui.add_space(16.0);
-                            let sep_rect = egui::Rect::from_min_size(
-                                ui.cursor().min,
-                                egui::vec2(ui.available_width() - 32.0, 1.0),
-                            );
-                            ui.painter().rect_filled(sep_rect, 0.0, theme.surface[3]);
-                        });
                         ui.add_space(8.0);
-
-                        if !channels_collapsed {
-                            for name in &channels {
-                                let (unread, has_highlight, selected) =
-                                    if let Some(b) = buffers.get(name.as_str()) {
-                                        (
-                                            b.unread_count,
-                                            b.has_highlight,
-                                            active_buffer == name.as_str(),
-                                        )
-                                    } else {
-                                        (0, false, false)
-                                    };
-
-                                ui.add_space(2.0);
-
-                                let clicked = render_channel_item(
-                                    ui,
-                                    name.as_str(),
-                                    unread,
-                                    has_highlight,
-                                    selected,
-                                    &theme,
-                                );
-
-                                if clicked.0 {
-                                    *active_buffer = name.to_string();
-                                }
-                                if clicked.1 {
-                                    *context_menu_visible = true;
-                                    *context_menu_target = Some(name.to_string());
-                                }
-
-                                ui.add_space(2.0);
+                        if render_network_header(
+                            ui,
+                            network,
+                            session.is_connected,
+                            collapsed,
+                            if collapsed { unread } else { 0 },
+                            has_highlight,
+                            &theme,
+                        ) {
+                            if collapsed {
+                                state.collapsed_sections.remove(network);
+                            } else {
+                                state.collapsed_sections.insert(network.clone());
                             }
                         }
-                    }
+                        ui.add_space(4.0);
 
-                    // PRIVATE MESSAGES section (collapsible - Phase 3)
-                    if !dms.is_empty() {
-                        let dms_collapsed = collapsed_sections.contains("dms");
-                        ui.add_space(12.0);
-
-                        let header_response = ui
-                            .horizontal(|ui| {
-                                ui.add_space(16.0);
-                                let caret = if dms_collapsed { "▶" } else { "▼" };
-                                ui.label(
-                                    egui::RichText::new(caret).size(9.0).color(theme.text_muted),
-                                );
-                                ui.add_space(4.0);
-                                ui.label(
-                                    egui::RichText::new("PRIVATE MESSAGES")
-                                        .size(11.0)
-                                        .strong()
-                                        .color(theme.text_muted),
-                                );
-                            })
-                            .response;
-
-                        if header_response.clicked() {
-                            if dms_collapsed {
-                                collapsed_sections.remove("dms");
-                            } else {
-                                collapsed_sections.insert("dms".to_string());
-                            }
+                        if collapsed {
+                            continue;
                         }
 
-                        ui.add_space(6.0);
-                        ui.horizontal(|ui| {
-                            ui.add_space(16.0);
-                            let sep_rect = egui::Rect::from_min_size(
-                                ui.cursor().min,
-                                egui::vec2(ui.available_width() - 32.0, 1.0),
+                        for name in channels.into_iter().chain(dms) {
+                            let (unread, has_highlight) = session
+                                .buffer(name)
+                                .map(|b| (b.unread_count, b.has_highlight))
+                                .unwrap_or((0, false));
+                            let is_selected =
+                                state.active_network == *network && state.active_buffer == *name;
+
+                            ui.add_space(2.0);
+
+                            let clicked = render_channel_item(
+                                ui,
+                                name,
+                                session.support.is_channel(name),
+                                unread,
+                                has_highlight,
+                                is_selected,
+                                &theme,
                             );
-                            ui.painter().rect_filled(sep_rect, 0.0, theme.surface[3]);
-                        });
-                        ui.add_space(8.0);
 
-                        if !dms_collapsed {
-                            for name in &dms {
-                                let (unread, has_highlight, selected) =
-                                    if let Some(b) = buffers.get(name.as_str()) {
-                                        (
-                                            b.unread_count,
-                                            b.has_highlight,
-                                            active_buffer == name.as_str(),
-                                        )
-                                    } else {
-                                        (0, false, false)
-                                    };
-
-                                ui.add_space(2.0);
-
-                                let clicked = render_channel_item(
-                                    ui,
-                                    name.as_str(),
-                                    unread,
-                                    has_highlight,
-                                    selected,
-                                    &theme,
-                                );
-
-                                if clicked.0 {
-                                    *active_buffer = name.to_string();
-                                }
-                                if clicked.1 {
-                                    *context_menu_visible = true;
-                                    *context_menu_target = Some(name.to_string());
-                                }
-
-                                ui.add_space(2.0);
+                            if clicked.0 {
+                                selected = Some((network.clone(), name.clone()));
+                            }
+                            if clicked.1 {
+                                *context_menu_visible = true;
+                                *context_menu_target = Some((network.clone(), name.clone()));
                             }
+
+                            ui.add_space(2.0);
                         }
                     }
 
                     // System buffer (always visible, no collapse)
-                    for name in &system {
-                        let (unread, has_highlight, selected) =
-                            if let Some(b) = buffers.get(name.as_str()) {
-                                (
-                                    b.unread_count,
-                                    b.has_highlight,
-                                    active_buffer == name.as_str(),
-                                )
-                            } else {
-                                (0, false, false)
-                            };
-
-                        ui.add_space(2.0);
-
+                    if filter.is_empty() || SYSTEM_BUFFER.to_lowercase().contains(&filter) {
+                        any_match = true;
+                        ui.add_space(8.0);
                         let clicked = render_channel_item(
                             ui,
-                            name.as_str(),
-                            unread,
-                            has_highlight,
-                            selected,
+                            SYSTEM_BUFFER,
+                            false,
+                            0,
+                            false,
+                            state.active_buffer == SYSTEM_BUFFER,
                             &theme,
                         );
-
                         if clicked.0 {
-                            *active_buffer = name.to_string();
-                        }
-                        if clicked.1 {
-                            *context_menu_visible = true;
-                            *context_menu_target = Some(name.to_string());
+                            selected = Some((state.active_network.clone(), SYSTEM_BUFFER.into()));
                         }
-
                         ui.add_space(2.0);
                     }
 
                     // Hint if no results after filtering
-                    if !channel_filter.is_empty()
-                        && channels.is_empty()
-                        && dms.is_empty()
-                        && system.is_empty()
-                    {
+                    if !any_match {
                         ui.add_space(16.0);
                         ui.horizontal(|ui| {
                             ui.add_space(16.0);
@@ -327,14 +201,89 @@ pub fn render_channel_list(
                         });
                     }
                 });
+
+            if let Some((network, name)) = selected {
+                state.switch_to_buffer(&network, &name);
+            }
         });
 }
 
+/// Split a session's buffers into (channels, private messages), applying the filter.
+fn filter_buffers<'a>(
+    session: &'a NetworkSession,
+    filter: &str,
+) -> (Vec<&'a String>, Vec<&'a String>) {
+    session
+        .buffers_order
+        .iter()
+        .filter(|name| filter.is_empty() || name.to_lowercase().contains(filter))
+        .partition(|name| session.support.is_channel(name))
+}
+
+/// Render a collapsible network node header.
+/// Returns true if the header was clicked.
+fn render_network_header(
+    ui: &mut egui::Ui,
+    network: &str,
+    is_connected: bool,
+    collapsed: bool,
+    unread: usize,
+    has_highlight: bool,
+    theme: &SlircTheme,
+) -> bool {
+    let response = ui
+        .horizontal(|ui| {
+            ui.add_space(16.0);
+            let caret = if collapsed { "▶" } else { "▼" };
+            ui.label(egui::RichText::new(caret).size(9.0).color(theme.text_muted));
+            ui.add_space(4.0);
+
+            // Connection status dot
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(8.0, 8.0), egui::Sense::hover());
+            if is_connected {
+                ui.painter()
+                    .circle_filled(rect.center(), 3.5, Color32::from_rgb(34, 197, 94));
+            } else {
+                ui.painter().circle_stroke(
+                    rect.center(),
+                    3.0,
+                    Stroke::new(1.2, Color32::from_rgb(100, 100, 100)),
+                );
+            }
+            ui.add_space(4.0);
+
+            ui.label(
+                egui::RichText::new(network)
+                    .size(12.0)
+                    .strong()
+                    .color(theme.text_secondary),
+            );
+
+            if unread > 0 {
+                let color = if has_highlight {
+                    theme.error
+                } else {
+                    theme.accent
+                };
+                ui.label(
+                    egui::RichText::new(format!("({})", unread))
+                        .size(11.0)
+                        .color(color),
+                );
+            }
+        })
+        .response
+        .interact(egui::Sense::click());
+
+    response.clicked()
+}
+
 /// Render a single channel item with modern styling
 /// Returns (left_clicked, right_clicked)
 fn render_channel_item(
     ui: &mut egui::Ui,
     name: &str,
+    is_channel: bool,
     unread: usize,
     has_highlight: bool,
     selected: bool,
@@ -374,7 +323,7 @@ fn render_channel_item(
     // Icon
     let icon = if name == "System" {
         "⚙"
-    } else if name.starts_with('#') || name.starts_with('&') {
+    } else if is_channel {
         "#"
     } else {
         "👤"
@@ -1,18 +1,22 @@
 //! User list panel rendering with role grouping and status indicators.
 
 use crate::buffer::ChannelBuffer;
-use crate::protocol::UserInfo;
+use crate::protocol::{NetworkId, UserInfo};
+use crate::server_support::ServerSupport;
 use crate::ui::theme::{self, SlircTheme};
 use eframe::egui::{self, Color32, Stroke};
 
 /// Render the right user list panel.
+#[allow(clippy::too_many_arguments)]
 pub fn render_user_list(
     ctx: &egui::Context,
     buffer: &ChannelBuffer,
+    support: &ServerSupport,
+    network: &str,
     _active_buffer: &str,
-    _nickname_input: &str,
+    _our_nick: &str,
     context_menu_visible: &mut bool,
-    context_menu_target: &mut Option<String>,
+    context_menu_target: &mut Option<(NetworkId, String)>,
 ) {
     let dark_mode = ctx.style().visuals.dark_mode;
     let theme = if dark_mode {
@@ -29,10 +33,12 @@ pub fn render_user_list(
         let mut regular = Vec::new();
 
         for user in &buffer.users {
-            match user.prefix {
-                Some('@') | Some('~') | Some('&') => ops.push(user),
-                Some('+') | Some('%') => voiced.push(user),
-                _ => regular.push(user),
+            if user.prefix.is_none() {
+                regular.push(user);
+            } else if support.is_at_least(user.prefix, 'o') {
+                ops.push(user);
+            } else {
+                voiced.push(user);
             }
         }
 
@@ -59,7 +65,9 @@ pub fn render_user_list(
                             ui,
                             "OPERATORS",
                             &ops,
+                            network,
                             &theme,
+                            support,
                             context_menu_visible,
                             context_menu_target,
                         );
@@ -71,7 +79,9 @@ pub fn render_user_list(
                             ui,
                             "VOICED",
                             &voiced,
+                            network,
                             &theme,
+                            support,
                             context_menu_visible,
                             context_menu_target,
                         );
@@ -84,7 +94,9 @@ pub fn render_user_list(
                             ui,
                             &label,
                             &regular,
+                            network,
                             &theme,
+                            support,
                             context_menu_visible,
                             context_menu_target,
                         );
@@ -94,13 +106,16 @@ pub fn render_user_list(
 }
 
 /// Render a section of users (Operators, Voiced, Online)
+#[allow(clippy::too_many_arguments)]
 fn render_user_section(
     ui: &mut egui::Ui,
     title: &str,
     users: &[&UserInfo],
+    network: &str,
     theme: &SlircTheme,
+    support: &ServerSupport,
     context_menu_visible: &mut bool,
-    context_menu_target: &mut Option<String>,
+    context_menu_target: &mut Option<(NetworkId, String)>,
 ) {
     // Section header with icon
     ui.add_space(16.0);
@@ -140,17 +155,22 @@ fn render_user_section(
 
     // Users in section
     for user in users {
-        let clicked = render_user_item(ui, user, theme);
+        let clicked = render_user_item(ui, user, theme, support);
         if clicked.1 {
             *context_menu_visible = true;
-            *context_menu_target = Some(format!("user:{}", user.nick));
+            *context_menu_target = Some((network.to_string(), format!("user:{}", user.nick)));
         }
     }
 }
 
 /// Render a single user item
 /// Returns (left_clicked, right_clicked)
-fn render_user_item(ui: &mut egui::Ui, user: &UserInfo, theme: &SlircTheme) -> (bool, bool) {
+fn render_user_item(
+    ui: &mut egui::Ui,
+    user: &UserInfo,
+    theme: &SlircTheme,
+    support: &ServerSupport,
+) -> (bool, bool) {
     let height = 32.0; // Increased from 28 for better touch targets
     let available_width = ui.available_width();
 
@@ -172,7 +192,7 @@ fn render_user_item(ui: &mut egui::Ui, user: &UserInfo, theme: &SlircTheme) -> (
     );
 
     // Role indicator overlay on avatar
-    let status_color = theme::prefix_color(theme, user.prefix);
+    let status_color = theme::prefix_color(theme, support, user.prefix);
     let ring_center = egui::pos2(rect.min.x + 20.0, rect.center().y);
     ui.painter()
         .circle_stroke(ring_center, 10.0, egui::Stroke::new(2.0, status_color));
@@ -193,20 +213,21 @@ fn render_user_item(ui: &mut egui::Ui, user: &UserInfo, theme: &SlircTheme) -> (
     );
 
     // Role badge (for ops/voiced)
+    let mode = user.prefix.and_then(|p| support.mode_for_prefix(p));
     if let Some(prefix) = user.prefix {
-        let badge_char = match prefix {
-            '@' => "OP",
-            '~' => "OW",
-            '&' => "AD",
-            '%' => "HO",
-            '+' => "V",
-            _ => "",
+        let badge_char = match mode {
+            Some('o') => "OP".to_string(),
+            Some('q') => "OW".to_string(),
+            Some('a') => "AD".to_string(),
+            Some('h') => "HO".to_string(),
+            Some('v') => "V".to_string(),
+            // Network-specific prefixes show as themselves
+            _ => prefix.to_string(),
         };
 
         if !badge_char.is_empty() {
             let badge_font = egui::FontId::new(8.0, egui::FontFamily::Proportional);
-            let galley =
-                ui.fonts(|f| f.layout_no_wrap(badge_char.to_string(), badge_font, Color32::WHITE));
+            let galley = ui.fonts(|f| f.layout_no_wrap(badge_char, badge_font, Color32::WHITE));
 
             let badge_width = galley.size().x + 6.0;
             let badge_height = 14.0;
@@ -229,13 +250,14 @@ fn render_user_item(ui: &mut egui::Ui, user: &UserInfo, theme: &SlircTheme) -> (
 
     // Tooltip on hover with full role name
     if hovered {
-        let prefix_text = match user.prefix {
-            Some('@') => "Operator",
-            Some('~') => "Owner",
-            Some('&') => "Admin",
-            Some('%') => "Half-Op",
-            Some('+') => "Voice",
-            _ => "User",
+        let prefix_text = match mode {
+            Some('o') => "Operator".to_string(),
+            Some('q') => "Owner".to_string(),
+            Some('a') => "Admin".to_string(),
+            Some('h') => "Half-Op".to_string(),
+            Some('v') => "Voice".to_string(),
+            Some(other) => format!("Mode +{}", other),
+            None => "User".to_string(),
         };
         response.clone().on_hover_text(prefix_text);
     }
@@ -243,11 +265,11 @@ fn render_user_item(ui: &mut egui::Ui, user: &UserInfo, theme: &SlircTheme) -> (
     (response.clicked(), response.secondary_clicked())
 }
 
-/// Sort users by prefix rank (ops first) then alphabetically.
-pub fn sort_users(users: &mut [UserInfo]) {
+/// Sort users by the network's prefix rank (ops first) then alphabetically.
+pub fn sort_users(users: &mut [UserInfo], support: &ServerSupport) {
     users.sort_by(|a, b| {
-        let ar = theme::prefix_rank(a.prefix);
-        let br = theme::prefix_rank(b.prefix);
+        let ar = support.prefix_rank(a.prefix);
+        let br = support.prefix_rank(b.prefix);
         br.cmp(&ar).then(a.nick.cmp(&b.nick))
     });
 }
@@ -1,7 +1,8 @@
 //! Quick switcher overlay for fast channel/DM navigation (Ctrl+K)
 //! Discord/Slack-style fuzzy search interface
 
-use crate::buffer::ChannelBuffer;
+use crate::protocol::NetworkId;
+use crate::state::{NetworkSession, SYSTEM_BUFFER};
 use crate::ui::theme::SlircTheme;
 use eframe::egui::{self, Color32, Key};
 use std::collections::HashMap;
@@ -12,7 +13,7 @@ pub struct QuickSwitcher {
     pub visible: bool,
     pub query: String,
     pub selected_index: usize,
-    matches: Vec<String>,
+    matches: Vec<(NetworkId, String)>,
 }
 
 impl QuickSwitcher {
@@ -33,12 +34,12 @@ impl QuickSwitcher {
     }
 
     /// Render the quick switcher overlay
-    /// Returns Some(buffer_name) if user selected a buffer
+    /// Returns Some((network, buffer_name)) if user selected a buffer
     pub fn render(
         &mut self,
         ctx: &egui::Context,
-        buffers: &HashMap<String, ChannelBuffer>,
-    ) -> Option<String> {
+        sessions: &HashMap<NetworkId, NetworkSession>,
+    ) -> Option<(NetworkId, String)> {
         if !self.visible {
             return None;
         }
@@ -49,7 +50,7 @@ impl QuickSwitcher {
             SlircTheme::light()
         };
 
-        let mut selected_buffer: Option<String> = None;
+        let mut selected_buffer: Option<(NetworkId, String)> = None;
 
         // Capture Escape key to close
         if ctx.input(|i| i.key_pressed(Key::Escape)) {
@@ -78,7 +79,7 @@ impl QuickSwitcher {
         }
 
         // Update matches based on query
-        self.update_matches(buffers);
+        self.update_matches(sessions);
 
         // Modal overlay
         egui::Window::new("Quick Switcher")
@@ -132,19 +133,21 @@ impl QuickSwitcher {
                                     );
                                 });
                             } else {
-                                for (i, buffer_name) in self.matches.iter().enumerate() {
+                                for (i, (network, buffer_name)) in self.matches.iter().enumerate() {
                                     let is_selected = i == self.selected_index;
 
                                     let response = self.render_result_item(
                                         ui,
+                                        network,
                                         buffer_name,
-                                        buffers.get(buffer_name),
+                                        sessions.get(network),
                                         is_selected,
                                         &theme,
                                     );
 
                                     if response.clicked() {
-                                        selected_buffer = Some(buffer_name.clone());
+                                        selected_buffer =
+                                            Some((network.clone(), buffer_name.clone()));
                                         self.hide();
                                         break;
                                     }
@@ -192,8 +195,9 @@ impl QuickSwitcher {
     fn render_result_item(
         &self,
         ui: &mut egui::Ui,
+        network: &str,
         buffer_name: &str,
-        buffer: Option<&ChannelBuffer>,
+        session: Option<&NetworkSession>,
         is_selected: bool,
         theme: &SlircTheme,
     ) -> egui::Response {
@@ -217,9 +221,9 @@ impl QuickSwitcher {
         }
 
         // Icon
-        let icon = if buffer_name == "System" {
+        let icon = if buffer_name == SYSTEM_BUFFER {
             "⚙"
-        } else if buffer_name.starts_with('#') || buffer_name.starts_with('&') {
+        } else if session.is_some_and(|s| s.support.is_channel(buffer_name)) {
             "#"
         } else {
             "👤"
@@ -248,14 +252,19 @@ impl QuickSwitcher {
             theme.text_primary,
         );
 
-        // Topic or user count
-        if let Some(buf) = buffer {
-            let subtitle = if !buf.topic.is_empty() {
-                &buf.topic
+        // Network, followed by topic or user count
+        if let Some(buf) = session.and_then(|s| s.buffer(buffer_name)) {
+            let detail = if !buf.topic.is_empty() {
+                buf.topic.clone()
             } else if !buf.users.is_empty() {
-                &format!("{} users", buf.users.len())
+                format!("{} users", buf.users.len())
             } else {
-                ""
+                String::new()
+            };
+            let subtitle = if detail.is_empty() {
+                network.to_string()
+            } else {
+                format!("{} · {}", network, detail)
             };
 
             if !subtitle.is_empty() {
@@ -273,12 +282,20 @@ impl QuickSwitcher {
     }
 
     /// Update matches based on current query
-    fn update_matches(&mut self, buffers: &HashMap<String, ChannelBuffer>) {
+    fn update_matches(&mut self, sessions: &HashMap<NetworkId, NetworkSession>) {
         let query_lower = self.query.to_lowercase();
 
-        // Collect all buffer names
-        let mut all_buffers: Vec<String> = buffers.keys().cloned().collect();
-        all_buffers.push("System".to_string());
+        // Collect all buffer names across networks
+        let mut all_buffers: Vec<(NetworkId, String)> = sessions
+            .iter()
+            .flat_map(|(network, session)| {
+                session
+                    .buffers
+                    .keys()
+                    .map(move |name| (network.clone(), name.to_string()))
+            })
+            .collect();
+        all_buffers.push((NetworkId::new(), SYSTEM_BUFFER.to_string()));
 
         // Sort and filter
         if query_lower.is_empty() {
@@ -287,15 +304,15 @@ impl QuickSwitcher {
             self.matches = all_buffers;
         } else {
             // Fuzzy match: contains query substring
-            let mut matches: Vec<String> = all_buffers
+            let mut matches: Vec<(NetworkId, String)> = all_buffers
                 .into_iter()
-                .filter(|name| name.to_lowercase().contains(&query_lower))
+                .filter(|(_, name)| name.to_lowercase().contains(&query_lower))
                 .collect();
 
             // Sort by relevance: exact prefix match first, then contains
             matches.sort_by(|a, b| {
-                let a_lower = a.to_lowercase();
-                let b_lower = b.to_lowercase();
+                let a_lower = a.1.to_lowercase();
+                let b_lower = b.1.to_lowercase();
                 let a_prefix = a_lower.starts_with(&query_lower);
                 let b_prefix = b_lower.starts_with(&query_lower);
 
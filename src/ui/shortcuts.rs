@@ -170,6 +170,12 @@ impl ShortcutRegistry {
                 description: "Next Channel",
                 action_id: "nav.next_channel",
             },
+            Shortcut {
+                category: ShortcutCategory::Navigation,
+                key_text: "Ctrl+Shift+U",
+                description: "Jump to First Unread",
+                action_id: "nav.first_unread",
+            },
             Shortcut {
                 category: ShortcutCategory::Navigation,
                 key_text: "F1",
@@ -298,6 +304,9 @@ mod tests {
         let shortcut = registry.find("file.connect");
         assert!(shortcut.is_some(), "Should find file.connect shortcut");
         assert_eq!(shortcut.unwrap().key_text, "Ctrl+N");
+
+        let unread = registry.find("nav.first_unread");
+        assert_eq!(unread.unwrap().key_text, "Ctrl+Shift+U");
     }
 
     #[test]
@@ -4,6 +4,8 @@
 
 use eframe::egui::Color32;
 
+use crate::server_support::ServerSupport;
+
 /// Modern theme with semantic color system (7-level surface hierarchy)
 #[derive(Clone, Debug)]
 #[allow(dead_code)]
@@ -119,24 +121,14 @@ pub fn nick_color(nick: &str) -> Color32 {
     NICK_COLORS[idx]
 }
 
-/// IRC user prefix ranks (higher = more privileged).
-pub fn prefix_rank(prefix: Option<char>) -> u8 {
-    match prefix {
-        Some('~') => 5,
-        Some('&') => 4,
-        Some('@') => 3,
-        Some('%') => 2,
-        Some('+') => 1,
-        _ => 0,
-    }
-}
-
-/// Color for user prefix/status indicator
-pub fn prefix_color(theme: &SlircTheme, prefix: Option<char>) -> Color32 {
-    match prefix {
-        Some('@') | Some('~') | Some('&') => theme.success, // Green for ops
-        Some('+') | Some('%') => theme.warning,             // Orange for voice
-        _ => theme.text_muted,                              // Gray for regular
+/// Color for user prefix/status indicator (ranks come from the network's PREFIX)
+pub fn prefix_color(theme: &SlircTheme, support: &ServerSupport, prefix: Option<char>) -> Color32 {
+    if prefix.is_none() {
+        theme.text_muted // Gray for regular
+    } else if support.is_at_least(prefix, 'o') {
+        theme.success // Green for ops
+    } else {
+        theme.warning // Orange for voice
     }
 }
 
@@ -181,10 +173,14 @@ mod tests {
     }
 
     #[test]
-    fn test_prefix_rank_ordering() {
-        assert!(prefix_rank(Some('~')) > prefix_rank(Some('@')));
-        assert!(prefix_rank(Some('@')) > prefix_rank(Some('+')));
-        assert!(prefix_rank(Some('+')) > prefix_rank(None));
+    fn test_prefix_color_ordering() {
+        let theme = SlircTheme::dark();
+        let mut support = ServerSupport::default();
+        support.apply(&["PREFIX=(qohv)~@%+"]);
+        assert_eq!(prefix_color(&theme, &support, Some('~')), theme.success);
+        assert_eq!(prefix_color(&theme, &support, Some('@')), theme.success);
+        assert_eq!(prefix_color(&theme, &support, Some('%')), theme.warning);
+        assert_eq!(prefix_color(&theme, &support, None), theme.text_muted);
     }
 
     #[test]
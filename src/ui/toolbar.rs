@@ -1,9 +1,12 @@
 //! Top toolbar rendering with connection controls.
 
+use std::time::{Duration, Instant};
+
 use crossbeam_channel::Sender;
 use eframe::egui::{self, Color32, RichText, Stroke};
 
-use crate::protocol::BackendAction;
+use crate::protocol::{BackendAction, NetworkAction};
+use crate::server_support::ServerSupport;
 
 /// Actions that the toolbar can request
 #[derive(Debug, Clone, PartialEq)]
@@ -14,7 +17,7 @@ pub enum ToolbarAction {
     OpenNickChangeDialog,
 }
 
-/// Render the top toolbar with connection controls.
+/// Render the top toolbar with connection controls for the active network.
 /// Returns Some(ToolbarAction) if an action was requested.
 #[allow(clippy::too_many_arguments)]
 pub fn render_toolbar(
@@ -24,8 +27,13 @@ pub fn render_toolbar(
     nickname_input: &mut String,
     channel_input: &mut String,
     is_connected: bool,
+    reconnect_at: Option<(u32, Instant)>,
+    lag: Option<Duration>,
+    send_queue_depth: usize,
     use_tls: &mut bool,
-    action_tx: &Sender<BackendAction>,
+    network: &str,
+    support: &ServerSupport,
+    action_tx: &Sender<NetworkAction>,
 ) -> Option<ToolbarAction> {
     let mut toolbar_action: Option<ToolbarAction> = None;
     let dark_mode = ctx.style().visuals.dark_mode;
@@ -39,7 +47,25 @@ pub fn render_toolbar(
         ui.spacing_mut().item_spacing.x = 8.0;
         ui.spacing_mut().button_padding = egui::vec2(8.0, 4.0);
 
-        if !is_connected {
+        if let (false, Some((attempt, at))) = (is_connected, reconnect_at) {
+            // Countdown and controls while an automatic reconnect is pending
+            let secs = at.saturating_duration_since(Instant::now()).as_secs();
+            ui.label(
+                RichText::new(format!(
+                    "Reconnecting to {} in {}s (attempt {})",
+                    network, secs, attempt
+                ))
+                .color(text_secondary),
+            );
+            if ui.button("Reconnect now").clicked() {
+                let _ = action_tx.send(NetworkAction::new(network, BackendAction::ReconnectNow));
+            }
+            if ui.button("Cancel").clicked() {
+                let _ = action_tx.send(NetworkAction::new(network, BackendAction::CancelReconnect));
+            }
+            // Keep the countdown ticking
+            ctx.request_repaint_after(Duration::from_millis(500));
+        } else if !is_connected {
             // Server/nick inputs when disconnected
             ui.add(
                 egui::TextEdit::singleline(server_input)
@@ -74,12 +100,8 @@ pub fn render_toolbar(
             if ui.button("Join").clicked()
                 || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)))
             {
-                let channel = if channel_input.starts_with('#') || channel_input.starts_with('&') {
-                    channel_input.clone()
-                } else {
-                    format!("#{}", channel_input)
-                };
-                let _ = action_tx.send(BackendAction::Join(channel));
+                let channel = support.channel_name(channel_input);
+                let _ = action_tx.send(NetworkAction::new(network, BackendAction::Join(channel)));
                 channel_input.clear();
             }
         }
@@ -87,11 +109,32 @@ pub fn render_toolbar(
         // Right side - connection status indicator
         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
             if is_connected {
-                ui.label(
-                    RichText::new(server_input.as_str())
-                        .color(text_secondary)
-                        .small(),
-                );
+                if send_queue_depth > 0 {
+                    ui.label(
+                        RichText::new(format!("⏳ {} queued", send_queue_depth))
+                            .color(text_secondary)
+                            .small(),
+                    )
+                    .on_hover_text("Lines held back by flood control");
+                }
+                if let Some(lag) = lag {
+                    let lag_ms = lag.as_millis();
+                    // Amber/red once the link gets sluggish
+                    let lag_color = if lag_ms >= 2000 {
+                        Color32::from_rgb(239, 68, 68)
+                    } else if lag_ms >= 500 {
+                        Color32::from_rgb(245, 158, 11)
+                    } else {
+                        text_secondary
+                    };
+                    ui.label(
+                        RichText::new(format!("{} ms", lag_ms))
+                            .color(lag_color)
+                            .small(),
+                    )
+                    .on_hover_text("Round-trip time to the server");
+                }
+                ui.label(RichText::new(network).color(text_secondary).small());
                 ui.add_space(4.0);
                 // Green glowing dot for connected
                 let (rect, _) =
@@ -1,10 +1,14 @@
 //! Topic bar component - displays channel info, topic, modes, and actions
 //! Rendered above the message area for channels
 
+use chrono::{DateTime, Local, Utc};
 use eframe::egui::{self, Stroke};
 use std::collections::HashMap;
 
 use crate::buffer::ChannelBuffer;
+use crate::channel_modes::ChannelModes;
+use crate::irc_name::IrcName;
+use crate::server_support::ServerSupport;
 use crate::ui::theme::SlircTheme;
 
 /// Actions that can be triggered from the topic bar
@@ -18,6 +22,10 @@ pub enum TopicBarAction {
     ToggleMute,
     /// User clicked pinned messages
     ShowPinned,
+    /// User asked to rejoin a channel they were kicked from
+    Rejoin(String),
+    /// User clicked the channel settings icon
+    ChannelSettings(String),
 }
 
 /// Render the topic bar for a channel
@@ -27,17 +35,18 @@ pub enum TopicBarAction {
 pub fn render_topic_bar(
     ui: &mut egui::Ui,
     active_buffer: &str,
-    buffers: &HashMap<String, ChannelBuffer>,
+    buffers: &HashMap<IrcName, ChannelBuffer>,
+    support: &ServerSupport,
     nickname: &str,
     theme: &SlircTheme,
     system_log: &mut Vec<String>,
 ) -> Option<TopicBarAction> {
     // Only render for channels
-    if !active_buffer.starts_with('#') && !active_buffer.starts_with('&') {
+    if !support.is_channel(active_buffer) {
         return None;
     }
 
-    let buffer = buffers.get(active_buffer)?;
+    let buffer = buffers.get(&support.irc_name(active_buffer))?;
     let mut action: Option<TopicBarAction> = None;
 
     // Topic bar frame with elevated background
@@ -48,7 +57,7 @@ pub fn render_topic_bar(
         .show(ui, |ui| {
             ui.horizontal(|ui| {
                 // 1. Channel icon
-                let icon = if active_buffer.starts_with('#') || active_buffer.starts_with('&') {
+                let icon = if support.is_channel(active_buffer) {
                     "#"
                 } else {
                     "👤"
@@ -62,8 +71,7 @@ pub fn render_topic_bar(
 
                 // 2. Channel name (without prefix)
                 let display_name = active_buffer
-                    .strip_prefix('#')
-                    .or_else(|| active_buffer.strip_prefix('&'))
+                    .strip_prefix(|c| support.chantypes.contains(c))
                     .unwrap_or(active_buffer);
                 ui.label(
                     egui::RichText::new(display_name)
@@ -73,9 +81,22 @@ pub fn render_topic_bar(
                 );
                 ui.add_space(16.0);
 
-                // 3. User count
+                // 3. User count, against the limit (+l) if there is one
+                let modes = &buffer.channel_modes;
                 let user_count = buffer.users.len();
-                if user_count > 1 {
+                if let Some(limit) = modes.limit {
+                    ui.label(
+                        egui::RichText::new(format!("👥 {}/{}", user_count, limit))
+                            .size(13.0)
+                            .color(if user_count >= limit as usize {
+                                theme.warning
+                            } else {
+                                theme.text_muted
+                            }),
+                    )
+                    .on_hover_text(format!("Limited to {} users", limit));
+                    ui.add_space(8.0);
+                } else if user_count > 1 {
                     ui.label(
                         egui::RichText::new(format!("👥 {}", user_count))
                             .size(13.0)
@@ -84,10 +105,21 @@ pub fn render_topic_bar(
                     ui.add_space(8.0);
                 }
 
-                // 4. Mode badges
-                if !buffer.channel_modes.is_empty() {
-                    let mode_text = format!("+{}", buffer.channel_modes);
+                // Key (+k): joining needs it
+                if let Some(key) = &modes.key {
+                    let hint = if key.is_empty() {
+                        "Key required to join".to_string()
+                    } else {
+                        format!("Key required to join: {}", key)
+                    };
+                    ui.label(egui::RichText::new("🔑").size(13.0))
+                        .on_hover_text(hint);
+                    ui.add_space(8.0);
+                }
 
+                // 4. Mode badges
+                let mode_text = modes.mode_string();
+                if !mode_text.is_empty() {
                     let badge_response = ui.add(
                         egui::Label::new(
                             egui::RichText::new(&mode_text)
@@ -105,7 +137,7 @@ pub fn render_topic_bar(
                             ui.layer_id(),
                             egui::Id::new("mode_tooltip"),
                             |ui| {
-                                ui.label(format_mode_description(&buffer.channel_modes));
+                                ui.label(format_mode_description(modes));
                             },
                         );
                     }
@@ -138,7 +170,8 @@ pub fn render_topic_bar(
 
                 // Double-click to edit
                 if topic_response.double_clicked() {
-                    let can_edit = check_topic_permission(active_buffer, buffers, nickname);
+                    let can_edit =
+                        check_topic_permission(active_buffer, buffers, support, nickname);
                     if can_edit {
                         action = Some(TopicBarAction::EditTopic(active_buffer.to_string()));
                     } else {
@@ -149,14 +182,49 @@ pub fn render_topic_bar(
                     }
                 }
 
-                // Tooltip with full topic
+                // Tooltip with full topic and who set it
                 if !topic.is_empty() && topic_response.hovered() && !topic_response.double_clicked()
                 {
-                    topic_response.on_hover_text(topic);
+                    match &buffer.topic_set_by {
+                        Some(set_by) => topic_response.on_hover_text(format!(
+                            "{}\n\n{}",
+                            topic,
+                            topic_setter_line(set_by, buffer.topic_set_at)
+                        )),
+                        None => topic_response.on_hover_text(topic),
+                    };
                 }
 
                 // 6. Action icons (right-aligned)
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    // Kicked: offer to rejoin
+                    if buffer.left {
+                        if ui
+                            .button("Rejoin")
+                            .on_hover_text("You are no longer in this channel")
+                            .clicked()
+                        {
+                            action = Some(TopicBarAction::Rejoin(active_buffer.to_string()));
+                        }
+                        ui.add_space(8.0);
+                    }
+
+                    // Channel settings icon
+                    let settings_response = ui.add(
+                        egui::Label::new(
+                            egui::RichText::new("⚙").size(16.0).color(theme.text_muted),
+                        )
+                        .sense(egui::Sense::click()),
+                    );
+                    if settings_response.clicked() {
+                        action = Some(TopicBarAction::ChannelSettings(active_buffer.to_string()));
+                    }
+                    if settings_response.hovered() {
+                        settings_response.on_hover_text("Channel settings");
+                    }
+
+                    ui.add_space(8.0);
+
                     // Search icon
                     let search_response = ui.add(
                         egui::Label::new(
@@ -232,48 +300,92 @@ pub fn render_topic_bar(
     action
 }
 
+/// "Set by alice on 2024-03-01 12:00" (local time), for the topic tooltip
+pub fn topic_setter_line(set_by: &str, set_at: Option<DateTime<Utc>>) -> String {
+    match set_at {
+        Some(at) => format!(
+            "Set by {} on {}",
+            set_by,
+            at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        ),
+        None => format!("Set by {}", set_by),
+    }
+}
+
 /// Check if user has permission to edit topic
 ///
 /// If channel has +t mode, only half-ops (%) or higher can edit.
 /// Otherwise, any channel member can edit.
 pub fn check_topic_permission(
     channel: &str,
-    buffers: &HashMap<String, ChannelBuffer>,
+    buffers: &HashMap<IrcName, ChannelBuffer>,
+    support: &ServerSupport,
     nickname: &str,
 ) -> bool {
-    if let Some(buffer) = buffers.get(channel) {
+    if let Some(buffer) = buffers.get(&support.irc_name(channel)) {
+        let cm = support.case_mapping();
         // If +t mode, only ops/half-ops can edit
-        if buffer.channel_modes.contains('t') {
-            buffer.users.iter().any(|u| {
-                u.nick == nickname && {
-                    let rank = crate::ui::theme::prefix_rank(u.prefix);
-                    rank >= 2 // Half-op or higher
-                }
-            })
+        if buffer.channel_modes.is_set('t') {
+            buffer
+                .users
+                .iter()
+                .any(|u| cm.names_equal(&u.nick, nickname) && support.is_at_least(u.prefix, 'h'))
         } else {
             // Anyone in channel can edit
-            buffer.users.iter().any(|u| u.nick == nickname)
+            buffer
+                .users
+                .iter()
+                .any(|u| cm.names_equal(&u.nick, nickname))
         }
     } else {
         false
     }
 }
 
+/// Check if user can change channel modes (channel operator or higher)
+pub fn check_mode_permission(
+    channel: &str,
+    buffers: &HashMap<IrcName, ChannelBuffer>,
+    support: &ServerSupport,
+    nickname: &str,
+) -> bool {
+    let cm = support.case_mapping();
+    buffers
+        .get(&support.irc_name(channel))
+        .is_some_and(|buffer| {
+            buffer
+                .users
+                .iter()
+                .any(|u| cm.names_equal(&u.nick, nickname) && support.is_at_least(u.prefix, 'o'))
+        })
+}
+
 /// Format mode description for tooltip
-pub fn format_mode_description(modes: &str) -> String {
+pub fn format_mode_description(modes: &ChannelModes) -> String {
     let mut descriptions = Vec::new();
 
-    for ch in modes.chars() {
+    for ch in modes.mode_string().chars().skip(1) {
         let desc = match ch {
-            'm' => "+m: Moderated (only voiced+ can speak)",
-            't' => "+t: Topic restricted to operators",
-            'n' => "+n: No external messages",
-            's' => "+s: Secret channel (hidden from /list)",
-            'i' => "+i: Invite only",
-            'p' => "+p: Private channel",
-            'k' => "+k: Key required to join",
-            'l' => "+l: User limit set",
-            _ => continue,
+            'm' => "+m: Moderated (only voiced+ can speak)".to_string(),
+            't' => "+t: Topic restricted to operators".to_string(),
+            'n' => "+n: No external messages".to_string(),
+            's' => "+s: Secret channel (hidden from /list)".to_string(),
+            'i' => "+i: Invite only".to_string(),
+            'p' => "+p: Private channel".to_string(),
+            'r' => "+r: Registered users only".to_string(),
+            'k' => match modes.key.as_deref() {
+                Some("") | None => "+k: Key required to join".to_string(),
+                Some(key) => format!("+k: Key required to join ({})", key),
+            },
+            'l' => match modes.limit {
+                Some(limit) => format!("+l: Limited to {} users", limit),
+                None => continue,
+            },
+            // Other modes with a parameter: show its value
+            mode => match modes.params.get(&mode) {
+                Some(value) => format!("+{}: {}", mode, value),
+                None => continue,
+            },
         };
         descriptions.push(desc);
     }
@@ -290,24 +402,55 @@ mod tests {
     use super::*;
     use crate::protocol::UserInfo;
 
+    fn modes(flags: &str) -> ChannelModes {
+        ChannelModes {
+            flags: flags.chars().collect(),
+            ..ChannelModes::default()
+        }
+    }
+
     #[test]
     fn test_format_mode_description() {
         assert_eq!(
-            format_mode_description("mtn"),
-            "+m: Moderated (only voiced+ can speak)\n+t: Topic restricted to operators\n+n: No external messages"
+            format_mode_description(&modes("mtn")),
+            "+m: Moderated (only voiced+ can speak)\n+n: No external messages\n+t: Topic restricted to operators"
         );
 
-        assert_eq!(format_mode_description(""), "No modes set");
+        assert_eq!(format_mode_description(&modes("")), "No modes set");
+
+        assert_eq!(
+            format_mode_description(&modes("si")),
+            "+i: Invite only\n+s: Secret channel (hidden from /list)"
+        );
+    }
 
+    #[test]
+    fn test_format_mode_description_with_parameters() {
+        let mut with_params = modes("n");
+        with_params.key = Some("hunter2".into());
+        with_params.limit = Some(30);
+        with_params.params.insert('f', "#overflow".into());
         assert_eq!(
-            format_mode_description("si"),
-            "+s: Secret channel (hidden from /list)\n+i: Invite only"
+            format_mode_description(&with_params),
+            "+f: #overflow\n+k: Key required to join (hunter2)\n+l: Limited to 30 users\n+n: No external messages"
         );
+
+        // A key we can't see is still described
+        with_params.key = Some(String::new());
+        assert!(format_mode_description(&with_params).contains("+k: Key required to join\n"));
+    }
+
+    #[test]
+    fn test_topic_setter_line() {
+        assert_eq!(topic_setter_line("alice", None), "Set by alice");
+        let at = DateTime::from_timestamp(1709294400, 0);
+        assert!(topic_setter_line("alice", at).starts_with("Set by alice on 2024-0"));
     }
 
     #[test]
     fn test_check_topic_permission_no_t_mode() {
         // Without +t, anyone in channel can edit
+        let support = ServerSupport::default();
         let mut buffers = HashMap::new();
         let mut buffer = ChannelBuffer::new();
         buffer.users = vec![
@@ -320,19 +463,23 @@ mod tests {
                 prefix: Some('@'),
             },
         ];
-        buffers.insert("#test".to_string(), buffer);
+        buffers.insert(support.irc_name("#test"), buffer);
 
-        assert!(check_topic_permission("#test", &buffers, "alice"));
-        assert!(check_topic_permission("#test", &buffers, "bob"));
-        assert!(!check_topic_permission("#test", &buffers, "charlie")); // Not in channel
+        assert!(check_topic_permission("#test", &buffers, &support, "alice"));
+        assert!(check_topic_permission("#test", &buffers, &support, "bob"));
+        assert!(!check_topic_permission(
+            "#test", &buffers, &support, "charlie"
+        )); // Not in channel
     }
 
     #[test]
     fn test_check_topic_permission_with_t_mode() {
         // With +t, only ops+ can edit
+        let mut support = ServerSupport::default();
+        support.apply(&["PREFIX=(ohv)@%+"]);
         let mut buffers = HashMap::new();
         let mut buffer = ChannelBuffer::new();
-        buffer.channel_modes = "t".to_string();
+        buffer.channel_modes.flags.insert('t');
         buffer.users = vec![
             UserInfo {
                 nick: "alice".to_string(),
@@ -351,11 +498,39 @@ mod tests {
                 prefix: Some('+'),
             },
         ];
-        buffers.insert("#test".to_string(), buffer);
+        buffers.insert(support.irc_name("#test"), buffer);
+
+        assert!(!check_topic_permission(
+            "#test", &buffers, &support, "alice"
+        )); // Regular user
+        assert!(check_topic_permission("#test", &buffers, &support, "bob")); // Op
+        assert!(check_topic_permission("#TEST", &buffers, &support, "Bob")); // Any case
+        assert!(check_topic_permission("#test", &buffers, &support, "carol")); // Half-op
+        assert!(!check_topic_permission("#test", &buffers, &support, "dave")); // Voice only - not enough
+    }
 
-        assert!(!check_topic_permission("#test", &buffers, "alice")); // Regular user
-        assert!(check_topic_permission("#test", &buffers, "bob")); // Op
-        assert!(check_topic_permission("#test", &buffers, "carol")); // Half-op
-        assert!(!check_topic_permission("#test", &buffers, "dave")); // Voice only - not enough
+    #[test]
+    fn test_check_mode_permission() {
+        let mut support = ServerSupport::default();
+        support.apply(&["PREFIX=(qohv)~@%+"]);
+        let mut buffers = HashMap::new();
+        let mut buffer = ChannelBuffer::new();
+        buffer.users = ["~owner", "@op", "%half", "user"]
+            .iter()
+            .map(|entry| {
+                let (prefix, nick) = support.split_prefix(entry);
+                UserInfo {
+                    nick: nick.to_string(),
+                    prefix,
+                }
+            })
+            .collect();
+        buffers.insert(support.irc_name("#test"), buffer);
+
+        assert!(check_mode_permission("#test", &buffers, &support, "owner"));
+        assert!(check_mode_permission("#Test", &buffers, &support, "OP"));
+        assert!(!check_mode_permission("#test", &buffers, &support, "half"));
+        assert!(!check_mode_permission("#test", &buffers, &support, "user"));
+        assert!(!check_mode_permission("#other", &buffers, &support, "op"));
     }
 }
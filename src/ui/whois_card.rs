@@ -0,0 +1,230 @@
+//! WHOIS user info card, opened from the user list context menu.
+//! The same reply is also written into the active buffer as text lines.
+
+use chrono::Local;
+use eframe::egui;
+use std::time::Duration;
+
+use crate::protocol::WhoisInfo;
+use crate::ui::theme::{self, SlircTheme};
+
+/// A WHOIS reply as text lines, for the message view and the system log
+pub fn whois_lines(info: &WhoisInfo) -> Vec<String> {
+    let mut lines = vec![format!(
+        "{} is {}@{} ({})",
+        info.nick, info.user, info.host, info.realname
+    )];
+    if let Some(account) = &info.account {
+        lines.push(format!("Account: {}", account));
+    }
+    if let Some(server) = &info.server {
+        match &info.server_info {
+            Some(about) => lines.push(format!("Server: {} ({})", server, about)),
+            None => lines.push(format!("Server: {}", server)),
+        }
+    }
+    if !info.channels.is_empty() {
+        lines.push(format!("Channels: {}", info.channels.join(" ")));
+    }
+    if let Some(idle) = info.idle {
+        lines.push(format!("Idle: {}", format_idle(idle)));
+    }
+    if let Some(signon) = info.signon {
+        lines.push(format!(
+            "Signed on: {}",
+            signon.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        ));
+    }
+    if let Some(actual) = &info.actual_host {
+        lines.push(format!("Actual host: {}", actual));
+    }
+    if info.secure {
+        lines.push("Using a secure connection".to_string());
+    }
+    if let Some(certfp) = &info.certfp {
+        lines.push(format!("Certificate fingerprint: {}", certfp));
+    }
+    if info.oper {
+        lines.push("Is an IRC operator".to_string());
+    }
+    lines
+}
+
+/// Idle time in its two largest units, e.g. "3h 12m" or "45s"
+pub fn format_idle(idle: Duration) -> String {
+    let secs = idle.as_secs();
+    let units = [
+        (secs / 86_400, "d"),
+        (secs / 3_600 % 24, "h"),
+        (secs / 60 % 60, "m"),
+        (secs % 60, "s"),
+    ];
+    let parts: Vec<String> = units
+        .iter()
+        .skip_while(|(value, _)| *value == 0)
+        .take(2)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// Render the info card for `nick`. `info` is `None` until the reply
+/// arrives. `open` is cleared when the card is closed.
+pub fn render_whois_card(
+    ctx: &egui::Context,
+    nick: &str,
+    info: Option<&WhoisInfo>,
+    open: &mut bool,
+    theme: &SlircTheme,
+) {
+    egui::Window::new(egui::RichText::new(nick).color(theme::nick_color(nick)))
+        .id(egui::Id::new(("whois_card", nick)))
+        .open(open)
+        .collapsible(false)
+        .resizable(false)
+        .default_width(280.0)
+        .show(ctx, |ui| {
+            let Some(info) = info else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(egui::RichText::new("Looking up…").color(theme.text_muted));
+                });
+                return;
+            };
+
+            ui.label(
+                egui::RichText::new(&info.realname)
+                    .size(15.0)
+                    .strong()
+                    .color(theme.text_primary),
+            );
+            ui.label(
+                egui::RichText::new(format!("{}@{}", info.user, info.host))
+                    .size(12.0)
+                    .monospace()
+                    .color(theme.text_secondary),
+            );
+
+            // Badges
+            ui.horizontal(|ui| {
+                if info.oper {
+                    ui.label(
+                        egui::RichText::new("IRC operator")
+                            .size(11.0)
+                            .color(theme.warning),
+                    );
+                }
+                if info.secure {
+                    ui.label(
+                        egui::RichText::new("🔒 TLS")
+                            .size(11.0)
+                            .color(theme.success),
+                    );
+                }
+            });
+            ui.add_space(6.0);
+
+            egui::Grid::new(("whois_card_fields", nick))
+                .num_columns(2)
+                .spacing([12.0, 4.0])
+                .show(ui, |ui| {
+                    let mut field = |label: &str, value: String| {
+                        ui.label(
+                            egui::RichText::new(label)
+                                .size(12.0)
+                                .color(theme.text_muted),
+                        );
+                        ui.label(
+                            egui::RichText::new(value)
+                                .size(12.0)
+                                .color(theme.text_primary),
+                        );
+                        ui.end_row();
+                    };
+                    if let Some(account) = &info.account {
+                        field("Account", account.clone());
+                    }
+                    if let Some(server) = &info.server {
+                        field("Server", server.clone());
+                    }
+                    if let Some(idle) = info.idle {
+                        field("Idle", format_idle(idle));
+                    }
+                    if let Some(signon) = info.signon {
+                        field(
+                            "Signed on",
+                            signon
+                                .with_timezone(&Local)
+                                .format("%Y-%m-%d %H:%M")
+                                .to_string(),
+                        );
+                    }
+                    if let Some(actual) = &info.actual_host {
+                        field("Actual host", actual.clone());
+                    }
+                    if let Some(certfp) = &info.certfp {
+                        field("Cert", certfp.clone());
+                    }
+                });
+
+            if !info.channels.is_empty() {
+                ui.add_space(6.0);
+                ui.label(
+                    egui::RichText::new("Channels")
+                        .size(12.0)
+                        .color(theme.text_muted),
+                );
+                ui.horizontal_wrapped(|ui| {
+                    for channel in &info.channels {
+                        ui.label(egui::RichText::new(channel).size(12.0).color(theme.accent));
+                    }
+                });
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_idle() {
+        assert_eq!(format_idle(Duration::from_secs(0)), "0s");
+        assert_eq!(format_idle(Duration::from_secs(45)), "45s");
+        assert_eq!(format_idle(Duration::from_secs(125)), "2m 5s");
+        assert_eq!(
+            format_idle(Duration::from_secs(3 * 3600 + 12 * 60 + 7)),
+            "3h 12m"
+        );
+        assert_eq!(format_idle(Duration::from_secs(86_400 + 60)), "1d 0h");
+    }
+
+    #[test]
+    fn test_whois_lines() {
+        let info = WhoisInfo {
+            nick: "alice".into(),
+            user: "al".into(),
+            host: "host.example".into(),
+            realname: "Alice".into(),
+            server: Some("irc.example.net".into()),
+            channels: vec!["@#rust".into(), "#irc".into()],
+            account: Some("alice".into()),
+            secure: true,
+            ..WhoisInfo::default()
+        };
+        assert_eq!(
+            whois_lines(&info),
+            [
+                "alice is al@host.example (Alice)",
+                "Account: alice",
+                "Server: irc.example.net",
+                "Channels: @#rust #irc",
+                "Using a secure connection",
+            ]
+        );
+    }
+}
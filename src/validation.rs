@@ -1,19 +1,30 @@
 //! Input validation for IRC protocol compliance
 
-/// Validates an IRC channel name according to RFC 2812
-pub fn validate_channel_name(name: &str) -> Result<(), String> {
+use crate::server_support::ServerSupport;
+
+/// Validates an IRC channel name against the server's CHANTYPES and
+/// CHANNELLEN (RFC 2812 rules where the server announced nothing)
+pub fn validate_channel_name(name: &str, support: &ServerSupport) -> Result<(), String> {
     if name.is_empty() {
         return Err("Channel name cannot be empty".to_string());
     }
 
-    // Channel must start with # or &
-    if !name.starts_with('#') && !name.starts_with('&') {
-        return Err("Channel name must start with # or &".to_string());
+    // Channel must start with one of the server's channel types
+    if !support.is_channel(name) {
+        let types: Vec<String> = support.chantypes.chars().map(String::from).collect();
+        return Err(format!(
+            "Channel name must start with {}",
+            types.join(" or ")
+        ));
     }
 
     // Maximum length per RFC 2812 is 50 characters
-    if name.len() > 50 {
-        return Err("Channel name too long (max 50 characters)".to_string());
+    let max_len = support.channellen.unwrap_or(50);
+    if name.len() > max_len {
+        return Err(format!(
+            "Channel name too long (max {} characters)",
+            max_len
+        ));
     }
 
     // Channel names cannot contain spaces, commas, or control characters
@@ -136,15 +147,23 @@ mod tests {
 
     #[test]
     fn test_validate_channel_name() {
-        assert!(validate_channel_name("#test").is_ok());
-        assert!(validate_channel_name("&local").is_ok());
-        assert!(validate_channel_name("#rust-lang").is_ok());
-
-        assert!(validate_channel_name("").is_err());
-        assert!(validate_channel_name("test").is_err()); // Missing #
-        assert!(validate_channel_name("#test channel").is_err()); // Space
-        assert!(validate_channel_name("#test,other").is_err()); // Comma
-        assert!(validate_channel_name(&"#".to_string().repeat(51)).is_err()); // Too long
+        let support = ServerSupport::default();
+        assert!(validate_channel_name("#test", &support).is_ok());
+        assert!(validate_channel_name("&local", &support).is_ok());
+        assert!(validate_channel_name("#rust-lang", &support).is_ok());
+
+        assert!(validate_channel_name("", &support).is_err());
+        assert!(validate_channel_name("test", &support).is_err()); // Missing #
+        assert!(validate_channel_name("#test channel", &support).is_err()); // Space
+        assert!(validate_channel_name("#test,other", &support).is_err()); // Comma
+        assert!(validate_channel_name(&"#".to_string().repeat(51), &support).is_err()); // Too long
+
+        // The server's CHANTYPES and CHANNELLEN replace the defaults
+        let mut support = ServerSupport::default();
+        support.apply(&["CHANTYPES=!+", "CHANNELLEN=8"]);
+        assert!(validate_channel_name("+modeless", &support).is_err()); // Too long
+        assert!(validate_channel_name("+short", &support).is_ok());
+        assert!(validate_channel_name("#test", &support).is_err());
     }
 
     #[test]
@@ -2,26 +2,51 @@
 //!
 //! Tests for event processing, command handling, and UI state management.
 
-use crossbeam_channel::unbounded;
+use chrono::{TimeZone, Utc};
+use crossbeam_channel::{unbounded, Receiver, SendError, Sender, TryRecvError};
 use slirc_client::app::SlircApp;
-use slirc_client::buffer::ChannelBuffer;
+use slirc_client::buffer::{ChannelBuffer, Delivery};
+use slirc_client::channel_modes::{ChannelModes, ModeChange};
 use slirc_client::config::DEFAULT_SERVER;
-use slirc_client::protocol::{BackendAction, GuiEvent, UserInfo};
+use slirc_client::protocol::{
+    BackendAction, GuiEvent, MessageTags, NetworkAction, NetworkEvent, UserInfo, WhoisInfo,
+};
+use slirc_client::server_support::ServerSupport;
 use slirc_client::state::ClientState;
 use std::collections::HashSet;
 
-/// Helper to create a test SlircApp instance
-fn create_test_app() -> (
-    SlircApp,
-    crossbeam_channel::Sender<GuiEvent>,
-    crossbeam_channel::Receiver<BackendAction>,
-) {
-    let (action_tx, action_rx) = unbounded::<BackendAction>();
-    let (event_tx, event_rx) = unbounded::<GuiEvent>();
+/// Network id used for the test session
+const NET: &str = "testnet";
 
-    // Create state with pre-populated System buffer
+/// Sends events to the app as if they came from the `NET` connection
+struct TestEvents(Sender<NetworkEvent>);
+
+impl TestEvents {
+    fn send(&self, event: GuiEvent) -> Result<(), SendError<NetworkEvent>> {
+        self.0.send(NetworkEvent::new(NET, event))
+    }
+}
+
+/// Receives actions the app sent, checking they target the `NET` connection
+struct TestActions(Receiver<NetworkAction>);
+
+impl TestActions {
+    fn try_recv(&self) -> Result<BackendAction, TryRecvError> {
+        let NetworkAction { network, action } = self.0.try_recv()?;
+        assert_eq!(network, NET);
+        Ok(action)
+    }
+}
+
+/// Helper to create a test SlircApp instance with an active `NET` session
+fn create_test_app() -> (SlircApp, TestEvents, TestActions) {
+    let (action_tx, action_rx) = unbounded::<NetworkAction>();
+    let (event_tx, event_rx) = unbounded::<NetworkEvent>();
+
+    // Create state with a session for the test network
     let mut state = ClientState::new();
     state.logger = None; // No logger in tests
+    state.session_mut(NET).our_nick = "tester".into();
 
     let app = SlircApp {
         state,
@@ -36,7 +61,13 @@ fn create_test_app() -> (
         context_menu_visible: false,
         context_menu_target: None,
         open_windows: HashSet::new(),
+        whois_card: None,
         theme: String::from("dark"),
+        timestamp_format: slirc_client::config::TimestampFormat::default(),
+        scroll_target: None,
+        viewed_buffer: None,
+        unsent_read: None,
+        read_sent_at: None,
         show_channel_list: true,
         show_user_list: true,
         quick_switcher: slirc_client::ui::quick_switcher::QuickSwitcher::default(),
@@ -46,7 +77,7 @@ fn create_test_app() -> (
         shortcuts: slirc_client::ui::shortcuts::ShortcutRegistry::new(),
         show_shortcuts_help: false,
     };
-    (app, event_tx, action_rx)
+    (app, TestEvents(event_tx), TestActions(action_rx))
 }
 
 #[test]
@@ -105,8 +136,8 @@ fn test_names_event_populates_users() {
     });
     app.process_events();
     // Buffer should be created and populated
-    assert!(app.state.buffers.contains_key("#test"));
-    let buf = app.state.buffers.get("#test").unwrap();
+    assert!(app.state.sessions[NET].buffers.contains_key("#test"));
+    let buf = app.state.sessions[NET].buffers.get("#test").unwrap();
     assert_eq!(buf.users.len(), 3);
     assert!(buf
         .users
@@ -127,7 +158,10 @@ fn test_user_mode_event_updates_prefix() {
         nick: "alice".into(),
         prefix: None,
     });
-    app.state.buffers.insert("#test".into(), buf);
+    app.state
+        .session_mut(NET)
+        .buffers
+        .insert("#test".into(), buf);
 
     let _ = event_tx.send(GuiEvent::UserMode {
         channel: "#test".into(),
@@ -136,7 +170,7 @@ fn test_user_mode_event_updates_prefix() {
         added: true,
     });
     app.process_events();
-    let b = app.state.buffers.get("#test").unwrap();
+    let b = app.state.sessions[NET].buffers.get("#test").unwrap();
     assert!(b
         .users
         .iter()
@@ -150,7 +184,7 @@ fn test_user_mode_event_updates_prefix() {
         added: false,
     });
     app.process_events();
-    let b2 = app.state.buffers.get("#test").unwrap();
+    let b2 = app.state.sessions[NET].buffers.get("#test").unwrap();
     assert!(b2
         .users
         .iter()
@@ -165,24 +199,25 @@ fn test_topic_event_updates_buffer_topic() {
         topic: "New Topic".into(),
     });
     app.process_events();
-    let b = app.state.buffers.get("#test").unwrap();
+    let b = app.state.sessions[NET].buffers.get("#test").unwrap();
     assert_eq!(b.topic, "New Topic");
 }
 
 #[test]
 fn test_whois_command_sends_action() {
     let (mut app, _, action_rx) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
 
     // Set the message input to a whois command and ensure the action is sent
     app.input.message_input = String::from("/whois someuser");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     let action = action_rx.try_recv().unwrap();
     match action {
@@ -191,6 +226,103 @@ fn test_whois_command_sends_action() {
     }
 }
 
+#[test]
+fn test_message_keeps_server_time() {
+    let (mut app, event_tx, _) = create_test_app();
+    app.state.session_mut(NET).ensure_buffer("#test");
+    let sent = Utc.with_ymd_and_hms(2024, 3, 1, 12, 34, 56).unwrap();
+    let _ = event_tx.send(GuiEvent::MessageReceived {
+        target: "#test".into(),
+        sender: "alice".into(),
+        text: "from the past".into(),
+        time: sent,
+        tags: MessageTags::default(),
+    });
+    app.process_events();
+    let buf = &app.state.sessions[NET].buffers["#test"];
+    assert_eq!(buf.messages.last().unwrap().timestamp, sent);
+}
+
+#[test]
+fn test_server_info_channel_types_and_prefixes() {
+    let (mut app, event_tx, _) = create_test_app();
+    let mut support = ServerSupport::default();
+    support.apply(&["PREFIX=(Yov)!@+", "CHANTYPES=#!", "STATUSMSG=@+"]);
+    let _ = event_tx.send(GuiEvent::ServerInfo(support));
+    let _ = event_tx.send(GuiEvent::Names {
+        channel: "!chan".into(),
+        names: vec![
+            UserInfo {
+                nick: "op".into(),
+                prefix: Some('@'),
+            },
+            UserInfo {
+                nick: "boss".into(),
+                prefix: Some('!'),
+            },
+        ],
+    });
+    for target in ["!chan", "@!chan"] {
+        let _ = event_tx.send(GuiEvent::MessageReceived {
+            target: target.into(),
+            sender: "alice".into(),
+            text: "hi".into(),
+            time: Utc::now(),
+            tags: MessageTags::default(),
+        });
+    }
+    app.process_events();
+
+    let session = &app.state.sessions[NET];
+    assert!(session.support.is_channel("!chan"));
+    let buf = &session.buffers["!chan"];
+    // Both lines land in the channel, whose speaker joins the user list
+    assert_eq!(buf.messages.len(), 2);
+    assert!(!session.buffers.contains_key("alice"));
+    let nicks: Vec<_> = buf.users.iter().map(|u| u.nick.as_str()).collect();
+    assert_eq!(nicks, ["boss", "op", "alice"]);
+}
+
+#[test]
+fn test_names_compare_under_casemapping() {
+    let (mut app, event_tx, _) = create_test_app();
+    let _ = event_tx.send(GuiEvent::Names {
+        channel: "#Rust".into(),
+        names: vec![UserInfo {
+            nick: "alice[m]".into(),
+            prefix: None,
+        }],
+    });
+    for (target, sender, text) in [
+        ("#rust", "bob", "TESTER: ping"),
+        ("tester", "Bob", "hi"),
+        ("tester", "bob", "again"),
+    ] {
+        let _ = event_tx.send(GuiEvent::MessageReceived {
+            target: target.into(),
+            sender: sender.into(),
+            text: text.into(),
+            time: Utc::now(),
+            tags: MessageTags::default(),
+        });
+    }
+    let _ = event_tx.send(GuiEvent::UserQuit {
+        nick: "ALICE{M}".into(),
+        message: None,
+        time: Utc::now(),
+    });
+    app.process_events();
+
+    let session = &app.state.sessions[NET];
+    // One buffer per channel and per query, shown as first seen
+    assert_eq!(session.buffers_order, ["#Rust", "Bob"]);
+    let buf = &session.buffers["#rust"];
+    assert!(buf.has_highlight);
+    let nicks: Vec<_> = buf.users.iter().map(|u| u.nick.as_str()).collect();
+    assert_eq!(nicks, ["bob"]);
+    assert_eq!(session.buffers["bob"].messages.len(), 2);
+}
+
 #[test]
 fn test_notice_message_type() {
     use slirc_client::buffer::MessageType;
@@ -199,9 +331,11 @@ fn test_notice_message_type() {
         target: "System".into(),
         sender: "-server-".into(),
         text: "This is a notice".into(),
+        time: Utc::now(),
+        tags: MessageTags::default(),
     });
     app.process_events();
-    let buf = app.state.buffers.get("-server-").unwrap();
+    let buf = app.state.sessions[NET].buffers.get("-server-").unwrap();
     assert!(!buf.messages.is_empty());
     assert_eq!(buf.messages.last().unwrap().msg_type, MessageType::Notice);
 }
@@ -224,21 +358,20 @@ fn test_status_messages_on_connect() {
 #[test]
 fn test_topic_command_set_and_show() {
     let (mut app, _, action_rx) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.state.active_buffer = "#test".into();
-    app.state
-        .buffers
-        .insert("#test".into(), ChannelBuffer::new());
+    app.state.session_mut(NET).ensure_buffer("#test");
 
     // Set the message input to a topic change command and ensure the action is sent
     app.input.message_input = String::from("/topic hello world");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     let action = action_rx.try_recv().unwrap();
     match action {
@@ -250,15 +383,21 @@ fn test_topic_command_set_and_show() {
     }
 
     // Now test that /topic with no args displays the topic in system_log
-    app.state.buffers.get_mut("#test").unwrap().topic = "Displayed Topic".into();
+    app.state
+        .session_mut(NET)
+        .buffers
+        .get_mut("#test")
+        .unwrap()
+        .topic = "Displayed Topic".into();
     app.input.message_input = String::from("/topic");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     assert!(app
         .state
@@ -270,20 +409,19 @@ fn test_topic_command_set_and_show() {
 #[test]
 fn test_kick_command_sends_action() {
     let (mut app, _, action_rx) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.state.active_buffer = "#test".into();
-    app.state
-        .buffers
-        .insert("#test".into(), ChannelBuffer::new());
+    app.state.session_mut(NET).ensure_buffer("#test");
 
     app.input.message_input = String::from("/kick alice Spamming");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     let action = action_rx.try_recv().unwrap();
     match action {
@@ -303,24 +441,23 @@ fn test_kick_command_sends_action() {
 #[test]
 fn test_me_command_sends_action_ctcp() {
     let (mut app, _, action_rx) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.state.active_buffer = "#test".into();
-    app.state
-        .buffers
-        .insert("#test".into(), ChannelBuffer::new());
+    app.state.session_mut(NET).ensure_buffer("#test");
 
     app.input.message_input = String::from("/me does something");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     let action = action_rx.try_recv().unwrap();
     match action {
-        BackendAction::SendMessage { target, text } => {
+        BackendAction::SendMessage { target, text, .. } => {
             assert_eq!(target, "#test");
             assert_eq!(text, "\x01ACTION does something\x01");
         }
@@ -328,22 +465,48 @@ fn test_me_command_sends_action_ctcp() {
     }
 }
 
+#[test]
+fn test_notice_command_sends_action() {
+    let (mut app, _, action_rx) = create_test_app();
+    app.state.session_mut(NET).is_connected = true;
+
+    app.input.message_input = String::from("/notice alice hello there");
+    assert!(slirc_client::commands::handle_user_command(
+        &app.input.message_input,
+        NET,
+        &app.state.active_buffer,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
+        &app.action_tx,
+        &mut app.state.system_log,
+    ));
+    match action_rx.try_recv().unwrap() {
+        BackendAction::SendNotice { target, text } => {
+            assert_eq!(target, "alice");
+            assert_eq!(text, "hello there");
+        }
+        _ => panic!("Expected SendNotice action"),
+    }
+}
+
 #[test]
 fn test_nick_command_sends_action() {
     let (mut app, _, action_rx) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.connection.nickname = "oldnick".into();
 
     app.input.message_input = String::from("/nick newnick");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
-    assert_eq!(app.connection.nickname, "newnick");
+    // Unchanged until the server confirms the new nick
+    assert_eq!(app.connection.nickname, "oldnick");
     let action = action_rx.try_recv().unwrap();
     match action {
         BackendAction::Nick(nick) => {
@@ -356,16 +519,17 @@ fn test_nick_command_sends_action() {
 #[test]
 fn test_quit_command_sends_action() {
     let (mut app, _, action_rx) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
 
     app.input.message_input = String::from("/quit Goodbye everyone");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     let action = action_rx.try_recv().unwrap();
     match action {
@@ -379,16 +543,17 @@ fn test_quit_command_sends_action() {
 #[test]
 fn test_quit_command_without_reason() {
     let (mut app, _, action_rx) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
 
     app.input.message_input = String::from("/quit");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     let action = action_rx.try_recv().unwrap();
     match action {
@@ -407,11 +572,12 @@ fn test_help_command_shows_usage() {
     app.input.message_input = String::from("/help");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     assert!(app.state.system_log.len() > original_log_size);
     assert!(app
@@ -430,11 +596,12 @@ fn test_unknown_command_logs_error() {
     app.input.message_input = String::from("/foobar");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     assert!(app.state.system_log.len() > original_log_size);
     assert!(app
@@ -454,11 +621,12 @@ fn test_msg_command_without_message() {
     app.input.message_input = String::from("/msg alice");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     assert!(app.state.system_log.len() > original_log_size);
     assert!(app.state.system_log.last().unwrap().contains("Usage"));
@@ -467,20 +635,19 @@ fn test_msg_command_without_message() {
 #[test]
 fn test_part_without_args_parts_active_channel() {
     let (mut app, _, action_rx) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.state.active_buffer = "#test".into();
-    app.state
-        .buffers
-        .insert("#test".into(), ChannelBuffer::new());
+    app.state.session_mut(NET).ensure_buffer("#test");
 
     app.input.message_input = String::from("/part");
     assert!(slirc_client::commands::handle_user_command(
         &app.input.message_input,
+        NET,
         &app.state.active_buffer,
-        &app.state.buffers,
+        &app.state.sessions[NET].buffers,
+        &app.state.sessions[NET].support,
         &app.action_tx,
         &mut app.state.system_log,
-        &mut app.connection.nickname,
     ));
     let action = action_rx.try_recv().unwrap();
     match action {
@@ -495,22 +662,21 @@ fn test_part_without_args_parts_active_channel() {
 #[test]
 fn test_user_joined_event() {
     let (mut app, event_tx, _) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.state.active_buffer = "#test".into();
-    app.state
-        .buffers
-        .insert("#test".into(), ChannelBuffer::new());
+    app.state.session_mut(NET).ensure_buffer("#test");
 
     event_tx
         .send(GuiEvent::UserJoined {
             channel: "#test".to_string(),
             nick: "alice".to_string(),
+            time: Utc::now(),
         })
         .unwrap();
 
     app.process_events();
 
-    let buffer = app.state.buffers.get("#test").unwrap();
+    let buffer = app.state.sessions[NET].buffers.get("#test").unwrap();
     assert!(buffer.users.iter().any(|u| u.nick == "alice"));
     assert!(buffer
         .messages
@@ -521,26 +687,30 @@ fn test_user_joined_event() {
 #[test]
 fn test_user_parted_event() {
     let (mut app, event_tx, _) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.state.active_buffer = "#test".into();
     let mut buffer = ChannelBuffer::new();
     buffer.users.push(UserInfo {
         nick: "alice".to_string(),
         prefix: None,
     });
-    app.state.buffers.insert("#test".into(), buffer);
+    app.state
+        .session_mut(NET)
+        .buffers
+        .insert("#test".into(), buffer);
 
     event_tx
         .send(GuiEvent::UserParted {
             channel: "#test".to_string(),
             nick: "alice".to_string(),
             message: Some("Goodbye".to_string()),
+            time: Utc::now(),
         })
         .unwrap();
 
     app.process_events();
 
-    let buffer = app.state.buffers.get("#test").unwrap();
+    let buffer = app.state.sessions[NET].buffers.get("#test").unwrap();
     assert!(!buffer.users.iter().any(|u| u.nick == "alice"));
     assert!(buffer
         .messages
@@ -552,25 +722,29 @@ fn test_user_parted_event() {
 #[test]
 fn test_user_quit_event() {
     let (mut app, event_tx, _) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.state.active_buffer = "#test".into();
     let mut buffer = ChannelBuffer::new();
     buffer.users.push(UserInfo {
         nick: "bob".to_string(),
         prefix: None,
     });
-    app.state.buffers.insert("#test".into(), buffer);
+    app.state
+        .session_mut(NET)
+        .buffers
+        .insert("#test".into(), buffer);
 
     event_tx
         .send(GuiEvent::UserQuit {
             nick: "bob".to_string(),
             message: Some("Connection reset".to_string()),
+            time: Utc::now(),
         })
         .unwrap();
 
     app.process_events();
 
-    let buffer = app.state.buffers.get("#test").unwrap();
+    let buffer = app.state.sessions[NET].buffers.get("#test").unwrap();
     assert!(!buffer.users.iter().any(|u| u.nick == "bob"));
     assert!(buffer.messages.iter().any(|m| m.text.contains("bob quit")));
     assert!(buffer
@@ -582,7 +756,8 @@ fn test_user_quit_event() {
 #[test]
 fn test_nick_changed_event() {
     let (mut app, event_tx, _) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
+    app.state.session_mut(NET).our_nick = "alice".into();
     app.connection.nickname = "alice".into();
     app.state.active_buffer = "#test".into();
     let mut buffer = ChannelBuffer::new();
@@ -590,7 +765,10 @@ fn test_nick_changed_event() {
         nick: "alice".to_string(),
         prefix: None,
     });
-    app.state.buffers.insert("#test".into(), buffer);
+    app.state
+        .session_mut(NET)
+        .buffers
+        .insert("#test".into(), buffer);
 
     event_tx
         .send(GuiEvent::NickChanged {
@@ -602,7 +780,7 @@ fn test_nick_changed_event() {
     app.process_events();
 
     assert_eq!(app.connection.nickname, "alice_away");
-    let buffer = app.state.buffers.get("#test").unwrap();
+    let buffer = app.state.sessions[NET].buffers.get("#test").unwrap();
     assert!(buffer.users.iter().any(|u| u.nick == "alice_away"));
     assert!(!buffer.users.iter().any(|u| u.nick == "alice"));
     assert!(buffer
@@ -611,20 +789,69 @@ fn test_nick_changed_event() {
         .any(|m| m.text.contains("alice is now known as alice_away")));
 }
 
+#[test]
+fn test_other_user_nick_change_keeps_our_nick() {
+    let (mut app, event_tx, _) = create_test_app();
+    app.connection.nickname = "tester".into();
+
+    event_tx
+        .send(GuiEvent::NickChanged {
+            old: "bob".to_string(),
+            new: "bobby".to_string(),
+        })
+        .unwrap();
+
+    app.process_events();
+
+    assert_eq!(app.connection.nickname, "tester");
+    assert_eq!(app.state.sessions[NET].our_nick, "tester");
+}
+
+#[test]
+fn test_nick_error_event() {
+    let (mut app, event_tx, _) = create_test_app();
+
+    event_tx
+        .send(GuiEvent::NickError {
+            nick: "taken".to_string(),
+            reason: "Nickname is already in use".to_string(),
+        })
+        .unwrap();
+
+    app.process_events();
+
+    assert!(app
+        .state
+        .system_log
+        .iter()
+        .any(|m| m.contains("taken") && m.contains("already in use")));
+    assert!(!app.state.status_messages.is_empty());
+
+    // Our nick is untouched and the user is asked for another one
+    assert_eq!(app.state.sessions[NET].our_nick, "tester");
+    let dialog = app
+        .dialogs
+        .nick_change_dialog
+        .as_ref()
+        .expect("nick dialog");
+    assert_eq!(dialog.nick_input, "taken");
+    assert_eq!(dialog.error.as_deref(), Some("Nickname is already in use"));
+}
+
 #[test]
 fn test_connected_event() {
     let (mut app, event_tx, _) = create_test_app();
-    app.state.is_connected = false;
+    app.state.session_mut(NET).is_connected = false;
     app.connection.server = "irc.example.com".into();
-    // Set state.server_name to simulate connection initiation
-    app.state.server_name = "irc.example.com".into();
+    // Set the session's server_name to simulate connection initiation
+    app.state.session_mut(NET).server_name = "irc.example.com".into();
 
     event_tx.send(GuiEvent::Connected).unwrap();
 
     app.process_events();
 
-    assert!(app.state.is_connected);
-    assert!(app.state.expanded_networks.contains("irc.example.com"));
+    assert!(app.state.is_connected());
+    assert!(app.state.expanded_networks.contains(NET));
     assert!(app.state.system_log.iter().any(|m| m.contains("Connected")));
     assert!(!app.state.status_messages.is_empty());
 }
@@ -632,7 +859,7 @@ fn test_connected_event() {
 #[test]
 fn test_disconnected_event() {
     let (mut app, event_tx, _) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
 
     event_tx
         .send(GuiEvent::Disconnected("Connection lost".to_string()))
@@ -640,7 +867,7 @@ fn test_disconnected_event() {
 
     app.process_events();
 
-    assert!(!app.state.is_connected);
+    assert!(!app.state.is_connected());
     assert!(app
         .state
         .system_log
@@ -653,6 +880,78 @@ fn test_disconnected_event() {
         .any(|m| m.contains("Connection lost")));
 }
 
+#[test]
+fn test_reconnecting_event_sets_countdown() {
+    let (mut app, event_tx, _) = create_test_app();
+
+    event_tx
+        .send(GuiEvent::Disconnected(
+            "Connection closed by server".to_string(),
+        ))
+        .unwrap();
+    event_tx
+        .send(GuiEvent::Reconnecting {
+            attempt: 2,
+            delay: std::time::Duration::from_secs(4),
+        })
+        .unwrap();
+
+    app.process_events();
+
+    let (attempt, _) = app.state.sessions[NET]
+        .reconnect_at
+        .expect("reconnect should be pending");
+    assert_eq!(attempt, 2);
+    assert!(app
+        .state
+        .system_log
+        .iter()
+        .any(|m| m.contains("Reconnecting") && m.contains("attempt 2")));
+
+    // Registering again clears the pending reconnect
+    event_tx.send(GuiEvent::Connected).unwrap();
+    app.process_events();
+    assert!(app.state.sessions[NET].reconnect_at.is_none());
+}
+
+#[test]
+fn test_lag_event_updates_session() {
+    let (mut app, event_tx, _) = create_test_app();
+    app.state.session_mut(NET).is_connected = true;
+
+    event_tx
+        .send(GuiEvent::Lag(std::time::Duration::from_millis(42)))
+        .unwrap();
+    app.process_events();
+    assert_eq!(
+        app.state.sessions[NET].lag,
+        Some(std::time::Duration::from_millis(42))
+    );
+
+    // Lag is meaningless once the link is gone
+    event_tx
+        .send(GuiEvent::Disconnected(
+            "Ping timeout: 120 seconds".to_string(),
+        ))
+        .unwrap();
+    app.process_events();
+    assert!(app.state.sessions[NET].lag.is_none());
+}
+
+#[test]
+fn test_send_queue_depth_event() {
+    let (mut app, event_tx, _) = create_test_app();
+    app.state.session_mut(NET).is_connected = true;
+
+    event_tx.send(GuiEvent::SendQueueDepth(12)).unwrap();
+    app.process_events();
+    assert_eq!(app.state.sessions[NET].send_queue_depth, 12);
+
+    event_tx.send(GuiEvent::SendQueueDepth(0)).unwrap();
+    app.process_events();
+    assert_eq!(app.state.sessions[NET].send_queue_depth, 0);
+}
+
 #[test]
 fn test_error_event() {
     let (mut app, event_tx, _) = create_test_app();
@@ -696,7 +995,7 @@ fn test_raw_message_event() {
 #[test]
 fn test_joined_channel_event() {
     let (mut app, event_tx, _) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.state.active_buffer = "System".into();
 
     event_tx
@@ -706,7 +1005,7 @@ fn test_joined_channel_event() {
     app.process_events();
 
     assert_eq!(app.state.active_buffer, "#newchan");
-    assert!(app.state.buffers.contains_key("#newchan"));
+    assert!(app.state.sessions[NET].buffers.contains_key("#newchan"));
     assert!(app
         .state
         .system_log
@@ -717,12 +1016,9 @@ fn test_joined_channel_event() {
 #[test]
 fn test_parted_channel_event() {
     let (mut app, event_tx, _) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.state.active_buffer = "#test".into();
-    app.state
-        .buffers
-        .insert("#test".into(), ChannelBuffer::new());
-    app.state.buffers_order.push("#test".into());
+    app.state.session_mut(NET).ensure_buffer("#test");
 
     event_tx
         .send(GuiEvent::PartedChannel("#test".to_string()))
@@ -730,8 +1026,10 @@ fn test_parted_channel_event() {
 
     app.process_events();
 
-    assert!(!app.state.buffers.contains_key("#test"));
-    assert!(!app.state.buffers_order.contains(&"#test".to_string()));
+    assert!(!app.state.sessions[NET].buffers.contains_key("#test"));
+    assert!(!app.state.sessions[NET]
+        .buffers_order
+        .contains(&"#test".to_string()));
     assert_eq!(app.state.active_buffer, "System");
     assert!(app
         .state
@@ -740,10 +1038,118 @@ fn test_parted_channel_event() {
         .any(|m| m.contains("Left #test")));
 }
 
+#[test]
+fn test_kicked_keeps_buffer_and_leaves_channel() {
+    let (mut app, event_tx, _) = create_test_app();
+    let buffer = app.state.session_mut(NET).ensure_buffer("#test");
+    buffer.users.push(UserInfo {
+        nick: "tester".to_string(),
+        prefix: None,
+    });
+
+    event_tx
+        .send(GuiEvent::Kicked {
+            channel: "#test".to_string(),
+            by: "op".to_string(),
+            reason: Some("flooding".to_string()),
+        })
+        .unwrap();
+    app.process_events();
+
+    let buffer = &app.state.sessions[NET].buffers["#test"];
+    assert!(buffer.left);
+    assert!(buffer.users.is_empty());
+    assert!(buffer
+        .messages
+        .iter()
+        .any(|m| m.text == "You were kicked by op (flooding)"));
+
+    // Rejoining makes it a normal channel again
+    event_tx
+        .send(GuiEvent::JoinedChannel("#test".to_string()))
+        .unwrap();
+    app.process_events();
+    assert!(!app.state.sessions[NET].buffers["#test"].left);
+}
+
+#[test]
+fn test_invite_pending_until_joined() {
+    let (mut app, event_tx, _) = create_test_app();
+    for _ in 0..2 {
+        event_tx
+            .send(GuiEvent::Invited {
+                channel: "#secret".to_string(),
+                by: "bob".to_string(),
+            })
+            .unwrap();
+    }
+    app.process_events();
+    assert_eq!(app.state.pending_invites.len(), 1);
+    assert_eq!(app.state.pending_invites[0].by, "bob");
+
+    event_tx
+        .send(GuiEvent::JoinedChannel("#secret".to_string()))
+        .unwrap();
+    app.process_events();
+    assert!(app.state.pending_invites.is_empty());
+}
+
+#[test]
+fn test_topic_changed_records_setter() {
+    let (mut app, event_tx, _) = create_test_app();
+    app.state.session_mut(NET).ensure_buffer("#test");
+    let time = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+
+    event_tx
+        .send(GuiEvent::TopicChanged {
+            channel: "#test".to_string(),
+            topic: "Release day".to_string(),
+            set_by: "alice".to_string(),
+            time,
+        })
+        .unwrap();
+    app.process_events();
+
+    let buffer = &app.state.sessions[NET].buffers["#test"];
+    assert_eq!(buffer.topic, "Release day");
+    assert_eq!(buffer.topic_set_by.as_deref(), Some("alice"));
+    assert_eq!(buffer.topic_set_at, Some(time));
+    assert!(buffer
+        .messages
+        .iter()
+        .any(|m| m.text == "alice changed the topic to: Release day"));
+}
+
+#[test]
+fn test_whois_result_shown_and_stored() {
+    let (mut app, event_tx, _) = create_test_app();
+    app.state.active_network = NET.into();
+    app.state.active_buffer = "#test".into();
+    app.state.session_mut(NET).ensure_buffer("#test");
+
+    event_tx
+        .send(GuiEvent::WhoisResult(WhoisInfo {
+            nick: "Alice".into(),
+            user: "al".into(),
+            host: "host.example".into(),
+            realname: "Alice".into(),
+            account: Some("alice".into()),
+            ..WhoisInfo::default()
+        }))
+        .unwrap();
+    app.process_events();
+
+    let session = &app.state.sessions[NET];
+    assert_eq!(session.whois["alice"].account.as_deref(), Some("alice"));
+    let block = &session.buffers["#test"].messages.last().unwrap().text;
+    assert!(block.starts_with("Alice is al@host.example (Alice)"));
+    assert!(block.contains("Account: alice"));
+}
+
 #[test]
 fn test_message_received_creates_pm_buffer() {
     let (mut app, event_tx, _) = create_test_app();
-    app.state.is_connected = true;
+    app.state.session_mut(NET).is_connected = true;
     app.connection.nickname = "me".into();
 
     // PM from alice
@@ -752,15 +1158,360 @@ fn test_message_received_creates_pm_buffer() {
             target: "me".to_string(),
             sender: "alice".to_string(),
             text: "Hello there!".to_string(),
+            time: Utc::now(),
+            tags: MessageTags::default(),
         })
         .unwrap();
 
     app.process_events();
 
-    assert!(app.state.buffers.contains_key("alice"));
-    let buffer = app.state.buffers.get("alice").unwrap();
+    assert!(app.state.sessions[NET].buffers.contains_key("alice"));
+    let buffer = app.state.sessions[NET].buffers.get("alice").unwrap();
     assert!(buffer
         .messages
         .iter()
         .any(|m| m.text.contains("Hello there!")));
 }
+
+#[test]
+fn test_own_message_pending_then_echoed() {
+    let (mut app, event_tx, _) = create_test_app();
+    app.state.session_mut(NET).is_connected = true;
+
+    // Our PM to bob goes in bob's buffer, pending until the echo arrives
+    event_tx
+        .send(GuiEvent::OwnMessagePending {
+            id: 1,
+            target: "bob".to_string(),
+            sender: "tester".to_string(),
+            text: "hi bob".to_string(),
+            tags: MessageTags::default(),
+        })
+        .unwrap();
+    app.process_events();
+
+    let buffer = &app.state.sessions[NET].buffers["bob"];
+    assert_eq!(buffer.messages[0].delivery, Delivery::Pending(1));
+
+    event_tx
+        .send(GuiEvent::OwnMessageEchoed {
+            id: 1,
+            target: "bob".to_string(),
+            text: "hi bob (edited by server)".to_string(),
+            tags: MessageTags {
+                msgid: Some("e1".to_string()),
+                ..Default::default()
+            },
+        })
+        .unwrap();
+    app.process_events();
+
+    let buffer = &app.state.sessions[NET].buffers["bob"];
+    assert_eq!(buffer.messages.len(), 1);
+    assert_eq!(buffer.messages[0].delivery, Delivery::Delivered);
+    assert_eq!(buffer.messages[0].text, "hi bob (edited by server)");
+    assert_eq!(buffer.messages[0].tags.msgid.as_deref(), Some("e1"));
+}
+
+#[test]
+fn test_own_message_failed() {
+    let (mut app, event_tx, _) = create_test_app();
+    app.state.session_mut(NET).is_connected = true;
+
+    event_tx
+        .send(GuiEvent::OwnMessagePending {
+            id: 4,
+            target: "#quiet".to_string(),
+            sender: "tester".to_string(),
+            text: "anyone?".to_string(),
+            tags: MessageTags::default(),
+        })
+        .unwrap();
+    event_tx
+        .send(GuiEvent::OwnMessageFailed {
+            id: 4,
+            target: "#quiet".to_string(),
+            reason: "Cannot send to channel".to_string(),
+        })
+        .unwrap();
+    app.process_events();
+
+    let buffer = &app.state.sessions[NET].buffers["#quiet"];
+    assert_eq!(
+        buffer.messages[0].delivery,
+        Delivery::Failed("Cannot send to channel".to_string())
+    );
+}
+
+#[test]
+fn test_join_fetches_latest_history() {
+    use slirc_client::buffer::HistoryState;
+    use slirc_client::protocol::HistoryAnchor;
+
+    let (mut app, event_tx, action_rx) = create_test_app();
+    event_tx
+        .send(GuiEvent::JoinedChannel("#newchan".to_string()))
+        .unwrap();
+    app.process_events();
+
+    match action_rx.try_recv() {
+        Ok(BackendAction::FetchHistory { target, anchor }) => {
+            assert_eq!(target, "#newchan");
+            assert_eq!(anchor, HistoryAnchor::Latest);
+        }
+        other => panic!("expected a history request, got {:?}", other),
+    }
+    assert_eq!(
+        app.state.sessions[NET].buffers["#newchan"].history,
+        HistoryState::Loading
+    );
+    // ...and its modes
+    match action_rx.try_recv() {
+        Ok(BackendAction::QueryModes { channel, lists }) => {
+            assert_eq!(channel, "#newchan");
+            assert!(lists.is_empty());
+        }
+        other => panic!("expected a mode request, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_channel_modes_tracked() {
+    let (mut app, event_tx, _) = create_test_app();
+    let mut support = ServerSupport::default();
+    support.apply(&["CHANMODES=beIq,k,fl,imnrst"]);
+    let time = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+    let change = |added, mode, arg: Option<&str>| ModeChange {
+        added,
+        mode,
+        arg: arg.map(str::to_string),
+    };
+
+    let _ = event_tx.send(GuiEvent::ServerInfo(support));
+    let _ = event_tx.send(GuiEvent::ChannelModeIs {
+        channel: "#test".into(),
+        changes: vec![
+            change(true, 'n', None),
+            change(true, 't', None),
+            change(true, 'k', Some("secret")),
+        ],
+    });
+    let _ = event_tx.send(GuiEvent::ModeList {
+        channel: "#TEST".into(),
+        mode: 'b',
+        entries: Vec::new(),
+    });
+    let _ = event_tx.send(GuiEvent::ChannelMode {
+        channel: "#test".into(),
+        changes: vec![
+            change(false, 'k', Some("secret")),
+            change(true, 'l', Some("25")),
+            change(true, 'b', Some("*!*@spam")),
+        ],
+        set_by: "op".into(),
+        time,
+    });
+    app.process_events();
+
+    let buffer = &app.state.sessions[NET].buffers["#test"];
+    let modes = &buffer.channel_modes;
+    assert_eq!(modes.mode_string(), "+lnt");
+    assert_eq!(modes.key, None);
+    assert_eq!(modes.limit, Some(25));
+    assert_eq!(modes.list('b')[0].mask, "*!*@spam");
+    assert_eq!(modes.list('b')[0].set_by.as_deref(), Some("op"));
+    assert_eq!(
+        buffer.messages.last().unwrap().text,
+        "op set mode -k+lb secret 25 *!*@spam"
+    );
+}
+
+#[test]
+fn test_channel_settings_dialog_follows_modes() {
+    let (mut app, event_tx, _) = create_test_app();
+    let support = ServerSupport::default();
+    app.state.session_mut(NET).ensure_buffer("#test");
+    app.dialogs
+        .open_channel_settings(NET, "#test", &ChannelModes::default(), &support, true);
+
+    let _ = event_tx.send(GuiEvent::ChannelModeIs {
+        channel: "#Test".into(),
+        changes: vec![ModeChange {
+            added: true,
+            mode: 'm',
+            arg: None,
+        }],
+    });
+    let _ = event_tx.send(GuiEvent::ModeList {
+        channel: "#test".into(),
+        mode: 'b',
+        entries: Vec::new(),
+    });
+    app.process_events();
+
+    let dialog = app.dialogs.channel_settings_dialog.as_ref().unwrap();
+    assert!(dialog.flags.contains(&('m', true)));
+    assert!(!dialog.is_modified());
+}
+
+#[test]
+fn test_history_inserted_above_without_duplicates() {
+    use slirc_client::buffer::HistoryState;
+    use slirc_client::protocol::HistoryMessage;
+
+    let (mut app, event_tx, _) = create_test_app();
+    app.state.session_mut(NET).ensure_buffer("#test");
+    let at = |min| Utc.with_ymd_and_hms(2024, 3, 1, 12, min, 0).unwrap();
+    event_tx
+        .send(GuiEvent::MessageReceived {
+            target: "#test".into(),
+            sender: "alice".into(),
+            text: "live".into(),
+            time: at(10),
+            tags: MessageTags::default(),
+        })
+        .unwrap();
+    app.process_events();
+
+    let page = vec![
+        HistoryMessage {
+            sender: "bob".into(),
+            text: "older".into(),
+            time: at(1),
+            tags: MessageTags {
+                msgid: Some("m1".into()),
+                ..Default::default()
+            },
+        },
+        HistoryMessage {
+            sender: "alice".into(),
+            text: "live".into(),
+            time: at(10),
+            tags: MessageTags {
+                msgid: Some("m2".into()),
+                ..Default::default()
+            },
+        },
+    ];
+    for _ in 0..2 {
+        event_tx
+            .send(GuiEvent::History {
+                target: "#TEST".into(),
+                messages: page.clone(),
+            })
+            .unwrap();
+    }
+    app.process_events();
+
+    let buffer = &app.state.sessions[NET].buffers["#test"];
+    let texts: Vec<&str> = buffer.messages.iter().map(|m| m.text.as_str()).collect();
+    assert_eq!(texts, vec!["older", "live"]);
+    assert_eq!(buffer.history, HistoryState::Idle);
+
+    event_tx
+        .send(GuiEvent::History {
+            target: "#test".into(),
+            messages: Vec::new(),
+        })
+        .unwrap();
+    app.process_events();
+    assert_eq!(
+        app.state.sessions[NET].buffers["#test"].history,
+        HistoryState::Exhausted
+    );
+}
+
+#[test]
+fn test_reactions_attach_to_message_by_msgid() {
+    let (mut app, event_tx, _) = create_test_app();
+    app.state.session_mut(NET).ensure_buffer("#test");
+
+    event_tx
+        .send(GuiEvent::MessageReceived {
+            target: "#test".into(),
+            sender: "alice".into(),
+            text: "ship it?".into(),
+            time: Utc::now(),
+            tags: MessageTags {
+                msgid: Some("m1".into()),
+                ..Default::default()
+            },
+        })
+        .unwrap();
+    for (sender, added) in [("bob", true), ("tester", true), ("bob", false)] {
+        event_tx
+            .send(GuiEvent::Reaction {
+                target: "#test".into(),
+                sender: sender.into(),
+                msgid: "m1".into(),
+                reaction: "👍".into(),
+                added,
+            })
+            .unwrap();
+    }
+    // Unknown message: ignored
+    event_tx
+        .send(GuiEvent::Reaction {
+            target: "#test".into(),
+            sender: "bob".into(),
+            msgid: "nope".into(),
+            reaction: "🎉".into(),
+            added: true,
+        })
+        .unwrap();
+    app.process_events();
+
+    let msg = &app.state.sessions[NET].buffers["#test"].messages[0];
+    assert_eq!(msg.reactions.len(), 1);
+    assert_eq!(msg.reactions[0].emoji, "👍");
+    assert_eq!(msg.reactions[0].nicks, vec!["tester"]);
+}
+
+#[test]
+fn test_message_burst_sends_one_markread() {
+    let (mut app, event_tx, action_rx) = create_test_app();
+    app.state.active_network = NET.into();
+    app.state.active_buffer = "#test".into();
+    app.state.session_mut(NET).ensure_buffer("#test");
+    app.state.session_mut(NET).ensure_buffer("#other");
+
+    // A busy channel: a new line on every frame while we watch
+    let base = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+    let start = std::time::Instant::now();
+    for i in 0..10 {
+        event_tx
+            .send(GuiEvent::MessageReceived {
+                target: "#test".into(),
+                sender: "alice".into(),
+                text: format!("line {i}"),
+                time: base + chrono::Duration::seconds(i),
+                tags: MessageTags::default(),
+            })
+            .unwrap();
+        app.process_events();
+        let now = start + std::time::Duration::from_millis(100 * i as u64);
+        app.mark_active_buffer_read(true, now);
+    }
+
+    match action_rx.try_recv() {
+        Ok(BackendAction::MarkRead { target, time }) => {
+            assert_eq!(target, "#test");
+            assert_eq!(time, base);
+        }
+        _ => panic!("Expected one MarkRead action"),
+    }
+    assert!(action_rx.try_recv().is_err());
+    assert!(app.has_unsent_read());
+
+    // Leaving the buffer sends what was held back
+    app.state.active_buffer = "#other".into();
+    app.mark_active_buffer_read(true, start + std::time::Duration::from_secs(1));
+    match action_rx.try_recv() {
+        Ok(BackendAction::MarkRead { target, time }) => {
+            assert_eq!(target, "#test");
+            assert_eq!(time, base + chrono::Duration::seconds(9));
+        }
+        _ => panic!("Expected the held-back MarkRead"),
+    }
+    assert!(action_rx.try_recv().is_err());
+}
@@ -1,22 +1,24 @@
 //! Comprehensive backend tests for network layer
 
 use crossbeam_channel::unbounded;
+use eframe::egui;
 use std::time::Duration;
 
-use slirc_client::protocol::{BackendAction, GuiEvent};
+use slirc_client::protocol::{BackendAction, GuiEvent, NetworkAction, NetworkEvent};
+use slirc_client::server_support::ServerSupport;
 
 #[test]
 fn test_backend_thread_creation() {
     // Test that the backend thread can be created without panicking
-    let (action_tx, action_rx) = unbounded::<BackendAction>();
-    let (event_tx, _event_rx) = unbounded::<GuiEvent>();
+    let (action_tx, action_rx) = unbounded::<NetworkAction>();
+    let (event_tx, _event_rx) = unbounded::<NetworkEvent>();
 
     let _handle = std::thread::spawn(move || {
-        slirc_client::backend::run_backend(action_rx, event_tx);
+        slirc_client::backend::run_backend(action_rx, event_tx, egui::Context::default());
     });
 
     // Send disconnect to allow thread to exit cleanly
-    let _ = action_tx.send(BackendAction::Disconnect);
+    let _ = action_tx.send(NetworkAction::new("testnet", BackendAction::Disconnect));
 
     // Thread should not panic
     std::thread::sleep(Duration::from_millis(100));
@@ -50,20 +52,25 @@ fn test_connection_validation() {
 
 #[test]
 fn test_disconnect_handling() {
-    let (action_tx, action_rx) = unbounded::<BackendAction>();
-    let (event_tx, event_rx) = unbounded::<GuiEvent>();
+    let (action_tx, action_rx) = unbounded::<NetworkAction>();
+    let (event_tx, event_rx) = unbounded::<NetworkEvent>();
 
     let _handle = std::thread::spawn(move || {
-        slirc_client::backend::run_backend(action_rx, event_tx);
+        slirc_client::backend::run_backend(action_rx, event_tx, egui::Context::default());
     });
 
     // Send disconnect
-    action_tx.send(BackendAction::Disconnect).unwrap();
+    action_tx
+        .send(NetworkAction::new("testnet", BackendAction::Disconnect))
+        .unwrap();
 
-    // Should receive disconnected event
+    // Should receive disconnected event tagged with the network
     match event_rx.recv_timeout(Duration::from_secs(2)) {
-        Ok(GuiEvent::Disconnected(_)) => {
-            // Expected
+        Ok(NetworkEvent {
+            network,
+            event: GuiEvent::Disconnected(_),
+        }) => {
+            assert_eq!(network, "testnet");
         }
         _ => panic!("Expected Disconnected event"),
     }
@@ -71,20 +78,55 @@ fn test_disconnect_handling() {
     drop(action_tx);
 }
 
+#[test]
+fn test_actions_routed_per_network() {
+    let (action_tx, action_rx) = unbounded::<NetworkAction>();
+    let (event_tx, event_rx) = unbounded::<NetworkEvent>();
+
+    let _handle = std::thread::spawn(move || {
+        slirc_client::backend::run_backend(action_rx, event_tx, egui::Context::default());
+    });
+
+    // Each network gets its own connection task, which answers independently
+    action_tx
+        .send(NetworkAction::new("net1", BackendAction::Disconnect))
+        .unwrap();
+    action_tx
+        .send(NetworkAction::new("net2", BackendAction::Disconnect))
+        .unwrap();
+
+    let mut networks = Vec::new();
+    for _ in 0..2 {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(NetworkEvent {
+                network,
+                event: GuiEvent::Disconnected(_),
+            }) => networks.push(network),
+            _ => panic!("Expected Disconnected event"),
+        }
+    }
+    networks.sort();
+    assert_eq!(networks, vec!["net1", "net2"]);
+
+    drop(action_tx);
+}
+
 #[test]
 fn test_channel_validation() {
     use slirc_client::validation;
 
+    let support = ServerSupport::default();
+
     // Valid channels
-    assert!(validation::validate_channel_name("#general").is_ok());
-    assert!(validation::validate_channel_name("&local").is_ok());
-    assert!(validation::validate_channel_name("#rust-lang").is_ok());
+    assert!(validation::validate_channel_name("#general", &support).is_ok());
+    assert!(validation::validate_channel_name("&local", &support).is_ok());
+    assert!(validation::validate_channel_name("#rust-lang", &support).is_ok());
 
     // Invalid channels
-    assert!(validation::validate_channel_name("").is_err());
-    assert!(validation::validate_channel_name("notachannel").is_err());
-    assert!(validation::validate_channel_name("#test channel").is_err());
-    assert!(validation::validate_channel_name(&"#".to_string().repeat(51)).is_err());
+    assert!(validation::validate_channel_name("", &support).is_err());
+    assert!(validation::validate_channel_name("notachannel", &support).is_err());
+    assert!(validation::validate_channel_name("#test channel", &support).is_err());
+    assert!(validation::validate_channel_name(&"#".to_string().repeat(51), &support).is_err());
 }
 
 #[test]
@@ -172,17 +214,24 @@ fn test_gui_event_types() {
 
 #[test]
 fn test_tls_configuration_parsing() {
-    use slirc_client::config::Network;
+    use slirc_client::config::{
+        Network, DEFAULT_FLOOD_BURST, DEFAULT_FLOOD_REFILL_MS, DEFAULT_PING_TIMEOUT_SECS,
+    };
 
     let network = Network {
         name: "Test".to_string(),
         servers: vec!["irc.libera.chat:6697".to_string()],
         nick: "testuser".to_string(),
+        alt_nicks: vec![],
         auto_connect: false,
         favorite_channels: vec![],
         nickserv_password: None,
         use_tls: true,
         auto_reconnect: true,
+        ping_timeout_secs: DEFAULT_PING_TIMEOUT_SECS,
+        flood_burst: DEFAULT_FLOOD_BURST,
+        flood_refill_ms: DEFAULT_FLOOD_REFILL_MS,
+        send_typing: true,
     };
 
     assert!(network.use_tls);
@@ -191,23 +240,30 @@ fn test_tls_configuration_parsing() {
 
 #[test]
 fn test_buffer_state_management() {
-    use slirc_client::buffer::{ChannelBuffer, MessageType, RenderedMessage};
-    use slirc_client::protocol::UserInfo;
+    use chrono::Utc;
+    use slirc_client::buffer::{ChannelBuffer, Delivery, MessageType, RenderedMessage};
+    use slirc_client::protocol::{MessageTags, UserInfo};
 
     let mut buffer = ChannelBuffer::new();
 
     // Test adding messages
     let msg1 = RenderedMessage {
-        timestamp: "12:00".to_string(),
+        timestamp: Utc::now(),
         sender: "Alice".to_string(),
         text: "Hello!".to_string(),
         msg_type: MessageType::Normal,
+        delivery: Delivery::Delivered,
+        tags: MessageTags::default(),
+        reactions: Vec::new(),
     };
     let msg2 = RenderedMessage {
-        timestamp: "12:01".to_string(),
+        timestamp: Utc::now(),
         sender: "Bob".to_string(),
         text: "Hi there!".to_string(),
         msg_type: MessageType::Normal,
+        delivery: Delivery::Delivered,
+        tags: MessageTags::default(),
+        reactions: Vec::new(),
     };
     buffer.add_message(msg1, false, false);
     buffer.add_message(msg2, false, false);
@@ -275,22 +331,23 @@ fn test_command_parsing() {
     let (action_tx, action_rx) = unbounded();
     let buffers = HashMap::new();
     let mut system_log = Vec::new();
-    let mut nickname = "test".to_string();
 
     // Test join command
     let is_cmd = commands::handle_user_command(
         "/join #test",
+        "testnet",
         "System",
         &buffers,
+        &ServerSupport::default(),
         &action_tx,
         &mut system_log,
-        &mut nickname,
     );
 
     assert!(is_cmd);
-    let action = action_rx.recv().unwrap();
+    let action: NetworkAction = action_rx.recv().unwrap();
+    assert_eq!(action.network, "testnet");
     assert!(matches!(
-        action,
+        action.action,
         slirc_client::protocol::BackendAction::Join(_)
     ));
 }
@@ -303,11 +360,16 @@ fn test_protocol_action_serialization() {
     let action = BackendAction::Connect {
         server: "test.server".to_string(),
         port: 6667,
+        fallback_servers: Vec::new(),
         nickname: "testuser".to_string(),
+        alt_nicks: Vec::new(),
         username: "testuser".to_string(),
         realname: "Test User".to_string(),
         use_tls: false,
         auto_reconnect: true,
+        ping_timeout_secs: 120,
+        flood_burst: 5,
+        flood_refill_ms: 2000,
         sasl_password: None,
     };
 
@@ -320,7 +382,7 @@ fn test_error_handling_in_validation() {
     use slirc_client::validation;
 
     // Test that errors contain meaningful messages
-    match validation::validate_channel_name("invalid") {
+    match validation::validate_channel_name("invalid", &ServerSupport::default()) {
         Err(msg) => assert!(msg.contains("must start with")),
         Ok(_) => panic!("Should have failed"),
     }
@@ -3,9 +3,10 @@
 //! These tests exercise full workflows across multiple modules to ensure
 //! proper integration between backend, events, commands, and UI state.
 
+use chrono::Utc;
 use crossbeam_channel::unbounded;
-use slirc_client::buffer::{ChannelBuffer, MessageType, RenderedMessage};
-use slirc_client::protocol::{BackendAction, GuiEvent, UserInfo};
+use slirc_client::buffer::{ChannelBuffer, Delivery, MessageType, RenderedMessage};
+use slirc_client::protocol::{BackendAction, GuiEvent, MessageTags, UserInfo};
 use std::collections::HashMap;
 
 /// Test channel buffer state management with multiple channels
@@ -22,10 +23,13 @@ fn test_multi_channel_buffer_state() {
 
     // Add messages to different channels
     let msg1 = RenderedMessage {
-        timestamp: "12:00".to_string(),
+        timestamp: Utc::now(),
         sender: "alice".to_string(),
         text: "Hello #rust!".to_string(),
         msg_type: MessageType::Normal,
+        delivery: Delivery::Delivered,
+        tags: MessageTags::default(),
+        reactions: Vec::new(),
     };
 
     buffers
@@ -34,10 +38,13 @@ fn test_multi_channel_buffer_state() {
         .add_message(msg1, false, false);
 
     let msg2 = RenderedMessage {
-        timestamp: "12:01".to_string(),
+        timestamp: Utc::now(),
         sender: "bob".to_string(),
         text: "Hello #test!".to_string(),
         msg_type: MessageType::Normal,
+        delivery: Delivery::Delivered,
+        tags: MessageTags::default(),
+        reactions: Vec::new(),
     };
 
     buffers
@@ -120,10 +127,13 @@ fn test_message_type_handling() {
     for (msg_type, text) in messages {
         buffer.add_message(
             RenderedMessage {
-                timestamp: "12:00".to_string(),
+                timestamp: Utc::now(),
                 sender: "system".to_string(),
                 text: text.to_string(),
                 msg_type: msg_type.clone(),
+                delivery: Delivery::Delivered,
+                tags: MessageTags::default(),
+                reactions: Vec::new(),
             },
             false,
             false,
@@ -147,10 +157,13 @@ fn test_buffer_message_trimming() {
     for i in 0..2100 {
         buffer.add_message(
             RenderedMessage {
-                timestamp: format!("12:{:02}", i % 60),
+                timestamp: Utc::now(),
                 sender: "test".to_string(),
                 text: format!("Message {}", i),
                 msg_type: MessageType::Normal,
+                delivery: Delivery::Delivered,
+                tags: MessageTags::default(),
+                reactions: Vec::new(),
             },
             false,
             false,
@@ -183,10 +196,13 @@ fn test_unread_and_highlight_tracking() {
     // Add normal message (not active, not highlight)
     buffer.add_message(
         RenderedMessage {
-            timestamp: "12:00".to_string(),
+            timestamp: Utc::now(),
             sender: "alice".to_string(),
             text: "Hello".to_string(),
             msg_type: MessageType::Normal,
+            delivery: Delivery::Delivered,
+            tags: MessageTags::default(),
+            reactions: Vec::new(),
         },
         false,
         false,
@@ -198,10 +214,13 @@ fn test_unread_and_highlight_tracking() {
     // Add highlighted message
     buffer.add_message(
         RenderedMessage {
-            timestamp: "12:01".to_string(),
+            timestamp: Utc::now(),
             sender: "bob".to_string(),
             text: "mynick: ping!".to_string(),
             msg_type: MessageType::Normal,
+            delivery: Delivery::Delivered,
+            tags: MessageTags::default(),
+            reactions: Vec::new(),
         },
         false,
         true, // highlight
@@ -218,10 +237,13 @@ fn test_unread_and_highlight_tracking() {
     // Active message shouldn't increment unread
     buffer.add_message(
         RenderedMessage {
-            timestamp: "12:02".to_string(),
+            timestamp: Utc::now(),
             sender: "charlie".to_string(),
             text: "Message in active buffer".to_string(),
             msg_type: MessageType::Normal,
+            delivery: Delivery::Delivered,
+            tags: MessageTags::default(),
+            reactions: Vec::new(),
         },
         true, // is_active
         false,
@@ -240,11 +262,16 @@ fn test_backend_action_channel() {
         .send(BackendAction::Connect {
             server: "irc.example.com".to_string(),
             port: 6667,
+            fallback_servers: Vec::new(),
             nickname: "testbot".to_string(),
+            alt_nicks: Vec::new(),
             username: "testuser".to_string(),
             realname: "Test User".to_string(),
             use_tls: false,
             auto_reconnect: true,
+            ping_timeout_secs: 120,
+            flood_burst: 5,
+            flood_refill_ms: 2000,
             sasl_password: None,
         })
         .unwrap();
@@ -256,6 +283,7 @@ fn test_backend_action_channel() {
         .send(BackendAction::SendMessage {
             target: "#test".to_string(),
             text: "Hello world!".to_string(),
+            client_tags: Vec::new(),
         })
         .unwrap();
     action_tx.send(BackendAction::Disconnect).unwrap();
@@ -272,7 +300,7 @@ fn test_backend_action_channel() {
     }
 
     match action_rx.recv().unwrap() {
-        BackendAction::SendMessage { target, text } => {
+        BackendAction::SendMessage { target, text, .. } => {
             assert_eq!(target, "#test");
             assert_eq!(text, "Hello world!");
         }
@@ -300,6 +328,8 @@ fn test_gui_event_channel() {
             target: "#test".to_string(),
             sender: "alice".to_string(),
             text: "Hello!".to_string(),
+            time: Utc::now(),
+            tags: MessageTags::default(),
         })
         .unwrap();
     event_tx
@@ -320,6 +350,7 @@ fn test_gui_event_channel() {
             target,
             sender,
             text,
+            ..
         } => {
             assert_eq!(target, "#test");
             assert_eq!(sender, "alice");
@@ -386,10 +417,13 @@ fn test_buffer_state_isolation() {
     // Add messages to channel2
     buffers.get_mut("#channel2").unwrap().add_message(
         RenderedMessage {
-            timestamp: "12:00".to_string(),
+            timestamp: Utc::now(),
             sender: "bob".to_string(),
             text: "Hello".to_string(),
             msg_type: MessageType::Normal,
+            delivery: Delivery::Delivered,
+            tags: MessageTags::default(),
+            reactions: Vec::new(),
         },
         false,
         false,
@@ -413,11 +447,16 @@ fn test_tls_backend_action() {
         .send(BackendAction::Connect {
             server: "irc.libera.chat".to_string(),
             port: 6697,
+            fallback_servers: Vec::new(),
             nickname: "testuser".to_string(),
+            alt_nicks: Vec::new(),
             username: "testuser".to_string(),
             realname: "Test User".to_string(),
             use_tls: true,
             auto_reconnect: true,
+            ping_timeout_secs: 120,
+            flood_burst: 5,
+            flood_refill_ms: 2000,
             sasl_password: None,
         })
         .unwrap();
@@ -449,11 +488,16 @@ fn test_tls_state_propagation() {
         .send(BackendAction::Connect {
             server: "secure.server.com".to_string(),
             port: 6697,
+            fallback_servers: Vec::new(),
             nickname: "user".to_string(),
+            alt_nicks: Vec::new(),
             username: "user".to_string(),
             realname: "User".to_string(),
             use_tls: true,
             auto_reconnect: true,
+            ping_timeout_secs: 120,
+            flood_burst: 5,
+            flood_refill_ms: 2000,
             sasl_password: None,
         })
         .unwrap();
@@ -468,11 +512,16 @@ fn test_tls_state_propagation() {
         .send(BackendAction::Connect {
             server: "plain.server.com".to_string(),
             port: 6667,
+            fallback_servers: Vec::new(),
             nickname: "user".to_string(),
+            alt_nicks: Vec::new(),
             username: "user".to_string(),
             realname: "User".to_string(),
             use_tls: false,
             auto_reconnect: true,
+            ping_timeout_secs: 120,
+            flood_burst: 5,
+            flood_refill_ms: 2000,
             sasl_password: None,
         })
         .unwrap();